@@ -1,7 +1,7 @@
 #![doc = include_str!("../README.md")]
 
 mod delayqueue;
+mod lru;
 mod store;
-mod utils;
 
 pub use store::MemoryBackend;
@@ -0,0 +1,55 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Keeps the order in which the keys were last used, the least recently used key is the first one
+/// to be popped.
+#[derive(Debug)]
+pub(crate) struct Lru<T> {
+    pub(crate) max_keys: usize,
+    tick: u64,
+    ticks: HashMap<T, u64>,
+    order: BTreeMap<u64, T>,
+}
+
+impl<T: Hash + Eq + Clone> Lru<T> {
+    pub(crate) fn new(max_keys: usize) -> Self {
+        Self {
+            max_keys,
+            tick: 0,
+            ticks: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Mark the item as the most recently used one
+    pub(crate) fn bump(&mut self, item: T) {
+        self.tick += 1;
+        if let Some(old) = self.ticks.insert(item.clone(), self.tick) {
+            self.order.remove(&old);
+        }
+        self.order.insert(self.tick, item);
+    }
+
+    /// Remove and return the least recently used item
+    pub(crate) fn pop_oldest(&mut self) -> Option<T> {
+        let (_, item) = self.order.pop_first()?;
+        self.ticks.remove(&item);
+        Some(item)
+    }
+
+    /// Keep only the items matching the predicate, in the same order
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        let ticks = &mut self.ticks;
+        self.order.retain(|_, item| {
+            let keep = f(item);
+            if !keep {
+                ticks.remove(item);
+            }
+            keep
+        });
+    }
+}
@@ -1,29 +1,58 @@
-use std::{collections::HashMap, convert::TryInto, sync::Arc, time::Duration};
+use std::{cmp::Ordering, collections::HashMap, convert::TryFrom, sync::Arc, time::Duration};
 
 use basteh::{
-    dev::{Mutation, OwnedValue, Provider, Value},
+    dev::{
+        add_big, add_float, add_number, resolve_range, run_list_mutations, run_mutations,
+        should_set, write_bit, ListMutation, Mutation, OwnedValue, Provider, SnapshotEntry, Value,
+        ValueKind,
+    },
     BastehError, Result,
 };
 use parking_lot::Mutex;
+use tokio::sync::Notify;
 
 use crate::delayqueue::{delayqueue, DelayQueueSender};
-use crate::utils::run_mutations;
+use crate::lru::Lru;
 
 type ScopeMap = HashMap<Arc<[u8]>, OwnedValue>;
-type InternalMap = HashMap<Arc<str>, ScopeMap>;
+type InternalMap = HashMap<Arc<[u8]>, ScopeMap>;
+type VersionMap = HashMap<Arc<[u8]>, HashMap<Arc<[u8]>, u64>>;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 struct ExpiryKey {
-    pub(crate) scope: Arc<str>,
+    pub(crate) scope: Arc<[u8]>,
     pub(crate) key: Arc<[u8]>,
 }
 
 impl ExpiryKey {
-    pub fn new(scope: Arc<str>, key: Arc<[u8]>) -> Self {
+    pub fn new(scope: Arc<[u8]>, key: Arc<[u8]>) -> Self {
         Self { scope, key }
     }
 }
 
+// Size of the payload of the value, without the memory overhead of the map and the allocations
+fn value_size(value: &OwnedValue) -> u64 {
+    match value {
+        OwnedValue::Number(_) => std::mem::size_of::<i64>() as u64,
+        OwnedValue::BigNumber(_) => std::mem::size_of::<i128>() as u64,
+        OwnedValue::String(s) => s.len() as u64,
+        OwnedValue::Bytes(b) => b.len() as u64,
+        OwnedValue::List(l) => l.iter().map(value_size).sum(),
+        OwnedValue::Map(m) => m.iter().map(|(f, v)| f.len() as u64 + value_size(v)).sum(),
+    }
+}
+
+// Items of the list between start and end, or an error if the value is not a list
+fn list_range(value: &OwnedValue, start: i64, end: i64) -> Result<Vec<OwnedValue>> {
+    match value {
+        OwnedValue::List(l) => {
+            let (start, take) = resolve_range(l.len(), start, end);
+            Ok(l.iter().skip(start).take(take).cloned().collect())
+        }
+        _ => Err(BastehError::TypeConversion),
+    }
+}
+
 /// An implementation of [`ExpiryStore`](basteh::dev::ExpiryStore) based on Arc-Mutex-Hashmap
 /// using tokio's delayqueue for expiration.
 ///
@@ -42,8 +71,17 @@ impl ExpiryKey {
 pub struct MemoryBackend {
     map: Arc<Mutex<InternalMap>>,
 
+    // Versions of the keys written by set_versioned, always locked after the map
+    versions: Arc<Mutex<VersionMap>>,
+
     // Send part of the channel used to send commands to delayqueue
     dq_tx: DelayQueueSender<ExpiryKey>,
+
+    // Wakes up the pop_blocking calls whenever items are added to a list
+    pushed: Arc<Notify>,
+
+    // Recency of the keys when the number of keys is limited, always locked after the map
+    lru: Option<Arc<Mutex<Lru<ExpiryKey>>>>,
 }
 
 impl MemoryBackend {
@@ -61,17 +99,75 @@ impl MemoryBackend {
             }
         });
 
-        Self { map, dq_tx }
+        Self {
+            map,
+            versions: Arc::new(Mutex::new(VersionMap::new())),
+            dq_tx,
+            pushed: Arc::new(Notify::new()),
+            lru: None,
+        }
     }
 
     pub fn start_default() -> Self {
         Self::start(2048)
     }
+
+    /// Limit the number of keys across all the scopes, the least recently used keys are evicted
+    /// to make room for the new ones. Reading or writing a key, or touching it, counts as using it.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.lru = Some(Arc::new(Mutex::new(Lru::new(max_keys))));
+        self
+    }
+
+    // Marks the key as the most recently used one and evicts the least recently used keys above
+    // the limit, it does nothing if the number of keys is not limited.
+    async fn used(&self, scope: &[u8], key: &[u8]) {
+        let lru = match self.lru {
+            Some(ref lru) => lru,
+            None => return,
+        };
+
+        let evicted = {
+            let mut map = self.map.lock();
+            let mut lru = lru.lock();
+            lru.bump(ExpiryKey::new(scope.into(), key.into()));
+
+            // Removed keys are left in the lru and skipped when popped, unless they pile up
+            let mut len: usize = map.values().map(|scope_map| scope_map.len()).sum();
+            if lru.len() > len.max(lru.max_keys) * 2 {
+                lru.retain(|exp| {
+                    map.get(&exp.scope)
+                        .is_some_and(|scope_map| scope_map.contains_key(&exp.key))
+                });
+            }
+
+            let mut evicted = Vec::new();
+            while len > lru.max_keys {
+                let exp = match lru.pop_oldest() {
+                    Some(exp) => exp,
+                    None => break,
+                };
+                if map
+                    .get_mut(&exp.scope)
+                    .and_then(|scope_map| scope_map.remove(&exp.key))
+                    .is_some()
+                {
+                    len -= 1;
+                    evicted.push(exp);
+                }
+            }
+            evicted
+        };
+
+        for exp in evicted {
+            self.dq_tx.remove(exp).await.ok();
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl Provider for MemoryBackend {
-    async fn keys(&self, scope: &str) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
         Ok(Box::new(
             self.map
                 .lock()
@@ -84,134 +180,656 @@ impl Provider for MemoryBackend {
         ))
     }
 
-    async fn set(&self, scope: &str, key: &[u8], value: Value<'_>) -> Result<()> {
-        let scope: Arc<str> = scope.into();
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        Ok(Box::new(
+            self.map
+                .lock()
+                .iter()
+                .filter(|(_, scope_map)| !scope_map.is_empty())
+                .map(|(scope, _)| scope.to_vec())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        ))
+    }
+
+    async fn size_bytes(&self, scope: &[u8]) -> Result<u64> {
+        Ok(self
+            .map
+            .lock()
+            .get(scope)
+            .map(|scope_map| {
+                scope_map
+                    .iter()
+                    .map(|(k, v)| k.len() as u64 + value_size(v))
+                    .sum()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> Result<()> {
+        let scope_map = {
+            let mut map = self.map.lock();
+            self.versions.lock().remove(scope);
+            map.remove(scope)
+        };
+
+        if let Some(scope_map) = scope_map {
+            let scope: Arc<[u8]> = scope.into();
+            for key in scope_map.into_keys() {
+                self.dq_tx
+                    .remove(ExpiryKey::new(scope.clone(), key))
+                    .await
+                    .ok();
+            }
+        }
+        Ok(())
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.upsert(scope, key, value).await.map(|_| ())
+    }
+
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<bool> {
+        let scope: Arc<[u8]> = scope.into();
         let key: Arc<[u8]> = key.into();
+        let is_list = matches!(value, Value::List(_));
 
-        if self
+        let existed = self
             .map
             .lock()
             .entry(scope.clone())
             .or_default()
             .insert(key.clone(), value.into_owned().into())
-            .is_some()
-        {
+            .is_some();
+        self.used(&scope, &key).await;
+        if existed {
             self.dq_tx
                 .remove(ExpiryKey::new(scope, key))
                 .await
                 .map_err(BastehError::custom)?;
         }
+        if is_list {
+            self.pushed.notify_waiters();
+        }
+        Ok(!existed)
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        // The expiry lives in the delayqueue, so it's kept as long as we don't touch it
+        self.map
+            .lock()
+            .entry(scope.into())
+            .or_default()
+            .insert(key.into(), value.into_owned());
+        self.used(scope, key).await;
         Ok(())
     }
 
-    async fn get<'a>(&'a self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>> {
+    async fn get<'a>(&'a self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = self
+            .map
+            .lock()
+            .get(scope)
+            .and_then(|scope_map| scope_map.get(key))
+            .map(|value| value.clone());
+        if value.is_some() {
+            self.used(scope, key).await;
+        }
+        Ok(value)
+    }
+
+    async fn value_kind(&self, scope: &[u8], key: &[u8]) -> Result<Option<ValueKind>> {
         Ok(self
             .map
             .lock()
             .get(scope)
             .and_then(|scope_map| scope_map.get(key))
-            .map(|value| value.clone()))
+            .map(|value| value.kind()))
     }
 
     async fn get_range<'a>(
         &'a self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         start: i64,
         end: i64,
     ) -> Result<Vec<OwnedValue>> {
-        Ok(self
+        let items = self
             .map
             .lock()
             .get(scope)
             .and_then(|scope_map| scope_map.get(key))
-            .map(|value| match value {
-                OwnedValue::List(l) => {
-                    let start: usize = start.try_into().unwrap_or_else(|_| {
-                        l.len().checked_sub(-start as usize).unwrap_or_default()
-                    });
-
-                    let take: usize = end
-                        .try_into()
-                        .unwrap_or_else(|_| l.len().checked_sub(-end as usize).unwrap_or_default())
-                        .checked_sub(start)
-                        .and_then(|end| end.checked_add(1))
-                        .unwrap_or(0);
-
-                    l.iter().skip(start).take(take).map(|v| v.clone()).collect()
-                }
-                _ => Vec::new(),
+            .map(|value| list_range(value, start, end))
+            .transpose()?;
+        if items.is_some() {
+            self.used(scope, key).await;
+        }
+        Ok(items.unwrap_or_default())
+    }
+
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> Result<Vec<Vec<OwnedValue>>> {
+        let map = self.map.lock();
+        let scope_map = map.get(scope);
+        requests
+            .iter()
+            .map(|(key, start, end)| {
+                scope_map
+                    .and_then(|scope_map| scope_map.get(*key))
+                    .map(|value| list_range(value, *start, *end))
+                    .unwrap_or_else(|| Ok(Vec::new()))
             })
-            .unwrap_or_default())
+            .collect()
     }
 
-    async fn push(&self, scope: &str, key: &[u8], value: Value<'_>) -> Result<()> {
-        let mut lock = self.map.lock();
-        let val = lock
-            .entry(scope.into())
-            .or_default()
-            .entry(key.into())
-            .or_insert_with(|| OwnedValue::List(Vec::new()));
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        {
+            let mut lock = self.map.lock();
+            let val = lock
+                .entry(scope.into())
+                .or_default()
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::List(Vec::new()));
 
-        match val {
-            OwnedValue::List(l) => l.push(value.into_owned()),
-            _ => return Err(BastehError::TypeConversion),
+            match val {
+                OwnedValue::List(l) => l.push(value.into_owned()),
+                _ => return Err(BastehError::TypeConversion),
+            }
         }
 
+        self.pushed.notify_waiters();
+        self.used(scope, key).await;
         Ok(())
     }
 
-    async fn push_multiple(&self, scope: &str, key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
-        let mut lock = self.map.lock();
-        let val = lock
-            .entry(scope.into())
-            .or_default()
-            .entry(key.into())
-            .or_insert_with(|| OwnedValue::List(Vec::new()));
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        {
+            let mut lock = self.map.lock();
+            let val = lock
+                .entry(scope.into())
+                .or_default()
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::List(Vec::new()));
 
-        match val {
-            OwnedValue::List(l) => l.extend(value.into_iter().map(|v| v.into_owned())),
-            _ => return Err(BastehError::TypeConversion),
+            match val {
+                OwnedValue::List(l) => l.extend(value.into_iter().map(|v| v.into_owned())),
+                _ => return Err(BastehError::TypeConversion),
+            }
         }
 
+        self.pushed.notify_waiters();
+        self.used(scope, key).await;
         Ok(())
     }
 
-    async fn pop(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>> {
-        let mut lock = self.map.lock();
-        let val = lock.entry(scope.into()).or_default().get_mut(key.into());
+    async fn push_returning_len(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<u64> {
+        let len = {
+            let mut lock = self.map.lock();
+            let val = lock
+                .entry(scope.into())
+                .or_default()
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::List(Vec::new()));
+
+            match val {
+                OwnedValue::List(l) => {
+                    l.push(value.into_owned());
+                    l.len() as u64
+                }
+                _ => return Err(BastehError::TypeConversion),
+            }
+        };
+
+        self.pushed.notify_waiters();
+        self.used(scope, key).await;
+        Ok(len)
+    }
+
+    async fn push_capped(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        max_len: u64,
+    ) -> Result<()> {
+        let emptied = {
+            let mut lock = self.map.lock();
+            let scope_map = lock.entry(scope.into()).or_default();
+            let val = scope_map
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::List(Vec::new()));
+
+            let emptied = match val {
+                OwnedValue::List(l) => {
+                    l.push(value.into_owned());
+                    let max_len = usize::try_from(max_len).unwrap_or(usize::MAX);
+                    l.drain(..l.len().saturating_sub(max_len));
+                    l.is_empty()
+                }
+                _ => return Err(BastehError::TypeConversion),
+            };
+
+            // Emptied lists are removed, the same as redis
+            if emptied {
+                scope_map.remove(key);
+            }
+            emptied
+        };
 
-        match val {
-            Some(OwnedValue::List(l)) => Ok(l.pop()),
-            _ => Err(BastehError::TypeConversion),
+        if emptied {
+            self.dq_tx
+                .remove(ExpiryKey::new(scope.into(), key.into()))
+                .await
+                .ok();
+        } else {
+            self.pushed.notify_waiters();
+            self.used(scope, key).await;
         }
+        Ok(())
     }
 
-    async fn mutate(&self, scope: &str, key: &[u8], mutations: Mutation) -> Result<i64> {
-        let mut guard = self.map.lock();
-        let scope_map = guard.entry(scope.into()).or_default();
+    async fn mutate_list(&self, scope: &[u8], key: &[u8], mutations: ListMutation) -> Result<u64> {
+        let len = {
+            let mut lock = self.map.lock();
+            let scope_map = lock.entry(scope.into()).or_default();
+            let val = scope_map
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::List(Vec::new()));
 
-        let value = if let Some(val) = scope_map.get(key) {
-            let num = match val {
-                OwnedValue::Number(n) => *n,
-                _ => return Err(BastehError::InvalidNumber),
+            let len = match val {
+                OwnedValue::List(l) => {
+                    run_list_mutations(l, &mutations);
+                    l.len()
+                }
+                _ => return Err(BastehError::TypeConversion),
             };
-            num
+
+            // Emptied lists are removed, the same as redis
+            if len == 0 {
+                scope_map.remove(key);
+            }
+            len
+        };
+
+        if len == 0 {
+            self.dq_tx
+                .remove(ExpiryKey::new(scope.into(), key.into()))
+                .await
+                .ok();
         } else {
-            0
+            self.pushed.notify_waiters();
+            self.used(scope, key).await;
+        }
+        Ok(len as u64)
+    }
+
+    async fn hset(&self, scope: &[u8], key: &[u8], field: &[u8], value: Value<'_>) -> Result<()> {
+        {
+            let mut lock = self.map.lock();
+            let val = lock
+                .entry(scope.into())
+                .or_default()
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::Map(Vec::new()));
+
+            let map = match val {
+                OwnedValue::Map(map) => map,
+                _ => return Err(BastehError::TypeConversion),
+            };
+            let value = value.into_owned();
+            match map.iter_mut().find(|(f, _)| f == field) {
+                Some((_, v)) => *v = value,
+                None => map.push((field.to_vec(), value)),
+            }
+        }
+
+        self.used(scope, key).await;
+        Ok(())
+    }
+
+    async fn hincr(&self, scope: &[u8], key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        let result = {
+            let mut lock = self.map.lock();
+            let val = lock
+                .entry(scope.into())
+                .or_default()
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::Map(Vec::new()));
+
+            let map = match val {
+                OwnedValue::Map(map) => map,
+                _ => return Err(BastehError::TypeConversion),
+            };
+            match map.iter_mut().find(|(f, _)| f == field) {
+                Some((_, v)) => {
+                    let result = add_number(Some(v.as_value()), delta)?;
+                    *v = OwnedValue::Number(result);
+                    result
+                }
+                None => {
+                    map.push((field.to_vec(), OwnedValue::Number(delta)));
+                    delta
+                }
+            }
+        };
+
+        self.used(scope, key).await;
+        Ok(result)
+    }
+
+    async fn set_bit(&self, scope: &[u8], key: &[u8], offset: u64, value: bool) -> Result<bool> {
+        let old = {
+            let mut lock = self.map.lock();
+            let val = lock
+                .entry(scope.into())
+                .or_default()
+                .entry(key.into())
+                .or_insert_with(|| OwnedValue::Bytes(Default::default()));
+
+            match val {
+                OwnedValue::Bytes(bytes) => write_bit(bytes, offset, value),
+                _ => return Err(BastehError::TypeConversion),
+            }
+        };
+
+        self.used(scope, key).await;
+        Ok(old)
+    }
+
+    async fn hget(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = match self
+            .map
+            .lock()
+            .get(scope)
+            .and_then(|scope_map| scope_map.get(key))
+        {
+            Some(OwnedValue::Map(map)) => {
+                map.iter().find(|(f, _)| f == field).map(|(_, v)| v.clone())
+            }
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => return Ok(None),
+        };
+
+        self.used(scope, key).await;
+        Ok(value)
+    }
+
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<bool> {
+        {
+            let mut lock = self.map.lock();
+            let scope_map = match lock.get_mut(scope) {
+                Some(scope_map) => scope_map,
+                None => return Ok(false),
+            };
+
+            match scope_map.get_mut(key) {
+                Some(OwnedValue::Map(map)) => {
+                    let len = map.len();
+                    map.retain(|(f, _)| f != field);
+                    if map.len() == len {
+                        return Ok(false);
+                    } else if !map.is_empty() {
+                        return Ok(true);
+                    }
+                }
+                Some(_) => return Err(BastehError::TypeConversion),
+                None => return Ok(false),
+            };
+
+            // Emptied maps are removed, the same as redis
+            scope_map.remove(key);
+        }
+
+        self.dq_tx
+            .remove(ExpiryKey::new(scope.into(), key.into()))
+            .await
+            .ok();
+        Ok(true)
+    }
+
+    async fn hgetall(&self, scope: &[u8], key: &[u8]) -> Result<Vec<(Vec<u8>, OwnedValue)>> {
+        let map = match self
+            .map
+            .lock()
+            .get(scope)
+            .and_then(|scope_map| scope_map.get(key))
+        {
+            Some(OwnedValue::Map(map)) => map.clone(),
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => return Ok(Vec::new()),
+        };
+
+        self.used(scope, key).await;
+        Ok(map)
+    }
+
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = {
+            let mut lock = self.map.lock();
+            let scope_map = match lock.get_mut(scope) {
+                Some(scope_map) => scope_map,
+                None => return Ok(None),
+            };
+
+            let value = match scope_map.get_mut(key) {
+                Some(OwnedValue::List(l)) => match l.pop() {
+                    Some(value) if l.is_empty() => value,
+                    value => return Ok(value),
+                },
+                Some(_) => return Err(BastehError::TypeConversion),
+                None => return Ok(None),
+            };
+
+            // Emptied lists are removed, the same as redis
+            scope_map.remove(key);
+            value
         };
 
-        let value = run_mutations(value, mutations);
+        self.dq_tx
+            .remove(ExpiryKey::new(scope.into(), key.into()))
+            .await
+            .ok();
+        Ok(Some(value))
+    }
+
+    async fn pop_blocking(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        timeout: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // Created before popping, so the pushes in between are not missed
+            let pushed = self.pushed.notified();
+            if let Some(value) = self.pop(scope, key).await? {
+                return Ok(Some(value));
+            }
+            if tokio::time::timeout_at(deadline, pushed).await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+
+    async fn pop_push(
+        &self,
+        src_scope: &[u8],
+        src_key: &[u8],
+        dst_scope: &[u8],
+        dst_key: &[u8],
+    ) -> Result<Option<OwnedValue>> {
+        // Rotating a single item list in place keeps the key
+        let same_key = src_scope == dst_scope && src_key == dst_key;
+
+        let (value, removed) = {
+            let mut map = self.map.lock();
+
+            // Check the destination first, so nothing is popped if it's not a list
+            if let Some(dst) = map
+                .get(dst_scope)
+                .and_then(|scope_map| scope_map.get(dst_key))
+            {
+                if !matches!(dst, OwnedValue::List(_)) {
+                    return Err(BastehError::TypeConversion);
+                }
+            }
+
+            let (value, emptied) = match map
+                .get_mut(src_scope)
+                .and_then(|scope_map| scope_map.get_mut(src_key))
+            {
+                Some(OwnedValue::List(l)) => match l.pop() {
+                    Some(value) => (value, l.is_empty()),
+                    None => return Ok(None),
+                },
+                Some(_) => return Err(BastehError::TypeConversion),
+                None => return Ok(None),
+            };
+
+            // Emptied lists are removed, the same as redis
+            let removed = emptied && !same_key;
+            if removed {
+                if let Some(scope_map) = map.get_mut(src_scope) {
+                    scope_map.remove(src_key);
+                }
+            }
+
+            match map
+                .entry(dst_scope.into())
+                .or_default()
+                .entry(dst_key.into())
+                .or_insert_with(|| OwnedValue::List(Vec::new()))
+            {
+                OwnedValue::List(l) => l.push(value.clone()),
+                _ => unreachable!(),
+            }
+
+            (value, removed)
+        };
+        self.pushed.notify_waiters();
+        self.used(dst_scope, dst_key).await;
+
+        if removed {
+            self.dq_tx
+                .remove(ExpiryKey::new(src_scope.into(), src_key.into()))
+                .await
+                .ok();
+        }
+        Ok(Some(value))
+    }
+
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        self.mutate_returning(scope, key, mutations)
+            .await
+            .map(|(_, value)| value)
+    }
+
+    async fn mutate_returning(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: Mutation,
+    ) -> Result<(i64, i64)> {
+        let res = {
+            let mut guard = self.map.lock();
+            let scope_map = guard.entry(scope.into()).or_default();
 
-        if let Some(value) = value {
+            let value = if let Some(val) = scope_map.get(key) {
+                let num = match val {
+                    OwnedValue::Number(n) => *n,
+                    _ => return Err(BastehError::NotANumber(val.kind())),
+                };
+                num
+            } else {
+                0
+            };
+
+            let old = value;
+            let value = run_mutations(value, mutations).ok_or(BastehError::InvalidNumber)?;
             scope_map.insert(key.into(), OwnedValue::Number(value));
-            Ok(value)
-        } else {
-            Err(BastehError::InvalidNumber)
+            (old, value)
+        };
+
+        self.used(scope, key).await;
+        Ok(res)
+    }
+
+    async fn incr_float(&self, scope: &[u8], key: &[u8], delta: f64) -> Result<f64> {
+        let value = {
+            let mut guard = self.map.lock();
+            let scope_map = guard.entry(scope.into()).or_default();
+
+            let value = add_float(scope_map.get(key).map(|v| v.as_value()), delta)?;
+            scope_map.insert(key.into(), OwnedValue::String(value.to_string()));
+            value
+        };
+
+        self.used(scope, key).await;
+        Ok(value)
+    }
+
+    async fn incr_big(&self, scope: &[u8], key: &[u8], delta: i128) -> Result<i128> {
+        let value = {
+            let mut guard = self.map.lock();
+            let scope_map = guard.entry(scope.into()).or_default();
+
+            let value = add_big(scope_map.get(key).map(|v| v.as_value()), delta)?;
+            scope_map.insert(key.into(), OwnedValue::from(value));
+            value
+        };
+
+        self.used(scope, key).await;
+        Ok(value)
+    }
+
+    async fn set_if(&self, scope: &[u8], key: &[u8], value: i64, ord: Ordering) -> Result<bool> {
+        let set = {
+            let mut guard = self.map.lock();
+            let scope_map = guard.entry(scope.into()).or_default();
+
+            let set = should_set(scope_map.get(key).map(|v| v.as_value()), value, ord)?;
+            if set {
+                scope_map.insert(key.into(), OwnedValue::Number(value));
+            }
+            set
+        };
+
+        if set {
+            self.used(scope, key).await;
         }
+        Ok(set)
+    }
+
+    async fn list_drain(&self, scope: &[u8], key: &[u8]) -> Result<Vec<OwnedValue>> {
+        let items = {
+            let mut map = self.map.lock();
+            let scope_map = match map.get_mut(scope) {
+                Some(scope_map) => scope_map,
+                None => return Ok(Vec::new()),
+            };
+
+            match scope_map.get(key) {
+                Some(OwnedValue::List(_)) => match scope_map.remove(key) {
+                    Some(OwnedValue::List(l)) => l,
+                    _ => unreachable!(),
+                },
+                Some(_) => return Err(BastehError::TypeConversion),
+                None => return Ok(Vec::new()),
+            }
+        };
+
+        self.dq_tx
+            .remove(ExpiryKey::new(scope.into(), key.into()))
+            .await
+            .ok();
+
+        Ok(items)
     }
 
-    async fn remove(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>> {
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
         let value = self
             .map
             .lock()
@@ -228,7 +846,45 @@ impl Provider for MemoryBackend {
         Ok(value)
     }
 
-    async fn contains_key(&self, scope: &str, key: &[u8]) -> Result<bool> {
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let deleted = self
+            .map
+            .lock()
+            .get_mut(scope)
+            .is_some_and(|scope_map| scope_map.remove(key).is_some());
+
+        if deleted {
+            self.dq_tx
+                .remove(ExpiryKey::new(scope.into(), key.into()))
+                .await
+                .ok();
+        }
+
+        Ok(deleted)
+    }
+
+    async fn remove_if(&self, scope: &[u8], key: &[u8], expected: Value<'_>) -> Result<bool> {
+        let removed = {
+            let mut map = self.map.lock();
+            match map.get_mut(scope) {
+                Some(scope_map) if scope_map.get(key).map(|v| v.as_value()) == Some(expected) => {
+                    scope_map.remove(key).is_some()
+                }
+                _ => false,
+            }
+        };
+
+        if removed {
+            self.dq_tx
+                .remove(ExpiryKey::new(scope.into(), key.into()))
+                .await
+                .ok();
+        }
+
+        Ok(removed)
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
         Ok(self
             .map
             .lock()
@@ -237,28 +893,49 @@ impl Provider for MemoryBackend {
             .unwrap_or(false))
     }
 
-    async fn persist(&self, scope: &str, key: &[u8]) -> Result<()> {
+    async fn touch(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let exists = self.contains_key(scope, key).await?;
+        if exists {
+            self.used(scope, key).await;
+        }
+        Ok(exists)
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> Result<Vec<bool>> {
+        let map = self.map.lock();
+        let scope_map = map.get(scope);
+        Ok(keys
+            .iter()
+            .map(|key| {
+                scope_map
+                    .map(|scope_map| scope_map.contains_key(*key))
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
         self.dq_tx
             .remove(ExpiryKey::new(scope.into(), key.into()))
             .await
             .map_err(BastehError::custom)
     }
 
-    async fn expire(&self, scope: &str, key: &[u8], expire_in: Duration) -> Result<()> {
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
         self.dq_tx
             .insert_or_update(ExpiryKey::new(scope.into(), key.into()), expire_in)
             .await
             .map_err(BastehError::custom)
     }
 
-    async fn expiry(&self, scope: &str, key: &[u8]) -> Result<Option<Duration>> {
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
         self.dq_tx
             .get(ExpiryKey::new(scope.into(), key.into()))
             .await
             .map_err(BastehError::custom)
     }
 
-    async fn extend(&self, scope: &str, key: &[u8], duration: Duration) -> Result<()> {
+    async fn extend(&self, scope: &[u8], key: &[u8], duration: Duration) -> Result<()> {
         self.dq_tx
             .extend(ExpiryKey::new(scope.into(), key.into()), duration)
             .await
@@ -267,12 +944,12 @@ impl Provider for MemoryBackend {
 
     async fn set_expiring(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         value: Value<'_>,
         expire_in: Duration,
     ) -> Result<()> {
-        let scope: Arc<str> = scope.into();
+        let scope: Arc<[u8]> = scope.into();
         let key: Arc<[u8]> = key.into();
 
         self.map
@@ -280,15 +957,121 @@ impl Provider for MemoryBackend {
             .entry(scope.clone())
             .or_default()
             .insert(key.clone(), value.to_owned().into());
+        self.used(&scope, &key).await;
         self.dq_tx
             .insert_or_update(ExpiryKey::new(scope, key), expire_in)
             .await
             .map_err(|e| BastehError::custom(e))
     }
 
+    async fn swap_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        let scope: Arc<[u8]> = scope.into();
+        let key: Arc<[u8]> = key.into();
+
+        let old = self
+            .map
+            .lock()
+            .entry(scope.clone())
+            .or_default()
+            .insert(key.clone(), value.into_owned());
+        self.used(&scope, &key).await;
+        self.dq_tx
+            .insert_or_update(ExpiryKey::new(scope, key), expire_in)
+            .await
+            .map_err(BastehError::custom)?;
+        Ok(old)
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        delta: i64,
+        ttl: Duration,
+    ) -> Result<i64> {
+        let scope: Arc<[u8]> = scope.into();
+        let key: Arc<[u8]> = key.into();
+
+        let (value, created) = {
+            let mut guard = self.map.lock();
+            let scope_map = guard.entry(scope.clone()).or_default();
+
+            let (current, created) = match scope_map.get(key.as_ref()) {
+                Some(OwnedValue::Number(n)) => (*n, false),
+                Some(val) => return Err(BastehError::NotANumber(val.kind())),
+                None => (0, true),
+            };
+            let value = current
+                .checked_add(delta)
+                .ok_or(BastehError::InvalidNumber)?;
+            scope_map.insert(key.clone(), OwnedValue::Number(value));
+            (value, created)
+        };
+        self.used(&scope, &key).await;
+
+        // Expired keys are already removed from the map, so only the persistent ones are left
+        let exp_key = ExpiryKey::new(scope, key);
+        if created
+            || self
+                .dq_tx
+                .get(exp_key.clone())
+                .await
+                .map_err(BastehError::custom)?
+                .is_none()
+        {
+            self.dq_tx
+                .insert_or_update(exp_key, ttl)
+                .await
+                .map_err(BastehError::custom)?;
+        }
+        Ok(value)
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> Result<bool> {
+        let scope: Arc<[u8]> = scope.into();
+        let key: Arc<[u8]> = key.into();
+
+        let replaced = {
+            let mut map = self.map.lock();
+            let mut versions = self.versions.lock();
+
+            let scope_versions = versions.entry(scope.clone()).or_default();
+            if matches!(scope_versions.get(&key), Some(current) if *current >= version) {
+                return Ok(false);
+            }
+            scope_versions.insert(key.clone(), version);
+
+            map.entry(scope.clone())
+                .or_default()
+                .insert(key.clone(), value.into_owned())
+                .is_some()
+        };
+        self.used(&scope, &key).await;
+
+        if replaced {
+            self.dq_tx
+                .remove(ExpiryKey::new(scope, key))
+                .await
+                .map_err(BastehError::custom)?;
+        }
+        Ok(true)
+    }
+
     async fn get_expiring(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
     ) -> Result<Option<(OwnedValue, Option<Duration>)>> {
         let val = self
@@ -298,6 +1081,7 @@ impl Provider for MemoryBackend {
             .and_then(|scope_map| scope_map.get(key))
             .cloned();
         if let Some(val) = val {
+            self.used(scope, key).await;
             let exp = self
                 .dq_tx
                 .get(ExpiryKey::new(scope.into(), key.into()))
@@ -308,18 +1092,237 @@ impl Provider for MemoryBackend {
             Ok(None)
         }
     }
+
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        // The values are read under a single lock, the expiries are asked from the queue after
+        let values = {
+            let map = self.map.lock();
+            let scope_map = map.get(scope);
+            keys.iter()
+                .map(|key| scope_map.and_then(|scope_map| scope_map.get(*key)).cloned())
+                .collect::<Vec<_>>()
+        };
+
+        let mut res = Vec::with_capacity(keys.len());
+        for (key, val) in keys.iter().zip(values) {
+            res.push(match val {
+                Some(val) => {
+                    let exp = self
+                        .dq_tx
+                        .get(ExpiryKey::new(scope.into(), (*key).into()))
+                        .await
+                        .map_err(BastehError::custom)?;
+                    Some((val, exp))
+                }
+                None => None,
+            });
+        }
+        Ok(res)
+    }
+
+    async fn snapshot(&self, scope: &[u8]) -> Result<Vec<SnapshotEntry>> {
+        // Same as get_expiring_multiple, the values are copied under a single lock
+        let entries = match self.map.lock().get(scope) {
+            Some(scope_map) => scope_map
+                .iter()
+                .map(|(key, val)| (key.clone(), val.clone()))
+                .collect::<Vec<_>>(),
+            None => return Ok(Vec::new()),
+        };
+
+        let scope: Arc<[u8]> = scope.into();
+        let mut res = Vec::with_capacity(entries.len());
+        for (key, val) in entries {
+            let exp = self
+                .dq_tx
+                .get(ExpiryKey::new(scope.clone(), key.clone()))
+                .await
+                .map_err(BastehError::custom)?;
+            res.push((key.to_vec(), val, exp));
+        }
+        Ok(res)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use basteh::test_utils::*;
+    use basteh::{test_utils::*, Basteh};
 
     #[tokio::test]
     async fn test_hashmap_store() {
         test_store(MemoryBackend::start_default()).await;
     }
 
+    #[tokio::test]
+    async fn test_hashmap_default_scope() {
+        let provider = MemoryBackend::start_default();
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .default_scope("app")
+            .finish();
+        let global = Basteh::build().provider(provider).finish();
+
+        store.set("key", "scoped").await.unwrap();
+        assert_eq!(
+            store.scope("app").get::<String>("key").await.unwrap(),
+            Some("scoped".to_owned())
+        );
+        assert!(!global.contains_key("key").await.unwrap());
+
+        global.set("key", "global").await.unwrap();
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("scoped".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_max_keys() {
+        let store = Basteh::build()
+            .provider(MemoryBackend::start_default().with_max_keys(3))
+            .finish();
+
+        store.set("key1", 1).await.unwrap();
+        store.set("key2", 2).await.unwrap();
+        store.set("key3", 3).await.unwrap();
+
+        // Touching the oldest key makes key2 the least recently used one
+        assert!(store.touch("key1").await.unwrap());
+        store.set("key4", 4).await.unwrap();
+        assert!(store.contains_key("key1").await.unwrap());
+        assert!(!store.contains_key("key2").await.unwrap());
+        assert!(store.contains_key("key3").await.unwrap());
+        assert!(store.contains_key("key4").await.unwrap());
+
+        // Reads count as a use too, and the limit is shared between the scopes
+        assert_eq!(store.get::<i64>("key3").await.unwrap(), Some(3));
+        store.scope("other").set("key5", 5).await.unwrap();
+        assert!(!store.contains_key("key1").await.unwrap());
+        assert!(store.contains_key("key3").await.unwrap());
+        assert!(store.contains_key("key4").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_max_keys_store() {
+        test_store(MemoryBackend::start_default().with_max_keys(10_000)).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_max_value_size() {
+        let store = Basteh::build()
+            .provider(MemoryBackend::start_default())
+            .max_value_size(8)
+            .finish();
+
+        store.set("small", "12345678").await.unwrap();
+        assert!(matches!(
+            store.set("big", "123456789").await,
+            Err(BastehError::ValueTooLarge { size: 9, max: 8 })
+        ));
+        assert!(matches!(
+            store
+                .set_expiring("big", "123456789", Duration::from_secs(10))
+                .await,
+            Err(BastehError::ValueTooLarge { .. })
+        ));
+        assert!(!store.contains_key("big").await.unwrap());
+
+        store.set_list("list", ["1234", "5678"]).await.unwrap();
+        assert!(matches!(
+            store.set_list("big_list", ["1234", "56789"]).await,
+            Err(BastehError::ValueTooLarge { size: 9, max: 8 })
+        ));
+        store.push("list", "12345678").await.unwrap();
+        assert!(matches!(
+            store.push("list", "123456789").await,
+            Err(BastehError::ValueTooLarge { .. })
+        ));
+        assert!(matches!(
+            store.extend_list("list", [1, 2]).await,
+            Err(BastehError::ValueTooLarge { size: 16, max: 8 })
+        ));
+        assert_eq!(
+            store
+                .get_range::<String>("list", 0, -1)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_max_key_size() {
+        let store = Basteh::build()
+            .provider(MemoryBackend::start_default())
+            .max_key_size(8)
+            .finish();
+
+        store.set("12345678", "value").await.unwrap();
+        assert_eq!(
+            store.get::<String>("12345678").await.unwrap(),
+            Some("value".to_owned())
+        );
+        assert!(matches!(
+            store.set("123456789", "value").await,
+            Err(BastehError::KeyTooLarge { size: 9, max: 8 })
+        ));
+        assert!(matches!(
+            store.get::<String>("123456789").await,
+            Err(BastehError::KeyTooLarge { .. })
+        ));
+        assert!(matches!(
+            store.contains_all(["key", "123456789"]).await,
+            Err(BastehError::KeyTooLarge { .. })
+        ));
+        assert!(matches!(
+            store.bloom("123456789").add("item").await,
+            Err(BastehError::KeyTooLarge { .. })
+        ));
+
+        // The limit applies to the normalized keys
+        let prefixed = store.with_key_normalizer(|key| [b"app:", key].concat());
+        prefixed.set("1234", "value").await.unwrap();
+        assert!(matches!(
+            prefixed.set("12345", "value").await,
+            Err(BastehError::KeyTooLarge { size: 9, max: 8 })
+        ));
+        assert_eq!(
+            store.get::<String>("app:1234").await.unwrap(),
+            Some("value".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_binary_scope() {
+        test_binary_scope(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_nested_list() {
+        test_nested_list(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_size_bytes() {
+        test_size_bytes(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_drop_scope() {
+        test_drop_scope(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_scopes() {
+        test_scopes(MemoryBackend::start_default()).await;
+    }
+
     #[tokio::test]
     async fn test_hashmap_mutations() {
         test_mutations(MemoryBackend::start_default()).await;
@@ -330,8 +1333,135 @@ mod tests {
         test_expiry(MemoryBackend::start_default(), 2).await;
     }
 
+    #[tokio::test]
+    async fn test_hashmap_conformance() {
+        conformance(MemoryBackend::start_default(), 2).await;
+    }
+
     #[tokio::test]
     async fn test_hashmap_expiry_store() {
         test_expiry_store(MemoryBackend::start_default(), 2).await;
     }
+
+    #[tokio::test]
+    async fn test_hashmap_session_store() {
+        test_session_store(MemoryBackend::start_default(), 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_layered() {
+        test_layered(
+            MemoryBackend::start_default(),
+            MemoryBackend::start_default(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_layered_preload() {
+        test_layered_preload(
+            MemoryBackend::start_default(),
+            MemoryBackend::start_default(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_layered_negative_ttl() {
+        test_layered_negative_ttl(
+            MemoryBackend::start_default(),
+            MemoryBackend::start_default(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_write_behind() {
+        test_write_behind(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_write_behind_concurrent_flush() {
+        test_write_behind_concurrent_flush(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_codecs() {
+        test_codecs(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_json_values() {
+        test_json_values(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_compression() {
+        test_compression(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_timeout() {
+        test_timeout(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_actix_web_extractor() {
+        test_actix_web_extractor(MemoryBackend::start_default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hashmap_axum_extractor() {
+        use axum::{
+            body::{to_bytes, Body},
+            extract::Path,
+            http::Request,
+            routing::{get, put},
+            Router,
+        };
+        use basteh::Basteh;
+        use tower::ServiceExt;
+
+        async fn get_key(Path(key): Path<String>, store: Basteh) -> String {
+            store.get::<String>(&key).await.unwrap().unwrap_or_default()
+        }
+
+        async fn set_key(Path((key, value)): Path<(String, String)>, store: Basteh) {
+            store.set(&key, value).await.unwrap();
+        }
+
+        async fn call(app: &Router, method: &str, uri: &str) -> String {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert!(res.status().is_success());
+            let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        }
+
+        let store = Basteh::build()
+            .provider(MemoryBackend::start_default())
+            .finish();
+        let app = Router::new()
+            .route("/{key}", get(get_key))
+            .route("/{key}/{value}", put(set_key))
+            .with_state(store.clone());
+
+        assert_eq!(call(&app, "GET", "/axum_key").await, "");
+        call(&app, "PUT", "/axum_key/axum_value").await;
+        assert_eq!(call(&app, "GET", "/axum_key").await, "axum_value");
+        assert_eq!(
+            store.get::<String>("axum_key").await.unwrap(),
+            Some("axum_value".to_owned())
+        );
+
+        // A store in the extensions takes precedence over the state
+        let scoped = store.scope("axum_scope");
+        scoped.set("axum_key", "scoped_value").await.unwrap();
+        let app = app.layer(axum::Extension(scoped));
+        assert_eq!(call(&app, "GET", "/axum_key").await, "scoped_value");
+    }
 }
@@ -0,0 +1,12 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// How long compaction waits for the running operations to finish before giving up
+pub(crate) const COMPACTION_WAIT: Duration = Duration::from_secs(1);
+
+/// Returned by [`RedbBackend::compact`](crate::RedbBackend::compact) if other operations kept
+/// the database busy, it's safe to try again later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Database is busy, compaction needs exclusive access to it")]
+pub struct CompactionBusy;
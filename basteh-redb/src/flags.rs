@@ -3,20 +3,23 @@ use std::{
     time::{Duration, Instant, SystemTime},
 };
 
+use basteh::dev::{Clock, SystemClock};
 use redb::TypeName;
 
 pub(crate) fn get_current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
+    SystemClock.timestamp()
 }
 
-/// Represent the expiration timestamp, we reserve 4 words but use only one of them for now
+// Set in the checksum word along with the checksum, so a zero checksum is told apart from none
+const CHECKSUM_PRESENT: u64 = 1 << 32;
+
+/// Represent the expiration timestamp, the time the value was last written(in milliseconds, zero
+/// if it's not tracked) and the checksum of the value(zero if it's not tracked), we reserve 4
+/// words but use only three of them for now
 /// TODO: What if SystemTime changes?
 #[derive(Debug, Default, Clone, Copy)]
 #[repr(C)]
-pub struct ExpiryFlags(u64);
+pub struct ExpiryFlags(u64, u64, u64);
 
 impl redb::RedbValue for ExpiryFlags {
     type SelfType<'a> = ExpiryFlags;
@@ -31,7 +34,11 @@ impl redb::RedbValue for ExpiryFlags {
     where
         Self: 'a,
     {
-        Self(u64::from_be_bytes(data[0..8].try_into().unwrap()))
+        Self(
+            u64::from_be_bytes(data[0..8].try_into().unwrap()),
+            u64::from_be_bytes(data[8..16].try_into().unwrap()),
+            u64::from_be_bytes(data[16..24].try_into().unwrap()),
+        )
     }
 
     fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
@@ -41,6 +48,8 @@ impl redb::RedbValue for ExpiryFlags {
     {
         let mut arr = [0_u8; 32];
         arr[0..8].copy_from_slice(&value.0.to_be_bytes());
+        arr[8..16].copy_from_slice(&value.1.to_be_bytes());
+        arr[16..24].copy_from_slice(&value.2.to_be_bytes());
         arr
     }
 
@@ -52,26 +61,45 @@ impl redb::RedbValue for ExpiryFlags {
 impl ExpiryFlags {
     /// Make a new flags struct with persist flag set to true. Provide 0 for nonce if it's a new key.
     pub fn new_persist() -> Self {
-        Self(0)
+        Self(0, 0, 0)
     }
 
     /// Make a new flags struct with persist flag set to false. Provide 0 for nonce if it's a new key.
     pub fn new_expiring(expires_in: Duration) -> Self {
-        let expires_at = get_current_timestamp() + expires_in.as_secs();
-        Self(expires_at)
+        Self::new_expiring_with_clock(expires_in, &SystemClock)
+    }
+
+    /// Same as [`new_expiring`](Self::new_expiring), reading the current time from `clock`
+    pub fn new_expiring_with_clock(expires_in: Duration, clock: &dyn Clock) -> Self {
+        Self(clock.timestamp() + expires_in.as_secs(), 0, 0)
+    }
+
+    /// Remove the expiration time, keeping the write time and the checksum
+    pub fn persist(&mut self) {
+        self.0 = 0;
     }
 
     /// Change the expiration time
     pub fn expire_in(&mut self, duration: Duration) {
-        self.0 = get_current_timestamp() + duration.as_secs()
+        self.expire_in_with_clock(duration, &SystemClock)
+    }
+
+    /// Same as [`expire_in`](Self::expire_in), reading the current time from `clock`
+    pub fn expire_in_with_clock(&mut self, duration: Duration, clock: &dyn Clock) {
+        self.0 = clock.timestamp() + duration.as_secs()
     }
 
     /// Get the expiration time, returns None if persist flag is true.
     pub fn expires_in(&self) -> Option<Duration> {
+        self.expires_in_with_clock(&SystemClock)
+    }
+
+    /// Same as [`expires_in`](Self::expires_in), reading the current time from `clock`
+    pub fn expires_in_with_clock(&self, clock: &dyn Clock) -> Option<Duration> {
         if self.0 == 0 {
             return None;
         }
-        let now = get_current_timestamp();
+        let now = clock.timestamp();
         if self.0 <= now {
             Some(Duration::default())
         } else {
@@ -92,15 +120,64 @@ impl ExpiryFlags {
         }
     }
 
+    /// Get the absolute expiration time, returns None if persist flag is true.
+    pub fn expires_at_systemtime(&self) -> Option<SystemTime> {
+        if self.0 == 0 {
+            return None;
+        }
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(self.0))
+    }
+
+    /// Set the write time to now
+    pub fn mark_written(&mut self) {
+        self.mark_written_with_clock(&SystemClock)
+    }
+
+    /// Same as [`mark_written`](Self::mark_written), reading the current time from `clock`
+    pub fn mark_written_with_clock(&mut self, clock: &dyn Clock) {
+        self.1 = clock
+            .now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+    }
+
+    /// Get the last time the value was written, returns None if it's not tracked.
+    pub fn written_at(&self) -> Option<SystemTime> {
+        if self.1 == 0 {
+            return None;
+        }
+        Some(SystemTime::UNIX_EPOCH + Duration::from_millis(self.1))
+    }
+
+    /// Set the checksum of the value
+    pub fn set_checksum(&mut self, checksum: u32) {
+        self.2 = CHECKSUM_PRESENT | u64::from(checksum);
+    }
+
+    /// Get the checksum of the value, returns None if it's not tracked.
+    pub fn checksum(&self) -> Option<u32> {
+        if self.2 & CHECKSUM_PRESENT == 0 {
+            return None;
+        }
+        Some(self.2 as u32)
+    }
+
     /// Check if the key is expired
     pub fn expired(&self) -> bool {
-        self.0 != 0 && self.0 <= get_current_timestamp()
+        self.expired_with_clock(&SystemClock)
+    }
+
+    /// Same as [`expired`](Self::expired), reading the current time from `clock`
+    pub fn expired_with_clock(&self, clock: &dyn Clock) -> bool {
+        self.0 != 0 && self.0 <= clock.timestamp()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use basteh::dev::MockClock;
 
     #[test]
     fn test_persist_flag() {
@@ -138,4 +215,78 @@ mod tests {
         assert!(expires_in.unwrap().as_millis() <= 2000);
         assert!(expires_in.unwrap().as_millis() >= 1000);
     }
+
+    #[test]
+    fn test_expires_at_systemtime() {
+        let flags = ExpiryFlags::new_persist();
+        assert_eq!(flags.expires_at_systemtime(), None);
+
+        let flags = ExpiryFlags::new_expiring(Duration::from_secs(100));
+        let expected = SystemTime::now() + Duration::from_secs(100);
+        let expires_at = flags.expires_at_systemtime().unwrap();
+
+        // We only store seconds, so it may be up to a second earlier
+        assert!(expires_at <= expected);
+        assert!(expires_at + Duration::from_secs(2) > expected);
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::default();
+        let mut flags = ExpiryFlags::new_expiring_with_clock(Duration::from_secs(60), &clock);
+        assert!(!flags.expired_with_clock(&clock));
+        assert_eq!(
+            flags.expires_in_with_clock(&clock),
+            Some(Duration::from_secs(60))
+        );
+
+        clock.advance(Duration::from_secs(59));
+        assert!(!flags.expired_with_clock(&clock));
+        clock.advance(Duration::from_secs(1));
+        assert!(flags.expired_with_clock(&clock));
+
+        flags.expire_in_with_clock(Duration::from_secs(10), &clock);
+        assert!(!flags.expired_with_clock(&clock));
+        clock.advance(Duration::from_secs(10));
+        assert!(flags.expired_with_clock(&clock));
+    }
+
+    #[test]
+    fn test_written_at() {
+        // Only milliseconds are stored, so the clock starts at a whole one
+        let clock =
+            MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123));
+        let mut flags = ExpiryFlags::new_expiring_with_clock(Duration::from_secs(60), &clock);
+        assert_eq!(flags.written_at(), None);
+
+        flags.mark_written_with_clock(&clock);
+        assert_eq!(flags.written_at(), Some(clock.now()));
+
+        // Changing the expiry keeps the write time
+        flags.persist();
+        assert_eq!(flags.expires_in(), None);
+        assert_eq!(flags.written_at(), Some(clock.now()));
+
+        let bytes = <ExpiryFlags as redb::RedbValue>::as_bytes(&flags);
+        let decoded = <ExpiryFlags as redb::RedbValue>::from_bytes(&bytes);
+        assert_eq!(decoded.written_at(), Some(clock.now()));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let mut flags = ExpiryFlags::new_persist();
+        assert_eq!(flags.checksum(), None);
+
+        flags.set_checksum(0);
+        assert_eq!(flags.checksum(), Some(0));
+
+        // Changing the expiry keeps the checksum
+        flags.set_checksum(u32::MAX);
+        flags.expire_in(Duration::from_secs(10));
+        assert_eq!(flags.checksum(), Some(u32::MAX));
+
+        let bytes = <ExpiryFlags as redb::RedbValue>::as_bytes(&flags);
+        let decoded = <ExpiryFlags as redb::RedbValue>::from_bytes(&bytes);
+        assert_eq!(decoded.checksum(), Some(u32::MAX));
+    }
 }
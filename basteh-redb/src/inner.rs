@@ -1,22 +1,36 @@
 use std::{
-    convert::TryInto,
+    cmp::Ordering,
+    convert::TryFrom,
+    ops::Bound,
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use basteh::{
-    dev::{Action, Mutation, OwnedValue},
+    dev::{
+        add_big, add_float, add_number, resolve_range, run_list_mutations, should_set, write_bit,
+        Action, ListMutation, Mutation, OwnedValue, SnapshotEntry, ValueKind,
+    },
     BastehError,
 };
+use parking_lot::RwLock;
 use redb::{
-    Error, ReadableTable, StorageError, TableDefinition, TableError, TableHandle, TypeName,
+    Error, ReadableTable, RedbValue, StorageError, Table, TableDefinition, TableError, TableHandle,
+    WriteTransaction,
 };
 
 use crate::{
+    compaction::{CompactionBusy, COMPACTION_WAIT},
     delayqueue::DelayQueue,
     flags::ExpiryFlags,
+    format::{
+        FormatVersionError, SelfTestError, CHECKSUMS_KEY, EXPIRY_TABLE_SUFFIX_KEY, FORMAT_VERSION,
+        FORMAT_VERSION_KEY, LEGACY_FORMAT_VERSION, METADATA_TABLE, SELF_TEST_KEY, SETTINGS_TABLE,
+    },
     message::{Message, Request, Response},
-    value::OwnedValueWrapper,
+    runtime,
+    stats::TableStat,
+    value::{OwnedValueWrapper, RawValueWrapper, ValueKindWrapper},
 };
 
 macro_rules! table_def {
@@ -36,26 +50,295 @@ macro_rules! exp_table_def {
     };
 }
 
+macro_rules! ver_table_def {
+    ($var_name:ident, $name:expr) => {
+        let $var_name = {
+            let mut __name = String::from($name);
+            __name.push_str(VERSIONS_TABLE_SUFFIX);
+            __name
+        };
+        let $var_name = TableDefinition::<&[u8], u64>::new(&$var_name);
+    };
+}
+
+// Postfix of the tables keeping the versions of the keys written by set_versioned
+const VERSIONS_TABLE_SUFFIX: &str = "__VERSIONS_TABLE__";
+
+// Default postfix of the tables keeping the expiry flags of the keys
+pub(crate) const EXPIRATIONS_TABLE_SUFFIX: &str = "__EXPIRATIONS_TABLE__";
+
+// Prefix of the tables of the scopes which are not valid utf-8, followed by the hex of the scope
+const BINARY_SCOPE_PREFIX: &str = "__BASTEH_BINARY__";
+
+/// Returns `InvalidKey` for the scopes whose table would be taken for one of the internal tables
+fn check_table_name(name: &str, exp_table: &str) -> Result<(), BastehError> {
+    if name == METADATA_TABLE
+        || name == SETTINGS_TABLE
+        || name.ends_with(exp_table)
+        || name.ends_with(VERSIONS_TABLE_SUFFIX)
+        || name.starts_with(BINARY_SCOPE_PREFIX)
+    {
+        return Err(BastehError::InvalidKey(name.to_owned()));
+    }
+    Ok(())
+}
+
+/// Name of the table of the scope, utf-8 scopes are used as they are and the others are hex
+/// encoded behind a prefix utf-8 scopes can't start with
+pub(crate) fn scope_table_name(scope: &[u8], exp_table: &str) -> Result<Box<str>, BastehError> {
+    match std::str::from_utf8(scope) {
+        Ok(name) => {
+            check_table_name(name, exp_table)?;
+            Ok(name.into())
+        }
+        Err(_) => {
+            let mut name = String::from(BINARY_SCOPE_PREFIX);
+            for byte in scope {
+                name.push_str(&format!("{:02x}", byte));
+            }
+            // A custom expiry suffix made of hex digits may still match it
+            if name.ends_with(exp_table) {
+                return Err(BastehError::InvalidKey(
+                    String::from_utf8_lossy(scope).into_owned(),
+                ));
+            }
+            Ok(name.into())
+        }
+    }
+}
+
+/// The scope a table was named after by `scope_table_name`
+fn table_scope(name: &str) -> Vec<u8> {
+    let hex = match name.strip_prefix(BINARY_SCOPE_PREFIX) {
+        Some(hex) => hex,
+        None => return name.as_bytes().to_vec(),
+    };
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| hex.get(i..i + 2))
+        .filter_map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct RedbInner {
-    db: Arc<redb::Database>,
-    exp_table: String,
+    // Compaction needs exclusive access to the database, other operations share it
+    pub(crate) db: Arc<RwLock<redb::Database>>,
+    pub(crate) exp_table: String,
     queue: DelayQueue,
     queue_started: bool,
+    pub(crate) write_timestamps: bool,
+    pub(crate) checksums: bool,
+    pub(crate) format_error: Option<FormatVersionError>,
 }
 
 impl RedbInner {
     pub(crate) fn from_db(db: redb::Database) -> Self {
         Self {
-            db: Arc::new(db),
-            exp_table: String::from("__EXPIRATIONS_TABLE__"),
+            db: Arc::new(RwLock::new(db)),
+            exp_table: String::from(EXPIRATIONS_TABLE_SUFFIX),
             queue: DelayQueue::new(),
             queue_started: false,
+            write_timestamps: false,
+            checksums: false,
+            format_error: None,
+        }
+    }
+
+    /// Record the time the key is written and the checksum of its value in its expiry flags, if
+    /// they're tracked. It's called after the value is written, removed keys are skipped.
+    fn mark_written(
+        &self,
+        txn: &WriteTransaction<'_>,
+        scope: &str,
+        key: &[u8],
+    ) -> Result<(), Error> {
+        if !self.write_timestamps && !self.checksums {
+            return Ok(());
+        }
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let checksum = match txn.open_table(table)?.get(key)? {
+            Some(value) => crc32fast::hash(value.value()),
+            None => return Ok(()),
+        };
+        let mut exp_table = txn.open_table(exp_table)?;
+        let mut exp = exp_table.get(key)?.map(|v| v.value()).unwrap_or_default();
+        if self.write_timestamps {
+            exp.mark_written();
+        }
+        if self.checksums {
+            exp.set_checksum(checksum);
+        }
+        exp_table.insert(key, exp)?;
+        Ok(())
+    }
+
+    /// The flags to change the expiry of the key on, so its write time and checksum are kept if
+    /// they're tracked
+    fn current_flags(
+        &self,
+        exp_table: &Table<'_, '_, &[u8], ExpiryFlags>,
+        key: &[u8],
+    ) -> Result<ExpiryFlags, Error> {
+        if !self.write_timestamps && !self.checksums {
+            return Ok(ExpiryFlags::new_persist());
+        }
+        Ok(exp_table.get(key)?.map(|v| v.value()).unwrap_or_default())
+    }
+
+    /// Decode the stored value, returns `Corruption` if the checksums are tracked and the value
+    /// doesn't match the one in its flags
+    fn decode_checked(
+        &self,
+        bytes: &[u8],
+        exp: Option<&ExpiryFlags>,
+    ) -> Result<OwnedValue, BastehError> {
+        if self.checksums && exp.and_then(|exp| exp.checksum()) != Some(crc32fast::hash(bytes)) {
+            return Err(BastehError::Corruption);
+        }
+        Ok(OwnedValueWrapper::from_bytes(bytes))
+    }
+
+    /// Check the format version of the database, marking new databases with the current one and
+    /// migrating the older ones if possible. Incompatible versions are kept in `format_error` and
+    /// returned for all the requests. The checksums mode and the expiry table suffix are recorded
+    /// along with the version, the ones stored in the database are kept on reopen.
+    pub fn check_format_version(&mut self) -> Result<(), Error> {
+        // The expiry tables are told apart from the scopes by the suffix, so it's read first
+        self.check_expiry_table_suffix()?;
+
+        // Databases written before the version was recorded are told apart by their data
+        let has_data = self.scopes()?.next().is_some();
+
+        let db = self.db.read();
+        let metadata = TableDefinition::<&str, u8>::new(METADATA_TABLE);
+
+        let txn = db.begin_write()?;
+        let found = {
+            let mut table = txn.open_table(metadata)?;
+            let found = table.get(FORMAT_VERSION_KEY)?.map(|v| v.value());
+            let found = match found {
+                Some(found) => found,
+                None if has_data => LEGACY_FORMAT_VERSION,
+                None => {
+                    table.insert(FORMAT_VERSION_KEY, FORMAT_VERSION)?;
+                    FORMAT_VERSION
+                }
+            };
+
+            // Version 1 values are read the same by version 2, only the version is changed so the
+            // older releases don't misdecode the values of the newer kinds
+            if found == LEGACY_FORMAT_VERSION {
+                log::info!(
+                    "Migrating database from format version {} to {}",
+                    LEGACY_FORMAT_VERSION,
+                    FORMAT_VERSION
+                );
+                table.insert(FORMAT_VERSION_KEY, FORMAT_VERSION)?;
+                FORMAT_VERSION
+            } else {
+                found
+            }
+        };
+        if found == FORMAT_VERSION {
+            let mut table = txn.open_table(metadata)?;
+            let stored = table.get(CHECKSUMS_KEY)?.map(|v| v.value());
+            let checksums = match stored {
+                Some(checksums) => checksums != 0,
+                // Databases holding data before the mode was recorded were written without them
+                None => {
+                    let checksums = self.checksums && !has_data;
+                    table.insert(CHECKSUMS_KEY, checksums as u8)?;
+                    checksums
+                }
+            };
+            if checksums != self.checksums {
+                log::warn!(
+                    "Database was created with checksums set to {}, ignoring the requested mode",
+                    checksums
+                );
+                self.checksums = checksums;
+            }
+        }
+        txn.commit()?;
+
+        if found != FORMAT_VERSION {
+            self.format_error = Some(FormatVersionError {
+                found,
+                supported: FORMAT_VERSION,
+            });
+        }
+        Ok(())
+    }
+
+    /// Keep the expiry table suffix the database was created with, the expiry tables are only found
+    /// by their names. Databases created before the suffix was recorded keep the requested one.
+    fn check_expiry_table_suffix(&mut self) -> Result<(), Error> {
+        let db = self.db.read();
+        let settings = TableDefinition::<&str, &str>::new(SETTINGS_TABLE);
+
+        let txn = db.begin_write()?;
+        let suffix = {
+            let mut table = txn.open_table(settings)?;
+            let stored = table
+                .get(EXPIRY_TABLE_SUFFIX_KEY)?
+                .map(|v| v.value().to_owned());
+            match stored {
+                Some(suffix) => suffix,
+                None => {
+                    table.insert(EXPIRY_TABLE_SUFFIX_KEY, self.exp_table.as_str())?;
+                    self.exp_table.clone()
+                }
+            }
+        };
+        txn.commit()?;
+
+        if suffix != self.exp_table {
+            log::warn!(
+                "Database was created with the expiry table suffix {:?}, ignoring the requested one",
+                suffix
+            );
+            self.exp_table = suffix;
         }
+        Ok(())
+    }
+
+    /// Write a value to the metadata table, read it back and remove it, to make sure the database
+    /// is usable before any request is handled.
+    pub fn self_test(&self) -> Result<(), BastehError> {
+        let db = self.db.read();
+        let metadata = TableDefinition::<&str, u8>::new(METADATA_TABLE);
+
+        (|| {
+            let txn = db.begin_write()?;
+            txn.open_table(metadata)?
+                .insert(SELF_TEST_KEY, FORMAT_VERSION)?;
+            txn.commit()?;
+
+            let found = db
+                .begin_read()?
+                .open_table(metadata)?
+                .get(SELF_TEST_KEY)?
+                .map(|v| v.value());
+
+            let txn = db.begin_write()?;
+            txn.open_table(metadata)?.remove(SELF_TEST_KEY)?;
+            txn.commit()?;
+
+            Ok(found)
+        })()
+        .map_err(|err: Error| BastehError::custom(err))
+        .and_then(|found| match found {
+            Some(FORMAT_VERSION) => Ok(()),
+            _ => Err(BastehError::custom(SelfTestError)),
+        })
     }
 
     pub fn scan_db(&mut self) -> Result<(), Error> {
-        let guard = self.db.begin_write()?;
+        let db = self.db.read();
+        let guard = db.begin_write()?;
         for table_name in guard.list_tables()? {
             table_def!(table, table_name.name());
             exp_table_def!(exp_table, table_name.name(), &self.exp_table);
@@ -100,7 +383,56 @@ impl RedbInner {
         guard.commit().map_err(Into::into)
     }
 
-    pub fn spawn_expiry_thread(&mut self) {
+    /// Compact the database file, waiting for the running operations to finish first
+    pub fn compact(&self) -> Result<bool, BastehError> {
+        let mut db = self
+            .db
+            .try_write_for(COMPACTION_WAIT)
+            .ok_or_else(|| BastehError::custom(CompactionBusy))?;
+        db.compact().map_err(BastehError::custom)
+    }
+
+    /// Remove the expired items of a single scope, returning how many were removed
+    pub fn sweep_expired(&self, scope: &str) -> Result<u64, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let expired_keys = match txn.open_table(exp_table) {
+            Ok(mut exp_table) => {
+                let expired_keys = exp_table
+                    .iter()?
+                    .filter_map(Result::ok)
+                    .filter(|(_, exp)| exp.value().expired())
+                    .map(|(key, _)| key.value().to_vec())
+                    .collect::<Vec<_>>();
+                for key in expired_keys.iter() {
+                    exp_table.remove(key.as_slice())?;
+                }
+                expired_keys
+            }
+            Err(TableError::TableDoesNotExist(_)) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        if !expired_keys.is_empty() {
+            let mut table = txn.open_table(table)?;
+            for key in expired_keys.iter() {
+                table.remove(key.as_slice())?;
+            }
+        }
+        txn.commit()?;
+
+        if self.queue_started {
+            for key in expired_keys.iter() {
+                self.queue.remove(scope, key);
+            }
+        }
+        Ok(expired_keys.len() as u64)
+    }
+
+    pub fn spawn_expiry_thread(&mut self, interval: Duration) {
         if !self.queue_started {
             self.queue_started = true;
         } else {
@@ -110,11 +442,12 @@ impl RedbInner {
         let db = self.db.clone();
         let mut queue = self.queue.clone();
 
-        tokio::task::spawn_blocking(move || loop {
-            if let Some(item) = queue.try_pop_for(Duration::from_millis(500)) {
+        runtime::spawn_blocking(move || loop {
+            if let Some(item) = queue.try_pop_for(interval) {
                 table_def!(table, &item.scope);
 
                 (|| {
+                    let db = db.read();
                     let txn = db.begin_write()?;
                     txn.open_table(table)?.remove(item.key.as_ref())?;
                     txn.commit().map_err(Error::from)
@@ -129,54 +462,318 @@ impl RedbInner {
 }
 
 impl RedbInner {
+    fn scopes(&self) -> Result<std::vec::IntoIter<Vec<u8>>, Error> {
+        let db = self.db.read();
+        let txn = db.begin_read()?;
+
+        let mut scopes = Vec::new();
+        for table_name in txn.list_tables()? {
+            let name = table_name.name();
+            if name.ends_with(&self.exp_table)
+                || name.ends_with(VERSIONS_TABLE_SUFFIX)
+                || name == METADATA_TABLE
+                || name == SETTINGS_TABLE
+            {
+                continue;
+            }
+            // Tables are kept after their last key is removed
+            table_def!(table, name);
+            if !txn.open_table(table)?.is_empty()? {
+                scopes.push(table_scope(name));
+            }
+        }
+        Ok(scopes.into_iter())
+    }
+
+    /// Count the entries of every scope's table, checking their expiry flags in the companion
+    /// tables
+    fn table_stats(&self) -> Result<Vec<TableStat>, Error> {
+        let db = self.db.read();
+        let txn = db.begin_read()?;
+
+        let mut stats = Vec::new();
+        for table_name in txn.list_tables()? {
+            let name = table_name.name();
+            if name.ends_with(&self.exp_table)
+                || name.ends_with(VERSIONS_TABLE_SUFFIX)
+                || name == METADATA_TABLE
+                || name == SETTINGS_TABLE
+            {
+                continue;
+            }
+            table_def!(table, name);
+            exp_table_def!(exp_table, name, &self.exp_table);
+
+            let table = txn.open_table(table)?;
+            let entries = table.len()?;
+
+            let (has_expiry_table, expired) = match txn.open_table(exp_table) {
+                Ok(exp_table) => {
+                    let mut expired = 0;
+                    for item in exp_table.iter()? {
+                        let (key, exp) = item?;
+                        // Flags of removed keys are not counted
+                        if exp.value().expired() && table.get(key.value())?.is_some() {
+                            expired += 1;
+                        }
+                    }
+                    (true, expired)
+                }
+                Err(TableError::TableDoesNotExist(_)) => (false, 0),
+                Err(e) => return Err(e.into()),
+            };
+
+            stats.push(TableStat {
+                scope: table_scope(name),
+                entries,
+                live: entries - expired,
+                expired,
+                has_expiry_table,
+            });
+        }
+        Ok(stats)
+    }
+
     fn keys(&self, scope: &str) -> Result<std::vec::IntoIter<Vec<u8>>, Error> {
+        let db = self.db.read();
         table_def!(table, scope);
 
-        match self.db.begin_read()?.open_table(table) {
-            Ok(r) => Ok(r
+        let keys = match db.begin_read()?.open_table(table) {
+            Ok(r) => r
                 .iter()?
                 .map(|v| v.map(|v| v.0.value().to_vec()))
-                .collect::<Result<Vec<_>, StorageError>>()?
-                .into_iter()),
+                .collect::<Result<Vec<_>, StorageError>>()?,
             Err(e) => match e {
-                TableError::TableDoesNotExist(_) => Ok(Vec::new().into_iter()),
-                e => Err(e.into()),
+                TableError::TableDoesNotExist(_) => Vec::new(),
+                e => return Err(e.into()),
             },
+        };
+        Ok(keys.into_iter())
+    }
+
+    /// Get up to `limit` keys of the table following `after` in order
+    fn keys_page(
+        &self,
+        scope: &str,
+        after: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+
+        let txn = db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        let keys = table
+            .range::<&[u8]>((start, Bound::Unbounded))?
+            .take(limit)
+            .map(|v| v.map(|v| v.0.value().to_vec()))
+            .collect::<Result<Vec<_>, StorageError>>()?;
+        Ok(keys)
+    }
+
+    fn size_bytes(&self, scope: &str) -> Result<u64, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+
+        let txn = db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut size = 0;
+        for item in table.iter()? {
+            let (key, value) = item?;
+            let value = value.value();
+            size += key.value().len() as u64
+                + <OwnedValueWrapper as RedbValue>::as_bytes(&value).len() as u64;
         }
+        Ok(size)
     }
 
     fn set(&self, scope: &str, key: &[u8], value: OwnedValue) -> Result<(), Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
+        let txn = db.begin_write()?;
+        txn.open_table(table)?.insert(key, value)?;
+        txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(())
+    }
+
+    /// Write the bytes as they are, without the kind byte, replacing the value and its expiry
+    fn set_raw(&self, scope: &str, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
         txn.open_table(table)?.insert(key, value)?;
         txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(())
+    }
+
+    /// Read the bytes as they are stored, without decoding them
+    fn get_raw(&self, scope: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+
+        let value = match db.begin_read()?.open_table(table) {
+            Ok(r) => r.get(key)?.map(|v| v.value().to_vec()),
+            Err(e) => match e {
+                TableError::TableDoesNotExist(_) => None,
+                e => return Err(e.into()),
+            },
+        };
+        Ok(value)
+    }
+
+    fn upsert(&self, scope: &str, key: &[u8], value: OwnedValue) -> Result<bool, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        // Expired keys which are not deleted yet are considered missing
+        let expired = txn
+            .open_table(exp_table)?
+            .remove(key)?
+            .is_some_and(|v| v.value().expired());
+        let existed = txn.open_table(table)?.insert(key, value)?.is_some();
+        self.mark_written(&txn, scope, key)?;
         txn.commit()?;
 
         if self.queue_started {
             self.queue.remove(scope, key);
         }
+        Ok(!existed || expired)
+    }
+
+    fn set_keepttl(&self, scope: &str, key: &[u8], value: OwnedValue) -> Result<(), Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        // Expired keys which are not deleted yet are considered missing
+        let expired = {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let expired = exp_table
+                .get(key)?
+                .map(|v| v.value().expired())
+                .unwrap_or(false);
+            if expired {
+                exp_table.remove(key)?;
+            }
+            expired
+        };
+        txn.open_table(table)?.insert(key, value)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if expired && self.queue_started {
+            self.queue.remove(scope, key);
+        }
         Ok(())
     }
 
-    fn get(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>, Error> {
+    fn drop_scope(&self, scope: &str) -> Result<(), Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
+        ver_table_def!(ver_table, scope);
+
+        let txn = db.begin_write()?;
+        let expiring_keys = match txn.open_table(exp_table) {
+            Ok(r) => r
+                .iter()?
+                .map(|v| v.map(|v| v.0.value().to_vec()))
+                .collect::<Result<Vec<_>, StorageError>>()?,
+            Err(TableError::TableDoesNotExist(_)) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        txn.delete_table(table)?;
+        txn.delete_table(exp_table)?;
+        txn.delete_table(ver_table)?;
+        txn.commit()?;
+
+        // Queued expirations would open the table again after it's deleted
+        if self.queue_started {
+            for key in expiring_keys {
+                self.queue.remove(scope, &key);
+            }
+        }
+        Ok(())
+    }
+
+    fn get(
+        &self,
+        scope: &str,
+        key: &[u8],
+    ) -> Result<Result<Option<OwnedValue>, BastehError>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let exp = match db.begin_read()?.open_table(exp_table) {
+            Ok(r) => r.get(key)?.map(|v| v.value()),
+            Err(_) => None,
+        };
+        if matches!(exp, Some(exp) if exp.expired()) {
+            return Ok(Ok(None));
+        }
+
+        let value = match db.begin_read()?.open_table(table) {
+            Ok(r) => r
+                .get(key)?
+                .map(|v| self.decode_checked(v.value(), exp.as_ref())),
+            Err(e) => match e {
+                TableError::TableDoesNotExist(_) => None,
+                e => return Err(e.into()),
+            },
+        };
+        Ok(value.transpose())
+    }
+
+    fn value_kind(&self, scope: &str, key: &[u8]) -> Result<Option<ValueKind>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], ValueKindWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
 
-        if let Ok(r) = self.db.begin_read()?.open_table(exp_table) {
+        if let Ok(r) = db.begin_read()?.open_table(exp_table) {
             if let Some(true) = r.get(key)?.map(|v| v.value().expired()) {
                 return Ok(None);
             }
         };
 
-        match self.db.begin_read()?.open_table(table) {
-            Ok(r) => Ok(r.get(key)?.map(|v| v.value())),
+        let kind = match db.begin_read()?.open_table(table) {
+            Ok(r) => r.get(key)?.and_then(|v| v.value()),
             Err(e) => match e {
-                TableError::TableDoesNotExist(_) => Ok(None),
+                TableError::TableDoesNotExist(_) => None,
                 e => return Err(e.into()),
             },
-        }
+        };
+        Ok(kind)
     }
 
     fn get_range(
@@ -185,143 +782,487 @@ impl RedbInner {
         key: &[u8],
         start: i64,
         end: i64,
-    ) -> Result<Vec<OwnedValue>, Error> {
-        table_def!(table, scope);
+    ) -> Result<Result<Vec<OwnedValue>, BastehError>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        if let Ok(r) = self.db.begin_read()?.open_table(exp_table) {
-            if let Some(true) = r.get(key)?.map(|v| v.value().expired()) {
-                return Ok(Vec::new());
-            }
+        let exp = match db.begin_read()?.open_table(exp_table) {
+            Ok(r) => r.get(key)?.map(|v| v.value()),
+            Err(_) => None,
         };
+        if matches!(exp, Some(exp) if exp.expired()) {
+            return Ok(Ok(Vec::new()));
+        }
 
-        match self.db.begin_read()?.open_table(table) {
-            Ok(r) => Ok(r
+        let value = match db.begin_read()?.open_table(table) {
+            Ok(r) => r
                 .get(key)?
-                .map(|v| match v.value() {
-                    OwnedValue::List(l) => {
-                        let start: usize = start.try_into().unwrap_or_else(|_| {
-                            l.len().checked_sub(-start as usize).unwrap_or_default()
-                        });
-
-                        let take: usize = end
-                            .try_into()
-                            .unwrap_or_else(|_| {
-                                l.len().checked_sub(-end as usize).unwrap_or_default()
-                            })
-                            .checked_sub(start)
-                            .and_then(|end| end.checked_add(1))
-                            .unwrap_or(0);
-
-                        l.into_iter().skip(start).take(take).collect()
-                    }
-                    _ => Vec::new(),
-                })
-                .unwrap_or_default()),
+                .map(|v| self.decode_checked(v.value(), exp.as_ref())),
             Err(e) => match e {
-                TableError::TableDoesNotExist(_) => Ok(Vec::new()),
+                TableError::TableDoesNotExist(_) => None,
                 e => return Err(e.into()),
             },
-        }
+        };
+        Ok(value
+            .transpose()
+            .and_then(|value| list_range(value, start, end)))
     }
 
-    fn pop(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>, Error> {
-        table_def!(table, scope);
+    fn get_range_multi(
+        &self,
+        scope: &str,
+        requests: &[(Box<[u8]>, i64, i64)],
+    ) -> Result<Result<Vec<Vec<OwnedValue>>, BastehError>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
-        let val;
-
-        {
-            let mut table = txn.open_table(table)?;
-            let list = if let Some(list) = table.get(key)? {
-                match list.value() {
-                    OwnedValue::List(mut l) => {
-                        val = l.pop();
-                        l
-                    }
-                    _ => {
-                        return Err(redb::Error::TableTypeMismatch {
-                            table: scope.to_string(),
-                            key: TypeName::new("Unknown"),
-                            value: TypeName::new("Vec<_>"),
-                        });
-                    }
-                }
+        let txn = db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => {
+                return Ok(Ok(vec![Vec::new(); requests.len()]))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let exp_table = txn.open_table(exp_table).ok();
+
+        let mut res = Vec::with_capacity(requests.len());
+        for (key, start, end) in requests {
+            let key: &[u8] = key;
+            let exp = match &exp_table {
+                Some(exp_table) => exp_table.get(key)?.map(|v| v.value()),
+                None => None,
+            };
+            let value = if matches!(exp, Some(exp) if exp.expired()) {
+                None
             } else {
-                val = None;
-                Vec::new()
+                match table.get(key)? {
+                    Some(v) => match self.decode_checked(v.value(), exp.as_ref()) {
+                        Ok(value) => Some(value),
+                        Err(e) => return Ok(Err(e)),
+                    },
+                    None => None,
+                }
             };
-            table.insert(key, OwnedValue::List(list))?;
-        }
-
-        txn.open_table(exp_table)?.remove(key)?;
-        txn.commit()?;
-
-        if self.queue_started {
-            self.queue.remove(scope, key);
+            match list_range(value, *start, *end) {
+                Ok(list) => res.push(list),
+                Err(e) => return Ok(Err(e)),
+            }
         }
-        Ok(val)
+        Ok(Ok(res))
     }
 
-    fn push(&self, scope: &str, key: &[u8], value: OwnedValue) -> Result<(), Error> {
+    fn pop(
+        &self,
+        scope: &str,
+        key: &[u8],
+    ) -> Result<Result<Option<OwnedValue>, BastehError>, Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
+        let txn = db.begin_write()?;
+        let val;
 
         {
             let mut table = txn.open_table(table)?;
-            let val = if let Some(list) = table.get(key)? {
-                match list.value() {
-                    OwnedValue::List(mut l) => {
-                        l.push(value);
-                        l
-                    }
-                    _ => {
-                        return Err(redb::Error::TableTypeMismatch {
-                            table: scope.to_string(),
-                            key: TypeName::new("Unknown"),
-                            value: TypeName::new("Vec<_>"),
-                        });
+            let list = table.get(key)?.map(|v| v.value());
+            match list {
+                Some(OwnedValue::List(mut l)) => {
+                    val = l.pop();
+                    // Emptied lists are removed, the same as redis
+                    if l.is_empty() {
+                        table.remove(key)?;
+                    } else {
+                        table.insert(key, OwnedValue::List(l))?;
                     }
                 }
-            } else {
-                vec![value]
-            };
-            table.insert(key, OwnedValue::List(val))?;
+                // Abort will be called by drop
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                // Missing keys are left missing
+                None => return Ok(Ok(None)),
+            }
         }
 
         txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
         txn.commit()?;
 
         if self.queue_started {
             self.queue.remove(scope, key);
         }
-        Ok(())
+        Ok(Ok(val))
     }
 
-    fn push_multiple(&self, scope: &str, key: &[u8], value: Vec<OwnedValue>) -> Result<(), Error> {
-        table_def!(table, scope);
-        exp_table_def!(exp_table, scope, &self.exp_table);
+    fn pop_push(
+        &self,
+        src_scope: &str,
+        src_key: &[u8],
+        dst_scope: &str,
+        dst_key: &[u8],
+    ) -> Result<Result<Option<OwnedValue>, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(src_table, src_scope);
+        table_def!(dst_table, dst_scope);
+
+        let txn = db.begin_write()?;
+
+        // Tables are opened one at a time, as source and destination may be the same
+        let val = {
+            let mut table = txn.open_table(src_table)?;
+            let list = table.get(src_key)?.map(|v| v.value());
+            match list {
+                Some(OwnedValue::List(mut l)) => match l.pop() {
+                    Some(val) => {
+                        table.insert(src_key, OwnedValue::List(l))?;
+                        val
+                    }
+                    None => return Ok(Ok(None)),
+                },
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => return Ok(Ok(None)),
+            }
+        };
+
+        {
+            let mut table = txn.open_table(dst_table)?;
+            let list = table.get(dst_key)?.map(|v| v.value());
+            let mut list = match list {
+                Some(OwnedValue::List(l)) => l,
+                // Dropping the transaction without committing rolls back the pop
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => Vec::new(),
+            };
+            list.push(val.clone());
+            table.insert(dst_key, OwnedValue::List(list))?;
+        }
+
+        // Emptied lists are removed, the same as redis. Checking after the push, so rotating a
+        // single item list in place keeps the key
+        let emptied = {
+            let mut table = txn.open_table(src_table)?;
+            let emptied = matches!(
+                table.get(src_key)?.map(|v| v.value()),
+                Some(OwnedValue::List(l)) if l.is_empty()
+            );
+            if emptied {
+                table.remove(src_key)?;
+            }
+            emptied
+        };
+        if emptied {
+            exp_table_def!(src_exp_table, src_scope, &self.exp_table);
+            txn.open_table(src_exp_table)?.remove(src_key)?;
+        }
+
+        self.mark_written(&txn, src_scope, src_key)?;
+        self.mark_written(&txn, dst_scope, dst_key)?;
+        txn.commit()?;
+
+        if emptied && self.queue_started {
+            self.queue.remove(src_scope, src_key);
+        }
+        Ok(Ok(Some(val)))
+    }
+
+    fn list_drain(
+        &self,
+        scope: &str,
+        key: &[u8],
+    ) -> Result<Result<Vec<OwnedValue>, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+
+        let expired = txn
+            .open_table(exp_table)?
+            .get(key)?
+            .map(|v| v.value().expired())
+            .unwrap_or(false);
+
+        let val = {
+            let mut table = txn.open_table(table)?;
+            let list = table.get(key)?.map(|v| v.value());
+            match list {
+                _ if expired => {
+                    table.remove(key)?;
+                    Vec::new()
+                }
+                Some(OwnedValue::List(l)) => {
+                    table.remove(key)?;
+                    l
+                }
+                // Abort will be called by drop
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => Vec::new(),
+            }
+        };
+
+        txn.open_table(exp_table)?.remove(key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(val))
+    }
+
+    fn hset(
+        &self,
+        scope: &str,
+        key: &[u8],
+        field: &[u8],
+        value: OwnedValue,
+    ) -> Result<Result<(), BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let expired = {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let expired = exp_table.get(key)?.is_some_and(|v| v.value().expired());
+
+            let mut table = txn.open_table(table)?;
+            let mut map = match table.get(key)?.map(|v| v.value()) {
+                // Expired values are replaced with a new persistent map
+                Some(_) if expired => {
+                    exp_table.remove(key)?;
+                    Vec::new()
+                }
+                Some(OwnedValue::Map(map)) => map,
+                // Abort will be called by drop
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => Vec::new(),
+            };
+
+            match map.iter_mut().find(|(f, _)| f == field) {
+                Some((_, v)) => *v = value,
+                None => map.push((field.to_vec(), value)),
+            }
+            table.insert(key, OwnedValue::Map(map))?;
+            expired
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if expired && self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(()))
+    }
+
+    fn hincr(
+        &self,
+        scope: &str,
+        key: &[u8],
+        field: &[u8],
+        delta: i64,
+    ) -> Result<Result<i64, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let (result, expired) = {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let expired = exp_table.get(key)?.is_some_and(|v| v.value().expired());
+
+            let mut table = txn.open_table(table)?;
+            let mut map = match table.get(key)?.map(|v| v.value()) {
+                // Expired values are replaced with a new persistent map
+                Some(_) if expired => {
+                    exp_table.remove(key)?;
+                    Vec::new()
+                }
+                Some(OwnedValue::Map(map)) => map,
+                // Abort will be called by drop
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => Vec::new(),
+            };
+
+            let result = match map.iter_mut().find(|(f, _)| f == field) {
+                Some((_, v)) => match add_number(Some(v.as_value()), delta) {
+                    Ok(result) => {
+                        *v = OwnedValue::Number(result);
+                        result
+                    }
+                    // Abort will be called by drop
+                    Err(err) => return Ok(Err(err)),
+                },
+                None => {
+                    map.push((field.to_vec(), OwnedValue::Number(delta)));
+                    delta
+                }
+            };
+            table.insert(key, OwnedValue::Map(map))?;
+            (result, expired)
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if expired && self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(result))
+    }
+
+    fn set_bit(
+        &self,
+        scope: &str,
+        key: &[u8],
+        offset: u64,
+        bit: bool,
+    ) -> Result<Result<bool, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let (previous, expired) = {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let expired = exp_table.get(key)?.is_some_and(|v| v.value().expired());
+
+            let mut table = txn.open_table(table)?;
+            let mut bytes = match table.get(key)?.map(|v| v.value()) {
+                // Expired values are replaced with new persistent bytes
+                Some(_) if expired => {
+                    exp_table.remove(key)?;
+                    Default::default()
+                }
+                Some(OwnedValue::Bytes(bytes)) => bytes,
+                // Abort will be called by drop
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => Default::default(),
+            };
+
+            let previous = write_bit(&mut bytes, offset, bit);
+            table.insert(key, OwnedValue::Bytes(bytes))?;
+            (previous, expired)
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if expired && self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(previous))
+    }
+
+    fn hdel(
+        &self,
+        scope: &str,
+        key: &[u8],
+        field: &[u8],
+    ) -> Result<Result<bool, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let emptied = {
+            let mut exp_table = txn.open_table(exp_table)?;
+            if exp_table.get(key)?.is_some_and(|v| v.value().expired()) {
+                return Ok(Ok(false));
+            }
+
+            let mut table = txn.open_table(table)?;
+            let mut map = match table.get(key)?.map(|v| v.value()) {
+                Some(OwnedValue::Map(map)) => map,
+                Some(_) => return Ok(Err(BastehError::TypeConversion)),
+                None => return Ok(Ok(false)),
+            };
+
+            let len = map.len();
+            map.retain(|(f, _)| f != field);
+            if map.len() == len {
+                return Ok(Ok(false));
+            }
+
+            // Emptied maps are removed, the same as redis
+            if map.is_empty() {
+                table.remove(key)?;
+                exp_table.remove(key)?;
+                true
+            } else {
+                table.insert(key, OwnedValue::Map(map))?;
+                false
+            }
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
 
-        let txn = self.db.begin_write()?;
+        if emptied && self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(true))
+    }
+
+    fn push(
+        &self,
+        scope: &str,
+        key: &[u8],
+        value: OwnedValue,
+    ) -> Result<Result<(), BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
 
         {
             let mut table = txn.open_table(table)?;
             let val = if let Some(list) = table.get(key)? {
                 match list.value() {
                     OwnedValue::List(mut l) => {
-                        l.extend(value);
+                        l.push(value);
                         l
                     }
-                    _ => {
-                        return Err(redb::Error::TableTypeMismatch {
-                            table: scope.to_string(),
-                            key: TypeName::new("Unknown"),
-                            value: TypeName::new("Vec<_>"),
-                        });
+                    // Abort will be called by drop
+                    _ => return Ok(Err(BastehError::TypeConversion)),
+                }
+            } else {
+                vec![value]
+            };
+            table.insert(key, OwnedValue::List(val))?;
+        }
+
+        txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(()))
+    }
+
+    fn push_multiple(
+        &self,
+        scope: &str,
+        key: &[u8],
+        value: Vec<OwnedValue>,
+    ) -> Result<Result<(), BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+
+        {
+            let mut table = txn.open_table(table)?;
+            let val = if let Some(list) = table.get(key)? {
+                match list.value() {
+                    OwnedValue::List(mut l) => {
+                        l.extend(value);
+                        l
                     }
+                    // Abort will be called by drop
+                    _ => return Ok(Err(BastehError::TypeConversion)),
                 }
             } else {
                 value
@@ -330,19 +1271,161 @@ impl RedbInner {
         }
 
         txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
         txn.commit()?;
 
         if self.queue_started {
             self.queue.remove(scope, key);
         }
-        Ok(())
+        Ok(Ok(()))
+    }
+
+    fn push_returning_len(
+        &self,
+        scope: &str,
+        key: &[u8],
+        value: OwnedValue,
+    ) -> Result<Result<u64, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+
+        let len = {
+            let mut table = txn.open_table(table)?;
+            let val = if let Some(list) = table.get(key)? {
+                match list.value() {
+                    OwnedValue::List(mut l) => {
+                        l.push(value);
+                        l
+                    }
+                    // Abort will be called by drop
+                    _ => return Ok(Err(BastehError::TypeConversion)),
+                }
+            } else {
+                vec![value]
+            };
+            let len = val.len() as u64;
+            table.insert(key, OwnedValue::List(val))?;
+            len
+        };
+
+        txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(len))
+    }
+
+    fn push_capped(
+        &self,
+        scope: &str,
+        key: &[u8],
+        value: OwnedValue,
+        max_len: u64,
+    ) -> Result<Result<(), BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+
+        {
+            let mut table = txn.open_table(table)?;
+            let mut val = if let Some(list) = table.get(key)? {
+                match list.value() {
+                    OwnedValue::List(mut l) => {
+                        l.push(value);
+                        l
+                    }
+                    // Abort will be called by drop
+                    _ => return Ok(Err(BastehError::TypeConversion)),
+                }
+            } else {
+                vec![value]
+            };
+
+            let max_len = usize::try_from(max_len).unwrap_or(usize::MAX);
+            val.drain(..val.len().saturating_sub(max_len));
+            // Emptied lists are removed, the same as redis
+            if val.is_empty() {
+                table.remove(key)?;
+            } else {
+                table.insert(key, OwnedValue::List(val))?;
+            }
+        }
+
+        txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(()))
+    }
+
+    fn mutate_list(
+        &self,
+        scope: &str,
+        key: &[u8],
+        mutations: ListMutation,
+    ) -> Result<Result<u64, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+
+        let len = {
+            let mut table = txn.open_table(table)?;
+            let mut val = if let Some(list) = table.get(key)? {
+                match list.value() {
+                    OwnedValue::List(l) => l,
+                    // Abort will be called by drop
+                    _ => return Ok(Err(BastehError::TypeConversion)),
+                }
+            } else {
+                Vec::new()
+            };
+
+            run_list_mutations(&mut val, &mutations);
+            let len = val.len() as u64;
+            // Emptied lists are removed, the same as redis
+            if val.is_empty() {
+                table.remove(key)?;
+            } else {
+                table.insert(key, OwnedValue::List(val))?;
+            }
+            len
+        };
+
+        txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(len))
     }
 
-    fn mutate(&self, scope: &str, key: &[u8], mutations: Mutation) -> Result<i64, Error> {
+    /// Returns the kind of the stored value as the inner error if it's not a number
+    fn mutate(
+        &self,
+        scope: &str,
+        key: &[u8],
+        mutations: Mutation,
+    ) -> Result<Result<(i64, i64), ValueKind>, Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
+        let txn = db.begin_write()?;
         let value = {
             let mut table = txn.open_table(table)?;
             let mut expired = false;
@@ -367,15 +1450,10 @@ impl RedbInner {
                 0
             } else {
                 if let Some(value) = table.remove(key)? {
-                    if let Ok(value) = value.value().try_into() {
-                        value
-                    } else {
+                    match value.value() {
+                        OwnedValue::Number(value) => value,
                         // Abort will be called by drop
-                        return Err(redb::Error::TableTypeMismatch {
-                            table: scope.to_string(),
-                            key: TypeName::new("i64"),
-                            value: TypeName::new("Unknown"),
-                        });
+                        value => return Ok(Err(value.kind())),
                     }
                 } else {
                     0
@@ -384,48 +1462,278 @@ impl RedbInner {
             let value = run_mutations(current, &mutations);
 
             table.insert(key, OwnedValue::Number(value))?;
+            (current, value)
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        Ok(Ok(value))
+    }
+
+    fn incr_float(
+        &self,
+        scope: &str,
+        key: &[u8],
+        delta: f64,
+    ) -> Result<Result<f64, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let value = {
+            let mut table = txn.open_table(table)?;
+            let mut expired = false;
+            if let Ok(mut r) = txn.open_table(exp_table) {
+                if matches!(r.get(key)?, Some(v) if v.value().expired()) {
+                    // Same as mutate, the expired value is treated as missing
+                    if self.queue_started {
+                        self.queue.remove(scope, key);
+                    }
+                    r.remove(key)?;
+
+                    expired = true;
+                }
+            };
+
+            let current = if expired {
+                None
+            } else {
+                table.get(key)?.map(|v| v.value())
+            };
+            let value = match add_float(current.as_ref().map(|v| v.as_value()), delta) {
+                Ok(value) => value,
+                // Abort will be called by drop
+                Err(e) => return Ok(Err(e)),
+            };
+
+            table.insert(key, OwnedValue::String(value.to_string()))?;
             value
         };
+        self.mark_written(&txn, scope, key)?;
         txn.commit()?;
 
-        Ok(value)
+        Ok(Ok(value))
+    }
+
+    fn incr_big(
+        &self,
+        scope: &str,
+        key: &[u8],
+        delta: i128,
+    ) -> Result<Result<i128, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let value = {
+            let mut table = txn.open_table(table)?;
+            let mut expired = false;
+            if let Ok(mut r) = txn.open_table(exp_table) {
+                if matches!(r.get(key)?, Some(v) if v.value().expired()) {
+                    // Same as mutate, the expired value is treated as missing
+                    if self.queue_started {
+                        self.queue.remove(scope, key);
+                    }
+                    r.remove(key)?;
+
+                    expired = true;
+                }
+            };
+
+            let current = if expired {
+                None
+            } else {
+                table.get(key)?.map(|v| v.value())
+            };
+            let value = match add_big(current.as_ref().map(|v| v.as_value()), delta) {
+                Ok(value) => value,
+                // Abort will be called by drop
+                Err(e) => return Ok(Err(e)),
+            };
+
+            table.insert(key, OwnedValue::from(value))?;
+            value
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        Ok(Ok(value))
+    }
+
+    fn set_if(
+        &self,
+        scope: &str,
+        key: &[u8],
+        value: i64,
+        ord: Ordering,
+    ) -> Result<Result<bool, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let expired = {
+            let mut table = txn.open_table(table)?;
+            let mut exp_table = txn.open_table(exp_table)?;
+            let expired = exp_table.get(key)?.is_some_and(|v| v.value().expired());
+
+            let current = if expired {
+                None
+            } else {
+                table.get(key)?.map(|v| v.value())
+            };
+            let set = match should_set(current.as_ref().map(|v| v.as_value()), value, ord) {
+                Ok(set) => set,
+                // Abort will be called by drop
+                Err(e) => return Ok(Err(e)),
+            };
+            if !set {
+                return Ok(Ok(false));
+            }
+
+            // Same as mutate, the expired value is replaced with a persistent one
+            if expired {
+                exp_table.remove(key)?;
+            }
+            table.insert(key, OwnedValue::Number(value))?;
+            expired
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if expired && self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(Ok(true))
     }
 
     fn remove(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let val = txn.open_table(table)?.remove(key)?.map(|v| v.value());
+        txn.open_table(exp_table)?.remove(key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+
+        Ok(val)
+    }
+
+    /// Remove the key and return whether it held a value which wasn't expired, without
+    /// deserializing the value
+    fn delete(&self, scope: &str, key: &[u8]) -> Result<bool, Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
-        let val = txn.open_table(table)?.remove(key)?.map(|v| v.value());
-        txn.open_table(exp_table)?.remove(key)?;
+        let txn = db.begin_write()?;
+        let removed = txn.open_table(table)?.remove(key)?.is_some();
+        let expired = txn
+            .open_table(exp_table)?
+            .remove(key)?
+            .is_some_and(|v| v.value().expired());
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+
+        Ok(removed && !expired)
+    }
+
+    fn remove_if(&self, scope: &str, key: &[u8], expected: OwnedValue) -> Result<bool, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        {
+            let mut table = txn.open_table(table)?;
+            let mut exp_table = txn.open_table(exp_table)?;
+            if exp_table.get(key)?.is_some_and(|v| v.value().expired()) {
+                return Ok(false);
+            }
+            if table.get(key)?.map(|v| v.value()) != Some(expected) {
+                // Abort will be called by drop
+                return Ok(false);
+            }
+
+            table.remove(key)?;
+            exp_table.remove(key)?;
+        }
         txn.commit()?;
 
         if self.queue_started {
             self.queue.remove(scope, key);
         }
-
-        Ok(val)
+        Ok(true)
     }
 
     fn contains_key(&self, scope: &str, key: &[u8]) -> Result<bool, Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        if let Ok(r) = self.db.begin_read()?.open_table(exp_table) {
+        if let Ok(r) = db.begin_read()?.open_table(exp_table) {
             if let Some(true) = r.get(key)?.map(|v| v.value().expired()) {
                 return Ok(false);
             }
         };
 
-        Ok(self.db.begin_read()?.open_table(table)?.get(key)?.is_some())
+        let found = match db.begin_read()?.open_table(table) {
+            Ok(table) => table.get(key)?.is_some(),
+            Err(TableError::TableDoesNotExist(_)) => false,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(found)
+    }
+
+    fn contains_multi(&self, scope: &str, keys: &[Box<[u8]>]) -> Result<Vec<bool>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(vec![false; keys.len()]),
+            Err(e) => return Err(e.into()),
+        };
+        let exp_table = txn.open_table(exp_table).ok();
+
+        let mut res = Vec::with_capacity(keys.len());
+        for key in keys {
+            let key: &[u8] = key;
+            let expired = match &exp_table {
+                Some(exp_table) => exp_table
+                    .get(key)?
+                    .map(|v| v.value().expired())
+                    .unwrap_or(false),
+                None => false,
+            };
+            res.push(!expired && table.get(key)?.is_some());
+        }
+        Ok(res)
     }
 
     pub fn expire(&mut self, scope: &str, key: &[u8], duration: Duration) -> Result<(), Error> {
+        let db = self.db.read();
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
-        txn.open_table(exp_table)?
-            .insert(key, ExpiryFlags::new_expiring(duration))?;
+        let txn = db.begin_write()?;
+        {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let mut exp = self.current_flags(&exp_table, key)?;
+            exp.expire_in(duration);
+            exp_table.insert(key, exp)?;
+        }
         txn.commit()?;
 
         if self.queue_started {
@@ -434,24 +1742,82 @@ impl RedbInner {
         Ok(())
     }
 
+    pub fn expire_multi(
+        &mut self,
+        scope: &str,
+        entries: Vec<(Box<[u8]>, Duration)>,
+    ) -> Result<(), Error> {
+        let db = self.db.read();
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        {
+            let mut exp_table = txn.open_table(exp_table)?;
+            for (key, duration) in &entries {
+                let mut exp = self.current_flags(&exp_table, key)?;
+                exp.expire_in(*duration);
+                exp_table.insert(key.as_ref(), exp)?;
+            }
+        }
+        txn.commit()?;
+
+        if self.queue_started {
+            let now = Instant::now();
+            for (key, duration) in &entries {
+                self.queue.push(scope, key, now + *duration);
+            }
+        }
+        Ok(())
+    }
+
     pub fn expiry(&self, scope: &str, key: &[u8]) -> Result<Option<Duration>, Error> {
+        let db = self.db.read();
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        match self.db.begin_read()?.open_table(exp_table) {
-            Ok(r) => Ok(r.get(key)?.and_then(|v| v.value().expires_in())),
+        let expiry = match db.begin_read()?.open_table(exp_table) {
+            Ok(r) => r.get(key)?.and_then(|v| v.value().expires_in()),
             Err(e) => match e {
-                TableError::TableDoesNotExist(_) => Ok(None),
+                TableError::TableDoesNotExist(_) => None,
                 e => return Err(e.into()),
             },
+        };
+        Ok(expiry)
+    }
+
+    pub fn modified_at(&self, scope: &str, key: &[u8]) -> Result<Option<SystemTime>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_read()?;
+        let exp = match txn.open_table(exp_table) {
+            Ok(r) => r.get(key)?.map(|v| v.value()),
+            Err(TableError::TableDoesNotExist(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+        if exp.is_some_and(|exp| exp.expired()) {
+            return Ok(None);
         }
+
+        let found = match txn.open_table(table) {
+            Ok(r) => r.get(key)?.is_some(),
+            Err(TableError::TableDoesNotExist(_)) => false,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(exp.filter(|_| found).and_then(|exp| exp.written_at()))
     }
 
     pub fn persist(&self, scope: &str, key: &[u8]) -> Result<(), Error> {
+        let db = self.db.read();
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
-        txn.open_table(exp_table)?
-            .insert(key, ExpiryFlags::new_persist())?;
+        let txn = db.begin_write()?;
+        {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let mut exp = self.current_flags(&exp_table, key)?;
+            exp.persist();
+            exp_table.insert(key, exp)?;
+        }
         txn.commit()?;
 
         if self.queue_started {
@@ -461,9 +1827,10 @@ impl RedbInner {
     }
 
     pub fn extend(&mut self, scope: &str, key: &[u8], duration: Duration) -> Result<(), Error> {
+        let db = self.db.read();
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
+        let txn = db.begin_write()?;
         let exp = {
             let exp = match txn.open_table(exp_table) {
                 Ok(r) => r.get(key)?.map(|v| {
@@ -502,13 +1869,15 @@ impl RedbInner {
         value: OwnedValue,
         duration: Duration,
     ) -> Result<(), Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let txn = self.db.begin_write()?;
+        let txn = db.begin_write()?;
         txn.open_table(table)?.insert(key, value)?;
         txn.open_table(exp_table)?
             .insert(key, ExpiryFlags::new_expiring(duration))?;
+        self.mark_written(&txn, scope, key)?;
         txn.commit()?;
 
         if self.queue_started {
@@ -517,15 +1886,133 @@ impl RedbInner {
         Ok(())
     }
 
-    pub fn get_expiring(
+    /// Set the value expiring after the duration and return the value it replaced
+    pub fn swap_expiring(
+        &mut self,
+        scope: &str,
+        key: &[u8],
+        value: OwnedValue,
+        duration: Duration,
+    ) -> Result<Option<OwnedValue>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        // Expired keys which are not deleted yet are considered missing
+        let expired = txn
+            .open_table(exp_table)?
+            .insert(key, ExpiryFlags::new_expiring(duration))?
+            .is_some_and(|v| v.value().expired());
+        let old = txn
+            .open_table(table)?
+            .insert(key, value)?
+            .map(|v| v.value())
+            .filter(|_| !expired);
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.push(scope, key, Instant::now() + duration);
+        }
+        Ok(old)
+    }
+
+    /// Add delta to the number, missing keys are set to delta and expire after the duration.
+    /// Existing keys keep their expiry, persistent ones get the duration as well.
+    pub fn incr_with_ttl_floor(
+        &mut self,
+        scope: &str,
+        key: &[u8],
+        delta: i64,
+        duration: Duration,
+    ) -> Result<Result<i64, BastehError>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let (value, expiring) = {
+            let mut table = txn.open_table(table)?;
+            let mut exp_table = txn.open_table(exp_table)?;
+            let exp = exp_table.get(key)?.map(|v| v.value());
+            let expired = exp.as_ref().is_some_and(|exp| exp.expired());
+
+            let current = if expired {
+                None
+            } else {
+                table.get(key)?.map(|v| v.value())
+            };
+            let value = match current {
+                Some(OwnedValue::Number(n)) => n.checked_add(delta),
+                // Abort will be called by drop
+                Some(val) => return Ok(Err(BastehError::NotANumber(val.kind()))),
+                None => Some(delta),
+            };
+            let value = match value {
+                Some(value) => value,
+                None => return Ok(Err(BastehError::InvalidNumber)),
+            };
+            table.insert(key, OwnedValue::Number(value))?;
+
+            // The expiry is never extended, only set if there is none
+            let expiring = !expired && exp.is_some_and(|exp| exp.expires_in().is_some());
+            if !expiring {
+                exp_table.insert(key, ExpiryFlags::new_expiring(duration))?;
+            }
+            (value, expiring)
+        };
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if !expiring && self.queue_started {
+            self.queue.push(scope, key, Instant::now() + duration);
+        }
+        Ok(Ok(value))
+    }
+
+    pub fn set_versioned(
         &self,
         scope: &str,
         key: &[u8],
-    ) -> Result<Option<(OwnedValue, Option<Duration>)>, Error> {
+        value: OwnedValue,
+        version: u64,
+    ) -> Result<bool, Error> {
+        let db = self.db.read();
         table_def!(table, scope);
         exp_table_def!(exp_table, scope, &self.exp_table);
+        ver_table_def!(ver_table, scope);
+
+        let txn = db.begin_write()?;
+        {
+            let mut versions = txn.open_table(ver_table)?;
+            if matches!(versions.get(key)?, Some(current) if current.value() >= version) {
+                return Ok(false);
+            }
+            versions.insert(key, version)?;
+        }
+        txn.open_table(table)?.insert(key, value)?;
+        txn.open_table(exp_table)?.remove(key)?;
+        self.mark_written(&txn, scope, key)?;
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.remove(scope, key);
+        }
+        Ok(true)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get_expiring(
+        &self,
+        scope: &str,
+        key: &[u8],
+    ) -> Result<Result<Option<(OwnedValue, Option<Duration>)>, BastehError>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
 
-        let exp_flags = match self.db.begin_read()?.open_table(exp_table) {
+        let exp_flags = match db.begin_read()?.open_table(exp_table) {
             Ok(r) => r.get(key)?.map(|v| v.value()),
             Err(e) => match e {
                 TableError::TableDoesNotExist(_) => None,
@@ -535,19 +2022,154 @@ impl RedbInner {
 
         if let Some(exp) = exp_flags {
             if exp.expired() {
-                return Ok(None);
+                return Ok(Ok(None));
             }
         }
 
-        let value = match self.db.begin_read()?.open_table(table) {
-            Ok(r) => r.get(key)?.map(|v| v.value()),
+        let value = match db.begin_read()?.open_table(table) {
+            Ok(r) => r
+                .get(key)?
+                .map(|v| self.decode_checked(v.value(), exp_flags.as_ref())),
             Err(e) => match e {
                 TableError::TableDoesNotExist(_) => None,
                 e => return Err(e.into()),
             },
         };
 
-        Ok(value.map(|v| (v, exp_flags.and_then(|e| e.expires_in()))))
+        Ok(value
+            .transpose()
+            .map(|value| value.map(|v| (v, exp_flags.and_then(|e| e.expires_in())))))
+    }
+
+    /// Same as get_expiring for multiple keys, all of them are read in a single transaction
+    #[allow(clippy::type_complexity)]
+    pub fn get_expiring_multi(
+        &self,
+        scope: &str,
+        keys: &[Box<[u8]>],
+    ) -> Result<Result<Vec<Option<(OwnedValue, Option<Duration>)>>, BastehError>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(Ok(vec![None; keys.len()])),
+            Err(e) => return Err(e.into()),
+        };
+        let exp_table = txn.open_table(exp_table).ok();
+
+        let mut res = Vec::with_capacity(keys.len());
+        for key in keys {
+            let key: &[u8] = key;
+            let exp_flags = match &exp_table {
+                Some(exp_table) => exp_table.get(key)?.map(|v| v.value()),
+                None => None,
+            };
+            if matches!(exp_flags, Some(exp) if exp.expired()) {
+                res.push(None);
+                continue;
+            }
+
+            let value = match table.get(key)? {
+                Some(v) => match self.decode_checked(v.value(), exp_flags.as_ref()) {
+                    Ok(value) => Some(value),
+                    Err(e) => return Ok(Err(e)),
+                },
+                None => None,
+            };
+            res.push(value.map(|v| (v, exp_flags.and_then(|e| e.expires_in()))));
+        }
+        Ok(Ok(res))
+    }
+
+    /// Read all the keys of the table with their expiries in a single transaction
+    pub fn snapshot(&self, scope: &str) -> Result<Vec<SnapshotEntry>, Error> {
+        let db = self.db.read();
+        table_def!(table, scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let exp_table = txn.open_table(exp_table).ok();
+
+        let mut res = Vec::new();
+        for item in table.iter()? {
+            let (key, value) = item?;
+            let exp_flags = match &exp_table {
+                Some(exp_table) => exp_table.get(key.value())?.map(|v| v.value()),
+                None => None,
+            };
+            if matches!(exp_flags, Some(exp) if exp.expired()) {
+                continue;
+            }
+            res.push((
+                key.value().to_vec(),
+                value.value(),
+                exp_flags.and_then(|e| e.expires_in()),
+            ));
+        }
+        Ok(res)
+    }
+
+    pub fn get_and_extend(
+        &mut self,
+        scope: &str,
+        key: &[u8],
+        duration: Duration,
+    ) -> Result<Result<Option<OwnedValue>, BastehError>, Error> {
+        let db = self.db.read();
+        let table = TableDefinition::<&[u8], RawValueWrapper>::new(scope);
+        exp_table_def!(exp_table, scope, &self.exp_table);
+
+        let txn = db.begin_write()?;
+        let value = {
+            let mut exp_table = txn.open_table(exp_table)?;
+            let exp = exp_table.get(key)?.map(|v| v.value());
+            if matches!(exp, Some(exp) if exp.expired()) {
+                return Ok(Ok(None));
+            }
+            let value = match txn.open_table(table)?.get(key)? {
+                Some(v) => self.decode_checked(v.value(), exp.as_ref()),
+                None => return Ok(Ok(None)),
+            };
+            let value = match value {
+                Ok(value) => value,
+                Err(e) => return Ok(Err(e)),
+            };
+
+            let mut exp = self.current_flags(&exp_table, key)?;
+            exp.expire_in(duration);
+            exp_table.insert(key, exp)?;
+            value
+        };
+        txn.commit()?;
+
+        if self.queue_started {
+            self.queue.push(scope, key, Instant::now() + duration);
+        }
+        Ok(Ok(Some(value)))
+    }
+}
+
+// Items of the stored list between start and end, or an error if the value is not a list
+fn list_range(
+    value: Option<OwnedValue>,
+    start: i64,
+    end: i64,
+) -> Result<Vec<OwnedValue>, BastehError> {
+    match value {
+        Some(OwnedValue::List(l)) => {
+            let (start, take) = resolve_range(l.len(), start, end);
+            Ok(l.into_iter().skip(start).take(take).collect())
+        }
+        Some(_) => Err(BastehError::TypeConversion),
+        None => Ok(Vec::new()),
     }
 }
 
@@ -589,6 +2211,11 @@ pub(crate) fn run_mutations(mut value: i64, mutations: &Mutation) -> i64 {
 impl RedbInner {
     pub fn listen(&mut self, rx: crossbeam_channel::Receiver<Message>) {
         while let Ok(Message { req, tx }) = rx.recv() {
+            if let Some(err) = self.format_error {
+                tx.send(Err(BastehError::custom(err))).ok();
+                continue;
+            }
+
             match req {
                 // Store methods
                 Request::Keys(scope) => {
@@ -599,35 +2226,181 @@ impl RedbInner {
                     )
                     .ok();
                 }
-                Request::Get(scope, key) => {
+                Request::KeysPage(scope, after, limit) => {
+                    tx.send(
+                        self.keys_page(&scope, after.as_deref(), limit)
+                            .map_err(BastehError::custom)
+                            .map(|v| Response::Iterator(Box::new(v.into_iter()))),
+                    )
+                    .ok();
+                }
+                Request::Scopes => {
+                    tx.send(
+                        self.scopes()
+                            .map_err(BastehError::custom)
+                            .map(|v| Response::Iterator(Box::new(v))),
+                    )
+                    .ok();
+                }
+                Request::SizeBytes(scope) => {
+                    tx.send(
+                        self.size_bytes(&scope)
+                            .map_err(BastehError::custom)
+                            .map(Response::Size),
+                    )
+                    .ok();
+                }
+                Request::DropScope(scope) => {
+                    tx.send(
+                        self.drop_scope(&scope)
+                            .map_err(BastehError::custom)
+                            .map(Response::Empty),
+                    )
+                    .ok();
+                }
+                Request::Get(scope, key) => {
+                    tx.send(
+                        self.get(&scope, &key)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Value),
+                    )
+                    .ok();
+                }
+                Request::ValueKind(scope, key) => {
+                    tx.send(
+                        self.value_kind(&scope, &key)
+                            .map_err(BastehError::custom)
+                            .map(Response::Kind),
+                    )
+                    .ok();
+                }
+                Request::GetRange(scope, key, start, end) => {
+                    tx.send(
+                        self.get_range(&scope, &key, start, end)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::ValueVec),
+                    )
+                    .ok();
+                }
+                Request::GetRangeMulti(scope, requests) => {
+                    tx.send(
+                        self.get_range_multi(&scope, &requests)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::ValueVecs),
+                    )
+                    .ok();
+                }
+                Request::Set(scope, key, value) => {
+                    tx.send(
+                        self.set(&scope, &key, value)
+                            .map_err(BastehError::custom)
+                            .map(Response::Empty),
+                    )
+                    .ok();
+                }
+                Request::SetRaw(scope, key, value) => {
+                    tx.send(
+                        self.set_raw(&scope, &key, &value)
+                            .map_err(BastehError::custom)
+                            .map(Response::Empty),
+                    )
+                    .ok();
+                }
+                Request::GetRaw(scope, key) => {
+                    tx.send(
+                        self.get_raw(&scope, &key)
+                            .map_err(BastehError::custom)
+                            .map(Response::Bytes),
+                    )
+                    .ok();
+                }
+                Request::Upsert(scope, key, value) => {
+                    tx.send(
+                        self.upsert(&scope, &key, value)
+                            .map_err(BastehError::custom)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
+                Request::SetKeepTtl(scope, key, value) => {
+                    tx.send(
+                        self.set_keepttl(&scope, &key, value)
+                            .map_err(BastehError::custom)
+                            .map(Response::Empty),
+                    )
+                    .ok();
+                }
+                Request::SetVersioned(scope, key, value, version) => {
+                    tx.send(
+                        self.set_versioned(&scope, &key, value, version)
+                            .map_err(BastehError::custom)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
+                Request::Pop(scope, key) => {
+                    tx.send(
+                        self.pop(&scope, &key)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Value),
+                    )
+                    .ok();
+                }
+                Request::PopPush(src_scope, src_key, dst_scope, dst_key) => {
                     tx.send(
-                        self.get(&scope, &key)
+                        self.pop_push(&src_scope, &src_key, &dst_scope, &dst_key)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::Value),
                     )
                     .ok();
                 }
-                Request::GetRange(scope, key, start, end) => {
+                Request::ListDrain(scope, key) => {
                     tx.send(
-                        self.get_range(&scope, &key, start, end)
+                        self.list_drain(&scope, &key)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::ValueVec),
                     )
                     .ok();
                 }
-                Request::Set(scope, key, value) => {
+                Request::HIncr(scope, key, field, delta) => {
                     tx.send(
-                        self.set(&scope, &key, value)
+                        self.hincr(&scope, &key, &field, delta)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Number),
+                    )
+                    .ok();
+                }
+                Request::HSet(scope, key, field, value) => {
+                    tx.send(
+                        self.hset(&scope, &key, &field, value)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::Empty),
                     )
                     .ok();
                 }
-                Request::Pop(scope, key) => {
+                Request::SetBit(scope, key, offset, bit) => {
                     tx.send(
-                        self.pop(&scope, &key)
+                        self.set_bit(&scope, &key, offset, bit)
                             .map_err(BastehError::custom)
-                            .map(Response::Value),
+                            .and_then(|res| res)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
+                Request::HDel(scope, key, field) => {
+                    tx.send(
+                        self.hdel(&scope, &key, &field)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Bool),
                     )
                     .ok();
                 }
@@ -635,6 +2408,7 @@ impl RedbInner {
                     tx.send(
                         self.push(&scope, &key, value)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::Empty),
                     )
                     .ok();
@@ -643,6 +2417,34 @@ impl RedbInner {
                     tx.send(
                         self.push_multiple(&scope, &key, value)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Empty),
+                    )
+                    .ok();
+                }
+                Request::PushReturningLen(scope, key, value) => {
+                    tx.send(
+                        self.push_returning_len(&scope, &key, value)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Count),
+                    )
+                    .ok();
+                }
+                Request::MutateList(scope, key, mutations) => {
+                    tx.send(
+                        self.mutate_list(&scope, &key, mutations)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Count),
+                    )
+                    .ok();
+                }
+                Request::PushCapped(scope, key, value, max_len) => {
+                    tx.send(
+                        self.push_capped(&scope, &key, value, max_len)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::Empty),
                     )
                     .ok();
@@ -651,10 +2453,47 @@ impl RedbInner {
                     tx.send(
                         self.mutate(&scope, &key, mutations)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res.map_err(BastehError::NotANumber))
+                            .map(Response::Numbers),
+                    )
+                    .ok();
+                }
+                Request::SetIf(scope, key, value, ord) => {
+                    tx.send(
+                        self.set_if(&scope, &key, value, ord)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
+                Request::IncrWithTtlFloor(scope, key, delta, dur) => {
+                    tx.send(
+                        self.incr_with_ttl_floor(&scope, &key, delta, dur)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::Number),
                     )
                     .ok();
                 }
+                Request::IncrFloat(scope, key, delta) => {
+                    tx.send(
+                        self.incr_float(&scope, &key, delta)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Float),
+                    )
+                    .ok();
+                }
+                Request::IncrBig(scope, key, delta) => {
+                    tx.send(
+                        self.incr_big(&scope, &key, delta)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::BigNumber),
+                    )
+                    .ok();
+                }
                 Request::Remove(scope, key) => {
                     tx.send(
                         self.remove(&scope, &key)
@@ -663,6 +2502,22 @@ impl RedbInner {
                     )
                     .ok();
                 }
+                Request::Delete(scope, key) => {
+                    tx.send(
+                        self.delete(&scope, &key)
+                            .map_err(BastehError::custom)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
+                Request::RemoveIf(scope, key, expected) => {
+                    tx.send(
+                        self.remove_if(&scope, &key, expected)
+                            .map_err(BastehError::custom)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
                 Request::Contains(scope, key) => {
                     tx.send(
                         self.contains_key(&scope, &key)
@@ -671,6 +2526,14 @@ impl RedbInner {
                     )
                     .ok();
                 }
+                Request::ContainsMulti(scope, keys) => {
+                    tx.send(
+                        self.contains_multi(&scope, &keys)
+                            .map_err(BastehError::custom)
+                            .map(Response::BoolVec),
+                    )
+                    .ok();
+                }
                 // Expiry methods
                 Request::Persist(scope, key) => {
                     tx.send(
@@ -688,6 +2551,14 @@ impl RedbInner {
                     )
                     .ok();
                 }
+                Request::ExpireMulti(scope, entries) => {
+                    tx.send(
+                        self.expire_multi(&scope, entries)
+                            .map_err(BastehError::custom)
+                            .map(Response::Empty),
+                    )
+                    .ok();
+                }
                 Request::Expiry(scope, key) => {
                     tx.send(
                         self.expiry(&scope, &key)
@@ -696,6 +2567,14 @@ impl RedbInner {
                     )
                     .ok();
                 }
+                Request::ModifiedAt(scope, key) => {
+                    tx.send(
+                        self.modified_at(&scope, &key)
+                            .map_err(BastehError::custom)
+                            .map(Response::Time),
+                    )
+                    .ok();
+                }
                 Request::Extend(scope, key, dur) => {
                     tx.send(
                         self.extend(&scope, &key, dur)
@@ -713,14 +2592,69 @@ impl RedbInner {
                     )
                     .ok();
                 }
+                Request::SwapExpiring(scope, key, value, dur) => {
+                    tx.send(
+                        self.swap_expiring(&scope, &key, value, dur)
+                            .map_err(BastehError::custom)
+                            .map(Response::Value),
+                    )
+                    .ok();
+                }
                 Request::GetExpiring(scope, key) => {
                     tx.send(
                         self.get_expiring(&scope, &key)
                             .map_err(BastehError::custom)
+                            .and_then(|res| res)
                             .map(Response::ValueDuration),
                     )
                     .ok();
                 }
+                Request::GetExpiringMulti(scope, keys) => {
+                    tx.send(
+                        self.get_expiring_multi(&scope, &keys)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::ValueDurationVec),
+                    )
+                    .ok();
+                }
+                Request::Snapshot(scope) => {
+                    tx.send(
+                        self.snapshot(&scope)
+                            .map_err(BastehError::custom)
+                            .map(Response::Snapshot),
+                    )
+                    .ok();
+                }
+                Request::GetAndExtend(scope, key, dur) => {
+                    tx.send(
+                        self.get_and_extend(&scope, &key, dur)
+                            .map_err(BastehError::custom)
+                            .and_then(|res| res)
+                            .map(Response::Value),
+                    )
+                    .ok();
+                }
+                // Maintenance methods
+                Request::Compact => {
+                    tx.send(self.compact().map(Response::Bool)).ok();
+                }
+                Request::SweepExpired(scope) => {
+                    tx.send(
+                        self.sweep_expired(&scope)
+                            .map_err(BastehError::custom)
+                            .map(Response::Count),
+                    )
+                    .ok();
+                }
+                Request::TableStats => {
+                    tx.send(
+                        self.table_stats()
+                            .map_err(BastehError::custom)
+                            .map(Response::TableStats),
+                    )
+                    .ok();
+                }
             }
         }
     }
@@ -728,7 +2662,7 @@ impl RedbInner {
 
 #[cfg(test)]
 mod tests {
-    use std::{path::Path, sync::Arc, time::Duration};
+    use std::{convert::TryInto, path::Path, sync::Arc, time::Duration};
 
     use bytes::{Bytes, BytesMut};
     use redb::TableDefinition;
@@ -736,12 +2670,15 @@ mod tests {
     use super::*;
 
     impl RedbInner {
-        fn from_arc_db(db: Arc<redb::Database>) -> Self {
+        fn from_arc_db(db: Arc<RwLock<redb::Database>>) -> Self {
             Self {
                 db,
-                exp_table: String::from("__EXPIRATIONS_TABLE__"),
+                exp_table: String::from(EXPIRATIONS_TABLE_SUFFIX),
                 queue: DelayQueue::new(),
                 queue_started: false,
+                write_timestamps: false,
+                checksums: false,
+                format_error: None,
             }
         }
     }
@@ -758,10 +2695,10 @@ mod tests {
     async fn test_redb_perform_deletion() {
         let dur = Duration::from_secs(1);
         let table = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope");
-        let db = Arc::new(open_database("/tmp/redb.perform_deletion.db"));
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.perform_deletion.db")));
 
         let mut store = RedbInner::from_arc_db(db.clone());
-        store.spawn_expiry_thread();
+        store.spawn_expiry_thread(Duration::from_millis(500));
 
         store
             .set_expiring(
@@ -776,11 +2713,13 @@ mod tests {
             store
                 .get("some_scope", b"key")
                 .unwrap()
+                .unwrap()
                 .map(|v| TryInto::<Bytes>::try_into(v).unwrap()),
             Some(Bytes::from_static(b"value"))
         );
         assert_eq!(
-            db.begin_read()
+            db.read()
+                .begin_read()
                 .unwrap()
                 .open_table(table)
                 .unwrap()
@@ -794,6 +2733,37 @@ mod tests {
         tokio::time::sleep(dur * 2).await;
 
         assert!(db
+            .read()
+            .begin_read()
+            .unwrap()
+            .open_table(table)
+            .unwrap()
+            .get(b"key".as_ref())
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_redb_expiry_interval() {
+        let table = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope");
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.expiry_interval.db")));
+
+        let mut store = RedbInner::from_arc_db(db.clone());
+        store.spawn_expiry_thread(Duration::from_millis(50));
+
+        store
+            .set_expiring(
+                "some_scope",
+                b"key",
+                OwnedValue::Number(10),
+                Duration::from_secs(1),
+            )
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+
+        assert!(db
+            .read()
             .begin_read()
             .unwrap()
             .open_table(table)
@@ -803,17 +2773,283 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_redb_corrupted_list() {
+        let list = OwnedValue::List(vec![
+            OwnedValue::Number(1),
+            OwnedValue::List(vec![OwnedValue::String("item".into())]),
+            OwnedValue::Map(vec![(b"field".to_vec(), OwnedValue::BigNumber(2))]),
+        ]);
+        let bytes = OwnedValueWrapper::as_bytes(&list);
+        assert_eq!(OwnedValueWrapper::from_bytes(&bytes), list);
+
+        // Truncated lists are read as bytes instead of panicking, unless cut between the items
+        for len in 1..bytes.len() {
+            assert_ne!(OwnedValueWrapper::from_bytes(&bytes[..len]), list);
+        }
+        let len = bytes.len() - 1;
+        assert_eq!(
+            OwnedValueWrapper::from_bytes(&bytes[..len]),
+            OwnedValue::Bytes(BytesMut::from(&bytes[1..len]))
+        );
+
+        // So are the ones nested too deep
+        let mut nested = OwnedValue::List(vec![]);
+        for _ in 0..100 {
+            nested = OwnedValue::List(vec![nested]);
+        }
+        let bytes = OwnedValueWrapper::as_bytes(&nested);
+        assert_eq!(
+            OwnedValueWrapper::from_bytes(&bytes),
+            OwnedValue::Bytes(BytesMut::from(&bytes[1..]))
+        );
+    }
+
+    #[test]
+    fn test_redb_legacy_format_version() {
+        let metadata = TableDefinition::<&str, u8>::new(METADATA_TABLE);
+        let table = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope");
+        let db = Arc::new(RwLock::new(open_database(
+            "/tmp/redb.legacy_format_version.db",
+        )));
+        let version = || {
+            let db = db.read();
+            let txn = db.begin_read().unwrap();
+            let table = txn.open_table(metadata).unwrap();
+            let version = table.get(FORMAT_VERSION_KEY).unwrap().map(|v| v.value());
+            version
+        };
+
+        // Written before the version was recorded
+        {
+            let db = db.read();
+            let txn = db.begin_write().unwrap();
+            txn.open_table(table)
+                .unwrap()
+                .insert(
+                    b"key".as_ref(),
+                    OwnedValue::List(vec![OwnedValue::String("item".into())]),
+                )
+                .unwrap();
+            txn.commit().unwrap();
+        }
+
+        // Version 1 databases are migrated to the current version and stay readable
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.check_format_version().unwrap();
+        assert!(inner.format_error.is_none());
+        assert_eq!(version(), Some(FORMAT_VERSION));
+        assert_eq!(
+            inner.get("some_scope", b"key").unwrap().unwrap(),
+            Some(OwnedValue::List(vec![OwnedValue::String("item".into())]))
+        );
+
+        {
+            let db = db.read();
+            let txn = db.begin_write().unwrap();
+            txn.open_table(metadata)
+                .unwrap()
+                .insert(FORMAT_VERSION_KEY, LEGACY_FORMAT_VERSION)
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.check_format_version().unwrap();
+        assert!(inner.format_error.is_none());
+        assert_eq!(version(), Some(FORMAT_VERSION));
+    }
+
+    #[test]
+    fn test_redb_expiry_table_suffix_recorded() {
+        let db = Arc::new(RwLock::new(open_database(
+            "/tmp/redb.expiry_table_suffix_recorded.db",
+        )));
+        let dur = Duration::from_secs(60);
+
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.exp_table = String::from("\0expiry");
+        inner.check_format_version().unwrap();
+        inner
+            .set_expiring("sessions", b"key", OwnedValue::Number(1), dur)
+            .unwrap();
+        // The expiry table of the scope under the default suffix
+        inner
+            .set(
+                "sessions__EXPIRATIONS_TABLE__",
+                b"key",
+                OwnedValue::Number(2),
+            )
+            .unwrap();
+
+        // Existing databases keep the suffix they were created with, so the scopes stay apart
+        let mut inner = RedbInner::from_arc_db(db);
+        inner.check_format_version().unwrap();
+        assert_eq!(inner.exp_table, "\0expiry");
+        assert!(matches!(
+            inner.get_expiring("sessions", b"key").unwrap(),
+            Ok(Some((OwnedValue::Number(1), Some(_))))
+        ));
+        assert!(matches!(
+            inner
+                .get_expiring("sessions__EXPIRATIONS_TABLE__", b"key")
+                .unwrap(),
+            Ok(Some((OwnedValue::Number(2), None)))
+        ));
+        let mut scopes = inner.scopes().unwrap().collect::<Vec<_>>();
+        scopes.sort();
+        assert_eq!(
+            scopes,
+            vec![
+                b"sessions".to_vec(),
+                b"sessions__EXPIRATIONS_TABLE__".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redb_checksums() {
+        let table = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope");
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.checksums.db")));
+        let overwrite = |value: OwnedValue| {
+            let db = db.read();
+            let txn = db.begin_write().unwrap();
+            txn.open_table(table)
+                .unwrap()
+                .insert(b"key".as_ref(), value)
+                .unwrap();
+            txn.commit().unwrap();
+        };
+
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.checksums = true;
+        inner.check_format_version().unwrap();
+        assert!(inner.checksums);
+
+        inner
+            .set("some_scope", b"key", OwnedValue::Number(10))
+            .unwrap();
+        inner
+            .expire("some_scope", b"key", Duration::from_secs(100))
+            .unwrap();
+        assert_eq!(
+            inner.get("some_scope", b"key").unwrap().unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+
+        // Values changed without updating their checksum are reported as corrupted
+        overwrite(OwnedValue::Number(20));
+        assert!(matches!(
+            inner.get("some_scope", b"key").unwrap(),
+            Err(BastehError::Corruption)
+        ));
+        assert!(matches!(
+            inner.get_expiring("some_scope", b"key").unwrap(),
+            Err(BastehError::Corruption)
+        ));
+
+        // The mode is kept on reopen
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.check_format_version().unwrap();
+        assert!(inner.checksums);
+        inner
+            .set("some_scope", b"key", OwnedValue::Number(30))
+            .unwrap();
+        assert_eq!(
+            inner.get("some_scope", b"key").unwrap().unwrap(),
+            Some(OwnedValue::Number(30))
+        );
+
+        // Databases holding data before the mode was recorded don't have checksums
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.checksums_legacy.db")));
+        {
+            let db = db.read();
+            let txn = db.begin_write().unwrap();
+            txn.open_table(table)
+                .unwrap()
+                .insert(b"key".as_ref(), OwnedValue::Number(10))
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        let mut inner = RedbInner::from_arc_db(db);
+        inner.checksums = true;
+        inner.check_format_version().unwrap();
+        assert!(!inner.checksums);
+        assert_eq!(
+            inner.get("some_scope", b"key").unwrap().unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redb_format_version() {
+        let metadata = TableDefinition::<&str, u8>::new(METADATA_TABLE);
+        let table = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope");
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.format_version.db")));
+
+        // New databases are marked with the current version
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.check_format_version().unwrap();
+        assert!(inner.format_error.is_none());
+        {
+            let db = db.read();
+            let txn = db.begin_read().unwrap();
+            let table = txn.open_table(metadata).unwrap();
+            let version = table.get(FORMAT_VERSION_KEY).unwrap().unwrap().value();
+            assert_eq!(version, FORMAT_VERSION);
+        }
+
+        {
+            let db = db.read();
+            let txn = db.begin_write().unwrap();
+            txn.open_table(metadata)
+                .unwrap()
+                .insert(FORMAT_VERSION_KEY, FORMAT_VERSION + 1)
+                .unwrap();
+            txn.open_table(table)
+                .unwrap()
+                .insert(b"key".as_ref(), OwnedValue::Number(10))
+                .unwrap();
+            txn.commit().unwrap();
+        }
+
+        // Values written by another version are rejected instead of being misdecoded
+        let mut inner = RedbInner::from_arc_db(db.clone());
+        inner.check_format_version().unwrap();
+        let expected = FormatVersionError {
+            found: FORMAT_VERSION + 1,
+            supported: FORMAT_VERSION,
+        };
+        assert_eq!(inner.format_error, Some(expected));
+
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        std::thread::spawn(move || inner.listen(rx));
+
+        let (res_tx, res_rx) = tokio::sync::oneshot::channel();
+        tx.send(Message {
+            req: Request::Get("some_scope".into(), b"key".as_ref().into()),
+            tx: res_tx,
+        })
+        .unwrap();
+        match res_rx.await.unwrap() {
+            Err(BastehError::Custom(err)) => {
+                assert_eq!(err.downcast_ref::<FormatVersionError>(), Some(&expected))
+            }
+            _ => panic!("Expected a format version error"),
+        }
+    }
+
     #[tokio::test]
     async fn test_redb_scan_db() {
         let dur = Duration::from_secs(1);
         let table = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope");
         let table2 = TableDefinition::<&[u8], OwnedValueWrapper>::new("some_scope2");
-        let db = Arc::new(open_database("/tmp/redb.scan_db.db"));
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.scan_db.db")));
 
         {
             exp_table_def!(exp_table, "some_scope", "__EXPIRATIONS_TABLE__");
             exp_table_def!(exp_table2, "some_scope2", "__EXPIRATIONS_TABLE__");
 
+            let db = db.read();
             let txn = db.begin_write().unwrap();
             txn.open_table(table)
                 .unwrap()
@@ -856,6 +3092,7 @@ mod tests {
         store.scan_db().unwrap();
 
         assert!(db
+            .read()
             .begin_read()
             .unwrap()
             .open_table(table)
@@ -866,6 +3103,7 @@ mod tests {
             .is_none());
 
         assert!(db
+            .read()
             .begin_read()
             .unwrap()
             .open_table(table2)
@@ -875,4 +3113,45 @@ mod tests {
             .map(|v| v.value())
             .is_none());
     }
+
+    #[tokio::test]
+    async fn test_redb_drop_scope() {
+        let db = Arc::new(RwLock::new(open_database("/tmp/redb.drop_scope.db")));
+        let table_names = || {
+            let db = db.read();
+            let txn = db.begin_read().unwrap();
+            txn.list_tables()
+                .unwrap()
+                .map(|t| t.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let mut store = RedbInner::from_arc_db(db.clone());
+        store.spawn_expiry_thread(Duration::from_millis(50));
+
+        store
+            .set("some_scope", b"key1", OwnedValue::Number(1))
+            .unwrap();
+        store
+            .set_expiring(
+                "some_scope",
+                b"key2",
+                OwnedValue::Number(2),
+                Duration::from_millis(200),
+            )
+            .unwrap();
+        store
+            .set_versioned("some_scope", b"key3", OwnedValue::Number(3), 1)
+            .unwrap();
+        assert!(table_names().iter().any(|name| name == "some_scope"));
+
+        store.drop_scope("some_scope").unwrap();
+        assert_eq!(store.keys("some_scope").unwrap().count(), 0);
+
+        // The queued expiration of key2 shouldn't create the table again
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!table_names()
+            .iter()
+            .any(|name| name.starts_with("some_scope")));
+    }
 }
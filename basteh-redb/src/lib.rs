@@ -1,18 +1,42 @@
-use std::time::Duration;
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant, SystemTime},
+};
 
 use basteh::{
-    dev::{OwnedValue, Provider, Value},
+    dev::{
+        paged_stream, BoxStream, ListMutation, OwnedValue, Provider, SnapshotEntry, Value,
+        ValueKind,
+    },
     BastehError,
 };
-use inner::RedbInner;
+use crossbeam_channel::TrySendError;
+use inner::{scope_table_name, RedbInner, EXPIRATIONS_TABLE_SUFFIX};
 use message::{Message, Request, Response};
 
+mod compaction;
 mod delayqueue;
 mod flags;
+mod format;
 mod inner;
 mod message;
+mod runtime;
+mod stats;
 mod value;
 
+pub use compaction::CompactionBusy;
+pub use flags::ExpiryFlags;
+pub use format::{FormatVersionError, SelfTestError, FORMAT_VERSION};
+pub use stats::TableStat;
+
+const DEFAULT_EXPIRY_INTERVAL: Duration = Duration::from_millis(500);
+// Number of requests waiting for the worker threads before new ones are rejected
+const REQUEST_QUEUE_SIZE: usize = 4096;
+// Number of keys fetched at once by keys_stream
+const KEYS_PAGE_SIZE: usize = 1000;
+// How often pop_blocking checks the list while waiting
+const POP_BLOCKING_INTERVAL: Duration = Duration::from_millis(50);
+
 /// Reexport of redb Database, to make sure we're using the same version
 pub use redb::Database;
 
@@ -43,6 +67,10 @@ pub struct RedbBackend<T = ()> {
 
     perform_deletion: bool,
     scan_db_on_start: bool,
+    expiry_interval: Duration,
+    write_timestamps: bool,
+    checksums: bool,
+    expiry_table_suffix: String,
 }
 
 impl RedbBackend<()> {
@@ -52,6 +80,10 @@ impl RedbBackend<()> {
             inner: db,
             perform_deletion: false,
             scan_db_on_start: false,
+            expiry_interval: DEFAULT_EXPIRY_INTERVAL,
+            write_timestamps: false,
+            checksums: false,
+            expiry_table_suffix: String::from(EXPIRATIONS_TABLE_SUFFIX),
         }
     }
 }
@@ -65,66 +97,371 @@ impl<T> RedbBackend<T> {
         self
     }
 
+    /// Set how long the expiry thread waits for an item before waking up when nothing is due,
+    /// defaults to 500ms.
+    ///
+    /// Items wake the thread up as soon as they're due, so this mostly bounds the idle wake-ups and
+    /// how long it takes for the thread to notice the backend is dropped. Lower values reduce the
+    /// deletion lag in those cases at the cost of more wake-ups, higher values save CPU.
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_expiry_interval(mut self, interval: Duration) -> Self {
+        self.expiry_interval = interval;
+        self
+    }
+
     /// If set to true, actor will scan the database on start to mark expired items.
     #[must_use = "Should be started by calling start method"]
     pub fn scan_db_on_start(mut self, to: bool) -> Self {
         self.scan_db_on_start = to;
         self
     }
+
+    /// Record the time each value is written, so it can be read with
+    /// [`modified_at`](basteh::dev::Provider::modified_at).
+    ///
+    /// It's off by default, as every write has to update the expiry table of the scope as well.
+    /// The time is kept in the expiry flags of the key, so it can be turned on or off for an
+    /// existing database, values written while it was off don't have a write time.
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_write_timestamps(mut self, to: bool) -> Self {
+        self.write_timestamps = to;
+        self
+    }
+
+    /// Store a CRC32 checksum of every value in its expiry flags, so corrupted values are detected
+    /// when read.
+    ///
+    /// It's off by default, as every write has to update the expiry table of the scope as well.
+    /// `get` and the other reads return [`Corruption`](basteh::BastehError::Corruption) for values
+    /// which don't match their checksum. The mode is recorded in the database when it's created,
+    /// existing databases keep the mode they were created with and this setting is ignored for
+    /// them. Databases holding data from before the mode was recorded are marked as having no
+    /// checksums.
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_checksums(mut self, to: bool) -> Self {
+        self.checksums = to;
+        self
+    }
+
+    /// Set the suffix added to the name of a scope to get the name of the table holding the
+    /// expiry flags of its keys, defaults to `__EXPIRATIONS_TABLE__`.
+    ///
+    /// Scopes ending with the suffix are rejected with
+    /// [`InvalidKey`](basteh::BastehError::InvalidKey), as their table would be taken for the
+    /// expiry table of another scope. If the scope names come from users and may end with the
+    /// default suffix, a suffix they can't contain(ex. with a `\0`) keeps them all usable.
+    ///
+    /// The expiry tables are only found by their names, so the suffix is recorded in the database
+    /// when it's created, existing databases keep the suffix they were created with and this
+    /// setting is ignored for them. Databases created before the suffix was recorded keep the one
+    /// they're opened with first.
+    ///
+    /// ## Panics
+    /// If the suffix is empty, as the expiry tables would be the tables of the scopes themselves.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh_redb::{Database, RedbBackend};
+    ///
+    /// # async fn your_main() {
+    /// let db = Database::create("/tmp/test.db").expect("Couldn't open redb database");
+    /// let provider = RedbBackend::from_db(db)
+    ///     .with_expiry_table_suffix("\0expiry")
+    ///     .start(4);
+    /// # }
+    /// ```
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_expiry_table_suffix(mut self, suffix: impl Into<String>) -> Self {
+        let suffix = suffix.into();
+        assert!(!suffix.is_empty(), "The expiry table suffix can't be empty");
+        self.expiry_table_suffix = suffix;
+        self
+    }
+
+    fn map_inner<U>(self, f: impl FnOnce(T) -> U) -> RedbBackend<U> {
+        RedbBackend {
+            inner: f(self.inner),
+            perform_deletion: self.perform_deletion,
+            scan_db_on_start: self.scan_db_on_start,
+            expiry_interval: self.expiry_interval,
+            write_timestamps: self.write_timestamps,
+            checksums: self.checksums,
+            expiry_table_suffix: self.expiry_table_suffix,
+        }
+    }
 }
 
 impl RedbBackend<redb::Database> {
+    /// Start the backend with `thread_num` threads handling the requests.
+    ///
+    /// If the database was written with another [`FORMAT_VERSION`](crate::FORMAT_VERSION), all
+    /// the operations return a [`FormatVersionError`](crate::FormatVersionError) and expired items
+    /// are left untouched.
     pub fn start(self, thread_num: usize) -> RedbBackend<crossbeam_channel::Sender<Message>> {
-        let mut inner = RedbInner::from_db(self.inner);
-        let (tx, rx) = crossbeam_channel::bounded(4096);
+        let mut backend = self.map_inner(RedbInner::from_db);
+        backend.inner.checksums = backend.checksums;
+        backend.inner.exp_table = backend.expiry_table_suffix.clone();
+        backend.inner.check_format_version().ok();
+        backend.spawn(thread_num)
+    }
 
-        if self.scan_db_on_start && self.perform_deletion {
+    /// Same as [`start`](Self::start), but checks the database before starting the backend and
+    /// returns the error instead of deferring it to the first operation.
+    ///
+    /// A value is written to the database, read back and removed, so it fails if the database
+    /// can't be written(ex. a full disk) or was written with another
+    /// [`FORMAT_VERSION`](crate::FORMAT_VERSION).
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh::Basteh;
+    /// use basteh_redb::{RedbBackend, Database};
+    ///
+    /// # async fn your_main() {
+    /// let db = Database::create("/tmp/test.db").expect("Couldn't open redb database");
+    /// let provider = RedbBackend::from_db(db)
+    ///     .start_checked(4)
+    ///     .expect("Database is not usable");
+    /// let storage = Basteh::build().provider(provider).finish();
+    /// # }
+    /// ```
+    pub fn start_checked(
+        self,
+        thread_num: usize,
+    ) -> basteh::Result<RedbBackend<crossbeam_channel::Sender<Message>>> {
+        let mut backend = self.map_inner(RedbInner::from_db);
+        backend.inner.checksums = backend.checksums;
+        backend.inner.exp_table = backend.expiry_table_suffix.clone();
+        backend
+            .inner
+            .check_format_version()
+            .map_err(BastehError::custom)?;
+        if let Some(err) = backend.inner.format_error {
+            return Err(BastehError::custom(err));
+        }
+        backend.inner.self_test()?;
+        Ok(backend.spawn(thread_num))
+    }
+}
+
+impl RedbBackend<RedbInner> {
+    fn spawn(self, thread_num: usize) -> RedbBackend<crossbeam_channel::Sender<Message>> {
+        let mut inner = self.inner;
+        inner.write_timestamps = self.write_timestamps;
+        let exp_table = inner.exp_table.clone();
+        let (tx, rx) = crossbeam_channel::bounded(REQUEST_QUEUE_SIZE);
+
+        let compatible = inner.format_error.is_none();
+
+        if compatible && self.scan_db_on_start && self.perform_deletion {
             inner.scan_db().ok();
         }
 
-        if self.perform_deletion {
-            inner.spawn_expiry_thread();
+        let perform_deletion = compatible && self.perform_deletion;
+        if perform_deletion {
+            inner.spawn_expiry_thread(self.expiry_interval);
         }
 
         for _ in 0..thread_num {
             let mut inner = inner.clone();
             let rx = rx.clone();
-            tokio::task::spawn_blocking(move || {
+            runtime::spawn_blocking(move || {
                 inner.listen(rx);
             });
         }
 
         RedbBackend {
             inner: tx,
-            perform_deletion: false,
+            perform_deletion,
             scan_db_on_start: false,
+            expiry_interval: self.expiry_interval,
+            write_timestamps: self.write_timestamps,
+            checksums: self.checksums,
+            // The suffix recorded in the database is used instead of the requested one
+            expiry_table_suffix: exp_table,
         }
     }
 }
 
 impl RedbBackend<crossbeam_channel::Sender<Message>> {
+    /// Scopes are stored as redb tables, which shouldn't collide with the internal tables
+    fn table_name(&self, scope: &[u8]) -> basteh::Result<Box<str>> {
+        scope_table_name(scope, &self.expiry_table_suffix)
+    }
+
     async fn msg(&self, req: Request) -> basteh::Result<Response> {
         let (tx, rx) = tokio::sync::oneshot::channel();
 
         self.inner
             .try_send(Message { req, tx })
-            .map_err(BastehError::custom)?;
-        rx.await.map_err(BastehError::custom)?
+            .map_err(|err| match err {
+                TrySendError::Disconnected(_) => BastehError::BackendUnavailable,
+                err => BastehError::custom(err),
+            })?;
+        // The sender is dropped without a response if the worker is gone
+        rx.await.map_err(|_| BastehError::BackendUnavailable)?
+    }
+
+    /// Returns true if expired items are deleted by the expiry thread, false if they're only
+    /// hidden until they're overwritten or swept.
+    ///
+    /// It's false if the backend was started without
+    /// [`perform_deletion`](RedbBackend::perform_deletion), or if the database was written with
+    /// another [`FORMAT_VERSION`](crate::FORMAT_VERSION).
+    pub fn is_deletion_enabled(&self) -> bool {
+        self.perform_deletion
+    }
+
+    /// Returns the number of requests waiting for a worker thread.
+    ///
+    /// The queue holds up to 4096 requests and the ones sent while it's full fail, a length
+    /// staying high under load means the worker threads can't keep up and more of them may help.
+    pub fn queue_len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Compact the database file to reclaim the space left by removed items, returns false if
+    /// there was nothing to compact.
+    ///
+    /// Compaction needs exclusive access to the database, so it waits for the running operations
+    /// to finish and the new ones wait for the compaction. If the database stays busy for more
+    /// than a second it gives up with [`CompactionBusy`](crate::CompactionBusy) without changing
+    /// anything. It can take a while on large databases, so it's best called periodically when
+    /// the load is low.
+    pub async fn compact(&self) -> basteh::Result<bool> {
+        match self.msg(Request::Compact).await? {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Remove the expired items of the scope from the database, returning how many were removed.
+    ///
+    /// Without [`perform_deletion`](RedbBackend::perform_deletion) expired items are only hidden,
+    /// this removes them on demand without starting the expiry thread, and is cheaper than
+    /// scanning the whole database as it only goes through the expiring items of the scope.
+    pub async fn sweep_expired(&self, scope: impl AsRef<[u8]>) -> basteh::Result<u64> {
+        match self
+            .msg(Request::SweepExpired(self.table_name(scope.as_ref())?))
+            .await?
+        {
+            Response::Count(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Get the entry counts of every scope, split between the live and the expired entries.
+    ///
+    /// Expired entries are counted until they're removed by the expiry thread or
+    /// [`sweep_expired`](Self::sweep_expired). All the tables are read in a single read
+    /// transaction, so the counts are consistent with each other.
+    pub async fn table_stats(&self) -> basteh::Result<Vec<TableStat>> {
+        match self.msg(Request::TableStats).await? {
+            Response::TableStats(r) => Ok(r),
+            _ => unreachable!(),
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
-    async fn keys(&self, scope: &str) -> basteh::Result<Box<dyn Iterator<Item = Vec<u8>>>> {
-        match self.msg(Request::Keys(scope.into())).await? {
+    async fn keys(&self, scope: &[u8]) -> basteh::Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        match self.msg(Request::Keys(self.table_name(scope)?)).await? {
+            Response::Iterator(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn keys_sorted(&self, scope: &[u8]) -> basteh::Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        // Redb tables are already sorted
+        self.keys(scope).await
+    }
+
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, basteh::Result<Vec<u8>>> {
+        paged_stream(move |after: Option<Vec<u8>>| async move {
+            let keys = match self
+                .msg(Request::KeysPage(
+                    self.table_name(scope)?,
+                    after.map(Into::into),
+                    KEYS_PAGE_SIZE,
+                ))
+                .await?
+            {
+                Response::Iterator(r) => r.collect::<Vec<_>>(),
+                _ => unreachable!(),
+            };
+            // A short page is the last one, otherwise the next page starts after its last key
+            let next = if keys.len() < KEYS_PAGE_SIZE {
+                None
+            } else {
+                keys.last().cloned()
+            };
+            Ok((keys, next))
+        })
+    }
+
+    async fn scopes(&self) -> basteh::Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        match self.msg(Request::Scopes).await? {
             Response::Iterator(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn set(&self, scope: &str, key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+    async fn size_bytes(&self, scope: &[u8]) -> basteh::Result<u64> {
         match self
-            .msg(Request::Set(scope.into(), key.into(), value.into_owned()))
+            .msg(Request::SizeBytes(self.table_name(scope)?))
+            .await?
+        {
+            Response::Size(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> basteh::Result<()> {
+        match self
+            .msg(Request::DropScope(self.table_name(scope)?))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+        match self
+            .msg(Request::Set(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<bool> {
+        match self
+            .msg(Request::Upsert(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+        match self
+            .msg(Request::SetKeepTtl(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+            ))
             .await?
         {
             Response::Empty(r) => Ok(r),
@@ -132,22 +469,64 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn get(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
-        match self.msg(Request::Get(scope.into(), key.into())).await? {
+    async fn get(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
+        match self
+            .msg(Request::Get(self.table_name(scope)?, key.into()))
+            .await?
+        {
             Response::Value(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> basteh::Result<()> {
+        match self
+            .msg(Request::SetRaw(
+                self.table_name(scope)?,
+                key.into(),
+                value.to_vec(),
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<Vec<u8>>> {
+        match self
+            .msg(Request::GetRaw(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Bytes(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn value_kind(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<ValueKind>> {
+        match self
+            .msg(Request::ValueKind(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Kind(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
     async fn get_range(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         start: i64,
         end: i64,
     ) -> basteh::Result<Vec<OwnedValue>> {
         match self
-            .msg(Request::GetRange(scope.into(), key.into(), start, end))
+            .msg(Request::GetRange(
+                self.table_name(scope)?,
+                key.into(),
+                start,
+                end,
+            ))
             .await?
         {
             Response::ValueVec(r) => Ok(r),
@@ -155,27 +534,39 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn push(&self, scope: &str, key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> basteh::Result<Vec<Vec<OwnedValue>>> {
         match self
-            .msg(Request::Push(scope.into(), key.into(), value.into_owned()))
+            .msg(Request::GetRangeMulti(
+                self.table_name(scope)?,
+                requests
+                    .iter()
+                    .map(|(key, start, end)| ((*key).into(), *start, *end))
+                    .collect(),
+            ))
             .await?
         {
-            Response::Empty(r) => Ok(r),
+            Response::ValueVecs(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn push_multiple(
+    async fn hset(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
-        value: Vec<Value<'_>>,
+        field: &[u8],
+        value: Value<'_>,
     ) -> basteh::Result<()> {
         match self
-            .msg(Request::PushMulti(
-                scope.into(),
+            .msg(Request::HSet(
+                self.table_name(scope)?,
                 key.into(),
-                value.into_iter().map(|v| v.into_owned()).collect(),
+                field.into(),
+                value.into_owned(),
             ))
             .await?
         {
@@ -184,21 +575,20 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn pop(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
-        match self.msg(Request::Pop(scope.into(), key.into())).await? {
-            Response::Value(r) => Ok(r),
-            _ => unreachable!(),
-        }
-    }
-
-    async fn mutate(
+    async fn hincr(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
-        mutations: basteh::dev::Mutation,
+        field: &[u8],
+        delta: i64,
     ) -> basteh::Result<i64> {
         match self
-            .msg(Request::MutateNumber(scope.into(), key.into(), mutations))
+            .msg(Request::HIncr(
+                self.table_name(scope)?,
+                key.into(),
+                field.into(),
+                delta,
+            ))
             .await?
         {
             Response::Number(r) => Ok(r),
@@ -206,16 +596,34 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn remove(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
-        match self.msg(Request::Remove(scope.into(), key.into())).await? {
-            Response::Value(r) => Ok(r),
+    async fn set_bit(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        offset: u64,
+        value: bool,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::SetBit(
+                self.table_name(scope)?,
+                key.into(),
+                offset,
+                value,
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn contains_key(&self, scope: &str, key: &[u8]) -> basteh::Result<bool> {
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> basteh::Result<bool> {
         match self
-            .msg(Request::Contains(scope.into(), key.into()))
+            .msg(Request::HDel(
+                self.table_name(scope)?,
+                key.into(),
+                field.into(),
+            ))
             .await?
         {
             Response::Bool(r) => Ok(r),
@@ -223,16 +631,32 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn persist(&self, scope: &str, key: &[u8]) -> basteh::Result<()> {
-        match self.msg(Request::Persist(scope.into(), key.into())).await? {
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+        match self
+            .msg(Request::Push(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
             Response::Empty(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn expire(&self, scope: &str, key: &[u8], expire_in: Duration) -> basteh::Result<()> {
+    async fn push_multiple(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Vec<Value<'_>>,
+    ) -> basteh::Result<()> {
         match self
-            .msg(Request::Expire(scope.into(), key.into(), expire_in))
+            .msg(Request::PushMulti(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_iter().map(|v| v.into_owned()).collect(),
+            ))
             .await?
         {
             Response::Empty(r) => Ok(r),
@@ -240,36 +664,57 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn expiry(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<Duration>> {
-        match self.msg(Request::Expiry(scope.into(), key.into())).await? {
-            Response::Duration(r) => Ok(r),
+    async fn push_returning_len(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+    ) -> basteh::Result<u64> {
+        match self
+            .msg(Request::PushReturningLen(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
+            Response::Count(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn extend(&self, scope: &str, key: &[u8], duration: Duration) -> basteh::Result<()> {
+    async fn mutate_list(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: ListMutation,
+    ) -> basteh::Result<u64> {
         match self
-            .msg(Request::Extend(scope.into(), key.into(), duration))
+            .msg(Request::MutateList(
+                self.table_name(scope)?,
+                key.into(),
+                mutations,
+            ))
             .await?
         {
-            Response::Empty(r) => Ok(r),
+            Response::Count(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn set_expiring(
+    async fn push_capped(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         value: Value<'_>,
-        expire_in: Duration,
+        max_len: u64,
     ) -> basteh::Result<()> {
         match self
-            .msg(Request::SetExpiring(
-                scope.into(),
+            .msg(Request::PushCapped(
+                self.table_name(scope)?,
                 key.into(),
                 value.into_owned(),
-                expire_in,
+                max_len,
             ))
             .await?
         {
@@ -278,57 +723,879 @@ impl Provider for RedbBackend<crossbeam_channel::Sender<Message>> {
         }
     }
 
-    async fn get_expiring(
-        &self,
-        scope: &str,
-        key: &[u8],
-    ) -> basteh::Result<Option<(OwnedValue, Option<Duration>)>> {
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
         match self
-            .msg(Request::GetExpiring(scope.into(), key.into()))
+            .msg(Request::Pop(self.table_name(scope)?, key.into()))
             .await?
         {
-            Response::ValueDuration(r) => Ok(r),
+            Response::Value(r) => Ok(r),
             _ => unreachable!(),
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::path::Path;
-
-    use basteh::test_utils::*;
-
-    use crate::RedbBackend;
 
-    type ReDb = RedbBackend<redb::Database>;
-
-    fn open_database(path: &str) -> ReDb {
-        let p = Path::new(path);
-        if p.exists() {
-            std::fs::remove_file(p).ok();
+    async fn pop_blocking(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        timeout: Duration,
+    ) -> basteh::Result<Option<OwnedValue>> {
+        // There is no notification for the pushes, so the list is checked periodically
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(value) = self.pop(scope, key).await? {
+                return Ok(Some(value));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            runtime::sleep(POP_BLOCKING_INTERVAL.min(deadline - now)).await;
         }
-
-        RedbBackend::from_db(redb::Database::create(path).unwrap())
     }
 
-    #[tokio::test]
-    async fn test_redb_store() {
-        test_store(open_database("/tmp/redb.store.db").start(1)).await;
+    async fn pop_push(
+        &self,
+        src_scope: &[u8],
+        src_key: &[u8],
+        dst_scope: &[u8],
+        dst_key: &[u8],
+    ) -> basteh::Result<Option<OwnedValue>> {
+        match self
+            .msg(Request::PopPush(
+                self.table_name(src_scope)?,
+                src_key.into(),
+                self.table_name(dst_scope)?,
+                dst_key.into(),
+            ))
+            .await?
+        {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
     }
 
-    #[tokio::test]
-    async fn test_redb_mutations() {
-        test_mutations(open_database("/tmp/redb.mutate.db").start(1)).await;
+    async fn list_drain(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Vec<OwnedValue>> {
+        match self
+            .msg(Request::ListDrain(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::ValueVec(r) => Ok(r),
+            _ => unreachable!(),
+        }
     }
 
-    #[tokio::test]
-    async fn test_redb_expiry() {
-        test_expiry(open_database("/tmp/redb.expiry.db").start(1), 2).await;
+    async fn mutate(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: basteh::dev::Mutation,
+    ) -> basteh::Result<i64> {
+        match self
+            .msg(Request::MutateNumber(
+                self.table_name(scope)?,
+                key.into(),
+                mutations,
+            ))
+            .await?
+        {
+            Response::Numbers((_, r)) => Ok(r),
+            _ => unreachable!(),
+        }
     }
 
-    #[tokio::test]
-    async fn test_redb_expiry_store() {
-        test_expiry_store(open_database("/tmp/redb.exp_store.db").start(1), 2).await;
+    async fn mutate_returning(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: basteh::dev::Mutation,
+    ) -> basteh::Result<(i64, i64)> {
+        match self
+            .msg(Request::MutateNumber(
+                self.table_name(scope)?,
+                key.into(),
+                mutations,
+            ))
+            .await?
+        {
+            Response::Numbers(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn incr_float(&self, scope: &[u8], key: &[u8], delta: f64) -> basteh::Result<f64> {
+        match self
+            .msg(Request::IncrFloat(
+                self.table_name(scope)?,
+                key.into(),
+                delta,
+            ))
+            .await?
+        {
+            Response::Float(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn incr_big(&self, scope: &[u8], key: &[u8], delta: i128) -> basteh::Result<i128> {
+        match self
+            .msg(Request::IncrBig(self.table_name(scope)?, key.into(), delta))
+            .await?
+        {
+            Response::BigNumber(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_if(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: i64,
+        ord: Ordering,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::SetIf(
+                self.table_name(scope)?,
+                key.into(),
+                value,
+                ord,
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
+        match self
+            .msg(Request::Remove(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> basteh::Result<bool> {
+        match self
+            .msg(Request::Delete(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn remove_if(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expected: Value<'_>,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::RemoveIf(
+                self.table_name(scope)?,
+                key.into(),
+                expected.into_owned(),
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> basteh::Result<bool> {
+        match self
+            .msg(Request::Contains(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> basteh::Result<Vec<bool>> {
+        match self
+            .msg(Request::ContainsMulti(
+                self.table_name(scope)?,
+                keys.iter().map(|key| (*key).into()).collect(),
+            ))
+            .await?
+        {
+            Response::BoolVec(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> basteh::Result<()> {
+        match self
+            .msg(Request::Persist(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> basteh::Result<()> {
+        match self
+            .msg(Request::Expire(
+                self.table_name(scope)?,
+                key.into(),
+                expire_in,
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn expire_multiple(
+        &self,
+        scope: &[u8],
+        entries: &[(&[u8], Duration)],
+    ) -> basteh::Result<()> {
+        match self
+            .msg(Request::ExpireMulti(
+                self.table_name(scope)?,
+                entries
+                    .iter()
+                    .map(|(key, expire_in)| ((*key).into(), *expire_in))
+                    .collect(),
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<Duration>> {
+        match self
+            .msg(Request::Expiry(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Duration(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn modified_at(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<SystemTime>> {
+        if !self.write_timestamps {
+            return Err(BastehError::MethodNotSupported);
+        }
+        match self
+            .msg(Request::ModifiedAt(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::Time(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn extend(&self, scope: &[u8], key: &[u8], duration: Duration) -> basteh::Result<()> {
+        match self
+            .msg(Request::Extend(
+                self.table_name(scope)?,
+                key.into(),
+                duration,
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> basteh::Result<()> {
+        match self
+            .msg(Request::SetExpiring(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+                expire_in,
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn swap_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> basteh::Result<Option<OwnedValue>> {
+        match self
+            .msg(Request::SwapExpiring(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+                expire_in,
+            ))
+            .await?
+        {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        delta: i64,
+        ttl: Duration,
+    ) -> basteh::Result<i64> {
+        match self
+            .msg(Request::IncrWithTtlFloor(
+                self.table_name(scope)?,
+                key.into(),
+                delta,
+                ttl,
+            ))
+            .await?
+        {
+            Response::Number(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::SetVersioned(
+                self.table_name(scope)?,
+                key.into(),
+                value.into_owned(),
+                version,
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+    ) -> basteh::Result<Option<(OwnedValue, Option<Duration>)>> {
+        match self
+            .msg(Request::GetExpiring(self.table_name(scope)?, key.into()))
+            .await?
+        {
+            Response::ValueDuration(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> basteh::Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        match self
+            .msg(Request::GetExpiringMulti(
+                self.table_name(scope)?,
+                keys.iter().map(|key| (*key).into()).collect(),
+            ))
+            .await?
+        {
+            Response::ValueDurationVec(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn snapshot(&self, scope: &[u8]) -> basteh::Result<Vec<SnapshotEntry>> {
+        match self.msg(Request::Snapshot(self.table_name(scope)?)).await? {
+            Response::Snapshot(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_and_extend(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expire_in: Duration,
+    ) -> basteh::Result<Option<OwnedValue>> {
+        match self
+            .msg(Request::GetAndExtend(
+                self.table_name(scope)?,
+                key.into(),
+                expire_in,
+            ))
+            .await?
+        {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use basteh::{
+        dev::{Provider, Value, ValueKind},
+        test_utils::*,
+        Basteh, BastehError,
+    };
+    use redb::ReadableTable;
+
+    use crate::format::{FORMAT_VERSION_KEY, METADATA_TABLE};
+    use crate::inner::RedbInner;
+    use crate::message::Message;
+    use crate::value::RawValueWrapper;
+    use crate::{FormatVersionError, RedbBackend, TableStat, FORMAT_VERSION};
+
+    type ReDb = RedbBackend<redb::Database>;
+
+    fn open_database(path: &str) -> ReDb {
+        let p = Path::new(path);
+        if p.exists() {
+            std::fs::remove_file(p).ok();
+        }
+
+        RedbBackend::from_db(redb::Database::create(path).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_redb_store() {
+        test_store(open_database("/tmp/redb.store.db").start(1)).await;
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn test_redb_async_std() {
+        async_std::task::block_on(async {
+            let provider = open_database("/tmp/redb.async_std.db").start(1);
+            let store = Basteh::build().provider(provider).finish();
+
+            store.set("key", "value").await.unwrap();
+            assert_eq!(
+                store.get::<String>("key").await.unwrap(),
+                Some("value".to_owned())
+            );
+            // Waits on the async-std timer
+            assert_eq!(
+                store
+                    .pop_blocking::<i64>("list", Duration::from_millis(100))
+                    .await
+                    .unwrap(),
+                None
+            );
+        });
+    }
+
+    #[tokio::test]
+    async fn test_redb_nested_list() {
+        test_nested_list(open_database("/tmp/redb.nested_list.db").start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_modified_at() {
+        test_modified_at(
+            open_database("/tmp/redb.modified_at.db")
+                .with_write_timestamps(true)
+                .start(1),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_store_checksums() {
+        let provider = open_database("/tmp/redb.store_checksums.db")
+            .with_checksums(true)
+            .start(1);
+        test_store(provider.clone()).await;
+        test_mutations(provider).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_raw() {
+        let backend = open_database("/tmp/redb.raw.db").map_inner(RedbInner::from_db);
+        let db = backend.inner.db.clone();
+        let store = Basteh::build()
+            .provider(backend.spawn(1))
+            .finish()
+            .scope("raw_scope");
+
+        store.set_raw("key", b"raw value").await.unwrap();
+        store.set("encoded", "value").await.unwrap();
+        assert_eq!(
+            store.get_raw("key").await.unwrap(),
+            Some(b"raw value".to_vec())
+        );
+        assert_eq!(store.get_raw("missing").await.unwrap(), None);
+
+        // Raw values are written without the value kind
+        let db = db.read();
+        let txn = db.begin_read().unwrap();
+        let table = txn
+            .open_table(redb::TableDefinition::<&[u8], RawValueWrapper>::new(
+                "raw_scope",
+            ))
+            .unwrap();
+        assert_eq!(
+            table.get(b"key".as_ref()).unwrap().unwrap().value(),
+            b"raw value"
+        );
+        assert_eq!(
+            table.get(b"encoded".as_ref()).unwrap().unwrap().value(),
+            [&[ValueKind::String as u8][..], b"value"].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redb_size_bytes() {
+        test_size_bytes(open_database("/tmp/redb.size_bytes.db").start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_scopes() {
+        test_scopes(open_database("/tmp/redb.scopes.db").start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_binary_scope() {
+        test_binary_scope(open_database("/tmp/redb.binary_scope.db").start(1)).await;
+
+        let store = Basteh::build()
+            .provider(open_database("/tmp/redb.binary_scope_names.db").start(1))
+            .finish();
+
+        // Binary scopes are listed back as they were given
+        store.scope(b"\xff\xfe").set("key", "value").await.unwrap();
+        let scope = store.scope("utf-8 scope ✓".as_bytes());
+        scope.set("key", "value").await.unwrap();
+        assert_eq!(
+            scope.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+        let scopes = store.scopes().await.unwrap().collect::<Vec<_>>();
+        assert!(scopes.contains(&b"\xff\xfe".to_vec()));
+        assert!(scopes.contains(&"utf-8 scope ✓".as_bytes().to_vec()));
+
+        // The table names of binary scopes can't be used as utf-8 scopes
+        assert!(matches!(
+            store
+                .scope("__BASTEH_BINARY__fffe")
+                .get::<String>("key")
+                .await,
+            Err(BastehError::InvalidKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_redb_reserved_scope() {
+        let store = Basteh::build()
+            .provider(open_database("/tmp/redb.reserved_scope.db").start(1))
+            .finish();
+
+        let sessions = store.scope("sessions");
+        sessions
+            .set_expiring("key", "value", Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        // The expiry table of the scope can't be written as a scope of its own
+        for name in [
+            "sessions__EXPIRATIONS_TABLE__",
+            "sessions__VERSIONS_TABLE__",
+            "__BASTEH_METADATA__",
+            "__BASTEH_SETTINGS__",
+        ] {
+            let scope = store.scope(name);
+            assert!(matches!(
+                scope.set("key", "value").await,
+                Err(BastehError::InvalidKey(found)) if found == name
+            ));
+            assert!(matches!(
+                scope.get::<String>("key").await,
+                Err(BastehError::InvalidKey(_))
+            ));
+        }
+
+        assert!(sessions.expiry("key").await.unwrap().is_some());
+        assert_eq!(
+            sessions.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redb_expiry_table_suffix() {
+        let store = Basteh::build()
+            .provider(
+                open_database("/tmp/redb.exp_suffix.db")
+                    .with_expiry_table_suffix("\0expiry")
+                    .start(1),
+            )
+            .finish();
+
+        let sessions = store.scope("sessions");
+        sessions
+            .set_expiring("key", "value", Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        // With another suffix, the default one is a plain scope name
+        let other = store.scope("sessions__EXPIRATIONS_TABLE__");
+        other.set("key", "other").await.unwrap();
+        assert!(other.expiry("key").await.unwrap().is_none());
+        assert!(sessions.expiry("key").await.unwrap().is_some());
+
+        assert!(matches!(
+            store.scope("sessions\0expiry").set("key", "value").await,
+            Err(BastehError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_redb_empty_expiry_table_suffix() {
+        let _ = open_database("/tmp/redb.empty_exp_suffix.db").with_expiry_table_suffix("");
+    }
+
+    #[tokio::test]
+    async fn test_redb_mutations() {
+        test_mutations(open_database("/tmp/redb.mutate.db").start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_expiry() {
+        test_expiry(open_database("/tmp/redb.expiry.db").start(1), 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_expiry_store() {
+        test_expiry_store(open_database("/tmp/redb.exp_store.db").start(1), 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_compact() {
+        let path = "/tmp/redb.compact.db";
+        let provider = open_database(path).start(2);
+        let store = Basteh::build().provider(provider.clone()).finish();
+
+        let value = "v".repeat(1024);
+        for i in 0..1000 {
+            store.set(format!("key{}", i), &value).await.unwrap();
+        }
+        for i in 0..990 {
+            store.remove::<String>(format!("key{}", i)).await.unwrap();
+        }
+
+        let size_before = std::fs::metadata(path).unwrap().len();
+        provider.compact().await.unwrap();
+        assert!(std::fs::metadata(path).unwrap().len() <= size_before);
+
+        // The remaining items are still readable and the database is still writable
+        for i in 990..1000 {
+            assert_eq!(
+                store.get::<String>(format!("key{}", i)).await.unwrap(),
+                Some(value.clone())
+            );
+        }
+        assert_eq!(store.keys().await.unwrap().count(), 10);
+        store.set("key", "value").await.unwrap();
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redb_is_deletion_enabled() {
+        let provider = open_database("/tmp/redb.deletion_enabled.db")
+            .perform_deletion(true)
+            .start(1);
+        assert!(provider.is_deletion_enabled());
+
+        let provider = open_database("/tmp/redb.deletion_disabled.db").start(1);
+        assert!(!provider.is_deletion_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_redb_queue_len() {
+        let backend = open_database("/tmp/redb.queue_len.db").map_inner(RedbInner::from_db);
+        let db = backend.inner.db.clone();
+        let provider = backend.spawn(1);
+
+        // The worker waits for the database while it's locked, as it does during a compaction
+        let guard = db.write();
+        test_queue_len(
+            provider.clone(),
+            || provider.queue_len(),
+            async {
+                provider
+                    .set(b"scope", b"blocked", Value::Number(1))
+                    .await
+                    .unwrap();
+            },
+            move || drop(guard),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_sweep_expired() {
+        let provider = open_database("/tmp/redb.sweep_expired.db").start(1);
+        let store = Basteh::build().provider(provider.clone()).finish();
+
+        for scope in ["scope1", "scope2"] {
+            let store = store.scope(scope);
+            store.set("persistent", "value").await.unwrap();
+            store
+                .set_expiring("expiring", "value", Duration::from_secs(1))
+                .await
+                .unwrap();
+        }
+        let size_before = provider.size_bytes(b"scope1").await.unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // Only the expired item of the swept scope should be removed from the table
+        assert_eq!(provider.sweep_expired("scope1").await.unwrap(), 1);
+        assert!(provider.size_bytes(b"scope1").await.unwrap() < size_before);
+        assert_eq!(provider.size_bytes(b"scope2").await.unwrap(), size_before);
+        assert_eq!(provider.sweep_expired("scope1").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_redb_table_stats() {
+        let provider = open_database("/tmp/redb.table_stats.db").start(1);
+        let store = Basteh::build().provider(provider.clone()).finish();
+
+        let scope1 = store.scope("scope1");
+        for i in 0..3 {
+            scope1.set(format!("persistent{}", i), i).await.unwrap();
+        }
+        scope1
+            .set_expiring("expiring", "value", Duration::from_secs(1))
+            .await
+            .unwrap();
+        scope1
+            .set_expiring("long_expiring", "value", Duration::from_secs(100))
+            .await
+            .unwrap();
+
+        let scope2 = store.scope("scope2");
+        scope2.set("persistent", "value").await.unwrap();
+        for i in 0..2 {
+            scope2
+                .set_expiring(format!("expiring{}", i), i, Duration::from_secs(1))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // Binary scopes are reported as they are, not by their table name
+        store.scope([0xff, 0x00]).set("key", "value").await.unwrap();
+
+        let stats = provider.table_stats().await.unwrap();
+        assert_eq!(
+            stats,
+            vec![
+                TableStat {
+                    scope: vec![0xff, 0x00],
+                    entries: 1,
+                    live: 1,
+                    expired: 0,
+                    has_expiry_table: true,
+                },
+                TableStat {
+                    scope: b"scope1".to_vec(),
+                    entries: 5,
+                    live: 4,
+                    expired: 1,
+                    has_expiry_table: true,
+                },
+                TableStat {
+                    scope: b"scope2".to_vec(),
+                    entries: 3,
+                    live: 1,
+                    expired: 2,
+                    has_expiry_table: true,
+                },
+            ]
+        );
+
+        // Swept entries are not counted anymore
+        provider.sweep_expired("scope2").await.unwrap();
+        let stats = provider.table_stats().await.unwrap();
+        assert_eq!((stats[2].entries, stats[2].expired), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_redb_start_checked() {
+        let path = "/tmp/redb.start_checked.db";
+        let store = open_database(path).start_checked(1).unwrap();
+        let store = Basteh::build().provider(store).finish();
+        store.set("key", "value").await.unwrap();
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+        drop(store);
+
+        // Unusable databases fail on start instead of on the first operation
+        let db = redb::Database::create("/tmp/redb.start_checked_version.db").unwrap();
+        {
+            let metadata = redb::TableDefinition::<&str, u8>::new(METADATA_TABLE);
+            let txn = db.begin_write().unwrap();
+            txn.open_table(metadata)
+                .unwrap()
+                .insert(FORMAT_VERSION_KEY, FORMAT_VERSION + 1)
+                .unwrap();
+            txn.commit().unwrap();
+        }
+        match RedbBackend::from_db(db).start_checked(1) {
+            Err(BastehError::Custom(err)) => {
+                assert!(err.downcast_ref::<FormatVersionError>().is_some())
+            }
+            Err(err) => panic!("Expected a format version error, got {:?}", err),
+            Ok(_) => panic!("Expected a format version error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redb_backend_unavailable() {
+        // Without any worker the channel is disconnected
+        let store = open_database("/tmp/redb.backend_unavailable.db").start(0);
+        assert!(matches!(
+            store.get(b"scope", b"key").await,
+            Err(BastehError::BackendUnavailable)
+        ));
+
+        // A worker going away while handling the request drops the response sender
+        let (tx, rx) = crossbeam_channel::bounded::<Message>(1);
+        let worker = std::thread::spawn(move || drop(rx.recv()));
+        let store = RedbBackend { inner: tx, ..store };
+        assert!(matches!(
+            store.set(b"scope", b"key", Value::Number(1)).await,
+            Err(BastehError::BackendUnavailable)
+        ));
+        worker.join().unwrap();
     }
 }
@@ -1,38 +1,92 @@
-use std::time::Duration;
+use std::{
+    cmp::Ordering,
+    time::{Duration, SystemTime},
+};
 
 use basteh::{
-    dev::{Mutation, OwnedValue},
+    dev::{ListMutation, Mutation, OwnedValue, SnapshotEntry, ValueKind},
     Result,
 };
 use tokio::sync::oneshot;
 
+use crate::stats::TableStat;
+
 pub enum Request {
     Keys(Box<str>),
+    KeysPage(Box<str>, Option<Box<[u8]>>, usize),
+    Scopes,
+    SizeBytes(Box<str>),
+    DropScope(Box<str>),
     Get(Box<str>, Box<[u8]>),
+    GetRaw(Box<str>, Box<[u8]>),
+    ValueKind(Box<str>, Box<[u8]>),
     GetRange(Box<str>, Box<[u8]>, i64, i64),
+    GetRangeMulti(Box<str>, Vec<(Box<[u8]>, i64, i64)>),
     Set(Box<str>, Box<[u8]>, OwnedValue),
+    SetRaw(Box<str>, Box<[u8]>, Vec<u8>),
+    SetKeepTtl(Box<str>, Box<[u8]>, OwnedValue),
+    Upsert(Box<str>, Box<[u8]>, OwnedValue),
     Pop(Box<str>, Box<[u8]>),
+    PopPush(Box<str>, Box<[u8]>, Box<str>, Box<[u8]>),
+    ListDrain(Box<str>, Box<[u8]>),
     Push(Box<str>, Box<[u8]>, OwnedValue),
+    HSet(Box<str>, Box<[u8]>, Box<[u8]>, OwnedValue),
+    HIncr(Box<str>, Box<[u8]>, Box<[u8]>, i64),
+    SetBit(Box<str>, Box<[u8]>, u64, bool),
+    HDel(Box<str>, Box<[u8]>, Box<[u8]>),
     PushMulti(Box<str>, Box<[u8]>, Vec<OwnedValue>),
+    PushCapped(Box<str>, Box<[u8]>, OwnedValue, u64),
+    PushReturningLen(Box<str>, Box<[u8]>, OwnedValue),
+    MutateList(Box<str>, Box<[u8]>, ListMutation),
+    IncrFloat(Box<str>, Box<[u8]>, f64),
+    IncrBig(Box<str>, Box<[u8]>, i128),
+    SetIf(Box<str>, Box<[u8]>, i64, Ordering),
     Remove(Box<str>, Box<[u8]>),
+    Delete(Box<str>, Box<[u8]>),
+    RemoveIf(Box<str>, Box<[u8]>, OwnedValue),
     Contains(Box<str>, Box<[u8]>),
+    ContainsMulti(Box<str>, Vec<Box<[u8]>>),
     MutateNumber(Box<str>, Box<[u8]>, Mutation),
     Expire(Box<str>, Box<[u8]>, Duration),
+    ExpireMulti(Box<str>, Vec<(Box<[u8]>, Duration)>),
     Persist(Box<str>, Box<[u8]>),
     Expiry(Box<str>, Box<[u8]>),
+    ModifiedAt(Box<str>, Box<[u8]>),
     Extend(Box<str>, Box<[u8]>, Duration),
     SetExpiring(Box<str>, Box<[u8]>, OwnedValue, Duration),
+    SwapExpiring(Box<str>, Box<[u8]>, OwnedValue, Duration),
+    IncrWithTtlFloor(Box<str>, Box<[u8]>, i64, Duration),
     GetExpiring(Box<str>, Box<[u8]>),
+    GetExpiringMulti(Box<str>, Vec<Box<[u8]>>),
+    Snapshot(Box<str>),
+    GetAndExtend(Box<str>, Box<[u8]>, Duration),
+    SetVersioned(Box<str>, Box<[u8]>, OwnedValue, u64),
+    Compact,
+    SweepExpired(Box<str>),
+    TableStats,
 }
 
 pub enum Response {
     Iterator(Box<dyn Iterator<Item = Vec<u8>> + Send + Sync>),
     Value(Option<OwnedValue>),
+    Bytes(Option<Vec<u8>>),
     ValueVec(Vec<OwnedValue>),
+    ValueVecs(Vec<Vec<OwnedValue>>),
+    Kind(Option<ValueKind>),
     Number(i64),
+    Numbers((i64, i64)),
+    Float(f64),
+    BigNumber(i128),
+    Size(u64),
+    Count(u64),
     Duration(Option<Duration>),
+    Time(Option<SystemTime>),
     ValueDuration(Option<(OwnedValue, Option<Duration>)>),
+    ValueDurationVec(Vec<Option<(OwnedValue, Option<Duration>)>>),
+    Snapshot(Vec<SnapshotEntry>),
     Bool(bool),
+    BoolVec(Vec<bool>),
+    TableStats(Vec<TableStat>),
     Empty(()),
 }
 
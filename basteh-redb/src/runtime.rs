@@ -0,0 +1,32 @@
+//! The few runtime facilities the backend needs, backed by tokio or by async-std with the
+//! `async-std` feature. The channels used to talk to the worker threads don't need a runtime.
+
+use std::time::Duration;
+
+/// Run the closure on the blocking threadpool of the runtime
+#[cfg(not(feature = "async-std"))]
+pub(crate) fn spawn_blocking<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    tokio::task::spawn_blocking(f);
+}
+
+/// Run the closure on the blocking threadpool of the runtime
+#[cfg(feature = "async-std")]
+pub(crate) fn spawn_blocking<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    async_std::task::spawn_blocking(f);
+}
+
+#[cfg(not(feature = "async-std"))]
+pub(crate) async fn sleep(dur: Duration) {
+    tokio::time::sleep(dur).await
+}
+
+#[cfg(feature = "async-std")]
+pub(crate) async fn sleep(dur: Duration) {
+    async_std::task::sleep(dur).await
+}
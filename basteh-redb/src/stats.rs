@@ -0,0 +1,15 @@
+/// Entry counts of a scope's table, returned by
+/// [`RedbBackend::table_stats`](crate::RedbBackend::table_stats)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStat {
+    /// The scope stored in the table
+    pub scope: Vec<u8>,
+    /// Number of the entries stored in the table, including the expired ones not removed yet
+    pub entries: u64,
+    /// Number of the entries which are not expired
+    pub live: u64,
+    /// Number of the entries which are expired but still stored
+    pub expired: u64,
+    /// Whether the scope has a companion table keeping the expiry flags
+    pub has_expiry_table: bool,
+}
@@ -1,4 +1,4 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use basteh::dev::{OwnedValue, ValueKind};
 use bytes::BytesMut;
@@ -36,47 +36,27 @@ impl redb::RedbValue for OwnedValueWrapper {
                     OwnedValue::Number(i64::from_le_bytes(data[1..9].try_into().unwrap()))
                 }
             }
+            ValueKind::BigNumber => {
+                if data.len() < std::mem::size_of::<i128>() + 1 {
+                    // Invalid data found, should we panic?
+                    OwnedValue::Number(0)
+                } else {
+                    OwnedValue::BigNumber(i128::from_le_bytes(data[1..17].try_into().unwrap()))
+                }
+            }
             ValueKind::String => {
                 OwnedValue::String(String::from_utf8_lossy(&data[1..]).into_owned())
             }
             ValueKind::Bytes => OwnedValue::Bytes(BytesMut::from(&data[1..])),
-            ValueKind::List => {
-                let mut index = 1;
-                let mut values = Vec::new();
-
-                while index < data.len() {
-                    let kind = ValueKind::from_u8(data[index]).unwrap_or(ValueKind::Number);
-                    index += 1;
-
-                    let len = u64::from_le_bytes(data[index..(index + 8)].try_into().unwrap());
-                    index += 8;
-
-                    match kind {
-                        ValueKind::List => {
-                            panic!("List of lists is not supported");
-                        }
-                        ValueKind::Number => {
-                            let n =
-                                i64::from_le_bytes(data[index..(index + 8)].try_into().unwrap());
-                            index += 8;
-                            values.push(OwnedValue::Number(n));
-                        }
-                        ValueKind::Bytes => {
-                            let b = BytesMut::from(&data[index..(index + len as usize)]);
-                            index += b.len();
-                            values.push(OwnedValue::Bytes(b));
-                        }
-                        ValueKind::String => {
-                            let s = data[index..(index + len as usize)].to_vec();
-                            index += s.len();
-                            values
-                                .push(OwnedValue::String(String::from_utf8_lossy(&s).into_owned()));
-                        }
-                    }
-                }
-
-                OwnedValue::List(values)
-            }
+            // Corrupted lists are read as bytes, so using them as lists fails instead of panicking
+            ValueKind::List => match decode_list(&data[1..], 0) {
+                Some(list) => OwnedValue::List(list),
+                None => OwnedValue::Bytes(BytesMut::from(&data[1..])),
+            },
+            ValueKind::Map => match decode_map(&data[1..], 0) {
+                Some(map) => OwnedValue::Map(map),
+                None => OwnedValue::Bytes(BytesMut::from(&data[1..])),
+            },
         }
     }
 
@@ -93,6 +73,11 @@ impl redb::RedbValue for OwnedValueWrapper {
                 res.push(kind);
                 res.extend_from_slice(&n.to_le_bytes())
             }
+            OwnedValue::BigNumber(n) => {
+                res.reserve(std::mem::size_of::<i128>() + 1);
+                res.push(kind);
+                res.extend_from_slice(&n.to_le_bytes())
+            }
             OwnedValue::Bytes(b) => {
                 res.reserve(b.len() + 1);
                 res.push(kind);
@@ -104,34 +89,12 @@ impl redb::RedbValue for OwnedValueWrapper {
                 res.extend_from_slice(&s.as_bytes())
             }
             OwnedValue::List(l) => {
-                res.reserve(std::mem::size_of::<u64>() + 1);
                 res.push(ValueKind::List as u8);
-
-                for item in l {
-                    match item {
-                        OwnedValue::List(_) => {
-                            panic!("List of lists is not supported")
-                        }
-                        OwnedValue::Number(n) => {
-                            res.reserve(17);
-                            res.push(ValueKind::Number as u8);
-                            res.extend_from_slice(&4__u64.to_le_bytes());
-                            res.extend_from_slice(&n.to_le_bytes());
-                        }
-                        OwnedValue::Bytes(b) => {
-                            res.reserve(b.len() + 9);
-                            res.push(ValueKind::Bytes as u8);
-                            res.extend_from_slice(&(b.len() as u64).to_le_bytes());
-                            res.extend_from_slice(&b);
-                        }
-                        OwnedValue::String(s) => {
-                            res.reserve(s.len() + 9);
-                            res.push(ValueKind::String as u8);
-                            res.extend_from_slice(&(s.len() as u64).to_le_bytes());
-                            res.extend_from_slice(&s.as_bytes());
-                        }
-                    }
-                }
+                encode_list(l, &mut res);
+            }
+            OwnedValue::Map(m) => {
+                res.push(ValueKind::Map as u8);
+                encode_map(m, &mut res);
             }
         }
 
@@ -142,3 +105,181 @@ impl redb::RedbValue for OwnedValueWrapper {
         redb::TypeName::new("Generic value")
     }
 }
+
+/// Reads only the kind of the values written as [`OwnedValueWrapper`], it has the same type name so
+/// the tables can be opened with either of them.
+#[derive(Debug)]
+pub(crate) struct ValueKindWrapper;
+
+impl redb::RedbValue for ValueKindWrapper {
+    type SelfType<'a> = Option<ValueKind>;
+
+    type AsBytes<'a> = [u8; 1];
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        data.first().and_then(|v| ValueKind::from_u8(*v))
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        // Only used for reading, the kind alone is not a valid value
+        [value.map(|kind| kind as u8).unwrap_or(u8::MAX)]
+    }
+
+    fn type_name() -> redb::TypeName {
+        <OwnedValueWrapper as redb::RedbValue>::type_name()
+    }
+}
+
+/// Reads and writes the bytes of the values as they are, ex. to check the checksum of the values
+/// written as [`OwnedValueWrapper`] before decoding them, or for `get_raw` and `set_raw`. It has the
+/// same type name so the tables can be opened with either of them.
+#[derive(Debug)]
+pub(crate) struct RawValueWrapper;
+
+impl redb::RedbValue for RawValueWrapper {
+    type SelfType<'a> = &'a [u8];
+
+    type AsBytes<'a> = &'a [u8];
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        data
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        value
+    }
+
+    fn type_name() -> redb::TypeName {
+        <OwnedValueWrapper as redb::RedbValue>::type_name()
+    }
+}
+
+/// Lists nested deeper than this are read as corrupted, so corrupted data can't overflow the stack
+const MAX_LIST_DEPTH: usize = 64;
+
+// Each list item is a kind byte, a u64 length and the data, a nested list is an item holding the
+// encoded items of the sublist. Returns None if the data is corrupted(ex. truncated).
+fn decode_list(mut data: &[u8], depth: usize) -> Option<Vec<OwnedValue>> {
+    if depth > MAX_LIST_DEPTH {
+        return None;
+    }
+    let mut values = Vec::new();
+
+    while let Some((kind, rest)) = data.split_first() {
+        let kind = ValueKind::from_u8(*kind).unwrap_or(ValueKind::Number);
+
+        let len = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?);
+        let rest = &rest[8..];
+
+        // Numbers have a fixed size, their length is not reliable
+        let len = match kind {
+            ValueKind::Number => 8,
+            ValueKind::BigNumber => 16,
+            _ => usize::try_from(len).ok()?,
+        };
+        let item = rest.get(..len)?;
+        data = &rest[len..];
+
+        values.push(match kind {
+            ValueKind::List => OwnedValue::List(decode_list(item, depth + 1)?),
+            ValueKind::Map => OwnedValue::Map(decode_map(item, depth + 1)?),
+            ValueKind::Number => OwnedValue::Number(i64::from_le_bytes(item.try_into().ok()?)),
+            ValueKind::BigNumber => {
+                OwnedValue::BigNumber(i128::from_le_bytes(item.try_into().ok()?))
+            }
+            ValueKind::Bytes => OwnedValue::Bytes(BytesMut::from(item)),
+            ValueKind::String => OwnedValue::String(String::from_utf8_lossy(item).into_owned()),
+        });
+    }
+
+    Some(values)
+}
+
+// A map is a list of its fields(as bytes items), each followed by its value
+fn decode_map(data: &[u8], depth: usize) -> Option<Vec<(Vec<u8>, OwnedValue)>> {
+    let mut items = decode_list(data, depth)?.into_iter();
+    let mut map = Vec::new();
+    while let (Some(OwnedValue::Bytes(field)), Some(value)) = (items.next(), items.next()) {
+        map.push((field.to_vec(), value));
+    }
+    Some(map)
+}
+
+fn encode_map(map: &[(Vec<u8>, OwnedValue)], res: &mut Vec<u8>) {
+    for (field, value) in map {
+        res.reserve(field.len() + 9);
+        res.push(ValueKind::Bytes as u8);
+        res.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        res.extend_from_slice(field);
+        encode_list(std::slice::from_ref(value), res);
+    }
+}
+
+fn encode_list(list: &[OwnedValue], res: &mut Vec<u8>) {
+    for item in list {
+        match item {
+            OwnedValue::List(l) => {
+                res.push(ValueKind::List as u8);
+                let len_index = res.len();
+                res.extend_from_slice(&0_u64.to_le_bytes());
+                encode_list(l, res);
+                let len = (res.len() - len_index - 8) as u64;
+                res[len_index..(len_index + 8)].copy_from_slice(&len.to_le_bytes());
+            }
+            OwnedValue::Map(m) => {
+                res.push(ValueKind::Map as u8);
+                let len_index = res.len();
+                res.extend_from_slice(&0_u64.to_le_bytes());
+                encode_map(m, res);
+                let len = (res.len() - len_index - 8) as u64;
+                res[len_index..(len_index + 8)].copy_from_slice(&len.to_le_bytes());
+            }
+            OwnedValue::Number(n) => {
+                res.reserve(17);
+                res.push(ValueKind::Number as u8);
+                res.extend_from_slice(&4_u64.to_le_bytes());
+                res.extend_from_slice(&n.to_le_bytes());
+            }
+            OwnedValue::BigNumber(n) => {
+                res.reserve(25);
+                res.push(ValueKind::BigNumber as u8);
+                res.extend_from_slice(&16_u64.to_le_bytes());
+                res.extend_from_slice(&n.to_le_bytes());
+            }
+            OwnedValue::Bytes(b) => {
+                res.reserve(b.len() + 9);
+                res.push(ValueKind::Bytes as u8);
+                res.extend_from_slice(&(b.len() as u64).to_le_bytes());
+                res.extend_from_slice(b);
+            }
+            OwnedValue::String(s) => {
+                res.reserve(s.len() + 9);
+                res.push(ValueKind::String as u8);
+                res.extend_from_slice(&(s.len() as u64).to_le_bytes());
+                res.extend_from_slice(s.as_bytes());
+            }
+        }
+    }
+}
@@ -1,17 +1,34 @@
 #![doc = include_str!("../README.md")]
 
-use std::time::Duration;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicUsize, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use basteh::{
-    dev::{Action, Mutation, OwnedValue, Provider, Value},
+    dev::{
+        paged_stream, Action, BloomParams, BoxStream, Capabilities, ListAction, ListMutation,
+        Mutation, OwnedValue, Provider, SnapshotEntry, Value, ValueKind, STREAM_CHUNK_SIZE,
+    },
     BastehError, Result,
 };
-use bytes::BytesMut;
-use redis::{aio::ConnectionManager, AsyncCommands, FromRedisValue, RedisResult, ToRedisArgs};
+use bytes::{Bytes, BytesMut};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use redis::{
+    aio::ConnectionManager, AsyncCommands, FromRedisValue, RedisResult, Script, ToRedisArgs,
+};
 
 pub use redis::{ConnectionAddr, ConnectionInfo, ErrorKind, RedisConnectionInfo, RedisError};
-use utils::run_mutations;
+use retry::{retry, RetryPolicy};
+use utils::{is_unknown_command, run_mutations, run_mutations_returning, to_basteh};
 
+mod retry;
 mod utils;
 
 #[inline]
@@ -19,6 +36,264 @@ fn get_full_key(scope: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Vec<u8> {
     [scope.as_ref(), b":", key.as_ref()].concat()
 }
 
+// Versions are zero-padded so they can be compared as strings without losing precision in lua
+const SET_VERSIONED_SCRIPT: &str = r#"
+local current = redis.call('HGET', KEYS[2], ARGV[1])
+if current and current >= ARGV[2] then
+    return 0
+end
+redis.call('HSET', KEYS[2], ARGV[1], ARGV[2])
+redis.call('DEL', KEYS[1])
+if ARGV[3] == 'l' then
+    if #ARGV > 3 then
+        redis.call('RPUSH', KEYS[1], unpack(ARGV, 4))
+    end
+else
+    redis.call('SET', KEYS[1], ARGV[4])
+end
+return 1
+"#;
+
+// ARGV[2] is the expected ordering of the new value against the current one as -1, 0 or 1, missing
+// keys are written unless equality is expected. Values which are not i64 numbers fail the same way
+// as mutate does. Lua numbers are doubles, so the numbers are compared as strings by their sign,
+// length and digits to stay exact near the limits of i64.
+const SET_IF_SCRIPT: &str = r#"
+local function parse(s)
+    local neg, digits = string.match(s, '^(-?)0*(%d*)$')
+    if neg == nil or (digits == '' and not string.match(s, '0$')) then
+        return nil
+    end
+    local max = neg == '-' and '9223372036854775808' or '9223372036854775807'
+    if #digits > #max or (#digits == #max and digits > max) then
+        return nil
+    end
+    return digits ~= '' and neg == '-', digits
+end
+local v = redis.call('GET', KEYS[1])
+if v then
+    local cneg, c = parse(v)
+    if c == nil then
+        return redis.error_reply('ERR value is not a number')
+    end
+    local nneg, n = parse(ARGV[1])
+    local o = 0
+    if nneg ~= cneg then
+        o = nneg and -1 or 1
+    else
+        if #n ~= #c then
+            o = #n > #c and 1 or -1
+        elseif n ~= c then
+            o = n > c and 1 or -1
+        end
+        if nneg then
+            o = -o
+        end
+    end
+    if o ~= tonumber(ARGV[2]) then
+        return 0
+    end
+elseif ARGV[2] == '0' then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'KEEPTTL')
+return 1
+"#;
+
+// ARGV[1] is the delta and ARGV[2] the ttl in milliseconds, which is only set if the key has none.
+// New keys are created by INCRBY without a ttl, so they get it as well.
+const INCR_WITH_TTL_FLOOR_SCRIPT: &str = r#"
+local v = redis.call('INCRBY', KEYS[1], ARGV[1])
+if redis.call('PTTL', KEYS[1]) < 0 then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return v
+"#;
+
+// ARGV[1] is 'l' when the expected value is a list with its items following it, otherwise ARGV[2] is
+// compared to the string value. Keys of another type are left alone.
+const REMOVE_IF_SCRIPT: &str = r#"
+local t = redis.call('TYPE', KEYS[1]).ok
+if ARGV[1] == 'l' then
+    if t ~= 'list' then
+        return 0
+    end
+    local items = redis.call('LRANGE', KEYS[1], 0, -1)
+    if #items ~= #ARGV - 1 then
+        return 0
+    end
+    for i, item in ipairs(items) do
+        if item ~= ARGV[i + 1] then
+            return 0
+        end
+    end
+elseif t ~= 'string' or redis.call('GET', KEYS[1]) ~= ARGV[2] then
+    return 0
+end
+return redis.call('DEL', KEYS[1])
+"#;
+
+// Lists can't be written with a single SET, so their ttl is carried over by hand
+const SET_LIST_KEEPTTL_SCRIPT: &str = r#"
+local ttl = redis.call('PTTL', KEYS[1])
+redis.call('DEL', KEYS[1])
+if #ARGV > 0 then
+    redis.call('RPUSH', KEYS[1], unpack(ARGV))
+end
+if ttl > 0 then
+    redis.call('PEXPIRE', KEYS[1], ttl)
+end
+return 1
+"#;
+
+// ARGV[1] tells if the value is a list, whose items are the rest of ARGV. Returns 1 if the key
+// didn't exist before.
+const UPSERT_SCRIPT: &str = r#"
+local existed = redis.call('EXISTS', KEYS[1])
+if ARGV[1] == 'list' then
+    redis.call('DEL', KEYS[1])
+    if #ARGV > 1 then
+        redis.call('RPUSH', KEYS[1], unpack(ARGV, 2))
+    end
+else
+    redis.call('SET', KEYS[1], ARGV[2])
+end
+return 1 - existed
+"#;
+
+// A transaction would still run the DEL if LRANGE fails on a non-list, so the type is checked first
+const LIST_DRAIN_SCRIPT: &str = r#"
+local kind = redis.call('TYPE', KEYS[1])['ok']
+if kind == 'none' then
+    return {}
+end
+if kind ~= 'list' then
+    return redis.error_reply('WRONGTYPE Operation against a key holding the wrong kind of value')
+end
+local items = redis.call('LRANGE', KEYS[1], 0, -1)
+redis.call('DEL', KEYS[1])
+return items
+"#;
+
+// Big numbers don't fit in lua's doubles, so they are added as decimal strings. ARGV[1] is the
+// delta, ARGV[2] and ARGV[3] are the largest magnitudes of positive and negative i128 results.
+const INCR_BIG_SCRIPT: &str = r#"
+local function parse(s)
+    local sign, digits = string.match(s, '^(%-?)0*(%d+)$')
+    if digits == nil then
+        return nil, nil
+    end
+    return sign == '-' and digits ~= '0', digits
+end
+local function cmp(a, b)
+    if #a ~= #b then
+        return #a < #b and -1 or 1
+    end
+    if a == b then
+        return 0
+    end
+    return a < b and -1 or 1
+end
+local function digit(s, i)
+    return tonumber(s:sub(-i - 1, -i - 1)) or 0
+end
+local function add(a, b)
+    local r, carry = {}, 0
+    for i = 0, math.max(#a, #b) - 1 do
+        local d = digit(a, i) + digit(b, i) + carry
+        carry = math.floor(d / 10)
+        table.insert(r, 1, d % 10)
+    end
+    if carry > 0 then
+        table.insert(r, 1, carry)
+    end
+    return table.concat(r)
+end
+local function sub(a, b)
+    local r, borrow = {}, 0
+    for i = 0, #a - 1 do
+        local d = digit(a, i) - digit(b, i) - borrow
+        borrow = d < 0 and 1 or 0
+        table.insert(r, 1, d % 10)
+    end
+    local s = string.gsub(table.concat(r), '^0+', '')
+    return s == '' and '0' or s
+end
+local v = redis.call('GET', KEYS[1]) or '0'
+local cneg, c = parse(v)
+if c == nil then
+    return redis.error_reply('ERR value is not an integer')
+end
+local dneg, d = parse(ARGV[1])
+local neg, r
+if cneg == dneg then
+    neg, r = cneg, add(c, d)
+elseif cmp(c, d) >= 0 then
+    neg, r = cneg, sub(c, d)
+else
+    neg, r = dneg, sub(d, c)
+end
+if r == '0' then
+    neg = false
+end
+if cmp(r, neg and ARGV[3] or ARGV[2]) > 0 then
+    return redis.error_reply('ERR increment or decrement would overflow')
+end
+if neg then
+    r = '-' .. r
+end
+redis.call('SET', KEYS[1], r, 'KEEPTTL')
+return r
+"#;
+
+// Redis lists can only hold strings, so a list inside a list can't be stored without flattening it
+// and losing its structure, it is rejected instead. Maps are stored as redis hashes, which are only
+// reachable through the hash methods.
+fn check_nested(value: &Value<'_>) -> Result<()> {
+    match value {
+        Value::Map(_) => Err(BastehError::MethodNotSupported),
+        Value::List(l)
+            if l.iter()
+                .any(|item| matches!(item, Value::List(_) | Value::Map(_))) =>
+        {
+            Err(BastehError::MethodNotSupported)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Escape the glob special characters, so the scope is matched literally in `SCAN` patterns
+fn escape_pattern(scope: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(scope.len());
+    for &c in scope {
+        if matches!(c, b'*' | b'?' | b'[' | b']' | b'\\') {
+            escaped.push(b'\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// LRANGE replies with an array, which is read as bytes when all of its items are integers,
+// a missing key is treated as an empty list the same way redis does
+fn list_from_range(value: Option<OwnedValue>) -> Result<Vec<OwnedValue>> {
+    match value {
+        None => Ok(Vec::new()),
+        Some(OwnedValue::List(l)) => Ok(l),
+        Some(OwnedValue::Bytes(b)) => Ok(b
+            .into_iter()
+            .map(Into::<Value>::into)
+            .map(|v| v.into_owned())
+            .collect::<Vec<_>>()),
+        _ => Err(BastehError::TypeConversion),
+    }
+}
+
+#[inline]
+fn get_versions_key(scope: impl AsRef<[u8]>) -> Vec<u8> {
+    [scope.as_ref(), b"\0__versions__"].concat()
+}
+
 /// An implementation of [`ExpiryStore`](basteh::dev::ExpiryStore) based on redis
 /// using redis-rs async runtime
 ///
@@ -43,9 +318,30 @@ fn get_full_key(scope: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Vec<u8> {
 /// # }
 /// ```
 ///
+/// ## Retries
+/// The connection manager reconnects on connection drops, but the requests failed in the meantime
+/// are reported as errors. Use [`with_retry`](RedisBackend::with_retry) to retry the idempotent
+/// reads(`keys`, `get`, `get_range`, `contains_key`, `expiry`, `get_expiring` and
+/// `get_expiring_multiple`) on transient errors. Writes, removes and mutations are never retried, as they may have been applied before
+/// the connection dropped.
+///
+/// ## Read replicas
+/// Use [`connect_split`](RedisBackend::connect_split) to send the reads to replicas while the
+/// writes go to the primary.
 #[derive(Clone)]
 pub struct RedisBackend {
+    client: redis::Client,
     con: ConnectionManager,
+    retry: Option<RetryPolicy>,
+    hash_tag_scope: bool,
+
+    // Connections to the read replicas, picked round-robin for the reads
+    replicas: Vec<ConnectionManager>,
+    next_replica: Arc<AtomicUsize>,
+
+    // Maps scopes to database indexes, with a connection kept for each database
+    scope_db: Option<fn(&[u8]) -> i64>,
+    db_cons: Arc<Mutex<HashMap<i64, ConnectionManager>>>,
 }
 
 impl RedisBackend {
@@ -53,35 +349,393 @@ impl RedisBackend {
     pub async fn connect(connection_info: ConnectionInfo) -> RedisResult<Self> {
         let client = redis::Client::open(connection_info)?;
         let con = client.get_tokio_connection_manager().await?;
-        Ok(Self { con })
+        Ok(Self {
+            client,
+            con,
+            retry: None,
+            hash_tag_scope: false,
+            replicas: Vec::new(),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            scope_db: None,
+            db_cons: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Same as [`connect`](RedisBackend::connect), but retries the connection up to `attempts`
+    /// times in total if redis is not reachable yet(ex. it's started at the same time as the
+    /// application), waiting `backoff` before the first retry and doubling it each time after.
+    ///
+    /// Only connection failures are retried, and only while connecting. Use
+    /// [`with_retry`](RedisBackend::with_retry) to retry the operations after that.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use basteh_redis::RedisBackend;
+    ///
+    /// # async fn your_main() {
+    /// let provider = RedisBackend::connect_with_retry(
+    ///     "redis://redis/".parse().unwrap(),
+    ///     10,
+    ///     Duration::from_millis(100),
+    /// )
+    /// .await
+    /// .expect("Redis connection failed");
+    /// # }
+    /// ```
+    pub async fn connect_with_retry(
+        connection_info: ConnectionInfo,
+        attempts: u32,
+        backoff: Duration,
+    ) -> RedisResult<Self> {
+        let policy = RetryPolicy {
+            attempts,
+            delay: backoff,
+        };
+        retry(Some(policy), || Self::connect(connection_info.clone())).await
     }
 
     /// Connect using the default redis port on local machine
     pub async fn connect_default() -> RedisResult<Self> {
         Self::connect("redis://127.0.0.1/".parse()?).await
     }
+
+    /// Connect to a primary for the writes and to a set of read replicas for the reads(`get`,
+    /// `keys`, `contains_key`, `expiry` and `get_range`), each read goes to the next replica in a
+    /// round-robin fashion. Everything else, including the reads done as part of a write, goes to
+    /// the primary. With no replicas it behaves the same as [`connect`](RedisBackend::connect).
+    ///
+    /// ## Note
+    /// Redis replicates asynchronously, so a read right after a write may not see it yet, or see
+    /// an expired key still alive. Don't use it for the data which must be read back right after
+    /// being written.
+    ///
+    /// The replicas are used for the scopes stored in their own database, scopes mapped to other
+    /// databases by [`with_scope_to_db_mapping`](RedisBackend::with_scope_to_db_mapping) are read
+    /// from the primary.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh_redis::RedisBackend;
+    ///
+    /// # async fn your_main() {
+    /// let provider = RedisBackend::connect_split(
+    ///     "redis://10.0.0.1/".parse().unwrap(),
+    ///     vec![
+    ///         "redis://10.0.0.2/".parse().unwrap(),
+    ///         "redis://10.0.0.3/".parse().unwrap(),
+    ///     ],
+    /// )
+    /// .await
+    /// .expect("Redis connection failed");
+    /// # }
+    /// ```
+    pub async fn connect_split(
+        primary: ConnectionInfo,
+        replicas: Vec<ConnectionInfo>,
+    ) -> RedisResult<Self> {
+        let mut backend = Self::connect(primary).await?;
+        for replica in replicas {
+            backend.replicas.push(
+                redis::Client::open(replica)?
+                    .get_tokio_connection_manager()
+                    .await?,
+            );
+        }
+        Ok(backend)
+    }
+
+    /// Retry idempotent reads failed with transient errors(connection drops, timeouts) up to
+    /// `attempts` times in total, waiting `delay` before the first retry and doubling it each time
+    /// after, up to 10 seconds(or `delay` if it's longer).
+    pub fn with_retry(mut self, attempts: u32, delay: Duration) -> Self {
+        self.retry = Some(RetryPolicy { attempts, delay });
+        self
+    }
+
+    /// Wrap the scopes in a hash tag(`{scope}`) when building the keys, so all the keys of a scope
+    /// are stored in the same slot of a redis cluster and multi-key commands work on them. It's
+    /// disabled by default.
+    ///
+    /// ## Note
+    /// The hash tag is part of the key stored in redis, so the keys written with and without this
+    /// option are not visible to each other. `keys` strips the whole `{scope}:` prefix, so the
+    /// returned keys are the same either way.
+    pub fn with_hash_tag_scope(mut self, enabled: bool) -> Self {
+        self.hash_tag_scope = enabled;
+        self
+    }
+
+    /// Store each scope in the logical database returned by `mapping`, instead of the one in the
+    /// connection info. By default all the scopes share the database of the connection info.
+    ///
+    /// ## Note
+    /// Keys are still prefixed with their scope, so several scopes can share a database.
+    ///
+    /// A separate connection is opened for each database the first time one of its scopes is
+    /// used, and kept for the lifetime of the backend(and its clones), so mapping scopes to many
+    /// databases opens as many connections. [`pop_push`](Provider::pop_push) between scopes
+    /// stored in different databases fails with
+    /// [`BastehError::MethodNotSupported`](basteh::BastehError::MethodNotSupported), as redis can't
+    /// move items between databases.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh_redis::RedisBackend;
+    ///
+    /// # async fn your_main() {
+    /// let provider = RedisBackend::connect_default()
+    ///     .await
+    ///     .expect("Redis connection failed")
+    ///     .with_scope_to_db_mapping(|scope| if scope == b"sessions" { 1 } else { 0 });
+    /// # }
+    /// ```
+    pub fn with_scope_to_db_mapping(mut self, mapping: fn(&[u8]) -> i64) -> Self {
+        self.scope_db = Some(mapping);
+        self
+    }
+
+    /// Get the connection to the database of the scope, opening it if it's the first use
+    async fn connection(&self, scope: &[u8]) -> Result<ConnectionManager> {
+        let db = match self.scope_db {
+            Some(scope_db) => scope_db(scope),
+            None => return Ok(self.con.clone()),
+        };
+        if db == self.client.get_connection_info().redis.db {
+            return Ok(self.con.clone());
+        }
+
+        if let Some(con) = self.db_cons.lock().unwrap().get(&db) {
+            return Ok(con.clone());
+        }
+
+        let mut connection_info = self.client.get_connection_info().clone();
+        connection_info.redis.db = db;
+        let con = redis::Client::open(connection_info)
+            .map_err(to_basteh)?
+            .get_tokio_connection_manager()
+            .await
+            .map_err(to_basteh)?;
+
+        // Another request may have connected in the meantime, keep the first connection
+        Ok(self
+            .db_cons
+            .lock()
+            .unwrap()
+            .entry(db)
+            .or_insert(con)
+            .clone())
+    }
+
+    /// Get the connection to serve a read of the scope from, the next replica if there are any
+    async fn read_connection(&self, scope: &[u8]) -> Result<ConnectionManager> {
+        if self.replicas.is_empty() {
+            return self.connection(scope).await;
+        }
+        if let Some(scope_db) = self.scope_db {
+            if scope_db(scope) != self.client.get_connection_info().redis.db {
+                return self.connection(scope).await;
+            }
+        }
+
+        let next = self.next_replica.fetch_add(1, AtomicOrdering::Relaxed);
+        Ok(self.replicas[next % self.replicas.len()].clone())
+    }
+
+    /// The prefix of the keys of the scope, the scope itself or its hash tag
+    fn scope_prefix(&self, scope: &[u8]) -> Vec<u8> {
+        if self.hash_tag_scope {
+            [b"{", scope, b"}"].concat()
+        } else {
+            scope.to_vec()
+        }
+    }
+
+    fn full_key(&self, scope: &[u8], key: &[u8]) -> Vec<u8> {
+        get_full_key(self.scope_prefix(scope), key)
+    }
+
+    fn versions_key(&self, scope: &[u8]) -> Vec<u8> {
+        get_versions_key(self.scope_prefix(scope))
+    }
+
+    /// Extract the scope of a full key, it's the part before the first `:`, so scopes containing
+    /// `:` can't be told apart from the keys.
+    fn key_scope<'k>(&self, full_key: &'k [u8]) -> Option<&'k [u8]> {
+        if full_key.ends_with(get_versions_key("").as_slice()) {
+            return None;
+        }
+        if self.hash_tag_scope {
+            let key = full_key.strip_prefix(b"{")?;
+            let end = key.windows(2).position(|w| w == b"}:")?;
+            Some(&key[..end])
+        } else {
+            let end = full_key.iter().position(|c| *c == b':')?;
+            Some(&full_key[..end])
+        }
+    }
+
+    /// Find out if a failed mutation was caused by a non-numeric value stored under the key,
+    /// otherwise return the original error.
+    async fn mutation_error(
+        &self,
+        mut con: ConnectionManager,
+        full_key: &[u8],
+        err: RedisError,
+    ) -> BastehError {
+        let kind = match redis::cmd("TYPE")
+            .arg(full_key)
+            .query_async::<_, String>(&mut con)
+            .await
+        {
+            Ok(ty) if ty == "list" => Some(ValueKind::List),
+            Ok(ty) if ty == "hash" => Some(ValueKind::Map),
+            Ok(ty) if ty == "string" => con
+                .get::<_, OwnedValueWrapper>(full_key)
+                .await
+                .ok()
+                .and_then(|v| v.0)
+                .map(|v| v.kind())
+                .filter(|kind| *kind != ValueKind::Number),
+            _ => None,
+        };
+
+        match kind {
+            Some(kind) => BastehError::NotANumber(kind),
+            None => BastehError::custom(err),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl Provider for RedisBackend {
-    async fn keys(&self, scope: &str) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
-        let keys = self
-            .con
-            .clone()
-            .keys::<_, Vec<Vec<u8>>>([scope, ":*"].concat())
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            nested_lists: false,
+            ..Capabilities::default()
+        }
+    }
+
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        let prefix = self.scope_prefix(scope);
+        let pattern = [prefix.as_slice(), b":*"].concat();
+        let con = self.read_connection(scope).await?;
+        let keys = retry(self.retry, || {
+            let mut con = con.clone();
+            let pattern = &pattern;
+            async move { con.keys::<_, Vec<Vec<u8>>>(pattern).await }
+        })
+        .await
+        .map_err(to_basteh)?
+        .into_iter()
+        .map(move |k| {
+            let ignored = prefix.len() + 1;
+            k[ignored..].to_vec()
+        })
+        .collect::<Vec<_>>();
+        Ok(Box::new(keys.into_iter()))
+    }
+
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, Result<Vec<u8>>> {
+        let prefix = self.scope_prefix(scope);
+        let pattern = get_full_key(escape_pattern(&prefix), "*");
+        let ignored = prefix.len() + 1;
+
+        paged_stream(move |cursor: Option<u64>| {
+            let pattern = pattern.clone();
+            async move {
+                let con = self.connection(scope).await?;
+                let (next, keys): (u64, Vec<Vec<u8>>) = retry(self.retry, || {
+                    let mut con = con.clone();
+                    let pattern = &pattern;
+                    async move {
+                        redis::cmd("SCAN")
+                            .cursor_arg(cursor.unwrap_or(0))
+                            .arg("MATCH")
+                            .arg(pattern)
+                            .arg("COUNT")
+                            .arg(1000)
+                            .query_async(&mut con)
+                            .await
+                    }
+                })
+                .await
+                .map_err(to_basteh)?;
+
+                let keys = keys.into_iter().map(|k| k[ignored..].to_vec()).collect();
+                // The scan is complete once the cursor is back at 0
+                Ok((keys, Some(next).filter(|next| *next != 0)))
+            }
+        })
+    }
+
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        let mut con = self.con.clone();
+
+        let mut scopes = HashSet::new();
+        let mut cursor = 0_u64;
+        loop {
+            let (next, batch): (u64, Vec<Vec<u8>>) = redis::cmd("SCAN")
+                .cursor_arg(cursor)
+                .arg("COUNT")
+                .arg(1000)
+                .query_async(&mut con)
+                .await
+                .map_err(to_basteh)?;
+            scopes.extend(
+                batch
+                    .iter()
+                    .filter_map(|key| self.key_scope(key))
+                    .map(<[u8]>::to_vec),
+            );
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        Ok(Box::new(scopes.into_iter()))
+    }
+
+    async fn size_bytes(&self, scope: &[u8]) -> Result<u64> {
+        let mut con = self.connection(scope).await?;
+        let keys = con
+            .keys::<_, Vec<Vec<u8>>>([self.scope_prefix(scope).as_slice(), b":*"].concat())
             .await
-            .map_err(BastehError::custom)?
+            .map_err(to_basteh)?;
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.cmd("MEMORY").arg("USAGE").arg(key);
+        }
+
+        // Keys removed between KEYS and MEMORY USAGE are reported as nil
+        Ok(pipe
+            .query_async::<_, Vec<Option<u64>>>(&mut con)
+            .await
+            .map_err(to_basteh)?
             .into_iter()
-            .map(move |k| {
-                let ignored = scope.len() + 1;
-                k[ignored..].to_vec()
-            })
-            .collect::<Vec<_>>();
-        Ok(Box::new(keys.into_iter()))
+            .flatten()
+            .sum())
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> Result<()> {
+        let mut con = self.connection(scope).await?;
+        let mut keys = con
+            .keys::<_, Vec<Vec<u8>>>([self.scope_prefix(scope).as_slice(), b":*"].concat())
+            .await
+            .map_err(to_basteh)?;
+        keys.push(self.versions_key(scope));
+
+        con.del(keys).await.map_err(BastehError::custom)
     }
 
-    async fn set(&self, scope: &str, key: &[u8], value: Value<'_>) -> Result<()> {
-        let full_key = get_full_key(scope, key);
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        check_nested(&value)?;
+        let full_key = self.full_key(scope, key);
         match value {
             Value::List(l) => {
                 redis::pipe()
@@ -90,185 +744,961 @@ impl Provider for RedisBackend {
                         full_key,
                         l.into_iter().map(ValueWrapper).collect::<Vec<_>>(),
                     )
-                    .query_async(&mut self.con.clone())
+                    .query_async(&mut self.connection(scope).await?)
                     .await
-                    .map_err(BastehError::custom)?;
+                    .map_err(to_basteh)?;
             }
             _ => {
-                self.con
-                    .clone()
+                self.connection(scope)
+                    .await?
                     .set(full_key, ValueWrapper(value))
                     .await
-                    .map_err(BastehError::custom)?;
+                    .map_err(to_basteh)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<bool> {
+        check_nested(&value)?;
+        let full_key = self.full_key(scope, key);
+        let kind = if matches!(value, Value::List(_)) {
+            "list"
+        } else {
+            "value"
+        };
+        Ok(Script::new(UPSERT_SCRIPT)
+            .key(full_key)
+            .arg(kind)
+            .arg(ValueWrapper(value))
+            .invoke_async::<_, bool>(&mut self.connection(scope).await?)
+            .await
+            .map_err(to_basteh)?)
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        check_nested(&value)?;
+        let full_key = self.full_key(scope, key);
+        match value {
+            Value::List(_) => {
+                Script::new(SET_LIST_KEEPTTL_SCRIPT)
+                    .key(full_key)
+                    .arg(ValueWrapper(value))
+                    .invoke_async::<_, ()>(&mut self.connection(scope).await?)
+                    .await
+                    .map_err(to_basteh)?;
+            }
+            _ => {
+                redis::cmd("SET")
+                    .arg(full_key)
+                    .arg(ValueWrapper(value))
+                    .arg("KEEPTTL")
+                    .query_async::<_, ()>(&mut self.connection(scope).await?)
+                    .await
+                    .map_err(to_basteh)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let full_key = self.full_key(scope, key);
+        let con = self.read_connection(scope).await?;
+        let res = retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move { con.get::<_, OwnedValueWrapper>(full_key).await }
+        })
+        .await;
+
+        match res {
+            Ok(v) => Ok(v.0),
+            // Lists and hashes can't be read with GET, they're read whole by their own commands
+            Err(err) if err.code() == Some("WRONGTYPE") => {
+                let ty = redis::cmd("TYPE")
+                    .arg(&full_key)
+                    .query_async::<_, String>(&mut con.clone())
+                    .await
+                    .map_err(to_basteh)?;
+                let value = match ty.as_str() {
+                    "list" => OwnedValue::List(self.get_range(scope, key, 0, -1).await?),
+                    "hash" => OwnedValue::Map(self.hgetall(scope, key).await?),
+                    _ => return Err(BastehError::TypeConversion),
+                };
+                // The key may be removed in between
+                Ok(match value {
+                    OwnedValue::List(ref l) if l.is_empty() => None,
+                    OwnedValue::Map(ref m) if m.is_empty() => None,
+                    value => Some(value),
+                })
+            }
+            Err(err) => Err(BastehError::custom(err)),
+        }
+    }
+
+    async fn get_stream(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+    ) -> Result<Option<BoxStream<'static, Result<Bytes>>>> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+        let policy = self.retry;
+
+        let (exists, len) = retry(policy, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move {
+                redis::pipe()
+                    .exists(full_key)
+                    .strlen(full_key)
+                    .query_async::<_, (bool, usize)>(&mut con)
+                    .await
             }
+        })
+        .await
+        .map_err(|err| match err.code() {
+            Some("WRONGTYPE") => BastehError::TypeConversion,
+            _ => BastehError::custom(err),
+        })?;
+        if !exists {
+            return Ok(None);
+        }
+
+        // Each chunk is read with its own GETRANGE, up to the length the value had at the start
+        let chunks = stream::iter((0..len).step_by(STREAM_CHUNK_SIZE)).then(move |start| {
+            let con = con.clone();
+            let full_key = full_key.clone();
+            let end = len.min(start + STREAM_CHUNK_SIZE) - 1;
+            async move {
+                retry(policy, || {
+                    let mut con = con.clone();
+                    let full_key = &full_key;
+                    async move {
+                        con.getrange::<_, Vec<u8>>(full_key, start as isize, end as isize)
+                            .await
+                    }
+                })
+                .await
+                .map(Bytes::from)
+                .map_err(BastehError::custom)
+            }
+        });
+        Ok(Some(chunks.boxed()))
+    }
+
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        self.connection(scope)
+            .await?
+            .set(self.full_key(scope, key), value)
+            .await
+            .map_err(to_basteh)?;
+        Ok(())
+    }
+
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+        retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move { con.get::<_, Option<Vec<u8>>>(full_key).await }
+        })
+        .await
+        .map_err(BastehError::custom)
+    }
+
+    async fn value_kind(&self, scope: &[u8], key: &[u8]) -> Result<Option<ValueKind>> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+        let kind = retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move {
+                redis::cmd("TYPE")
+                    .arg(full_key)
+                    .query_async::<_, String>(&mut con)
+                    .await
+            }
+        })
+        .await
+        .map_err(to_basteh)?;
+
+        match kind.as_str() {
+            "none" => Ok(None),
+            "list" => Ok(Some(ValueKind::List)),
+            "hash" => Ok(Some(ValueKind::Map)),
+            // Numbers, strings and bytes are all stored as redis strings, so they're told apart
+            // the same way get does, by fetching the value
+            "string" => Ok(self.get(scope, key).await?.map(|value| value.kind())),
+            _ => Err(BastehError::TypeConversion),
+        }
+    }
+
+    async fn get_range(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        let full_key = self.full_key(scope, key);
+        let con = self.read_connection(scope).await?;
+        retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move {
+                con.lrange::<_, OwnedValueWrapper>(full_key, start as isize, end as isize)
+                    .await
+            }
+        })
+        .await
+        .map_err(|err| match err.code() {
+            Some("WRONGTYPE") => BastehError::TypeConversion,
+            _ => BastehError::custom(err),
+        })
+        .and_then(|v| list_from_range(v.0))
+    }
+
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> Result<Vec<Vec<OwnedValue>>> {
+        let mut pipe = redis::pipe();
+        for (key, start, end) in requests {
+            pipe.lrange(self.full_key(scope, key), *start as isize, *end as isize);
+        }
+
+        let con = self.read_connection(scope).await?;
+        let res: Vec<OwnedValueWrapper> = retry(self.retry, || {
+            let mut con = con.clone();
+            let pipe = &pipe;
+            async move { pipe.query_async(&mut con).await }
+        })
+        .await
+        .map_err(|err| match err.code() {
+            Some("WRONGTYPE") => BastehError::TypeConversion,
+            _ => BastehError::custom(err),
+        })?;
+
+        res.into_iter().map(|v| list_from_range(v.0)).collect()
+    }
+
+    async fn hset(&self, scope: &[u8], key: &[u8], field: &[u8], value: Value<'_>) -> Result<()> {
+        if let Value::List(_) | Value::Map(_) = value {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .hset::<_, _, _, ()>(full_key, field, ValueWrapper(value))
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn hincr(&self, scope: &[u8], key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        let full_key = self.full_key(scope, key);
+        let mut con = self.connection(scope).await?;
+
+        let res = con.hincr::<_, _, _, i64>(&full_key, field, delta).await;
+        match res {
+            Ok(res) => Ok(res),
+            Err(err) if err.code() == Some("WRONGTYPE") => Err(BastehError::TypeConversion),
+            Err(err) if err.to_string().contains("would overflow") => {
+                Err(BastehError::InvalidNumber)
+            }
+            // Report the kind of the field like the other backends, numbers too big for an i64
+            // are read as big numbers
+            Err(err) => match con.hget::<_, _, OwnedValueWrapper>(&full_key, field).await {
+                Ok(OwnedValueWrapper(Some(OwnedValue::BigNumber(_)))) => {
+                    Err(BastehError::InvalidNumber)
+                }
+                Ok(OwnedValueWrapper(Some(value))) => Err(BastehError::NotANumber(value.kind())),
+                _ => Err(BastehError::custom(err)),
+            },
+        }
+    }
+
+    async fn hget(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<Option<OwnedValue>> {
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .hget::<_, _, OwnedValueWrapper>(full_key, field)
+            .await
+            .map(|v| v.0)
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<bool> {
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .hdel::<_, _, i64>(full_key, field)
+            .await
+            .map(|removed| removed > 0)
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn hgetall(&self, scope: &[u8], key: &[u8]) -> Result<Vec<(Vec<u8>, OwnedValue)>> {
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .hgetall::<_, Vec<(Vec<u8>, OwnedValueWrapper)>>(full_key)
+            .await
+            .map(|fields| {
+                fields
+                    .into_iter()
+                    .filter_map(|(field, value)| value.0.map(|value| (field, value)))
+                    .collect()
+            })
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn set_bit(&self, scope: &[u8], key: &[u8], offset: u64, value: bool) -> Result<bool> {
+        redis::cmd("SETBIT")
+            .arg(self.full_key(scope, key))
+            .arg(offset)
+            .arg(value)
+            .query_async::<_, bool>(&mut self.connection(scope).await?)
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn get_bit(&self, scope: &[u8], key: &[u8], offset: u64) -> Result<bool> {
+        redis::cmd("GETBIT")
+            .arg(self.full_key(scope, key))
+            .arg(offset)
+            .query_async::<_, bool>(&mut self.connection(scope).await?)
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    // The filters are kept by the RedisBloom module if it's loaded, otherwise the bits are set
+    // with SETBIT, the same as the default implementation but in a single pipeline
+    async fn bloom_add(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<()> {
+        let full_key = self.full_key(scope, key);
+        let mut con = self.connection(scope).await?;
+
+        match redis::cmd("BF.INSERT")
+            .arg(&full_key)
+            .arg("CAPACITY")
+            .arg(params.capacity)
+            .arg("ERROR")
+            .arg(params.error_rate)
+            .arg("ITEMS")
+            .arg(item)
+            .query_async::<_, ()>(&mut con)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(err) if is_unknown_command(&err) => {
+                let mut pipe = redis::pipe();
+                for offset in params.positions(item) {
+                    pipe.cmd("SETBIT")
+                        .arg(&full_key)
+                        .arg(offset)
+                        .arg(1)
+                        .ignore();
+                }
+                pipe.query_async::<_, ()>(&mut con)
+                    .await
+                    .map_err(|err| match err.code() {
+                        Some("WRONGTYPE") => BastehError::TypeConversion,
+                        _ => BastehError::custom(err),
+                    })
+            }
+            Err(err) => Err(BastehError::custom(err)),
+        }
+    }
+
+    async fn bloom_maybe_contains(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<bool> {
+        let full_key = self.full_key(scope, key);
+        let mut con = self.connection(scope).await?;
+
+        match redis::cmd("BF.EXISTS")
+            .arg(&full_key)
+            .arg(item)
+            .query_async::<_, bool>(&mut con)
+            .await
+        {
+            Ok(exists) => Ok(exists),
+            Err(err) if is_unknown_command(&err) => {
+                let mut pipe = redis::pipe();
+                for offset in params.positions(item) {
+                    pipe.cmd("GETBIT").arg(&full_key).arg(offset);
+                }
+                pipe.query_async::<_, Vec<bool>>(&mut con)
+                    .await
+                    .map(|bits| bits.into_iter().all(|bit| bit))
+                    .map_err(|err| match err.code() {
+                        Some("WRONGTYPE") => BastehError::TypeConversion,
+                        _ => BastehError::custom(err),
+                    })
+            }
+            Err(err) => Err(BastehError::custom(err)),
+        }
+    }
+
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        if let Value::List(_) | Value::Map(_) = value {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .rpush(full_key, ValueWrapper(value))
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        if value
+            .iter()
+            .any(|item| matches!(item, Value::List(_) | Value::Map(_)))
+        {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .rpush(
+                full_key,
+                value.into_iter().map(ValueWrapper).collect::<Vec<_>>(),
+            )
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn push_returning_len(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<u64> {
+        if let Value::List(_) | Value::Map(_) = value {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .rpush(full_key, ValueWrapper(value))
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn push_capped(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        max_len: u64,
+    ) -> Result<()> {
+        if let Value::List(_) | Value::Map(_) = value {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let full_key = self.full_key(scope, key);
+        // LTRIM with a start past the stop empties the list, which removes it
+        let (start, stop) = match isize::try_from(max_len) {
+            Ok(0) => (1, 0),
+            Ok(max_len) => (-max_len, -1),
+            Err(_) => (0, -1),
+        };
+        redis::pipe()
+            .atomic()
+            .rpush(&full_key, ValueWrapper(value))
+            .ignore()
+            .ltrim(full_key, start, stop)
+            .ignore()
+            .query_async::<_, ()>(&mut self.connection(scope).await?)
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn mutate_list(&self, scope: &[u8], key: &[u8], mutations: ListMutation) -> Result<u64> {
+        let full_key = self.full_key(scope, key);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for act in mutations.iter() {
+            match act {
+                ListAction::Push(OwnedValue::List(_) | OwnedValue::Map(_)) => {
+                    return Err(BastehError::MethodNotSupported);
+                }
+                ListAction::Push(value) => {
+                    pipe.rpush(&full_key, ValueWrapper(value.as_value()))
+                        .ignore();
+                }
+                ListAction::Pop => {
+                    pipe.rpop(&full_key, None).ignore();
+                }
+                ListAction::Trim(start, end) => {
+                    let start = isize::try_from(*start).unwrap_or(isize::MIN);
+                    let end = isize::try_from(*end).unwrap_or(isize::MAX);
+                    pipe.ltrim(&full_key, start, end).ignore();
+                }
+            }
+        }
+        pipe.llen(full_key)
+            .query_async::<_, (u64,)>(&mut self.connection(scope).await?)
+            .await
+            .map(|(len,)| len)
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
+            .rpop::<_, OwnedValueWrapper>(full_key, None)
+            .await
+            .map(|v| v.0)
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn pop_blocking(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        timeout: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        // A timeout of zero makes BRPOP block forever
+        if timeout.is_zero() {
+            return self.pop(scope, key).await;
         }
-        Ok(())
-    }
 
-    async fn get(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
-            .get::<_, OwnedValueWrapper>(full_key)
+        // BRPOP holds the connection until it returns, so it can't go through the shared one
+        let mut connection_info = self.client.get_connection_info().clone();
+        if let Some(scope_db) = self.scope_db {
+            connection_info.redis.db = scope_db(scope);
+        }
+        let mut con = redis::Client::open(connection_info)
+            .map_err(BastehError::custom)?
+            .get_async_connection()
             .await
-            .map(|v| v.0)
-            .map_err(BastehError::custom)
+            .map_err(BastehError::custom)?;
+
+        redis::cmd("BRPOP")
+            .arg(self.full_key(scope, key))
+            .arg(timeout.as_secs_f64())
+            .query_async::<_, Option<(Vec<u8>, OwnedValueWrapper)>>(&mut con)
+            .await
+            .map(|v| v.and_then(|(_, value)| value.0))
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
     }
 
-    async fn get_range(
+    async fn pop_push(
         &self,
-        scope: &str,
-        key: &[u8],
-        start: i64,
-        end: i64,
-    ) -> Result<Vec<OwnedValue>> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
-            .lrange::<_, OwnedValueWrapper>(full_key, start as isize, end as isize)
+        src_scope: &[u8],
+        src_key: &[u8],
+        dst_scope: &[u8],
+        dst_key: &[u8],
+    ) -> Result<Option<OwnedValue>> {
+        // LMOVE can't move items between databases
+        if let Some(scope_db) = self.scope_db {
+            if scope_db(src_scope) != scope_db(dst_scope) {
+                return Err(BastehError::MethodNotSupported);
+            }
+        }
+
+        redis::cmd("LMOVE")
+            .arg(self.full_key(src_scope, src_key))
+            .arg(self.full_key(dst_scope, dst_key))
+            .arg("RIGHT")
+            .arg("RIGHT")
+            .query_async::<_, OwnedValueWrapper>(&mut self.connection(src_scope).await?)
             .await
             .map(|v| v.0)
-            .map_err(BastehError::custom)
-            .and_then(|v| match v {
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn list_drain(&self, scope: &[u8], key: &[u8]) -> Result<Vec<OwnedValue>> {
+        Script::new(LIST_DRAIN_SCRIPT)
+            .key(self.full_key(scope, key))
+            .invoke_async::<_, OwnedValueWrapper>(&mut self.connection(scope).await?)
+            .await
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+            .and_then(|v| match v.0 {
                 Some(OwnedValue::List(l)) => Ok(l),
                 Some(OwnedValue::Bytes(b)) => Ok(b
                     .into_iter()
                     .map(Into::<Value>::into)
                     .map(|v| v.into_owned())
                     .collect::<Vec<_>>()),
+                None => Ok(Vec::new()),
                 _ => Err(BastehError::TypeConversion),
             })
     }
 
-    async fn push(&self, scope: &str, key: &[u8], value: Value<'_>) -> Result<()> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
-            .rpush(full_key, ValueWrapper(value))
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        let full_key = self.full_key(scope, key);
+        let strict = mutations.is_strict();
+
+        let con = self.connection(scope).await?;
+
+        let res = if mutations.len() == 0 {
+            let mut con = con.clone();
+
+            // Get the value or set to 0 and return
+            match con.get::<_, Option<i64>>(&full_key).await {
+                Ok(Some(res)) => Ok(res),
+                Ok(None) => con.set(&full_key, 0_i64).await.map(|()| 0),
+                Err(err) => Err(err),
+            }
+        } else if mutations.len() == 1 {
+            match mutations.into_iter().next().unwrap() {
+                Action::Incr(delta) => con.clone().incr(&full_key, delta).await,
+                Action::Decr(delta) => con.clone().decr(&full_key, delta).await,
+                // KEEPTTL as mutations shouldn't change the expiry
+                Action::Set(value) if !strict => redis::cmd("SET")
+                    .arg(&full_key)
+                    .arg(value)
+                    .arg("KEEPTTL")
+                    .query_async::<_, ()>(&mut con.clone())
+                    .await
+                    .map(|()| value),
+                action => run_mutations(con.clone(), full_key.clone(), [action], strict).await,
+            }
+        } else {
+            run_mutations(con.clone(), full_key.clone(), mutations.into_iter(), strict).await
+        };
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(err) => Err(self.mutation_error(con, &full_key, err).await),
+        }
+    }
+
+    async fn mutate_returning(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: Mutation,
+    ) -> Result<(i64, i64)> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+
+        match run_mutations_returning(con.clone(), full_key.clone(), mutations.into_iter()).await {
+            Ok(res) => Ok(res),
+            Err(err) => Err(self.mutation_error(con, &full_key, err).await),
+        }
+    }
+
+    async fn incr_float(&self, scope: &[u8], key: &[u8], delta: f64) -> Result<f64> {
+        let full_key = self.full_key(scope, key);
+
+        let con = self.connection(scope).await?;
+
+        let res = redis::cmd("INCRBYFLOAT")
+            .arg(&full_key)
+            .arg(delta)
+            .query_async::<_, String>(&mut con.clone())
+            .await;
+
+        match res {
+            Ok(res) => res.parse().map_err(BastehError::custom),
+            Err(err) => Err(self.mutation_error(con, &full_key, err).await),
+        }
+    }
+
+    async fn incr_big(&self, scope: &[u8], key: &[u8], delta: i128) -> Result<i128> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+
+        let res = Script::new(INCR_BIG_SCRIPT)
+            .key(&full_key)
+            .arg(delta.to_string())
+            .arg(i128::MAX.to_string())
+            .arg(i128::MIN.unsigned_abs().to_string())
+            .invoke_async::<_, String>(&mut con.clone())
+            .await;
+
+        match res {
+            Ok(res) => res.parse().map_err(BastehError::custom),
+            Err(err) if err.to_string().contains("would overflow") => {
+                Err(BastehError::InvalidNumber)
+            }
+            Err(err) => Err(self.mutation_error(con, &full_key, err).await),
+        }
+    }
+
+    async fn set_if(&self, scope: &[u8], key: &[u8], value: i64, ord: Ordering) -> Result<bool> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+
+        let res = Script::new(SET_IF_SCRIPT)
+            .key(&full_key)
+            .arg(value)
+            .arg(ord as i8)
+            .invoke_async::<_, bool>(&mut con.clone())
+            .await;
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(err) => Err(self.mutation_error(con, &full_key, err).await),
+        }
+    }
+
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let full_key = self.full_key(scope, key);
+        let deleted: u64 = self
+            .connection(scope)
+            .await?
+            .del(full_key)
             .await
-            .map_err(BastehError::custom)?;
-        Ok(())
+            .map_err(to_basteh)?;
+        Ok(deleted > 0)
     }
 
-    async fn push_multiple(&self, scope: &str, key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
-            .rpush(
-                full_key,
-                value.into_iter().map(ValueWrapper).collect::<Vec<_>>(),
-            )
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let full_key = self.full_key(scope, key);
+        let mut con = self.connection(scope).await?;
+
+        // GETDEL is only available since redis 6.2, older servers get a GET and DEL pipeline
+        match redis::cmd("GETDEL")
+            .arg(&full_key)
+            .query_async::<_, OwnedValueWrapper>(&mut con)
             .await
-            .map_err(BastehError::custom)?;
-        Ok(())
+        {
+            Ok(v) => Ok(v.0),
+            Err(err) if is_unknown_command(&err) => Ok(redis::pipe()
+                .get(&full_key)
+                .del(full_key)
+                .ignore()
+                .query_async::<_, Vec<OwnedValueWrapper>>(&mut con)
+                .await
+                .map_err(to_basteh)?
+                .into_iter()
+                .next()
+                .and_then(|v| v.0)),
+            Err(err) => Err(BastehError::custom(err)),
+        }
     }
 
-    async fn pop(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
-            .rpop::<_, OwnedValueWrapper>(full_key, None)
+    async fn remove_if(&self, scope: &[u8], key: &[u8], expected: Value<'_>) -> Result<bool> {
+        check_nested(&expected)?;
+        let kind = if let Value::List(_) = expected {
+            "l"
+        } else {
+            "s"
+        };
+
+        Script::new(REMOVE_IF_SCRIPT)
+            .key(self.full_key(scope, key))
+            .arg(kind)
+            .arg(ValueWrapper(expected))
+            .invoke_async::<_, bool>(&mut self.connection(scope).await?)
             .await
-            .map(|v| v.0)
             .map_err(BastehError::custom)
     }
 
-    async fn mutate(&self, scope: &str, key: &[u8], mutations: Mutation) -> Result<i64> {
-        let full_key = get_full_key(scope, key);
+    async fn remove_matching(&self, scope: &[u8], pattern: &str) -> Result<u64> {
+        let pattern = get_full_key(escape_pattern(&self.scope_prefix(scope)), pattern);
+        let mut con = self.connection(scope).await?;
 
-        if mutations.len() == 0 {
-            let mut con = self.con.clone();
-
-            // Get the value or set to 0 and return
-            let res = con
-                .get::<_, Option<i64>>(&full_key)
+        let mut keys = Vec::new();
+        let mut cursor = 0_u64;
+        loop {
+            let (next, batch): (u64, Vec<Vec<u8>>) = redis::cmd("SCAN")
+                .cursor_arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(1000)
+                .query_async(&mut con)
                 .await
-                .map_err(BastehError::custom)?;
-
-            if let Some(res) = res {
-                Ok(res)
-            } else {
-                con.set(full_key, 0__i64)
-                    .await
-                    .map_err(BastehError::custom)?;
-                Ok(0)
+                .map_err(to_basteh)?;
+            keys.extend(batch);
+            if next == 0 {
+                break;
             }
-        } else if mutations.len() == 1 {
-            match mutations.into_iter().next().unwrap() {
-                Action::Incr(delta) => self
-                    .con
-                    .clone()
-                    .incr(full_key, delta)
-                    .await
-                    .map_err(BastehError::custom),
-                Action::Decr(delta) => self
-                    .con
-                    .clone()
-                    .decr(full_key, delta)
-                    .await
-                    .map_err(BastehError::custom),
-                Action::Set(value) => {
-                    self.con
-                        .clone()
-                        .set(full_key, value)
-                        .await
-                        .map_err(BastehError::custom)?;
-                    return Ok(value);
-                }
-                action => run_mutations(self.con.clone(), full_key, [action])
+            cursor = next;
+        }
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        // SCAN may return a key more than once
+        keys.sort_unstable();
+        keys.dedup();
+
+        let mut pipe = redis::pipe();
+        for batch in keys.chunks(1000) {
+            pipe.del(batch);
+        }
+        let removed: Vec<u64> = pipe.query_async(&mut con).await.map_err(to_basteh)?;
+        Ok(removed.into_iter().sum())
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let full_key = self.full_key(scope, key);
+        let con = self.read_connection(scope).await?;
+        let res: u8 = retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move { con.exists(full_key).await }
+        })
+        .await
+        .map_err(to_basteh)?;
+        Ok(res > 0)
+    }
+
+    // TOUCH updates the access time used by redis' LRU/LFU eviction policies
+    async fn touch(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+        let res: u8 = retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move {
+                redis::cmd("TOUCH")
+                    .arg(full_key)
+                    .query_async(&mut con)
                     .await
-                    .map_err(|e| BastehError::Custom(Box::new(e))),
             }
-        } else {
-            run_mutations(self.con.clone(), full_key, mutations.into_iter())
-                .await
-                .map_err(|e| BastehError::Custom(Box::new(e)))
+        })
+        .await
+        .map_err(to_basteh)?;
+        Ok(res > 0)
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> Result<Vec<bool>> {
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.exists(self.full_key(scope, key));
         }
+
+        let con = self.connection(scope).await?;
+        retry(self.retry, || {
+            let mut con = con.clone();
+            let pipe = &pipe;
+            async move { pipe.query_async(&mut con).await }
+        })
+        .await
+        .map_err(BastehError::custom)
     }
 
-    async fn remove(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>> {
-        let full_key = get_full_key(scope, key);
-        Ok(redis::pipe()
-            .get(&full_key)
-            .del(full_key)
-            .ignore()
-            .query_async::<_, Vec<OwnedValueWrapper>>(&mut self.con.clone())
-            .await
-            .map_err(BastehError::custom)?
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        let mut pipe = redis::pipe();
+        for key in keys {
+            let full_key = self.full_key(scope, key);
+            pipe.get(&full_key).pttl(&full_key);
+        }
+
+        let con = self.read_connection(scope).await?;
+        let res: Vec<(OwnedValueWrapper, i64)> = retry(self.retry, || {
+            let mut con = con.clone();
+            let pipe = &pipe;
+            async move { pipe.query_async(&mut con).await }
+        })
+        .await
+        .map_err(BastehError::custom)?;
+
+        Ok(res
             .into_iter()
-            .next()
-            .and_then(|v| v.0))
+            .map(|(value, ttl)| {
+                // PTTL is -1 for persistent keys and -2 for missing ones
+                let expiry = u64::try_from(ttl).ok().map(Duration::from_millis);
+                value.0.map(|value| (value, expiry))
+            })
+            .collect())
     }
 
-    async fn contains_key(&self, scope: &str, key: &[u8]) -> Result<bool> {
-        let full_key = get_full_key(scope, key);
-        let res: u8 = self
-            .con
-            .clone()
-            .exists(full_key)
-            .await
-            .map_err(BastehError::custom)?;
-        Ok(res > 0)
+    async fn snapshot(&self, scope: &[u8]) -> Result<Vec<SnapshotEntry>> {
+        // SCAN may return a key more than once
+        let keys = self
+            .keys_stream(scope)
+            .try_collect::<HashSet<_>>()
+            .await?
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let mut res = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(1000) {
+            let key_refs = chunk.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            let values = self.get_expiring_multiple(scope, &key_refs).await?;
+            res.extend(chunk.iter().zip(values).filter_map(|(key, value)| {
+                value.map(|(value, expiry)| (key.clone(), value, expiry))
+            }));
+        }
+        Ok(res)
     }
 
-    async fn persist(&self, scope: &str, key: &[u8]) -> Result<()> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
             .persist(full_key)
             .await
-            .map_err(BastehError::custom)?;
+            .map_err(to_basteh)?;
         Ok(())
     }
 
-    async fn expiry(&self, scope: &str, key: &[u8]) -> Result<Option<Duration>> {
-        let full_key = get_full_key(scope, key);
-        let res: i32 = self
-            .con
-            .clone()
-            .ttl(full_key)
-            .await
-            .map_err(BastehError::custom)?;
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
+        let full_key = self.full_key(scope, key);
+        let con = self.read_connection(scope).await?;
+        let res: i32 = retry(self.retry, || {
+            let mut con = con.clone();
+            let full_key = &full_key;
+            async move { con.ttl(full_key).await }
+        })
+        .await
+        .map_err(to_basteh)?;
         Ok(if res >= 0 {
             Some(Duration::from_secs(res as u64))
         } else {
@@ -276,31 +1706,137 @@ impl Provider for RedisBackend {
         })
     }
 
-    async fn expire(&self, scope: &str, key: &[u8], expire_in: Duration) -> Result<()> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
             .expire(full_key, expire_in.as_secs() as usize)
             .await
-            .map_err(BastehError::custom)?;
+            .map_err(to_basteh)?;
         Ok(())
     }
 
+    async fn expire_multiple(&self, scope: &[u8], entries: &[(&[u8], Duration)]) -> Result<()> {
+        let mut pipe = redis::pipe();
+        for (key, expire_in) in entries {
+            pipe.expire(self.full_key(scope, key), expire_in.as_secs() as usize)
+                .ignore();
+        }
+
+        pipe.query_async(&mut self.connection(scope).await?)
+            .await
+            .map_err(BastehError::custom)
+    }
+
+    async fn get_and_extend(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        let full_key = self.full_key(scope, key);
+        redis::cmd("GETEX")
+            .arg(full_key)
+            .arg("EX")
+            .arg(expire_in.as_secs())
+            .query_async::<_, OwnedValueWrapper>(&mut self.connection(scope).await?)
+            .await
+            .map(|v| v.0)
+            .map_err(BastehError::custom)
+    }
+
     async fn set_expiring(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         value: Value<'_>,
         expire_in: Duration,
     ) -> Result<()> {
-        let full_key = get_full_key(scope, key);
-        self.con
-            .clone()
+        check_nested(&value)?;
+        let full_key = self.full_key(scope, key);
+        self.connection(scope)
+            .await?
             .set_ex(full_key, ValueWrapper(value), expire_in.as_secs() as usize)
             .await
-            .map_err(BastehError::custom)?;
+            .map_err(to_basteh)?;
         Ok(())
     }
+
+    async fn swap_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        // SET can't store lists, and its GET option fails on keys holding one
+        if let Value::List(_) | Value::Map(_) = value {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let full_key = self.full_key(scope, key);
+        redis::cmd("SET")
+            .arg(full_key)
+            .arg(ValueWrapper(value))
+            .arg("EX")
+            .arg(expire_in.as_secs())
+            .arg("GET")
+            .query_async::<_, OwnedValueWrapper>(&mut self.connection(scope).await?)
+            .await
+            .map(|v| v.0)
+            .map_err(|err| match err.code() {
+                Some("WRONGTYPE") => BastehError::TypeConversion,
+                _ => BastehError::custom(err),
+            })
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        delta: i64,
+        ttl: Duration,
+    ) -> Result<i64> {
+        let full_key = self.full_key(scope, key);
+        let con = self.connection(scope).await?;
+
+        let res = Script::new(INCR_WITH_TTL_FLOOR_SCRIPT)
+            .key(&full_key)
+            .arg(delta)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async::<_, i64>(&mut con.clone())
+            .await;
+
+        match res {
+            Ok(res) => Ok(res),
+            Err(err) => Err(self.mutation_error(con, &full_key, err).await),
+        }
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> Result<bool> {
+        check_nested(&value)?;
+        let kind = if matches!(value, Value::List(_)) {
+            "l"
+        } else {
+            "v"
+        };
+
+        Script::new(SET_VERSIONED_SCRIPT)
+            .key(self.full_key(scope, key))
+            .key(self.versions_key(scope))
+            .arg(key)
+            .arg(format!("{:020}", version))
+            .arg(kind)
+            .arg(ValueWrapper(value))
+            .invoke_async(&mut self.connection(scope).await?)
+            .await
+            .map_err(BastehError::custom)
+    }
 }
 
 struct ValueWrapper<'a>(Value<'a>);
@@ -312,6 +1848,7 @@ impl<'a> ToRedisArgs for ValueWrapper<'a> {
     {
         match &self.0 {
             Value::Number(n) => <i64 as ToRedisArgs>::write_redis_args(&n, out),
+            Value::BigNumber(n) => <String as ToRedisArgs>::write_redis_args(&n.to_string(), out),
             Value::Bytes(b) => <&[u8] as ToRedisArgs>::write_redis_args(&b.as_ref(), out),
             Value::String(s) => <&str as ToRedisArgs>::write_redis_args(&s.as_ref(), out),
             Value::List(l) => {
@@ -319,6 +1856,12 @@ impl<'a> ToRedisArgs for ValueWrapper<'a> {
                     ValueWrapper(item.clone()).write_redis_args(out);
                 }
             }
+            Value::Map(m) => {
+                for (field, value) in m {
+                    <&[u8] as ToRedisArgs>::write_redis_args(&field.as_slice(), out);
+                    ValueWrapper(value.clone()).write_redis_args(out);
+                }
+            }
         }
     }
 }
@@ -329,10 +1872,13 @@ impl<'a> FromRedisValue for OwnedValueWrapper {
         Ok(OwnedValueWrapper(match v {
             // If it's Nil then return None
             redis::Value::Nil => None,
-            // Otherwise try to decode as Number, String or Bytes in order
+            // Otherwise try to decode as Number, BigNumber, String or Bytes in order
             _ => Some(
                 <i64 as FromRedisValue>::from_redis_value(v)
                     .map(OwnedValue::Number)
+                    .or_else(|_| {
+                        <i128 as FromRedisValue>::from_redis_value(v).map(OwnedValue::BigNumber)
+                    })
                     .or_else(|_| {
                         <String as FromRedisValue>::from_redis_value(v).map(OwnedValue::String)
                     })
@@ -358,7 +1904,7 @@ impl<'a> FromRedisValue for OwnedValueWrapper {
 #[cfg(test)]
 mod test {
     use super::*;
-    use basteh::test_utils::*;
+    use basteh::{test_utils::*, Basteh};
     use std::sync::Once;
 
     static INIT: Once = Once::new();
@@ -377,11 +1923,203 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_redis_connect_with_retry() {
+        // A free port, the fake server starts listening on it after a while
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let info: ConnectionInfo = format!("redis://127.0.0.1:{}/", port).parse().unwrap();
+
+        assert!(RedisBackend::connect(info.clone()).await.is_err());
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+                .await
+                .unwrap();
+            // Connecting to database 0 without a password doesn't send any commands
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        assert!(
+            RedisBackend::connect_with_retry(info, 10, Duration::from_millis(50))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redis_retry_reads() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A fake server dropping the first connection on its first request, and answering every
+        // request with the same string after that
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        tokio::spawn({
+            let connections = connections.clone();
+            async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let first = connections.fetch_add(1, AtomicOrdering::SeqCst) == 0;
+                    tokio::spawn(async move {
+                        let mut buf = [0; 1024];
+                        while let Ok(n) = socket.read(&mut buf).await {
+                            if n == 0 || first {
+                                break;
+                            }
+                            if socket.write_all(b"$5\r\nvalue\r\n").await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            }
+        });
+        let info: ConnectionInfo = format!("redis://127.0.0.1:{}/", port).parse().unwrap();
+
+        let provider = RedisBackend::connect(info)
+            .await
+            .unwrap()
+            .with_retry(3, Duration::from_millis(50));
+        assert_eq!(
+            provider.get(b"retry", b"key").await.unwrap(),
+            Some(OwnedValue::String("value".to_owned()))
+        );
+        assert_eq!(connections.load(AtomicOrdering::SeqCst), 2);
+    }
+
     #[tokio::test]
     async fn test_redis_store() {
         test_store(get_connection().await).await;
     }
 
+    #[tokio::test]
+    async fn test_redis_binary_scope() {
+        test_binary_scope(get_connection().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_redis_size_bytes() {
+        test_size_bytes(get_connection().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_redis_hash_tag_scope() {
+        let provider = get_connection().await.with_hash_tag_scope(true);
+        assert_eq!(provider.full_key(b"scope", b"key"), b"{scope}:key".to_vec());
+        assert_eq!(
+            provider.versions_key(b"scope"),
+            b"{scope}\0__versions__".to_vec()
+        );
+
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .finish()
+            .scope("hash_tag");
+        store.set("key", "value").await.unwrap();
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+        assert_eq!(
+            store.keys().await.unwrap().collect::<Vec<_>>(),
+            vec![b"key".to_vec()]
+        );
+
+        // The hash tag is part of the stored key
+        let mut con = provider.con.clone();
+        assert!(con.exists::<_, bool>("{hash_tag}:key").await.unwrap());
+        assert!(!con.exists::<_, bool>("hash_tag:key").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_redis_scope_to_db_mapping() {
+        fn mapping(scope: &[u8]) -> i64 {
+            match scope {
+                b"db_one" => 1,
+                b"db_two" => 2,
+                _ => 0,
+            }
+        }
+
+        let provider = get_connection().await.with_scope_to_db_mapping(mapping);
+        let mapped = Basteh::build().provider(provider.clone()).finish();
+        let unmapped = Basteh::build().provider(get_connection().await).finish();
+
+        let one = mapped.scope("db_one");
+        let two = mapped.scope("db_two");
+        one.drop_scope().await.unwrap();
+        two.drop_scope().await.unwrap();
+
+        one.set("key1", "one").await.unwrap();
+        two.set("key2", "two").await.unwrap();
+        assert_eq!(
+            one.get::<String>("key1").await.unwrap(),
+            Some("one".to_owned())
+        );
+        assert_eq!(
+            two.get::<String>("key2").await.unwrap(),
+            Some("two".to_owned())
+        );
+        assert_eq!(
+            one.keys().await.unwrap().collect::<Vec<_>>(),
+            vec![b"key1".to_vec()]
+        );
+        assert_eq!(
+            two.keys().await.unwrap().collect::<Vec<_>>(),
+            vec![b"key2".to_vec()]
+        );
+
+        // The keys are not stored in the default database
+        assert_eq!(unmapped.scope("db_one").keys().await.unwrap().count(), 0);
+        assert_eq!(unmapped.scope("db_two").keys().await.unwrap().count(), 0);
+
+        // Items can't be moved between databases
+        one.push("list", "item").await.unwrap();
+        assert!(matches!(
+            provider
+                .pop_push(b"db_one", b"list", b"db_two", b"list")
+                .await,
+            Err(BastehError::MethodNotSupported)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_redis_drop_scope() {
+        test_drop_scope(get_connection().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_redis_scopes() {
+        test_scopes(get_connection().await).await;
+    }
+
+    #[tokio::test]
+    async fn test_redis_nested_list() {
+        let provider = get_connection().await;
+        assert!(!provider.capabilities().nested_lists);
+
+        let store = Basteh::build().provider(provider).finish();
+        let nested = vec![Value::Number(1), Value::List(vec![Value::Number(2)])];
+
+        assert!(matches!(
+            store.set("nested_list_key", nested).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            store.push("nested_list_key", vec![1, 2]).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(!store.contains_key("nested_list_key").await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_redis_mutations() {
         test_mutations(get_connection().await).await;
@@ -396,4 +2134,145 @@ mod test {
     async fn test_redis_expiry_store() {
         test_expiry_store(get_connection().await, 5).await;
     }
+
+    #[tokio::test]
+    async fn test_redis_mutate_keeps_ttl() {
+        let provider = get_connection().await;
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .finish()
+            .scope("mutate_ttl");
+
+        store
+            .set_expiring("key", 10, Duration::from_secs(100))
+            .await
+            .unwrap();
+        assert_eq!(store.mutate("key", |m| m.set(20)).await.unwrap(), 20);
+        assert_eq!(store.mutate("key", |m| m.set(1).incr(2)).await.unwrap(), 3);
+
+        let mut con = provider.con.clone();
+        let ttl: i64 = con.pttl("mutate_ttl:key").await.unwrap();
+        assert!(ttl > 0);
+    }
+
+    #[tokio::test]
+    async fn test_redis_session_store() {
+        test_session_store(get_connection().await, 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_redis_get_hash() {
+        let store = Basteh::build()
+            .provider(get_connection().await)
+            .finish()
+            .scope("get_hash");
+
+        store.hset("map", "field1", 1).await.unwrap();
+        store.hset("map", "field2", "value").await.unwrap();
+        let mut fields = match store.get::<OwnedValue>("map").await.unwrap() {
+            Some(OwnedValue::Map(fields)) => fields,
+            value => panic!("Expected a map, got {:?}", value),
+        };
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            fields,
+            vec![
+                (b"field1".to_vec(), OwnedValue::Number(1)),
+                (b"field2".to_vec(), OwnedValue::String("value".to_owned())),
+            ]
+        );
+
+        // And so are lists
+        store.push("list", "value").await.unwrap();
+        assert_eq!(
+            store.get::<OwnedValue>("list").await.unwrap(),
+            Some(OwnedValue::List(vec![OwnedValue::String(
+                "value".to_owned()
+            )]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redis_incr_with_ttl_floor_millis() {
+        let provider = get_connection().await;
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .finish()
+            .scope("incr_ttl_millis");
+
+        // Sub-second ttls shouldn't be truncated to 0
+        let ttl = Duration::from_millis(500);
+        assert_eq!(store.incr_with_ttl_floor("key", 1, ttl).await.unwrap(), 1);
+        let mut con = provider.con.clone();
+        let pttl: i64 = con.pttl("incr_ttl_millis:key").await.unwrap();
+        assert!(pttl > 0 && pttl <= 500);
+
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(store.get::<i64>("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_redis_remove() {
+        let store = Basteh::build()
+            .provider(get_connection().await)
+            .finish()
+            .scope("remove");
+
+        store.set("key", "value").await.unwrap();
+        assert_eq!(
+            store.remove::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+        assert!(!store.contains_key("key").await.unwrap());
+        assert_eq!(store.remove::<String>("key").await.unwrap(), None);
+    }
+
+    // Needs a second redis endpoint which doesn't replicate from the primary, so the test can tell
+    // where the reads are served from, e.g. BASTEH_REDIS_REPLICA=redis://127.0.0.1/1
+    #[tokio::test]
+    async fn test_redis_split() {
+        let replica = match std::env::var("BASTEH_REDIS_REPLICA") {
+            Ok(replica) => replica,
+            Err(_) => return,
+        };
+        get_connection().await;
+
+        let provider = RedisBackend::connect_split(
+            "redis://127.0.0.1/".parse().unwrap(),
+            vec![replica.parse().unwrap()],
+        )
+        .await
+        .unwrap();
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .finish()
+            .scope("split");
+
+        let mut replica_con = redis::Client::open(replica)
+            .unwrap()
+            .get_async_connection()
+            .await
+            .unwrap();
+        let _: () = replica_con.del("split:key").await.unwrap();
+        let _: () = replica_con
+            .set("split:replicated", "replica")
+            .await
+            .unwrap();
+
+        // Writes go to the primary
+        store.set("key", "value").await.unwrap();
+        let mut con = provider.con.clone();
+        let value: Option<String> = con.get("split:key").await.unwrap();
+        assert_eq!(value, Some("value".to_owned()));
+        let value: Option<String> = replica_con.get("split:key").await.unwrap();
+        assert_eq!(value, None);
+
+        // Reads are served from the replica
+        assert_eq!(store.get::<String>("key").await.unwrap(), None);
+        assert!(!store.contains_key("key").await.unwrap());
+        assert_eq!(
+            store.get::<String>("replicated").await.unwrap(),
+            Some("replica".to_owned())
+        );
+    }
 }
@@ -0,0 +1,150 @@
+use std::{future::Future, time::Duration};
+
+use redis::{RedisError, RedisResult};
+
+/// The longest wait between two attempts, unless the policy starts with a longer one
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// Retry policy used for idempotent operations, the delay is doubled after each failed attempt.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    pub(crate) attempts: u32,
+    pub(crate) delay: Duration,
+}
+
+/// Errors which are likely to go away by trying again, like dropped connections. Cluster
+/// redirections are not, as the connection manager can't follow them.
+fn is_transient(err: &RedisError) -> bool {
+    err.is_io_error()
+        || err.is_connection_dropped()
+        || err.is_connection_refusal()
+        || err.is_timeout()
+}
+
+/// Run the operation, retrying transient failures according to the policy, the last error is
+/// returned if all the attempts fail.
+pub(crate) async fn retry<T, F, Fut>(policy: Option<RetryPolicy>, mut op: F) -> RedisResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = RedisResult<T>>,
+{
+    let policy = match policy {
+        Some(policy) => policy,
+        None => return op().await,
+    };
+
+    let max_delay = policy.delay.max(MAX_DELAY);
+    let mut delay = policy.delay;
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(err) if attempt < policy.attempts && is_transient(&err) => {
+                tokio::time::sleep(delay).await;
+                delay = delay.saturating_mul(2).min(max_delay);
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn connection_reset() -> RedisError {
+        std::io::Error::new(std::io::ErrorKind::ConnectionReset, "Connection reset").into()
+    }
+
+    fn policy() -> Option<RetryPolicy> {
+        Some(RetryPolicy {
+            attempts: 3,
+            delay: Duration::from_millis(10),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_failure() {
+        let calls = AtomicU32::new(0);
+        let res = retry(policy(), || async {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(connection_reset())
+            } else {
+                Ok(10)
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), 10);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let calls = AtomicU32::new(0);
+        let res: RedisResult<()> = retry(policy(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(connection_reset())
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_caps_delay() {
+        let policy = Some(RetryPolicy {
+            attempts: 100,
+            delay: Duration::from_secs(1),
+        });
+        let start = tokio::time::Instant::now();
+        let res: RedisResult<()> = retry(policy, || async { Err(connection_reset()) }).await;
+
+        assert!(res.is_err());
+        // 1 + 2 + 4 + 8 seconds, then capped to 10 seconds for the remaining 95 waits
+        assert_eq!(start.elapsed(), Duration::from_secs(15 + 95 * 10));
+    }
+
+    #[tokio::test]
+    async fn test_retry_ignores_other_errors() {
+        let calls = AtomicU32::new(0);
+        let res: RedisResult<()> = retry(policy(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err((redis::ErrorKind::TypeError, "Not a number").into())
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Cluster redirections can't be followed by retrying
+        let calls = AtomicU32::new(0);
+        let res: RedisResult<()> = retry(policy(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err((
+                redis::ErrorKind::Moved,
+                "Moved",
+                "1 127.0.0.1:6380".to_owned(),
+            )
+                .into())
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Without a policy nothing is retried
+        let calls = AtomicU32::new(0);
+        let res: RedisResult<()> = retry(None, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(connection_reset())
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,15 +1,31 @@
 use std::{cmp::Ordering, fmt::Write};
 
-use basteh::dev::Action;
-use redis::{aio::ConnectionManager, RedisError, Script};
+use basteh::{dev::Action, BastehError};
+use redis::{aio::ConnectionManager, ErrorKind, FromRedisValue, RedisError, Script};
 
 pub(super) async fn run_mutations(
-    mut con: ConnectionManager,
+    con: ConnectionManager,
     key: Vec<u8>,
     mutations: impl IntoIterator<Item = Action>,
+    strict: bool,
 ) -> std::result::Result<i64, RedisError> {
-    let (script, args) = make_script(mutations);
+    invoke_script(con, key, make_script(mutations, strict, false)).await
+}
 
+/// Same as [`run_mutations`], returning the value before the mutations as well
+pub(super) async fn run_mutations_returning(
+    con: ConnectionManager,
+    key: Vec<u8>,
+    mutations: impl IntoIterator<Item = Action>,
+) -> std::result::Result<(i64, i64), RedisError> {
+    invoke_script(con, key, make_script(mutations, true, true)).await
+}
+
+async fn invoke_script<T: FromRedisValue>(
+    mut con: ConnectionManager,
+    key: Vec<u8>,
+    (script, args): (String, Vec<i64>),
+) -> std::result::Result<T, RedisError> {
     let script = Script::new(&script);
     let args = args.into_iter();
 
@@ -22,14 +38,49 @@ pub(super) async fn run_mutations(
     script.key(key).invoke_async(&mut con).await
 }
 
-fn make_script(mutations: impl IntoIterator<Item = Action>) -> (String, Vec<i64>) {
+/// Convert the errors of redis, to be used as `.map_err(to_basteh)?`
+#[inline]
+pub(super) fn to_basteh(err: RedisError) -> BastehError {
+    BastehError::custom(err)
+}
+
+/// Check if the server rejected the command because it doesn't know it(ex. older versions)
+pub(super) fn is_unknown_command(err: &RedisError) -> bool {
+    err.kind() == ErrorKind::ResponseError
+        && err
+            .detail()
+            .is_some_and(|detail| detail.starts_with("unknown command"))
+}
+
+fn make_script(
+    mutations: impl IntoIterator<Item = Action>,
+    strict: bool,
+    returning: bool,
+) -> (String, Vec<i64>) {
     let mut script = String::new();
     let mut args = Vec::new();
-    script.push_str("local r=tonumber(redis.call('GET', KEYS[1]))\n");
+    // Missing keys start from 0, values which are not numbers are only overwritten by a leading
+    // set, unless strict(or the old value is returned) where they're reported as an error right away
+    script.push_str(
+        "local v=redis.call('GET', KEYS[1])\n\
+         local r=0\n\
+         if v then r=tonumber(v) end\n",
+    );
+    if strict || returning {
+        script
+            .push_str("if r==nil then return redis.error_reply('ERR value is not a number') end\n");
+    }
+    script.push_str("local o=r\n");
 
     write_operation(mutations, &mut script, &mut args);
 
-    script.push_str("redis.call('SET', KEYS[1], r)\nreturn r");
+    // KEEPTTL as mutations shouldn't change the expiry
+    script.push_str("redis.call('SET', KEYS[1], r, 'KEEPTTL')\n");
+    script.push_str(if returning {
+        "return {o, r}"
+    } else {
+        "return r"
+    });
 
     (script, args)
 }
@@ -55,6 +55,14 @@ impl DelayQueue {
         queue.push(item);
     }
 
+    /// Remove the items of the tree whose keys start with the prefix
+    pub fn remove_prefix(&mut self, tree_name: &[u8], prefix: &[u8]) {
+        self.inner
+            .queue
+            .lock()
+            .retain(|item| item.scope.as_ref() != tree_name || !item.key.starts_with(prefix));
+    }
+
     pub fn try_pop_for(&mut self, duration: Duration) -> Option<DelayedIem> {
         let try_until = Instant::now() + duration;
         let mut queue = self.inner.queue.lock();
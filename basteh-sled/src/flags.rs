@@ -1,10 +1,9 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use basteh::dev::{Clock, SystemClock};
 use byteorder::LittleEndian;
 use zerocopy::{AsBytes, FromBytes, Unaligned, U16, U64};
 
-use super::utils::get_current_timestamp;
-
 /// Represents expiry data and is stored as suffix to the value.
 ///
 /// Nonce is used to ignore expiration requests after the value has changed as we don't have direct access to delay-queue
@@ -17,6 +16,14 @@ pub struct ExpiryFlags {
     pub persist: U16<LittleEndian>,
 }
 
+/// Flags of the values stored without expiry(see `SledBackend::without_expiry`), they're always
+/// persistent.
+pub(crate) static PERSISTENT_FLAGS: ExpiryFlags = ExpiryFlags {
+    nonce: U64::ZERO,
+    expires_at: U64::ZERO,
+    persist: U16::from_bytes([1, 0]),
+};
+
 impl ExpiryFlags {
     /// Make a new flags struct with persist flag set to true. Provide 0 for nonce if it's a new key.
     pub fn new_persist(nonce: u64) -> Self {
@@ -29,7 +36,12 @@ impl ExpiryFlags {
 
     /// Make a new flags struct with persist flag set to false. Provide 0 for nonce if it's a new key.
     pub fn new_expiring(nonce: u64, expires_in: Duration) -> Self {
-        let expires_at = get_current_timestamp() + expires_in.as_secs();
+        Self::new_expiring_with_clock(nonce, expires_in, &SystemClock)
+    }
+
+    /// Same as [`new_expiring`](Self::new_expiring), reading the current time from `clock`
+    pub fn new_expiring_with_clock(nonce: u64, expires_in: Duration, clock: &dyn Clock) -> Self {
+        let expires_at = clock.timestamp() + expires_in.as_secs();
         Self {
             nonce: U64::new(nonce),
             expires_at: U64::new(expires_at),
@@ -53,17 +65,26 @@ impl ExpiryFlags {
 
     /// Change the expiration time
     pub fn expire_in(&mut self, duration: Duration) {
-        self.expires_at
-            .set(get_current_timestamp() + duration.as_secs())
+        self.expire_in_with_clock(duration, &SystemClock)
+    }
+
+    /// Same as [`expire_in`](Self::expire_in), reading the current time from `clock`
+    pub fn expire_in_with_clock(&mut self, duration: Duration, clock: &dyn Clock) {
+        self.expires_at.set(clock.timestamp() + duration.as_secs())
     }
 
     /// Get the expiration time, returns None if persist flag is true.
     pub fn expires_in(&self) -> Option<Duration> {
+        self.expires_in_with_clock(&SystemClock)
+    }
+
+    /// Same as [`expires_in`](Self::expires_in), reading the current time from `clock`
+    pub fn expires_in_with_clock(&self, clock: &dyn Clock) -> Option<Duration> {
         if self.persist.get() == 1 {
             return None;
         }
         let expires_at = self.expires_at.get();
-        let now = get_current_timestamp();
+        let now = clock.timestamp();
         if expires_at <= now {
             Some(Duration::default())
         } else {
@@ -71,16 +92,30 @@ impl ExpiryFlags {
         }
     }
 
+    /// Get the absolute expiration time, returns None if persist flag is true.
+    pub fn expires_at_systemtime(&self) -> Option<SystemTime> {
+        if self.persist.get() == 1 {
+            return None;
+        }
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(self.expires_at.get()))
+    }
+
     /// Check if the key is expired
     pub fn expired(&self) -> bool {
+        self.expired_with_clock(&SystemClock)
+    }
+
+    /// Same as [`expired`](Self::expired), reading the current time from `clock`
+    pub fn expired_with_clock(&self, clock: &dyn Clock) -> bool {
         let expires_at = self.expires_at.get();
-        self.persist.get() == 0 && expires_at <= get_current_timestamp()
+        self.persist.get() == 0 && expires_at <= clock.timestamp()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use basteh::dev::MockClock;
 
     #[test]
     fn test_persist_flag() {
@@ -118,4 +153,39 @@ mod tests {
         assert!(expires_in.unwrap().as_millis() <= 2000);
         assert!(expires_in.unwrap().as_millis() >= 1000);
     }
+
+    #[test]
+    fn test_expires_at_systemtime() {
+        let flags = ExpiryFlags::new_persist(0);
+        assert_eq!(flags.expires_at_systemtime(), None);
+
+        let flags = ExpiryFlags::new_expiring(0, Duration::from_secs(100));
+        let expected = SystemTime::now() + Duration::from_secs(100);
+        let expires_at = flags.expires_at_systemtime().unwrap();
+
+        // We only store seconds, so it may be up to a second earlier
+        assert!(expires_at <= expected);
+        assert!(expires_at + Duration::from_secs(2) > expected);
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::default();
+        let mut flags = ExpiryFlags::new_expiring_with_clock(0, Duration::from_secs(60), &clock);
+        assert!(!flags.expired_with_clock(&clock));
+        assert_eq!(
+            flags.expires_in_with_clock(&clock),
+            Some(Duration::from_secs(60))
+        );
+
+        clock.advance(Duration::from_secs(59));
+        assert!(!flags.expired_with_clock(&clock));
+        clock.advance(Duration::from_secs(1));
+        assert!(flags.expired_with_clock(&clock));
+
+        flags.expire_in_with_clock(Duration::from_secs(10), &clock);
+        assert!(!flags.expired_with_clock(&clock));
+        clock.advance(Duration::from_secs(10));
+        assert!(flags.expired_with_clock(&clock));
+    }
 }
@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+/// Version of the on-disk format(value encoding and expiry flags) written by this crate, it is
+/// bumped whenever the format changes in an incompatible way.
+///
+/// The version is stored once per database in a dedicated tree. Databases holding data before the
+/// version was recorded use version 1, which version 2 extended with nested lists, maps, big
+/// numbers and string items in lists(they were stored as bytes). Version 1 values are read the
+/// same by version 2, so these databases are marked as version 2 on start.
+pub const FORMAT_VERSION: u8 = 2;
+
+/// Version of the databases written before the version was recorded
+pub(crate) const LEGACY_FORMAT_VERSION: u8 = 1;
+
+pub(crate) const METADATA_TREE: &[u8] = b"__BASTEH_METADATA__";
+pub(crate) const FORMAT_VERSION_KEY: &[u8] = b"format_version";
+pub(crate) const SELF_TEST_KEY: &[u8] = b"self_test";
+pub(crate) const CHECKSUMS_KEY: &[u8] = b"checksums";
+pub(crate) const TIMESTAMPS_KEY: &[u8] = b"timestamps";
+pub(crate) const SCOPE_STRATEGY_KEY: &[u8] = b"scope_strategy";
+pub(crate) const NUMBER_ENDIANNESS_KEY: &[u8] = b"number_endianness";
+pub(crate) const EXPIRY_FLAGS_KEY: &[u8] = b"expiry_flags";
+
+/// Returned for all the operations of a backend started on a database written with another
+/// format version, so the values are never misdecoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Database uses format version {found}, but only version {supported} is supported")]
+pub struct FormatVersionError {
+    /// The version stored in the database
+    pub found: u8,
+    /// The version this crate reads and writes
+    pub supported: u8,
+}
+
+/// Returned by [`SledBackend::start_checked`](crate::SledBackend::start_checked) if a value
+/// written to the database couldn't be read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Database self-test failed, a written value couldn't be read back")]
+pub struct SelfTestError;
@@ -1,30 +1,181 @@
-use std::convert::TryInto;
-use std::time::Duration;
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+use std::ops::Bound;
+use std::time::{Duration, SystemTime};
 
-use basteh::dev::{Mutation, OwnedValue, Value};
+use basteh::dev::{
+    add_big, add_float, add_number, resolve_range, run_list_mutations, should_set, write_bit,
+    ListMutation, Mutation, OwnedValue, SnapshotEntry, Value, ValueKind,
+};
 use basteh::BastehError;
-use sled::IVec;
+use sled::{
+    transaction::{
+        ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+        TransactionalTree,
+    },
+    IVec, Transactional,
+};
 
-use crate::decode;
-use crate::utils::{decode_mut, run_mutations};
+use crate::utils::{run_mutations, to_basteh, Encoding};
 
-use super::message::{Message, Request, Response};
+use super::message::{FlagsUpdate, Message, Request, Response};
 use crate::{
     delayqueue::{DelayQueue, DelayedIem},
-    encode, ExpiryFlags,
+    format::{
+        FormatVersionError, SelfTestError, CHECKSUMS_KEY, EXPIRY_FLAGS_KEY, FORMAT_VERSION,
+        FORMAT_VERSION_KEY, LEGACY_FORMAT_VERSION, METADATA_TREE, NUMBER_ENDIANNESS_KEY,
+        SCOPE_STRATEGY_KEY, SELF_TEST_KEY, TIMESTAMPS_KEY,
+    },
+    runtime,
+    scopes::{scope_prefix, split_key, ScopeStrategy, SCOPES_TREE},
+    store::ExpiredItem,
+    value::Endian,
+    ExpiryFlags,
 };
 
 type Result<T> = std::result::Result<T, BastehError>;
 
+// Versions of the keys written by set_versioned are kept in a sibling tree
+const VERSIONS_TREE_SUFFIX: &[u8] = b"__VERSIONS__";
+
+// Name of the tree sled opens by default, it's never used as a scope
+const DEFAULT_TREE: &[u8] = b"__sled__default";
+
+fn versions_tree_name(scope: &[u8]) -> Vec<u8> {
+    [scope, VERSIONS_TREE_SUFFIX].concat()
+}
+
+/// Returns `InvalidKey` for the scopes whose tree would be taken for one of the internal trees,
+/// it only matters when every scope has its own tree
+fn check_tree_name(scope: &[u8]) -> Result<()> {
+    if scope == METADATA_TREE
+        || scope == SCOPES_TREE
+        || scope == DEFAULT_TREE
+        || scope.ends_with(VERSIONS_TREE_SUFFIX)
+    {
+        return Err(BastehError::InvalidKey(
+            String::from_utf8_lossy(scope).into_owned(),
+        ));
+    }
+    Ok(())
+}
+
 #[inline]
 pub(crate) fn open_tree(db: &sled::Db, scope: &[u8]) -> Result<sled::Tree> {
     db.open_tree(scope).map_err(BastehError::custom)
 }
 
+/// Remove the expired keys of a tree and push the expiring ones into the queue
+fn scan_tree(db: &sled::Db, encoding: Encoding, tree_name: &IVec, queue: &mut DelayQueue) {
+    if tree_name.ends_with(VERSIONS_TREE_SUFFIX) || tree_name.as_ref() == METADATA_TREE {
+        return;
+    }
+
+    let tree = if let Ok(tree) = open_tree(db, tree_name) {
+        tree
+    } else {
+        log::warn!("Failed to open tree {:?}", tree_name);
+        return;
+    };
+
+    let mut deleted_keys = vec![];
+    for kv in tree.iter() {
+        let (key, value) = if let Ok((key, value)) = kv {
+            (key, value)
+        } else {
+            log::warn!(
+                "Failed to read key-value pair, {:?} in tree {:?}",
+                kv,
+                tree_name
+            );
+            continue;
+        };
+
+        if let Some((_, exp)) = encoding.decode(&value) {
+            if exp.expired() {
+                deleted_keys.push(key);
+            } else if let Some(dur) = exp.expires_in() {
+                queue.push(DelayedIem::new(
+                    tree_name.to_vec().into(),
+                    key.to_vec().into(),
+                    exp.nonce.get(),
+                    dur,
+                ));
+            }
+        } else {
+            log::warn!("Failed to decode key ({:?}) in tree ({:?})", key, tree_name);
+        }
+    }
+    for key in deleted_keys {
+        tree.remove(&key).unwrap();
+    }
+}
+
+/// Pop a value from the source list and push it to the destination list, as part of a transaction
+fn pop_push_transaction(
+    encoding: Encoding,
+    src: &TransactionalTree,
+    src_key: &[u8],
+    dst: &TransactionalTree,
+    dst_key: &[u8],
+) -> ConflictableTransactionResult<Option<OwnedValue>, BastehError> {
+    let bytes = src.get(src_key)?;
+    let (value, bytes) = match bytes.as_deref().and_then(|bytes| encoding.decode(bytes)) {
+        Some((Value::List(mut l), exp)) => match l.pop() {
+            Some(value) => (value.into_owned(), encoding.encode(Value::List(l), exp)),
+            None => return Ok(None),
+        },
+        Some(_) => {
+            return Err(ConflictableTransactionError::Abort(
+                BastehError::TypeConversion,
+            ))
+        }
+        None => return Ok(None),
+    };
+    src.insert(src_key, bytes)?;
+
+    // Reading after the write, so it works if source and destination are the same
+    let bytes = dst.get(dst_key)?;
+    let bytes = match bytes.as_deref().and_then(|bytes| encoding.decode(bytes)) {
+        Some((Value::List(mut l), exp)) => {
+            l.push(value.as_value());
+            encoding.encode(Value::List(l), exp)
+        }
+        // Aborting rolls back the pop as well
+        Some(_) => {
+            return Err(ConflictableTransactionError::Abort(
+                BastehError::TypeConversion,
+            ))
+        }
+        None => encoding.encode(
+            Value::List(vec![value.as_value()]),
+            &ExpiryFlags::new_persist(0),
+        ),
+    };
+    dst.insert(dst_key, bytes)?;
+
+    // Emptied lists are removed, the same as redis. Checking after the push, so rotating a single
+    // item list in place keeps the key
+    if let Some((Value::List(l), _)) = src
+        .get(src_key)?
+        .as_deref()
+        .and_then(|bytes| encoding.decode(bytes))
+    {
+        if l.is_empty() {
+            src.remove(src_key)?;
+        }
+    }
+
+    Ok(Some(value))
+}
+
 #[derive(Clone)]
 pub(crate) struct SledInner {
     pub(crate) db: sled::Db,
     pub(crate) queue: DelayQueue,
+    pub(crate) format_error: Option<FormatVersionError>,
+    pub(crate) encoding: Encoding,
+    pub(crate) scope_strategy: ScopeStrategy,
 }
 
 impl SledInner {
@@ -32,58 +183,259 @@ impl SledInner {
         Self {
             db,
             queue: DelayQueue::new(),
+            format_error: None,
+            encoding: Encoding::default(),
+            scope_strategy: ScopeStrategy::default(),
         }
     }
 
-    pub fn scan_db(&mut self) {
+    /// Open the tree holding the keys of the scope, returning it with its name
+    fn scope_tree(&self, scope: &[u8]) -> Result<(sled::Tree, IVec)> {
+        let tree_name = match self.scope_strategy {
+            ScopeStrategy::Tree => {
+                check_tree_name(scope)?;
+                scope
+            }
+            ScopeStrategy::Prefix => SCOPES_TREE,
+        };
+        Ok((open_tree(&self.db, tree_name)?, tree_name.into()))
+    }
+
+    /// Prefix of the keys of the scope in its tree, it's empty if the scope has its own tree
+    fn key_prefix(&self, scope: &[u8]) -> Vec<u8> {
+        match self.scope_strategy {
+            ScopeStrategy::Tree => Vec::new(),
+            ScopeStrategy::Prefix => scope_prefix(scope),
+        }
+    }
+
+    /// Open the tree holding the key, returning it with its name and the key as it's stored in it
+    fn locate(&self, scope: IVec, key: IVec) -> Result<(sled::Tree, IVec, IVec)> {
+        match self.scope_strategy {
+            ScopeStrategy::Tree => {
+                check_tree_name(&scope)?;
+                Ok((open_tree(&self.db, &scope)?, scope, key))
+            }
+            ScopeStrategy::Prefix => {
+                let (tree, tree_name) = self.scope_tree(&scope)?;
+                let key = [self.key_prefix(&scope).as_slice(), &key].concat();
+                Ok((tree, tree_name, key.into()))
+            }
+        }
+    }
+
+    /// Whether any tree other than the metadata one has a key, to tell the databases written
+    /// before the metadata was recorded apart from the new ones
+    fn has_data(&self) -> Result<bool> {
         for tree_name in self.db.tree_names() {
-            let tree = if let Ok(tree) = open_tree(&self.db, &tree_name) {
-                tree
-            } else {
-                log::warn!("Failed to open tree {:?}", tree_name);
-                continue;
-            };
+            if tree_name.as_ref() != METADATA_TREE && !open_tree(&self.db, &tree_name)?.is_empty() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
 
-            let mut deleted_keys = vec![];
-            for kv in tree.iter() {
-                let (key, value) = if let Ok((key, value)) = kv {
-                    (key, value)
+    /// Check the format version of the database, marking new databases with the current one and
+    /// migrating the older ones if possible. Incompatible versions are kept in `format_error` and
+    /// returned for all the requests.
+    ///
+    /// Whether the values have expiry flags, checksums and write times, how the scopes are stored
+    /// and the byte order of the numbers, is also recorded for new databases, existing ones keep
+    /// the modes they were created with. Databases holding data before the modes were recorded are
+    /// marked with the modes they were written with.
+    pub fn check_format_version(&mut self) -> Result<()> {
+        let tree = open_tree(&self.db, METADATA_TREE)?;
+        let mut found = match tree.get(FORMAT_VERSION_KEY).map_err(to_basteh)? {
+            Some(version) => version.first().copied().unwrap_or_default(),
+            None => {
+                // Databases written before the metadata was recorded keep the legacy modes, only
+                // the new ones are created with the requested modes
+                let legacy = self.has_data()?;
+                let version = if legacy {
+                    LEGACY_FORMAT_VERSION
                 } else {
-                    log::warn!(
-                        "Failed to read key-value pair, {:?} in tree {:?}",
-                        kv,
-                        tree_name
-                    );
-                    continue;
+                    FORMAT_VERSION
                 };
-
-                if let Some((_, exp)) = decode(&value) {
-                    if exp.expired() {
-                        deleted_keys.push(key);
-                    } else if let Some(dur) = exp.expires_in() {
-                        self.queue.push(DelayedIem::new(
-                            tree_name.to_vec().into(),
-                            key.to_vec().into(),
-                            exp.nonce.get(),
-                            dur,
-                        ));
-                    }
+                tree.insert(FORMAT_VERSION_KEY, &[version])
+                    .map_err(to_basteh)?;
+                tree.insert(EXPIRY_FLAGS_KEY, &[(self.encoding.expiry || legacy) as u8])
+                    .map_err(to_basteh)?;
+                tree.insert(CHECKSUMS_KEY, &[(self.encoding.checksums && !legacy) as u8])
+                    .map_err(to_basteh)?;
+                tree.insert(
+                    TIMESTAMPS_KEY,
+                    &[(self.encoding.timestamps && !legacy) as u8],
+                )
+                .map_err(to_basteh)?;
+                let scope_strategy = if legacy {
+                    ScopeStrategy::Tree
                 } else {
-                    log::warn!("Failed to decode key ({:?}) in tree ({:?})", key, tree_name);
-                }
+                    self.scope_strategy
+                };
+                tree.insert(SCOPE_STRATEGY_KEY, &[scope_strategy.as_byte()])
+                    .map_err(to_basteh)?;
+                let numbers = if legacy {
+                    Endian::Little
+                } else {
+                    self.encoding.numbers
+                };
+                tree.insert(NUMBER_ENDIANNESS_KEY, &[numbers.as_byte()])
+                    .map_err(to_basteh)?;
+                version
             }
-            for key in deleted_keys {
-                tree.remove(&key).unwrap();
+        };
+
+        // Version 1 values are read the same by version 2, only the version is changed so the
+        // older releases don't misdecode the values of the newer kinds
+        if found == LEGACY_FORMAT_VERSION {
+            log::info!(
+                "Migrating database from format version {} to {}",
+                LEGACY_FORMAT_VERSION,
+                FORMAT_VERSION
+            );
+            tree.insert(FORMAT_VERSION_KEY, &[FORMAT_VERSION])
+                .map_err(to_basteh)?;
+            found = FORMAT_VERSION;
+        }
+
+        // The values of the databases created before the mode was recorded can't be told apart,
+        // they keep the requested mode from then on
+        let expiry = match tree.get(EXPIRY_FLAGS_KEY).map_err(to_basteh)? {
+            Some(v) => v.first() == Some(&1),
+            None => {
+                tree.insert(EXPIRY_FLAGS_KEY, &[self.encoding.expiry as u8])
+                    .map_err(to_basteh)?;
+                self.encoding.expiry
             }
+        };
+        if expiry != self.encoding.expiry {
+            log::warn!(
+                "Database was created with expiry flags set to {}, ignoring the requested mode",
+                expiry
+            );
+            self.encoding.expiry = expiry;
+        }
+
+        // Databases created before checksums were added don't have the key
+        let checksums = tree
+            .get(CHECKSUMS_KEY)
+            .map_err(to_basteh)?
+            .is_some_and(|v| v.first() == Some(&1));
+        if checksums != self.encoding.checksums {
+            log::warn!(
+                "Database was created with checksums set to {}, ignoring the requested mode",
+                checksums
+            );
+            self.encoding.checksums = checksums;
+        }
+
+        let timestamps = tree
+            .get(TIMESTAMPS_KEY)
+            .map_err(to_basteh)?
+            .is_some_and(|v| v.first() == Some(&1));
+        if timestamps != self.encoding.timestamps {
+            log::warn!(
+                "Database was created with write timestamps set to {}, ignoring the requested mode",
+                timestamps
+            );
+            self.encoding.timestamps = timestamps;
         }
+
+        // Databases created before the strategy could be set have a tree per scope
+        let scope_strategy = ScopeStrategy::from_byte(
+            tree.get(SCOPE_STRATEGY_KEY)
+                .map_err(to_basteh)?
+                .and_then(|v| v.first().copied())
+                .unwrap_or_default(),
+        );
+        if scope_strategy != self.scope_strategy {
+            log::warn!(
+                "Database was created with the {:?} scope strategy, ignoring the requested one",
+                scope_strategy
+            );
+            self.scope_strategy = scope_strategy;
+        }
+
+        // Databases created before the byte order could be set use little-endian numbers
+        let numbers = Endian::from_byte(
+            tree.get(NUMBER_ENDIANNESS_KEY)
+                .map_err(to_basteh)?
+                .and_then(|v| v.first().copied())
+                .unwrap_or_default(),
+        );
+        if numbers != self.encoding.numbers {
+            log::warn!(
+                "Database was created with {:?}-endian numbers, ignoring the requested byte order",
+                numbers
+            );
+            self.encoding.numbers = numbers;
+        }
+
+        if found != FORMAT_VERSION {
+            self.format_error = Some(FormatVersionError {
+                found,
+                supported: FORMAT_VERSION,
+            });
+        }
+        Ok(())
+    }
+
+    /// Write a value to the metadata tree, flush it to the disk, read it back and remove it, to
+    /// make sure the database is usable before any request is handled.
+    pub fn self_test(&self) -> Result<()> {
+        let tree = open_tree(&self.db, METADATA_TREE)?;
+        tree.insert(SELF_TEST_KEY, &[FORMAT_VERSION])
+            .map_err(to_basteh)?;
+        tree.flush().map_err(to_basteh)?;
+        let found = tree.get(SELF_TEST_KEY).map_err(to_basteh)?;
+        tree.remove(SELF_TEST_KEY).map_err(to_basteh)?;
+
+        match found {
+            Some(value) if value.as_ref() == [FORMAT_VERSION] => Ok(()),
+            _ => Err(BastehError::custom(SelfTestError)),
+        }
+    }
+
+    /// Scan all the trees for expired keys, removing them and queuing the expiry of the others.
+    ///
+    /// Trees are split between as many threads as available cores, and this method returns once
+    /// all of them are scanned.
+    pub fn scan_db(&mut self) {
+        let tree_names = self.db.tree_names();
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(tree_names.len());
+
+        if threads <= 1 {
+            for tree_name in tree_names.iter() {
+                scan_tree(&self.db, self.encoding, tree_name, &mut self.queue);
+            }
+            return;
+        }
+
+        let chunk_size = tree_names.len().div_ceil(threads);
+        std::thread::scope(|s| {
+            for chunk in tree_names.chunks(chunk_size) {
+                let db = &self.db;
+                let encoding = self.encoding;
+                let mut queue = self.queue.clone();
+                s.spawn(move || {
+                    for tree_name in chunk {
+                        scan_tree(db, encoding, tree_name, &mut queue);
+                    }
+                });
+            }
+        });
     }
 
-    pub fn spawn_expiry_thread(&mut self) {
+    pub fn spawn_expiry_thread(&mut self, interval: Duration) {
         let db = self.db.clone();
+        let encoding = self.encoding;
         let mut queue = self.queue.clone();
 
-        tokio::task::spawn_blocking(move || loop {
-            if let Some(item) = queue.try_pop_for(Duration::from_millis(500)) {
+        runtime::spawn_blocking(move || loop {
+            if let Some(item) = queue.try_pop_for(interval) {
                 let tree = if let Ok(tree) = open_tree(&db, &item.scope) {
                     tree
                 } else {
@@ -93,7 +445,7 @@ impl SledInner {
 
                 let res = tree.get(&item.key).and_then(|val| {
                     if let Some(bytes) = val {
-                        if let Some((_, exp)) = decode(&bytes) {
+                        if let Some((_, exp)) = encoding.decode(&bytes) {
                             if exp.nonce.get() == item.nonce && exp.persist.get() == 0 {
                                 tree.remove(&item.key)?;
                             }
@@ -116,19 +468,176 @@ impl SledInner {
 /// Store methods
 impl SledInner {
     pub fn keys(&self, scope: IVec) -> Result<impl Iterator<Item = Vec<u8>> + Send + Sync> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, _) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+        let prefix_len = prefix.len();
         Ok(Box::new(
-            tree.iter()
+            tree.scan_prefix(prefix)
                 .filter(|v| v.is_ok())
-                .map(|item| item.unwrap().0.as_ref().into()),
+                .map(move |item| item.unwrap().0[prefix_len..].into()),
         ))
     }
 
+    /// Get up to `limit` keys of the scope following `after` in order
+    pub fn keys_page(
+        &self,
+        scope: IVec,
+        after: Option<IVec>,
+        limit: usize,
+    ) -> Result<Vec<Vec<u8>>> {
+        let (tree, _) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+        let iter = match after {
+            Some(after) => tree.range((
+                Bound::Excluded([prefix.as_slice(), &after].concat()),
+                Bound::Unbounded,
+            )),
+            None => tree.scan_prefix(&prefix),
+        };
+        iter.take_while(|item| !matches!(item, Ok((key, _)) if !key.starts_with(&prefix)))
+            .take(limit)
+            .map(|item| {
+                item.map(|(key, _)| key[prefix.len()..].to_vec())
+                    .map_err(BastehError::custom)
+            })
+            .collect()
+    }
+
+    pub fn scopes(&self) -> Result<Vec<Vec<u8>>> {
+        let mut scopes = Vec::new();
+        for tree_name in self.db.tree_names() {
+            if tree_name.ends_with(VERSIONS_TREE_SUFFIX)
+                || tree_name.as_ref() == METADATA_TREE
+                || tree_name.as_ref() == DEFAULT_TREE
+                || tree_name.as_ref() == SCOPES_TREE
+            {
+                continue;
+            }
+            // Trees are kept after their last key is removed
+            if !open_tree(&self.db, &tree_name)?.is_empty() {
+                scopes.push(tree_name.to_vec());
+            }
+        }
+
+        // Jumping from the first key of each scope past all the keys sharing its prefix
+        let tree = open_tree(&self.db, SCOPES_TREE)?;
+        let mut from = Vec::new();
+        while let Some((key, _)) = tree
+            .range(from.as_slice()..)
+            .next()
+            .transpose()
+            .map_err(to_basteh)?
+        {
+            let (scope, _) = match split_key(&key) {
+                Some(split) => split,
+                None => break,
+            };
+            from = scope_prefix(&scope);
+            // The prefix ends with the \0 separator, so \x01 in its place is right after its keys
+            *from.last_mut().unwrap() = 1;
+            scopes.push(scope);
+        }
+        Ok(scopes)
+    }
+
+    pub fn size_bytes(&self, scope: IVec) -> Result<u64> {
+        let (tree, _) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+        tree.scan_prefix(&prefix).try_fold(0, |size, item| {
+            let (key, value) = item.map_err(to_basteh)?;
+            Ok(size + (key.len() - prefix.len()) as u64 + value.len() as u64)
+        })
+    }
+
+    pub fn drop_scope(&mut self, scope: IVec) -> Result<()> {
+        let (_, tree_name) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+
+        // Queued expirations would open the tree again after it's dropped
+        self.queue.remove_prefix(&tree_name, &prefix);
+
+        match self.scope_strategy {
+            ScopeStrategy::Tree => {
+                self.db.drop_tree(&scope).map_err(to_basteh)?;
+                self.db
+                    .drop_tree(versions_tree_name(&scope))
+                    .map_err(to_basteh)?;
+            }
+            ScopeStrategy::Prefix => {
+                for tree_name in [tree_name.to_vec(), versions_tree_name(&tree_name)] {
+                    let tree = open_tree(&self.db, &tree_name)?;
+                    let mut batch = sled::Batch::default();
+                    for item in tree.scan_prefix(&prefix).keys() {
+                        batch.remove(item.map_err(to_basteh)?);
+                    }
+                    tree.apply_batch(batch).map_err(to_basteh)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sweep_expired(&self, scope: IVec) -> Result<u64> {
+        let (tree, _) = self.scope_tree(&scope)?;
+        let mut removed = 0;
+        for item in tree.scan_prefix(self.key_prefix(&scope)) {
+            let (key, value) = item.map_err(to_basteh)?;
+            if let Some((_, exp)) = self.encoding.decode(&value) {
+                // Only remove the value if it wasn't replaced since we've read it
+                if exp.expired()
+                    && tree
+                        .compare_and_swap(&key, Some(value), None::<IVec>)
+                        .map_err(to_basteh)?
+                        .is_ok()
+                {
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn take_expired(&self, remove: bool) -> Result<Vec<ExpiredItem>> {
+        let mut expired = Vec::new();
+        for tree_name in self.db.tree_names() {
+            if tree_name.ends_with(VERSIONS_TREE_SUFFIX) || tree_name.as_ref() == METADATA_TREE {
+                continue;
+            }
+
+            let tree = open_tree(&self.db, &tree_name)?;
+            for item in tree.iter() {
+                let (key, bytes) = item.map_err(to_basteh)?;
+                let value = match self.encoding.decode(&bytes) {
+                    Some((value, exp)) if exp.expired() => value.into_owned(),
+                    _ => continue,
+                };
+                // Skip the items replaced since we've read them
+                if remove
+                    && tree
+                        .compare_and_swap(&key, Some(bytes), None::<IVec>)
+                        .map_err(to_basteh)?
+                        .is_err()
+                {
+                    continue;
+                }
+                if tree_name.as_ref() == SCOPES_TREE {
+                    if let Some((scope, key)) = split_key(&key) {
+                        expired.push((scope, key.to_vec(), value));
+                    }
+                } else {
+                    expired.push((tree_name.to_vec(), key.to_vec(), value));
+                }
+            }
+        }
+        Ok(expired)
+    }
+
     pub fn set(&self, scope: IVec, key: IVec, value: OwnedValue) -> Result<()> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, _, key) = self.locate(scope, key)?;
         tree.update_and_fetch(&key, |bytes| {
             let nonce = if let Some(bytes) = bytes {
-                decode(&bytes)
+                self.encoding
+                    .decode(&bytes)
                     .map(|(_, exp)| exp.next_nonce())
                     .unwrap_or_default()
             } else {
@@ -136,28 +645,152 @@ impl SledInner {
             };
 
             let exp = ExpiryFlags::new_persist(nonce);
-            let val = encode(value.as_value(), &exp);
+            let val = self.encoding.encode(value.as_value(), &exp);
 
             Some(val)
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
         Ok(())
     }
 
-    pub fn get(&self, scope: IVec, key: IVec) -> Result<Option<OwnedValue>> {
-        let tree = open_tree(&self.db, &scope)?;
-        tree.get(&key)
-            .map(|val| {
-                val.and_then(|bytes| {
-                    let (val, exp) = decode(&bytes)?;
-                    if !exp.expired() {
-                        Some(val.into_owned())
-                    } else {
-                        None
-                    }
-                })
+    pub fn upsert(&self, scope: IVec, key: IVec, value: OwnedValue) -> Result<bool> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut inserted = true;
+
+        tree.update_and_fetch(&key, |bytes| {
+            // Expired keys which are not deleted yet are considered missing
+            let nonce = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((_, exp)) => {
+                    inserted = exp.expired();
+                    exp.next_nonce()
+                }
+                None => {
+                    inserted = true;
+                    0
+                }
+            };
+
+            let exp = ExpiryFlags::new_persist(nonce);
+            Some(self.encoding.encode(value.as_value(), &exp))
+        })
+        .map_err(to_basteh)?;
+
+        Ok(inserted)
+    }
+
+    pub fn set_keepttl(&mut self, scope: IVec, key: IVec, value: OwnedValue) -> Result<()> {
+        let (tree, scope, key) = self.locate(scope, key)?;
+        let mut expiry = None;
+
+        tree.update_and_fetch(&key, |bytes| {
+            // Expired keys which are not deleted yet are considered missing
+            let exp = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((_, exp)) if !exp.expired() => {
+                    let mut exp = *exp;
+                    exp.increase_nonce();
+                    exp
+                }
+                _ => ExpiryFlags::new_persist(0),
+            };
+
+            // Sending values to outer scope
+            expiry = exp.expires_in().map(|dur| (exp.nonce.get(), dur));
+
+            Some(self.encoding.encode(value.as_value(), &exp))
+        })
+        .map_err(to_basteh)?;
+
+        // The nonce is changed, so the expiry should be queued again
+        if let Some((nonce, dur)) = expiry {
+            self.queue.push(DelayedIem::new(scope, key, nonce, dur));
+        }
+        Ok(())
+    }
+
+    pub fn set_versioned(
+        &self,
+        scope: IVec,
+        key: IVec,
+        value: OwnedValue,
+        version: u64,
+    ) -> Result<bool> {
+        let (tree, scope, key) = self.locate(scope, key)?;
+        let versions = open_tree(&self.db, &versions_tree_name(&scope))?;
+
+        (&tree, &versions)
+            .transaction(|(tree, versions)| {
+                let current = versions
+                    .get(&key)?
+                    .and_then(|v| v.as_ref().try_into().ok())
+                    .map(u64::from_be_bytes);
+                if matches!(current, Some(current) if current >= version) {
+                    return Ok(false);
+                }
+
+                let nonce = tree
+                    .get(&key)?
+                    .and_then(|bytes| {
+                        self.encoding
+                            .decode(&bytes)
+                            .map(|(_, exp)| exp.next_nonce())
+                    })
+                    .unwrap_or_default();
+                let exp = ExpiryFlags::new_persist(nonce);
+
+                tree.insert(&key, self.encoding.encode(value.as_value(), &exp))?;
+                versions.insert(&key, &version.to_be_bytes())?;
+                Ok(true)
+            })
+            .map_err(|err: TransactionError<BastehError>| match err {
+                TransactionError::Abort(err) => err,
+                TransactionError::Storage(err) => BastehError::custom(err),
             })
-            .map_err(BastehError::custom)
+    }
+
+    pub fn get(&self, scope: IVec, key: IVec) -> Result<Option<OwnedValue>> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let val = tree.get(&key).map_err(to_basteh)?;
+        if let Some(bytes) = &val {
+            self.encoding.check(bytes)?;
+        }
+        Ok(val.and_then(|bytes| {
+            let (val, exp) = self.encoding.decode(&bytes)?;
+            if !exp.expired() {
+                Some(val.into_owned())
+            } else {
+                None
+            }
+        }))
+    }
+
+    pub fn get_raw(&self, scope: IVec, key: IVec) -> Result<Option<Vec<u8>>> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        Ok(tree
+            .get(&key)
+            .map_err(to_basteh)?
+            .map(|bytes| bytes.to_vec()))
+    }
+
+    pub fn set_raw(&self, scope: IVec, key: IVec, value: Vec<u8>) -> Result<()> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        tree.insert(&key, value).map_err(to_basteh)?;
+        Ok(())
+    }
+
+    pub fn value_kind(&self, scope: IVec, key: IVec) -> Result<Option<ValueKind>> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let val = tree.get(&key).map_err(to_basteh)?;
+        if let Some(bytes) = &val {
+            self.encoding.check(bytes)?;
+        }
+        Ok(val.and_then(|bytes| {
+            let (kind, exp) = self.encoding.decode_kind(&bytes)?;
+            if !exp.expired() {
+                Some(kind)
+            } else {
+                None
+            }
+        }))
     }
 
     pub fn get_range(
@@ -167,72 +800,90 @@ impl SledInner {
         start: i64,
         end: i64,
     ) -> Result<Vec<OwnedValue>> {
-        let tree = open_tree(&self.db, &scope)?;
-        tree.get(&key)
-            .map(|val| {
-                val.and_then(|bytes| {
-                    let (val, exp) = decode(&bytes)?;
-                    if !exp.expired() {
-                        match val {
-                            Value::List(l) => {
-                                let start: usize = start.try_into().unwrap_or_else(|_| {
-                                    l.len().checked_sub(-start as usize).unwrap_or_default()
-                                });
-
-                                let take: usize = end
-                                    .try_into()
-                                    .unwrap_or_else(|_| {
-                                        l.len().checked_sub(-end as usize).unwrap_or_default()
-                                    })
-                                    .checked_sub(start)
-                                    .and_then(|end| end.checked_add(1))
-                                    .unwrap_or(0);
-
-                                Some(
-                                    l.into_iter()
-                                        .skip(start)
-                                        .take(take)
-                                        .map(|v| v.into_owned())
-                                        .collect(),
-                                )
-                            }
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    }
+        let (tree, _, key) = self.locate(scope, key)?;
+        let val = tree.get(&key).map_err(to_basteh)?;
+        self.list_range(val.as_deref(), start, end)
+    }
+
+    pub fn get_range_multi(
+        &self,
+        scope: IVec,
+        requests: Vec<(IVec, i64, i64)>,
+    ) -> Result<Vec<Vec<OwnedValue>>> {
+        let (tree, _) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+
+        // Read in a transaction, so the lists are not changed in between
+        tree.transaction(|tree| {
+            requests
+                .iter()
+                .map(|(key, start, end)| {
+                    let val = tree.get([prefix.as_slice(), key].concat())?;
+                    self.list_range(val.as_deref(), *start, *end)
+                        .map_err(ConflictableTransactionError::Abort)
                 })
-                .unwrap_or_default()
-            })
-            .map_err(BastehError::custom)
+                .collect::<ConflictableTransactionResult<_, BastehError>>()
+        })
+        .map_err(|err: TransactionError<BastehError>| match err {
+            TransactionError::Abort(err) => err,
+            TransactionError::Storage(err) => BastehError::custom(err),
+        })
     }
 
-    pub fn mutate(&self, scope: IVec, key: IVec, mutations: Mutation) -> Result<i64> {
+    // Items of the stored list between start and end, empty if there is no list or it's expired
+    fn list_range(&self, val: Option<&[u8]>, start: i64, end: i64) -> Result<Vec<OwnedValue>> {
+        if let Some(bytes) = val {
+            self.encoding.check(bytes)?;
+        }
+        match val.and_then(|bytes| self.encoding.decode(bytes)) {
+            Some((_, exp)) if exp.expired() => Ok(Vec::new()),
+            Some((Value::List(l), _)) => {
+                let (start, take) = resolve_range(l.len(), start, end);
+                Ok(l.into_iter()
+                    .skip(start)
+                    .take(take)
+                    .map(|v| v.into_owned())
+                    .collect())
+            }
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Mutate the number and return it before and after the mutations
+    pub fn mutate(&self, scope: IVec, key: IVec, mutations: Mutation) -> Result<(i64, i64)> {
         // value will be some if the stored value is either expired or valid number
         let mut value = None;
+        // kind will be some if the stored value is not a number
+        let mut kind = None;
 
-        match open_tree(&self.db, &scope)?.update_and_fetch(key, |existing| {
-            let (val, exp) = if let Some((val, exp)) = existing.and_then(decode) {
-                if !exp.expired() {
-                    (
-                        match val {
-                            Value::Number(n) => Some(n),
-                            _ => None,
-                        },
-                        *exp,
-                    )
+        let (tree, _, key) = self.locate(scope, key)?;
+        match tree.update_and_fetch(key, |existing| {
+            let (val, exp) =
+                if let Some((val, exp)) = existing.and_then(|bytes| self.encoding.decode(bytes)) {
+                    if !exp.expired() {
+                        (
+                            match val {
+                                Value::Number(n) => Some(n),
+                                val => {
+                                    kind = Some(val.kind());
+                                    None
+                                }
+                            },
+                            *exp,
+                        )
+                    } else {
+                        (Some(0), ExpiryFlags::new_persist(exp.next_nonce()))
+                    }
                 } else {
-                    (Some(0), ExpiryFlags::new_persist(exp.next_nonce()))
-                }
-            } else {
-                (Some(0), ExpiryFlags::new_persist(0))
-            };
+                    (Some(0), ExpiryFlags::new_persist(0))
+                };
 
-            if let Some(val) = val {
-                let val = run_mutations(val, &mutations);
-                value = Some(val);
+            if let Some(old) = val {
+                let val = run_mutations(old, &mutations);
+                value = Some((old, val));
 
-                let val = encode(Value::Number(val), &exp);
+                let val = self.encoding.encode(Value::Number(val), &exp);
 
                 Some(val)
             } else {
@@ -240,52 +891,380 @@ impl SledInner {
                 existing.map(|v| v.into())
             }
         }) {
-            Ok(_) => match value {
-                Some(value) => Ok(value),
-                None => Err(BastehError::InvalidNumber),
+            Ok(_) => match (value, kind) {
+                (Some(value), _) => Ok(value),
+                (None, Some(kind)) => Err(BastehError::NotANumber(kind)),
+                (None, None) => Err(BastehError::InvalidNumber),
             },
             Err(err) => Err(BastehError::custom(err)),
         }
     }
 
-    fn pop(&self, scope: IVec, key: IVec) -> Result<Option<OwnedValue>> {
-        let tree = open_tree(&self.db, &scope)?;
+    pub fn incr_float(&self, scope: IVec, key: IVec, delta: f64) -> Result<f64> {
+        let mut result = Err(BastehError::InvalidNumber);
+
+        let (tree, _, key) = self.locate(scope, key)?;
+        tree.update_and_fetch(key, |existing| {
+            let (val, exp) = match existing.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (Some(val), *exp),
+                Some((_, exp)) => (None, ExpiryFlags::new_persist(exp.next_nonce())),
+                None => (None, ExpiryFlags::new_persist(0)),
+            };
+
+            result = add_float(val, delta);
+            match result {
+                Ok(value) => Some(
+                    self.encoding
+                        .encode(Value::String(value.to_string().into()), &exp),
+                ),
+                // If the value is not numeric, leave it as is
+                Err(_) => existing.map(|v| v.into()),
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    pub fn incr_big(&self, scope: IVec, key: IVec, delta: i128) -> Result<i128> {
+        let mut result = Err(BastehError::InvalidNumber);
+
+        let (tree, _, key) = self.locate(scope, key)?;
+        tree.update_and_fetch(key, |existing| {
+            let (val, exp) = match existing.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (Some(val), *exp),
+                Some((_, exp)) => (None, ExpiryFlags::new_persist(exp.next_nonce())),
+                None => (None, ExpiryFlags::new_persist(0)),
+            };
+
+            result = add_big(val, delta);
+            match result {
+                Ok(value) => Some(
+                    self.encoding
+                        .encode(OwnedValue::from(value).as_value(), &exp),
+                ),
+                // If the value is not numeric, leave it as is
+                Err(_) => existing.map(|v| v.into()),
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    pub fn set_if(&self, scope: IVec, key: IVec, value: i64, ord: Ordering) -> Result<bool> {
+        let mut result = Ok(false);
+
+        let (tree, _, key) = self.locate(scope, key)?;
+        tree.update_and_fetch(key, |existing| {
+            let (val, exp) = match existing.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (Some(val), *exp),
+                Some((_, exp)) => (None, ExpiryFlags::new_persist(exp.next_nonce())),
+                None => (None, ExpiryFlags::new_persist(0)),
+            };
+
+            result = should_set(val, value, ord);
+            match result {
+                Ok(true) => Some(self.encoding.encode(Value::Number(value), &exp)),
+                // Leave the value as is if it's not replaced
+                _ => existing.map(|v| v.into()),
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    fn pop(&self, scope: IVec, key: IVec) -> Result<Option<OwnedValue>> {
+        let (tree, _, key) = self.locate(scope, key)?;
+
+        let mut succeed = false;
+        let mut poped_value = None;
+
+        tree.update_and_fetch(&key, |bytes| {
+            let (val, exp) = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some(decoded) => decoded,
+                None => {
+                    // Missing and undecodable keys are left untouched
+                    succeed = true;
+                    poped_value = None;
+                    return bytes.map(|v| v.to_vec());
+                }
+            };
+
+            match val {
+                Value::List(mut l) => {
+                    succeed = true;
+                    poped_value = l.pop().map(|v| v.into_owned());
+                    // Emptied lists are removed, the same as redis
+                    if l.is_empty() {
+                        None
+                    } else {
+                        Some(self.encoding.encode(Value::List(l), exp))
+                    }
+                }
+                _ => {
+                    succeed = false;
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        if succeed {
+            Ok(poped_value)
+        } else {
+            Err(BastehError::TypeConversion)
+        }
+    }
+
+    fn pop_push(
+        &self,
+        src_scope: IVec,
+        src_key: IVec,
+        dst_scope: IVec,
+        dst_key: IVec,
+    ) -> Result<Option<OwnedValue>> {
+        let (src, src_scope, src_key) = self.locate(src_scope, src_key)?;
+        let (dst, dst_scope, dst_key) = self.locate(dst_scope, dst_key)?;
+
+        let res = if src_scope == dst_scope {
+            src.transaction(|tree| {
+                pop_push_transaction(self.encoding, tree, &src_key, tree, &dst_key)
+            })
+        } else {
+            (&src, &dst).transaction(|(src, dst)| {
+                pop_push_transaction(self.encoding, src, &src_key, dst, &dst_key)
+            })
+        };
+
+        res.map_err(|err: TransactionError<BastehError>| match err {
+            TransactionError::Abort(err) => err,
+            TransactionError::Storage(err) => BastehError::custom(err),
+        })
+    }
+
+    fn list_drain(&self, scope: IVec, key: IVec) -> Result<Vec<OwnedValue>> {
+        let (tree, _, key) = self.locate(scope, key)?;
+
+        let mut drained = Ok(Vec::new());
+
+        tree.update_and_fetch(&key, |bytes| {
+            match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((_, exp)) if exp.expired() => {
+                    drained = Ok(Vec::new());
+                    None
+                }
+                Some((Value::List(l), _)) => {
+                    drained = Ok(l.into_iter().map(|v| v.into_owned()).collect());
+                    None
+                }
+                Some(_) => {
+                    drained = Err(BastehError::TypeConversion);
+                    bytes.map(|v| v.to_vec())
+                }
+                None => {
+                    drained = Ok(Vec::new());
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        drained
+    }
+
+    fn hset(&self, scope: IVec, key: IVec, field: IVec, value: OwnedValue) -> Result<()> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut succeed = false;
+
+        tree.update_and_fetch(&key, |bytes| {
+            let (val, exp) = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (val, *exp),
+                Some((_, exp)) => (
+                    Value::Map(Vec::new()),
+                    ExpiryFlags::new_persist(exp.next_nonce()),
+                ),
+                None => (Value::Map(Vec::new()), ExpiryFlags::new_persist(0)),
+            };
+
+            match val {
+                Value::Map(mut map) => {
+                    succeed = true;
+
+                    match map.iter_mut().find(|(f, _)| f == field.as_ref()) {
+                        Some((_, v)) => *v = value.as_value(),
+                        None => map.push((field.to_vec(), value.as_value())),
+                    }
+                    Some(self.encoding.encode(Value::Map(map), &exp))
+                }
+                _ => {
+                    succeed = false;
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        if succeed {
+            Ok(())
+        } else {
+            Err(BastehError::TypeConversion)
+        }
+    }
+
+    fn hincr(&self, scope: IVec, key: IVec, field: IVec, delta: i64) -> Result<i64> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut result = Ok(0);
+
+        tree.update_and_fetch(&key, |bytes| {
+            let (val, exp) = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (val, *exp),
+                Some((_, exp)) => (
+                    Value::Map(Vec::new()),
+                    ExpiryFlags::new_persist(exp.next_nonce()),
+                ),
+                None => (Value::Map(Vec::new()), ExpiryFlags::new_persist(0)),
+            };
+
+            match val {
+                Value::Map(mut map) => {
+                    match map.iter_mut().find(|(f, _)| f == field.as_ref()) {
+                        Some((_, v)) => {
+                            result = add_number(Some(v.clone()), delta);
+                            if let Ok(n) = result {
+                                *v = Value::Number(n);
+                            }
+                        }
+                        None => {
+                            result = Ok(delta);
+                            map.push((field.to_vec(), Value::Number(delta)));
+                        }
+                    }
+                    if result.is_ok() {
+                        Some(self.encoding.encode(Value::Map(map), &exp))
+                    } else {
+                        bytes.map(|v| v.to_vec())
+                    }
+                }
+                _ => {
+                    result = Err(BastehError::TypeConversion);
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    fn set_bit(&self, scope: IVec, key: IVec, offset: u64, bit: bool) -> Result<bool> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut result = Ok(false);
+
+        tree.update_and_fetch(&key, |bytes| {
+            let (val, exp) = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (val, *exp),
+                Some((_, exp)) => (
+                    Value::Bytes(Default::default()),
+                    ExpiryFlags::new_persist(exp.next_nonce()),
+                ),
+                None => (
+                    Value::Bytes(Default::default()),
+                    ExpiryFlags::new_persist(0),
+                ),
+            };
+
+            match val {
+                Value::Bytes(b) => {
+                    let mut b = bytes::BytesMut::from(b.as_ref());
+                    result = Ok(write_bit(&mut b, offset, bit));
+                    Some(self.encoding.encode(Value::Bytes(b.freeze()), &exp))
+                }
+                _ => {
+                    result = Err(BastehError::TypeConversion);
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    fn hdel(&self, scope: IVec, key: IVec, field: IVec) -> Result<bool> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut result = Ok(false);
+
+        tree.update_and_fetch(&key, |bytes| {
+            match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((_, exp)) if exp.expired() => {
+                    result = Ok(false);
+                    bytes.map(|v| v.to_vec())
+                }
+                Some((Value::Map(mut map), exp)) => {
+                    let len = map.len();
+                    map.retain(|(f, _)| f != field.as_ref());
+                    result = Ok(map.len() != len);
+                    // Emptied maps are removed, the same as redis
+                    if map.is_empty() {
+                        None
+                    } else {
+                        Some(self.encoding.encode(Value::Map(map), exp))
+                    }
+                }
+                Some(_) => {
+                    result = Err(BastehError::TypeConversion);
+                    bytes.map(|v| v.to_vec())
+                }
+                None => {
+                    result = Ok(false);
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
 
+    fn push(&self, scope: IVec, key: IVec, value: OwnedValue) -> Result<()> {
+        let (tree, _, key) = self.locate(scope, key)?;
         let mut succeed = false;
-        let mut poped_value = None;
 
         tree.update_and_fetch(&key, |bytes| {
             let (val, exp) = bytes
-                .and_then(decode)
+                .and_then(|bytes| self.encoding.decode(bytes))
                 .map(|(v, exp)| (v, *exp))
                 .unwrap_or_else(|| (Value::List(Vec::new()), ExpiryFlags::new_persist(0)));
 
             match val {
                 Value::List(mut l) => {
                     succeed = true;
-                    poped_value = l.pop().map(|v| v.into_owned());
-                    let val = encode(Value::List(l), &exp);
+
+                    l.push(value.as_value());
+                    let val = self.encoding.encode(Value::List(l), &exp);
                     Some(val)
                 }
                 _ => bytes.map(|v| v.to_vec()),
             }
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
 
         if succeed {
-            Ok(poped_value)
+            Ok(())
         } else {
             Err(BastehError::TypeConversion)
         }
     }
 
-    fn push(&self, scope: IVec, key: IVec, value: OwnedValue) -> Result<()> {
-        let tree = open_tree(&self.db, &scope)?;
+    fn push_multiple(&self, scope: IVec, key: IVec, value: Vec<OwnedValue>) -> Result<()> {
+        let (tree, _, key) = self.locate(scope, key)?;
         let mut succeed = false;
 
         tree.update_and_fetch(&key, |bytes| {
             let (val, exp) = bytes
-                .and_then(decode)
+                .and_then(|bytes| self.encoding.decode(bytes))
                 .map(|(v, exp)| (v, *exp))
                 .unwrap_or_else(|| (Value::List(Vec::new()), ExpiryFlags::new_persist(0)));
 
@@ -293,14 +1272,16 @@ impl SledInner {
                 Value::List(mut l) => {
                     succeed = true;
 
-                    l.push(value.as_value());
-                    let val = encode(Value::List(l), &exp);
+                    for v in value.iter() {
+                        l.push(v.as_value());
+                    }
+                    let val = self.encoding.encode(Value::List(l), &exp);
                     Some(val)
                 }
                 _ => bytes.map(|v| v.to_vec()),
             }
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
 
         if succeed {
             Ok(())
@@ -309,13 +1290,85 @@ impl SledInner {
         }
     }
 
-    fn push_multiple(&self, scope: IVec, key: IVec, value: Vec<OwnedValue>) -> Result<()> {
-        let tree = open_tree(&self.db, &scope)?;
+    fn push_returning_len(&self, scope: IVec, key: IVec, value: OwnedValue) -> Result<u64> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut result = Ok(0);
+
+        tree.update_and_fetch(&key, |bytes| {
+            // Expired lists are replaced, so their items are not counted
+            let (val, exp) = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (val, *exp),
+                Some((_, exp)) => (
+                    Value::List(Vec::new()),
+                    ExpiryFlags::new_persist(exp.next_nonce()),
+                ),
+                None => (Value::List(Vec::new()), ExpiryFlags::new_persist(0)),
+            };
+
+            match val {
+                Value::List(mut l) => {
+                    l.push(value.as_value());
+                    result = Ok(l.len() as u64);
+                    Some(self.encoding.encode(Value::List(l), &exp))
+                }
+                _ => {
+                    result = Err(BastehError::TypeConversion);
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    fn mutate_list(&self, scope: IVec, key: IVec, mutations: ListMutation) -> Result<u64> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let mut result = Ok(0);
+
+        tree.update_and_fetch(&key, |bytes| {
+            // Expired lists are replaced, the same as push
+            let (val, exp) = match bytes.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (val, *exp),
+                Some((_, exp)) => (
+                    Value::List(Vec::new()),
+                    ExpiryFlags::new_persist(exp.next_nonce()),
+                ),
+                None => (Value::List(Vec::new()), ExpiryFlags::new_persist(0)),
+            };
+
+            match val {
+                Value::List(l) => {
+                    let mut l = l.into_iter().map(Value::into_owned).collect();
+                    run_list_mutations(&mut l, &mutations);
+                    result = Ok(l.len() as u64);
+                    // Emptied lists are removed, the same as redis
+                    if l.is_empty() {
+                        None
+                    } else {
+                        let l = l.iter().map(OwnedValue::as_value).collect();
+                        Some(self.encoding.encode(Value::List(l), &exp))
+                    }
+                }
+                _ => {
+                    result = Err(BastehError::TypeConversion);
+                    bytes.map(|v| v.to_vec())
+                }
+            }
+        })
+        .map_err(to_basteh)?;
+
+        result
+    }
+
+    fn push_capped(&self, scope: IVec, key: IVec, value: OwnedValue, max_len: u64) -> Result<()> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        let max_len = usize::try_from(max_len).unwrap_or(usize::MAX);
         let mut succeed = false;
 
         tree.update_and_fetch(&key, |bytes| {
             let (val, exp) = bytes
-                .and_then(decode)
+                .and_then(|bytes| self.encoding.decode(bytes))
                 .map(|(v, exp)| (v, *exp))
                 .unwrap_or_else(|| (Value::List(Vec::new()), ExpiryFlags::new_persist(0)));
 
@@ -323,16 +1376,19 @@ impl SledInner {
                 Value::List(mut l) => {
                     succeed = true;
 
-                    for v in value.iter() {
-                        l.push(v.as_value());
+                    l.push(value.as_value());
+                    l.drain(..l.len().saturating_sub(max_len));
+                    // Emptied lists are removed, the same as redis
+                    if l.is_empty() {
+                        None
+                    } else {
+                        Some(self.encoding.encode(Value::List(l), &exp))
                     }
-                    let val = encode(Value::List(l), &exp);
-                    Some(val)
                 }
                 _ => bytes.map(|v| v.to_vec()),
             }
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
 
         if succeed {
             Ok(())
@@ -342,11 +1398,11 @@ impl SledInner {
     }
 
     pub fn remove(&self, scope: IVec, key: IVec) -> Result<Option<OwnedValue>> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, _, key) = self.locate(scope, key)?;
         tree.remove(&key)
             .map(|val| {
                 val.and_then(|bytes| {
-                    let (val, exp) = decode(&bytes)?;
+                    let (val, exp) = self.encoding.decode(&bytes)?;
                     if !exp.expired() {
                         Some(val.into_owned())
                     } else {
@@ -357,33 +1413,76 @@ impl SledInner {
             .map_err(BastehError::custom)
     }
 
+    /// Remove the key and return whether it held a value which wasn't expired, only the kind and
+    /// the flags of the removed value are decoded
+    pub fn delete(&self, scope: IVec, key: IVec) -> Result<bool> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        Ok(tree.remove(&key).map_err(to_basteh)?.is_some_and(
+            |bytes| matches!(self.encoding.decode_kind(&bytes), Some((_, exp)) if !exp.expired()),
+        ))
+    }
+
+    pub fn remove_if(&self, scope: IVec, key: IVec, expected: OwnedValue) -> Result<bool> {
+        let (tree, _, key) = self.locate(scope, key)?;
+        loop {
+            let bytes = match tree.get(&key).map_err(to_basteh)? {
+                Some(bytes) => bytes,
+                None => return Ok(false),
+            };
+            match self.encoding.decode(&bytes) {
+                Some((val, exp)) if !exp.expired() && val == expected.as_value() => {}
+                _ => return Ok(false),
+            }
+            // Retry if the value was replaced since we've read it
+            if tree
+                .compare_and_swap(&key, Some(bytes), None::<IVec>)
+                .map_err(to_basteh)?
+                .is_ok()
+            {
+                return Ok(true);
+            }
+        }
+    }
+
     pub fn contains(&self, scope: IVec, key: IVec) -> Result<bool> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, _, key) = self.locate(scope, key)?;
         tree.contains_key(&key).map_err(BastehError::custom)
     }
+
+    pub fn contains_multi(&self, scope: IVec, keys: Vec<IVec>) -> Result<Vec<bool>> {
+        let (tree, _) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+        keys.iter()
+            .map(|key| {
+                tree.contains_key([prefix.as_slice(), key].concat())
+                    .map_err(BastehError::custom)
+            })
+            .collect()
+    }
 }
 
 /// Expiry methods
 impl SledInner {
     pub fn set_expiry(&mut self, scope: IVec, key: IVec, duration: Duration) -> Result<()> {
         let mut nonce = 0;
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, scope, key) = self.locate(scope, key)?;
         let val = tree
             .update_and_fetch(&key, |existing| {
                 let mut bytes = sled::IVec::from(existing?);
 
                 // If we can't decode the bytes, leave them as they are
-                if let Some((_, exp)) = decode_mut(&mut bytes) {
+                if let Some((_, exp)) = self.encoding.decode_mut(&mut bytes) {
                     exp.increase_nonce();
                     exp.expire_in(duration);
                     exp.persist.set(0);
 
                     // Sending values to outer scope
                     nonce = exp.nonce.get();
+                    self.encoding.update_checksum(&mut bytes);
                 }
                 Some(bytes)
             })
-            .map_err(BastehError::custom)?;
+            .map_err(to_basteh)?;
 
         // We can't add item to queue in update_and_fetch as it may run multiple times
         // before taking into effect.
@@ -394,12 +1493,35 @@ impl SledInner {
         Ok(())
     }
 
+    pub fn set_expiry_multi(&mut self, scope: IVec, entries: Vec<(IVec, Duration)>) -> Result<()> {
+        for (key, duration) in entries {
+            self.set_expiry(scope.clone(), key, duration)?;
+        }
+        Ok(())
+    }
+
+    pub fn modified_at(&self, scope: IVec, key: IVec) -> Result<Option<SystemTime>> {
+        if !self.encoding.timestamps {
+            return Err(BastehError::MethodNotSupported);
+        }
+        let (tree, _, key) = self.locate(scope, key)?;
+        let bytes = match tree.get(&key).map_err(to_basteh)? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        self.encoding.check(&bytes)?;
+        match self.encoding.decode_kind(&bytes) {
+            Some((_, exp)) if !exp.expired() => Ok(self.encoding.written_at(&bytes)),
+            _ => Ok(None),
+        }
+    }
+
     pub fn get_expiry(&self, scope: IVec, key: IVec) -> Result<Option<Duration>> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, _, key) = self.locate(scope, key)?;
         tree.get(&key)
             .map(|val| {
                 val.and_then(|bytes| {
-                    let (_, exp) = decode(&bytes)?;
+                    let (_, exp) = self.encoding.decode(&bytes)?;
                     exp.expires_in()
                 })
             })
@@ -407,27 +1529,61 @@ impl SledInner {
     }
 
     pub fn persist(&self, scope: IVec, key: IVec) -> Result<()> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, _, key) = self.locate(scope, key)?;
         tree.update_and_fetch(&key, |existing| {
             let mut bytes = sled::IVec::from(existing?);
-            if let Some((_, exp)) = decode_mut(&mut bytes) {
+            if let Some((_, exp)) = self.encoding.decode_mut(&mut bytes) {
                 exp.persist.set(1);
+                self.encoding.update_checksum(&mut bytes);
             }
             Some(bytes)
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
         Ok(())
     }
 
+    /// Apply `f` to the flags of the key in place, leaving the value as it is. Returns false if
+    /// the key doesn't exist.
+    pub fn update_flags(&mut self, scope: IVec, key: IVec, f: FlagsUpdate) -> Result<bool> {
+        let mut found = false;
+        let mut expiry = None;
+        let (tree, scope, key) = self.locate(scope, key)?;
+        tree.update_and_fetch(&key, |existing| {
+            // It may run more than once, only the last run is applied
+            found = false;
+            expiry = None;
+            let mut bytes = sled::IVec::from(existing?);
+
+            // If we can't decode the bytes, leave them as they are
+            if let Some((_, exp)) = self.encoding.decode_mut(&mut bytes) {
+                f(exp);
+
+                // Sending values to outer scope
+                found = true;
+                expiry = exp.expires_in().map(|dur| (exp.nonce.get(), dur));
+                self.encoding.update_checksum(&mut bytes);
+            }
+            Some(bytes)
+        })
+        .map_err(to_basteh)?;
+
+        // The expiry may have been changed, so it's queued again with the new flags
+        if let Some((nonce, duration)) = expiry {
+            self.queue
+                .push(DelayedIem::new(scope, key, nonce, duration));
+        }
+        Ok(found)
+    }
+
     pub fn extend_expiry(&mut self, scope: IVec, key: IVec, duration: Duration) -> Result<()> {
         let mut nonce = 0;
         let mut total_duration = None;
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, scope, key) = self.locate(scope, key)?;
         tree.update_and_fetch(&key, |existing| {
             let mut bytes = sled::IVec::from(existing?);
 
             // If we can't decode the bytes, leave them as they are
-            if let Some((_, exp)) = decode_mut(&mut bytes) {
+            if let Some((_, exp)) = self.encoding.decode_mut(&mut bytes) {
                 exp.increase_nonce();
                 if let Some(expiry) = exp.expires_in() {
                     exp.expire_in(expiry + duration);
@@ -439,10 +1595,11 @@ impl SledInner {
                 // Sending values to outer scope to prevent decoding again
                 nonce = exp.nonce.get();
                 total_duration = exp.expires_in();
+                self.encoding.update_checksum(&mut bytes);
             }
             Some(bytes)
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
         if let Some(total_duration) = total_duration {
             self.queue
                 .push(DelayedIem::new(scope, key, nonce, total_duration));
@@ -460,12 +1617,13 @@ impl SledInner {
         value: OwnedValue,
         duration: Duration,
     ) -> Result<()> {
-        let tree = open_tree(&self.db, &scope)?;
+        let (tree, scope, key) = self.locate(scope, key)?;
         let mut nonce = 0;
 
         tree.update_and_fetch(key.as_ref(), |bytes| {
             nonce = if let Some(bytes) = bytes {
-                decode(&bytes)
+                self.encoding
+                    .decode(&bytes)
                     .map(|(_, exp)| exp.next_nonce())
                     .unwrap_or_default()
             } else {
@@ -473,11 +1631,11 @@ impl SledInner {
             };
 
             let exp = ExpiryFlags::new_expiring(nonce, duration);
-            let val = encode(value.as_value(), &exp);
+            let val = self.encoding.encode(value.as_value(), &exp);
 
             Some(val)
         })
-        .map_err(BastehError::custom)?;
+        .map_err(to_basteh)?;
 
         self.queue
             .push(DelayedIem::new(scope, key, nonce, duration));
@@ -485,15 +1643,108 @@ impl SledInner {
         Ok(())
     }
 
+    /// Set the value expiring after the duration and return the value it replaced
+    pub fn swap_expiring(
+        &mut self,
+        scope: IVec,
+        key: IVec,
+        value: OwnedValue,
+        duration: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        let (tree, scope, key) = self.locate(scope, key)?;
+        let mut nonce = 0;
+
+        let old = tree
+            .fetch_and_update(key.as_ref(), |bytes| {
+                nonce = bytes
+                    .and_then(|bytes| self.encoding.decode(bytes))
+                    .map(|(_, exp)| exp.next_nonce())
+                    .unwrap_or_default();
+
+                let exp = ExpiryFlags::new_expiring(nonce, duration);
+                Some(self.encoding.encode(value.as_value(), &exp))
+            })
+            .map_err(to_basteh)?;
+
+        self.queue
+            .push(DelayedIem::new(scope, key, nonce, duration));
+
+        // Expired keys which are not deleted yet are considered missing
+        Ok(old.and_then(|bytes| match self.encoding.decode(&bytes) {
+            Some((val, exp)) if !exp.expired() => Some(val.into_owned()),
+            _ => None,
+        }))
+    }
+
+    /// Add delta to the number, missing keys are set to delta and expire after the duration.
+    /// Existing keys keep their expiry, persistent ones get the duration as well.
+    pub fn incr_with_ttl_floor(
+        &mut self,
+        scope: IVec,
+        key: IVec,
+        delta: i64,
+        duration: Duration,
+    ) -> Result<i64> {
+        let (tree, scope, key) = self.locate(scope, key)?;
+        let mut result = Err(BastehError::InvalidNumber);
+        // Will be some if a new expiry is set, and should be queued
+        let mut nonce = None;
+
+        tree.update_and_fetch(key.as_ref(), |existing| {
+            nonce = None;
+            let (val, exp) = match existing.and_then(|bytes| self.encoding.decode(bytes)) {
+                Some((val, exp)) if !exp.expired() => (Some(val), *exp),
+                Some((_, exp)) => (None, ExpiryFlags::new_persist(exp.next_nonce())),
+                None => (None, ExpiryFlags::new_persist(0)),
+            };
+
+            let current = match val {
+                Some(Value::Number(n)) => n,
+                Some(val) => {
+                    result = Err(BastehError::NotANumber(val.kind()));
+                    return existing.map(|v| v.into());
+                }
+                None => 0,
+            };
+            let value = match current.checked_add(delta) {
+                Some(value) => value,
+                None => {
+                    result = Err(BastehError::InvalidNumber);
+                    return existing.map(|v| v.into());
+                }
+            };
+            result = Ok(value);
+
+            let exp = if exp.expires_in().is_some() {
+                exp
+            } else {
+                let exp = ExpiryFlags::new_expiring(exp.next_nonce(), duration);
+                nonce = Some(exp.nonce.get());
+                exp
+            };
+            Some(self.encoding.encode(Value::Number(value), &exp))
+        })
+        .map_err(to_basteh)?;
+
+        if let Some(nonce) = nonce {
+            self.queue
+                .push(DelayedIem::new(scope, key, nonce, duration));
+        }
+        result
+    }
+
     pub fn get_expiring(
         &self,
         scope: IVec,
         key: IVec,
     ) -> Result<Option<(OwnedValue, Option<Duration>)>> {
-        let tree = open_tree(&self.db, &scope)?;
-        let val = tree.get(&key).map_err(BastehError::custom)?;
+        let (tree, _, key) = self.locate(scope, key)?;
+        let val = tree.get(&key).map_err(to_basteh)?;
+        if let Some(bytes) = &val {
+            self.encoding.check(bytes)?;
+        }
         Ok(val.and_then(|bytes| {
-            let (val, exp) = decode(&bytes)?;
+            let (val, exp) = self.encoding.decode(&bytes)?;
             if !exp.expired() {
                 Some((val.into_owned(), exp.expires_in()))
             } else {
@@ -501,20 +1752,159 @@ impl SledInner {
             }
         }))
     }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get_expiring_multi(
+        &self,
+        scope: IVec,
+        keys: Vec<IVec>,
+    ) -> Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        let (tree, _) = self.scope_tree(&scope)?;
+        let prefix = self.key_prefix(&scope);
+
+        // Read in a transaction, so the values are not changed in between
+        tree.transaction(|tree| {
+            keys.iter()
+                .map(|key| {
+                    let val = tree.get([prefix.as_slice(), key].concat())?;
+                    if let Some(bytes) = &val {
+                        self.encoding
+                            .check(bytes)
+                            .map_err(ConflictableTransactionError::Abort)?;
+                    }
+                    Ok(val.and_then(|bytes| {
+                        let (val, exp) = self.encoding.decode(&bytes)?;
+                        if !exp.expired() {
+                            Some((val.into_owned(), exp.expires_in()))
+                        } else {
+                            None
+                        }
+                    }))
+                })
+                .collect::<ConflictableTransactionResult<_, BastehError>>()
+        })
+        .map_err(|err: TransactionError<BastehError>| match err {
+            TransactionError::Abort(err) => err,
+            TransactionError::Storage(err) => BastehError::custom(err),
+        })
+    }
+
+    /// Sled can't iterate in a transaction, so the keys are listed first and their values are
+    /// read in a single transaction after
+    pub fn snapshot(&self, scope: IVec) -> Result<Vec<SnapshotEntry>> {
+        let keys = self
+            .keys(scope.clone())?
+            .map(IVec::from)
+            .collect::<Vec<_>>();
+        let values = self.get_expiring_multi(scope, keys.clone())?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|(value, expiry)| (key.to_vec(), value, expiry)))
+            .collect())
+    }
+
+    pub fn get_and_extend(
+        &mut self,
+        scope: IVec,
+        key: IVec,
+        duration: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        let mut nonce = None;
+        let (tree, scope, key) = self.locate(scope, key)?;
+        let val = tree
+            .update_and_fetch(&key, |existing| {
+                let mut bytes = sled::IVec::from(existing?);
+                nonce = None;
+
+                // Expired items are left as they are, the same as undecodable ones
+                if let Some((_, exp)) = self.encoding.decode_mut(&mut bytes) {
+                    if !exp.expired() {
+                        exp.increase_nonce();
+                        exp.expire_in(duration);
+                        exp.persist.set(0);
+
+                        // Sending values to outer scope
+                        nonce = Some(exp.nonce.get());
+                        self.encoding.update_checksum(&mut bytes);
+                    }
+                }
+                Some(bytes)
+            })
+            .map_err(to_basteh)?;
+
+        // We can't add item to queue in update_and_fetch as it may run multiple times
+        // before taking into effect.
+        match (nonce, val) {
+            (Some(nonce), Some(bytes)) => {
+                let value = self
+                    .encoding
+                    .decode(&bytes)
+                    .map(|(val, _)| val.into_owned());
+                self.queue
+                    .push(DelayedIem::new(scope, key, nonce, duration));
+                Ok(value)
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 impl SledInner {
     pub fn listen(&mut self, rx: crossbeam_channel::Receiver<Message>) {
         while let Ok(Message { req, tx }) = rx.recv() {
+            if let Some(err) = self.format_error {
+                tx.send(Err(BastehError::custom(err))).ok();
+                continue;
+            }
+
             match req {
                 // Store methods
                 Request::Keys(scope) => {
                     tx.send(self.keys(scope).map(|v| Response::Iterator(Box::new(v))))
                         .ok();
                 }
+                Request::KeysPage(scope, after, limit) => {
+                    tx.send(
+                        self.keys_page(scope, after, limit)
+                            .map(|v| Response::Iterator(Box::new(v.into_iter()))),
+                    )
+                    .ok();
+                }
+                Request::Scopes => {
+                    tx.send(
+                        self.scopes()
+                            .map(|v| Response::Iterator(Box::new(v.into_iter()))),
+                    )
+                    .ok();
+                }
+                Request::SizeBytes(scope) => {
+                    tx.send(self.size_bytes(scope).map(Response::Size)).ok();
+                }
+                Request::DropScope(scope) => {
+                    tx.send(self.drop_scope(scope).map(Response::Empty)).ok();
+                }
+                Request::SweepExpired(scope) => {
+                    tx.send(self.sweep_expired(scope).map(Response::Count)).ok();
+                }
+                Request::TakeExpired(remove) => {
+                    tx.send(self.take_expired(remove).map(Response::Expired))
+                        .ok();
+                }
                 Request::Get(scope, key) => {
                     tx.send(self.get(scope, key).map(Response::Value)).ok();
                 }
+                Request::GetRaw(scope, key) => {
+                    tx.send(self.get_raw(scope, key).map(Response::Bytes)).ok();
+                }
+                Request::SetRaw(scope, key, value) => {
+                    tx.send(self.set_raw(scope, key, value).map(Response::Empty))
+                        .ok();
+                }
+                Request::ValueKind(scope, key) => {
+                    tx.send(self.value_kind(scope, key).map(Response::Kind))
+                        .ok();
+                }
                 Request::GetRange(scope, key, start, end) => {
                     tx.send(
                         self.get_range(scope, key, start, end)
@@ -522,52 +1912,141 @@ impl SledInner {
                     )
                     .ok();
                 }
+                Request::GetRangeMulti(scope, requests) => {
+                    tx.send(
+                        self.get_range_multi(scope, requests)
+                            .map(Response::ValueVecs),
+                    )
+                    .ok();
+                }
                 Request::Set(scope, key, value) => {
                     tx.send(self.set(scope, key, value).map(Response::Empty))
                         .ok();
                 }
+                Request::Upsert(scope, key, value) => {
+                    tx.send(self.upsert(scope, key, value).map(Response::Bool))
+                        .ok();
+                }
+                Request::SetKeepTtl(scope, key, value) => {
+                    tx.send(self.set_keepttl(scope, key, value).map(Response::Empty))
+                        .ok();
+                }
+                Request::SetVersioned(scope, key, value, version) => {
+                    tx.send(
+                        self.set_versioned(scope, key, value, version)
+                            .map(Response::Bool),
+                    )
+                    .ok();
+                }
                 Request::Pop(scope, key) => {
+                    tx.send(self.pop(scope, key).map(Response::Value)).ok();
+                }
+                Request::PopPush(src_scope, src_key, dst_scope, dst_key) => {
                     tx.send(
-                        self.pop(scope, key)
-                            .map_err(BastehError::custom)
+                        self.pop_push(src_scope, src_key, dst_scope, dst_key)
                             .map(Response::Value),
                     )
                     .ok();
                 }
+                Request::ListDrain(scope, key) => {
+                    tx.send(self.list_drain(scope, key).map(Response::ValueVec))
+                        .ok();
+                }
+                Request::HIncr(scope, key, field, delta) => {
+                    tx.send(self.hincr(scope, key, field, delta).map(Response::Number))
+                        .ok();
+                }
+                Request::HSet(scope, key, field, value) => {
+                    tx.send(self.hset(scope, key, field, value).map(Response::Empty))
+                        .ok();
+                }
+                Request::HDel(scope, key, field) => {
+                    tx.send(self.hdel(scope, key, field).map(Response::Bool))
+                        .ok();
+                }
+                Request::SetBit(scope, key, offset, bit) => {
+                    tx.send(self.set_bit(scope, key, offset, bit).map(Response::Bool))
+                        .ok();
+                }
                 Request::Push(scope, key, value) => {
+                    tx.send(self.push(scope, key, value).map(Response::Empty))
+                        .ok();
+                }
+                Request::PushMulti(scope, key, value) => {
+                    tx.send(self.push_multiple(scope, key, value).map(Response::Empty))
+                        .ok();
+                }
+                Request::PushCapped(scope, key, value, max_len) => {
                     tx.send(
-                        self.push(scope, key, value)
-                            .map_err(BastehError::custom)
+                        self.push_capped(scope, key, value, max_len)
                             .map(Response::Empty),
                     )
                     .ok();
                 }
-                Request::PushMulti(scope, key, value) => {
+                Request::PushReturningLen(scope, key, value) => {
                     tx.send(
-                        self.push_multiple(scope, key, value)
-                            .map_err(BastehError::custom)
-                            .map(Response::Empty),
+                        self.push_returning_len(scope, key, value)
+                            .map(Response::Count),
                     )
                     .ok();
                 }
+                Request::MutateList(scope, key, mutations) => {
+                    tx.send(self.mutate_list(scope, key, mutations).map(Response::Count))
+                        .ok();
+                }
                 Request::MutateNumber(scope, key, mutations) => {
-                    tx.send(self.mutate(scope, key, mutations).map(Response::Number))
+                    tx.send(self.mutate(scope, key, mutations).map(Response::Numbers))
+                        .ok();
+                }
+                Request::IncrFloat(scope, key, delta) => {
+                    tx.send(self.incr_float(scope, key, delta).map(Response::Float))
+                        .ok();
+                }
+                Request::IncrBig(scope, key, delta) => {
+                    tx.send(self.incr_big(scope, key, delta).map(Response::BigNumber))
+                        .ok();
+                }
+                Request::SetIf(scope, key, value, ord) => {
+                    tx.send(self.set_if(scope, key, value, ord).map(Response::Bool))
                         .ok();
                 }
                 Request::Remove(scope, key) => {
                     tx.send(self.remove(scope, key).map(Response::Value)).ok();
                 }
+                Request::Delete(scope, key) => {
+                    tx.send(self.delete(scope, key).map(Response::Bool)).ok();
+                }
+                Request::RemoveIf(scope, key, expected) => {
+                    tx.send(self.remove_if(scope, key, expected).map(Response::Bool))
+                        .ok();
+                }
                 Request::Contains(scope, key) => {
                     tx.send(self.contains(scope, key).map(Response::Bool)).ok();
                 }
+                Request::ContainsMulti(scope, keys) => {
+                    tx.send(self.contains_multi(scope, keys).map(Response::BoolVec))
+                        .ok();
+                }
                 // Expiry methods
                 Request::Persist(scope, key) => {
                     tx.send(self.persist(scope, key).map(Response::Empty)).ok();
                 }
+                Request::UpdateFlags(scope, key, f) => {
+                    tx.send(self.update_flags(scope, key, f).map(Response::Bool))
+                        .ok();
+                }
                 Request::Expire(scope, key, dur) => {
                     tx.send(self.set_expiry(scope, key, dur).map(Response::Empty))
                         .ok();
                 }
+                Request::ExpireMulti(scope, entries) => {
+                    tx.send(self.set_expiry_multi(scope, entries).map(Response::Empty))
+                        .ok();
+                }
+                Request::ModifiedAt(scope, key) => {
+                    tx.send(self.modified_at(scope, key).map(Response::Time))
+                        .ok();
+                }
                 Request::Expiry(scope, key) => {
                     tx.send(self.get_expiry(scope, key).map(Response::Duration))
                         .ok();
@@ -584,10 +2063,38 @@ impl SledInner {
                     )
                     .ok();
                 }
+                Request::IncrWithTtlFloor(scope, key, delta, dur) => {
+                    tx.send(
+                        self.incr_with_ttl_floor(scope, key, delta, dur)
+                            .map(Response::Number),
+                    )
+                    .ok();
+                }
                 Request::GetExpiring(scope, key) => {
                     tx.send(self.get_expiring(scope, key).map(Response::ValueDuration))
                         .ok();
                 }
+                Request::GetExpiringMulti(scope, keys) => {
+                    tx.send(
+                        self.get_expiring_multi(scope, keys)
+                            .map(Response::ValueDurationVec),
+                    )
+                    .ok();
+                }
+                Request::SwapExpiring(scope, key, value, dur) => {
+                    tx.send(
+                        self.swap_expiring(scope, key, value, dur)
+                            .map(Response::Value),
+                    )
+                    .ok();
+                }
+                Request::GetAndExtend(scope, key, dur) => {
+                    tx.send(self.get_and_extend(scope, key, dur).map(Response::Value))
+                        .ok();
+                }
+                Request::Snapshot(scope) => {
+                    tx.send(self.snapshot(scope).map(Response::Snapshot)).ok();
+                }
             }
         }
     }
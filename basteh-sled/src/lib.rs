@@ -2,13 +2,19 @@
 
 mod delayqueue;
 mod flags;
+mod format;
 mod inner;
 mod message;
+mod runtime;
+mod scopes;
 mod store;
 mod utils;
 mod value;
 
 pub use flags::ExpiryFlags;
+pub use format::{FormatVersionError, SelfTestError, FORMAT_VERSION};
+pub use scopes::ScopeStrategy;
 pub use sled::Config as SledConfig;
-pub use store::SledBackend;
+pub use store::{ExpiredItem, SledBackend};
 pub use utils::{decode, encode};
+pub use value::Endian;
@@ -1,43 +1,101 @@
-use std::time::Duration;
+use std::{
+    cmp::Ordering,
+    time::{Duration, SystemTime},
+};
 
 use basteh::{
-    dev::{Mutation, OwnedValue},
+    dev::{ListMutation, Mutation, OwnedValue, SnapshotEntry, ValueKind},
     Result,
 };
 use sled::IVec;
 use tokio::sync::oneshot;
 
+use crate::store::ExpiredItem;
+use crate::ExpiryFlags;
+
 type Scope = IVec;
 type Key = IVec;
 type Value = OwnedValue;
+/// A change to the flags of a key, it may be applied more than once if the key is written
+/// concurrently
+pub(crate) type FlagsUpdate = Box<dyn Fn(&mut ExpiryFlags) + Send>;
 
 pub enum Request {
     Keys(Scope),
+    KeysPage(Scope, Option<Key>, usize),
+    Scopes,
+    SizeBytes(Scope),
+    DropScope(Scope),
+    SweepExpired(Scope),
+    TakeExpired(bool),
     Get(Scope, Key),
+    GetRaw(Scope, Key),
+    ValueKind(Scope, Key),
     GetRange(Scope, Key, i64, i64),
+    GetRangeMulti(Scope, Vec<(Key, i64, i64)>),
     Set(Scope, Key, Value),
+    SetKeepTtl(Scope, Key, Value),
+    Upsert(Scope, Key, Value),
+    SetRaw(Scope, Key, Vec<u8>),
     Pop(Scope, Key),
+    PopPush(Scope, Key, Scope, Key),
+    ListDrain(Scope, Key),
     Push(Scope, Key, Value),
+    HSet(Scope, Key, Key, Value),
+    HIncr(Scope, Key, Key, i64),
+    SetBit(Scope, Key, u64, bool),
+    HDel(Scope, Key, Key),
     PushMulti(Scope, Key, Vec<Value>),
+    PushCapped(Scope, Key, Value, u64),
+    PushReturningLen(Scope, Key, Value),
+    MutateList(Scope, Key, ListMutation),
+    IncrFloat(Scope, Key, f64),
+    IncrBig(Scope, Key, i128),
+    SetIf(Scope, Key, i64, Ordering),
     Remove(Scope, Key),
+    Delete(Scope, Key),
+    RemoveIf(Scope, Key, Value),
     Contains(Scope, Key),
+    ContainsMulti(Scope, Vec<Key>),
     MutateNumber(Scope, Key, Mutation),
     Expire(Scope, Key, Duration),
+    ExpireMulti(Scope, Vec<(Key, Duration)>),
     Persist(Scope, Key),
+    UpdateFlags(Scope, Key, FlagsUpdate),
     Expiry(Scope, Key),
+    ModifiedAt(Scope, Key),
     Extend(Scope, Key, Duration),
     SetExpiring(Scope, Key, Value, Duration),
+    SwapExpiring(Scope, Key, Value, Duration),
+    IncrWithTtlFloor(Scope, Key, i64, Duration),
     GetExpiring(Scope, Key),
+    GetExpiringMulti(Scope, Vec<Key>),
+    Snapshot(Scope),
+    GetAndExtend(Scope, Key, Duration),
+    SetVersioned(Scope, Key, Value, u64),
 }
 
 pub enum Response {
     Iterator(Box<dyn Iterator<Item = Vec<u8>> + Send + Sync>),
     Value(Option<Value>),
+    Bytes(Option<Vec<u8>>),
     ValueVec(Vec<Value>),
+    ValueVecs(Vec<Vec<Value>>),
+    Kind(Option<ValueKind>),
     Number(i64),
+    Numbers((i64, i64)),
+    Float(f64),
+    BigNumber(i128),
+    Size(u64),
+    Count(u64),
+    Expired(Vec<ExpiredItem>),
     Duration(Option<Duration>),
+    Time(Option<SystemTime>),
     ValueDuration(Option<(Value, Option<Duration>)>),
+    ValueDurationVec(Vec<Option<(Value, Option<Duration>)>>),
+    Snapshot(Vec<SnapshotEntry>),
     Bool(bool),
+    BoolVec(Vec<bool>),
     Empty(()),
 }
 
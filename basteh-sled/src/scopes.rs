@@ -0,0 +1,61 @@
+/// How the scopes are laid out in the database, set with
+/// [`with_scope_strategy`](crate::SledBackend::with_scope_strategy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScopeStrategy {
+    /// Every scope is stored in its own sled tree, named after the scope
+    #[default]
+    Tree,
+    /// All the scopes are stored in a single tree, with the keys prefixed by their scope and a
+    /// `\0` separator
+    Prefix,
+}
+
+impl ScopeStrategy {
+    pub(crate) fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Self::Prefix,
+            _ => Self::Tree,
+        }
+    }
+
+    pub(crate) fn as_byte(self) -> u8 {
+        match self {
+            Self::Tree => 0,
+            Self::Prefix => 1,
+        }
+    }
+}
+
+/// Name of the tree holding all the scopes with the prefix strategy
+pub(crate) const SCOPES_TREE: &[u8] = b"__BASTEH_SCOPES__";
+
+/// Prefix of the keys of the scope in the scopes tree.
+///
+/// `\0` and `\x01` bytes of the scope are escaped as `\x01\x01` and `\x01\x02`, so the first `\0`
+/// always ends the scope and a scope is never a prefix of another one.
+pub(crate) fn scope_prefix(scope: &[u8]) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(scope.len() + 1);
+    for byte in scope {
+        match byte {
+            0 => prefix.extend_from_slice(&[1, 1]),
+            1 => prefix.extend_from_slice(&[1, 2]),
+            byte => prefix.push(*byte),
+        }
+    }
+    prefix.push(0);
+    prefix
+}
+
+/// Split a key of the scopes tree into its scope and the key itself
+pub(crate) fn split_key(stored: &[u8]) -> Option<(Vec<u8>, &[u8])> {
+    let end = stored.iter().position(|byte| *byte == 0)?;
+    let mut scope = Vec::with_capacity(end);
+    let mut escaped = stored[..end].iter();
+    while let Some(byte) = escaped.next() {
+        match byte {
+            1 => scope.push(escaped.next()? - 1),
+            byte => scope.push(*byte),
+        }
+    }
+    Some((scope, &stored[end + 1..]))
+}
@@ -1,10 +1,30 @@
-use std::time::Duration;
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant, SystemTime},
+};
 
-use basteh::dev::{OwnedValue, Provider, Value};
+use basteh::dev::{
+    paged_stream, BoxStream, ListMutation, OwnedValue, Provider, SnapshotEntry, Value, ValueKind,
+};
 use basteh::{BastehError, Result};
+use crossbeam_channel::TrySendError;
 
 use crate::inner::SledInner;
 use crate::message::{Message, Request, Response};
+use crate::runtime;
+use crate::{Endian, ExpiryFlags, ScopeStrategy};
+
+const DEFAULT_EXPIRY_INTERVAL: Duration = Duration::from_millis(500);
+// Number of requests waiting for the worker threads before new ones are rejected
+const REQUEST_QUEUE_SIZE: usize = 4096;
+// Number of keys fetched at once by keys_stream
+const KEYS_PAGE_SIZE: usize = 1000;
+// How often pop_blocking checks the list while waiting
+const POP_BLOCKING_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The scope, key and last value of an expired item, returned by
+/// [`take_expired`](SledBackend::take_expired)
+pub type ExpiredItem = (Vec<u8>, Vec<u8>, OwnedValue);
 
 /// An implementation of [`ExpiryStore`](basteh::dev::ExpiryStore) using sled with tokio's blocking
 /// tasksZ
@@ -35,6 +55,12 @@ pub struct SledBackend {
 
     perform_deletion: bool,
     scan_db_on_start: bool,
+    expiry_interval: Duration,
+    expiry: bool,
+    checksums: bool,
+    write_timestamps: bool,
+    scope_strategy: ScopeStrategy,
+    number_endianness: Endian,
 }
 
 impl SledBackend {
@@ -46,13 +72,132 @@ impl SledBackend {
         self
     }
 
+    /// Set how long the expiry thread waits for an item before waking up when nothing is due,
+    /// defaults to 500ms.
+    ///
+    /// Items wake the thread up as soon as they're due, so this mostly bounds the idle wake-ups and
+    /// how long it takes for the thread to notice the backend is dropped. Lower values reduce the
+    /// deletion lag in those cases at the cost of more wake-ups, higher values save CPU.
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_expiry_interval(mut self, interval: Duration) -> Self {
+        self.expiry_interval = interval;
+        self
+    }
+
     /// If set to true, actor will scan the database on start to mark expired items.
+    ///
+    /// The trees are scanned in parallel, using as many threads as available cores, and `start`
+    /// returns after the scan is finished.
     #[must_use = "Should be started by calling start method"]
     pub fn scan_db_on_start(mut self, to: bool) -> Self {
         self.scan_db_on_start = to;
         self
     }
 
+    /// Store the values without the expiry flags suffix, for databases which never use expiry.
+    ///
+    /// It saves the 18 bytes of flags stored with every value, and the stored bytes are only the
+    /// encoded value, so other programs can read them without [`decode`](crate::decode). In
+    /// exchange the expiry methods(`expire`, `expire_multiple`, `expiry`, `persist`, `extend`,
+    /// `set_expiring`, `get_expiring`, `get_and_extend` and `incr_with_ttl_floor`) return
+    /// [`MethodNotSupported`](basteh::BastehError::MethodNotSupported), and
+    /// [`perform_deletion`](Self::perform_deletion) and [`scan_db_on_start`](Self::scan_db_on_start)
+    /// are ignored.
+    ///
+    /// The two layouts can't be told apart, so the mode is recorded in the database when it's
+    /// created and existing databases keep the mode they were created with, this setting is
+    /// ignored for them. Databases holding data from before the mode was recorded are marked as
+    /// having the expiry flags.
+    #[must_use = "Should be started by calling start method"]
+    pub fn without_expiry(mut self) -> Self {
+        self.expiry = false;
+        self
+    }
+
+    /// Store a CRC32 checksum with every value, so corrupted values are detected when read.
+    ///
+    /// It's off by default and adds 4 bytes to every value, `get` and the other reads return
+    /// [`Corruption`](basteh::BastehError::Corruption) for values which don't match their
+    /// checksum. The mode is recorded in the database when it's created, existing databases keep
+    /// the mode they were created with and this setting is ignored for them. Databases holding
+    /// data from before the mode was recorded are marked as having no checksums.
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_checksums(mut self, to: bool) -> Self {
+        self.checksums = to;
+        self
+    }
+
+    /// Store the time each value is written with it, so it can be read with
+    /// [`modified_at`](basteh::dev::Provider::modified_at).
+    ///
+    /// It's off by default and adds 8 bytes to every value, `modified_at` returns
+    /// [`MethodNotSupported`](basteh::BastehError::MethodNotSupported) without it. Same as
+    /// [`with_checksums`](Self::with_checksums), the mode is recorded in the database when it's
+    /// created and existing databases keep it. Databases holding data from before the mode was
+    /// recorded are marked as having no write times.
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_write_timestamps(mut self, to: bool) -> Self {
+        self.write_timestamps = to;
+        self
+    }
+
+    /// Set how the scopes are stored, defaults to [`ScopeStrategy::Tree`] which opens a sled tree
+    /// per scope.
+    ///
+    /// [`ScopeStrategy::Prefix`] stores all the scopes in a single tree instead, with the keys
+    /// prefixed by their scope, which suits databases with many small scopes as sled keeps some
+    /// state for each open tree. In exchange the keys are longer and `drop_scope` removes the keys
+    /// one by one instead of dropping a tree. Same as [`with_checksums`](Self::with_checksums),
+    /// the strategy is recorded in the database when it's created and existing databases keep it.
+    /// Databases holding data from before the strategy was recorded are marked as using
+    /// [`ScopeStrategy::Tree`].
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh_sled::{ScopeStrategy, SledBackend, SledConfig};
+    ///
+    /// # async fn your_main() {
+    /// let db = SledConfig::default().open().expect("Couldn't open sled database");
+    /// let provider = SledBackend::from_db(db)
+    ///     .with_scope_strategy(ScopeStrategy::Prefix)
+    ///     .start(4);
+    /// # }
+    /// ```
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_scope_strategy(mut self, strategy: ScopeStrategy) -> Self {
+        self.scope_strategy = strategy;
+        self
+    }
+
+    /// Set the byte order of the stored numbers(including the ones in lists and maps), defaults
+    /// to [`Endian::Little`].
+    ///
+    /// It's meant for interop, when other programs read the values written by basteh from the
+    /// database directly(ex. with [`without_expiry`](Self::without_expiry)) and expect the
+    /// numbers in a specific byte order. A number is stored as its kind byte followed by its 8
+    /// bytes(16 for big numbers). The lengths of the items of lists and maps, and the expiry
+    /// flags, are always little-endian. Same as [`with_checksums`](Self::with_checksums), the
+    /// byte order is recorded in the database when it's created and existing databases keep it.
+    /// Databases holding data from before the byte order was recorded are marked as using
+    /// [`Endian::Little`].
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh_sled::{Endian, SledBackend, SledConfig};
+    ///
+    /// # async fn your_main() {
+    /// let db = SledConfig::default().open().expect("Couldn't open sled database");
+    /// let provider = SledBackend::from_db(db)
+    ///     .with_number_endianness(Endian::Big)
+    ///     .start(4);
+    /// # }
+    /// ```
+    #[must_use = "Should be started by calling start method"]
+    pub fn with_number_endianness(mut self, endian: Endian) -> Self {
+        self.number_endianness = endian;
+        self
+    }
+
     #[must_use = "Should be started by calling start method"]
     pub fn from_db(db: sled::Db) -> Self {
         Self {
@@ -60,27 +205,55 @@ impl SledBackend {
             tx: None,
             perform_deletion: false,
             scan_db_on_start: false,
+            expiry_interval: DEFAULT_EXPIRY_INTERVAL,
+            expiry: true,
+            checksums: false,
+            write_timestamps: false,
+            scope_strategy: ScopeStrategy::Tree,
+            number_endianness: Endian::Little,
         }
     }
 
+    /// Start the backend with `thread_num` threads handling the requests.
+    ///
+    /// If the database was written with another [`FORMAT_VERSION`](crate::FORMAT_VERSION), all
+    /// the operations return a [`FormatVersionError`](crate::FormatVersionError) and expired items
+    /// are left untouched.
     pub fn start(mut self, thread_num: usize) -> Self {
         let mut inner = SledInner::from_db(self.db.take().unwrap());
-        let (tx, rx) = crossbeam_channel::bounded(4096);
+        let (tx, rx) = crossbeam_channel::bounded(REQUEST_QUEUE_SIZE);
 
         self.tx = Some(tx);
+        inner.encoding.checksums = self.checksums;
+        inner.encoding.timestamps = self.write_timestamps;
+        inner.scope_strategy = self.scope_strategy;
+        inner.encoding.numbers = self.number_endianness;
+        inner.encoding.expiry = self.expiry;
+
+        if let Err(err) = inner.check_format_version() {
+            log::error!("Failed to check the format version: {}", err);
+        }
+        // The mode recorded in the database is used instead of the requested one
+        self.expiry = inner.encoding.expiry;
+        if !self.expiry {
+            self.perform_deletion = false;
+        }
+        let compatible = inner.format_error.is_none();
 
-        if self.scan_db_on_start && self.perform_deletion {
+        if compatible && self.scan_db_on_start && self.perform_deletion {
             inner.scan_db();
         }
 
+        // Keep the flag only if the expiry thread is actually running
+        self.perform_deletion = compatible && self.perform_deletion;
         if self.perform_deletion {
-            inner.spawn_expiry_thread();
+            inner.spawn_expiry_thread(self.expiry_interval);
         }
 
         for _ in 0..thread_num {
             let mut inner = inner.clone();
             let rx = rx.clone();
-            tokio::task::spawn_blocking(move || {
+            runtime::spawn_blocking(move || {
                 inner.listen(rx);
             });
         }
@@ -88,6 +261,139 @@ impl SledBackend {
         self
     }
 
+    /// Same as [`start`](Self::start), but checks the database before starting the backend and
+    /// returns the error instead of deferring it to the first operation.
+    ///
+    /// A value is written to the database, flushed, read back and removed, so it fails if the
+    /// database can't be written(ex. a full disk) or was written with another
+    /// [`FORMAT_VERSION`](crate::FORMAT_VERSION).
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh::Basteh;
+    /// use basteh_sled::{SledBackend, SledConfig};
+    ///
+    /// # async fn your_main() {
+    /// let db = SledConfig::default().open().expect("Couldn't open sled database");
+    /// let provider = SledBackend::from_db(db)
+    ///     .start_checked(4)
+    ///     .expect("Database is not usable");
+    /// let storage = Basteh::build().provider(provider).finish();
+    /// # }
+    /// ```
+    pub fn start_checked(self, thread_num: usize) -> Result<Self> {
+        let mut inner = SledInner::from_db(self.db.clone().unwrap());
+        inner.encoding.checksums = self.checksums;
+        inner.encoding.timestamps = self.write_timestamps;
+        inner.scope_strategy = self.scope_strategy;
+        inner.encoding.numbers = self.number_endianness;
+        inner.encoding.expiry = self.expiry;
+        inner.check_format_version()?;
+        if let Some(err) = inner.format_error {
+            return Err(BastehError::custom(err));
+        }
+        inner.self_test()?;
+        Ok(self.start(thread_num))
+    }
+
+    /// Returns true if expired items are deleted by the expiry thread, false if they're only
+    /// hidden until they're overwritten or swept.
+    ///
+    /// It's false if the backend was started without [`perform_deletion`](Self::perform_deletion),
+    /// or if the database was written with another [`FORMAT_VERSION`](crate::FORMAT_VERSION).
+    pub fn is_deletion_enabled(&self) -> bool {
+        self.tx.is_some() && self.perform_deletion
+    }
+
+    /// Returns the number of requests waiting for a worker thread, 0 if the backend is not
+    /// started.
+    ///
+    /// The queue holds up to 4096 requests and the ones sent while it's full fail, a length
+    /// staying high under load means the worker threads can't keep up and more of them may help.
+    pub fn queue_len(&self) -> usize {
+        self.tx.as_ref().map_or(0, |tx| tx.len())
+    }
+
+    /// Get the `(scope, key, value)` of all the items which are expired but not removed from the
+    /// database yet, removing them as well if `remove` is true.
+    ///
+    /// It can be used to log or archive the expired items, an item removed by the expiry thread
+    /// or a sweep before this call is not returned. With `remove`, items which are replaced
+    /// while they're being collected are skipped.
+    pub async fn take_expired(&self, remove: bool) -> Result<Vec<ExpiredItem>> {
+        match self.msg(Request::TakeExpired(remove)).await? {
+            Response::Expired(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Remove the expired items of the scope from the database, returning how many were removed.
+    ///
+    /// Without [`perform_deletion`](Self::perform_deletion) expired items are only hidden, this
+    /// removes them on demand without starting the expiry thread, going through a single scope
+    /// instead of the whole database.
+    pub async fn sweep_expired(&self, scope: impl AsRef<[u8]>) -> Result<u64> {
+        match self
+            .msg(Request::SweepExpired(scope.as_ref().into()))
+            .await?
+        {
+            Response::Count(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Atomically apply `f` to the [`ExpiryFlags`](crate::ExpiryFlags) stored with the key,
+    /// leaving the value bytes untouched, and return whether the key exists.
+    ///
+    /// It gives direct access to the flags without decoding and encoding the value manually.
+    /// `f` may be called more than once if the key is written concurrently, so it shouldn't have
+    /// side effects. If the resulting flags expire, the key is queued for expiry again, change the
+    /// nonce with it so the pending notifications of the old expiry are ignored. It returns
+    /// [`MethodNotSupported`](basteh::BastehError::MethodNotSupported) for the values stored
+    /// [`without_expiry`](Self::without_expiry).
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use basteh_sled::{SledBackend, SledConfig};
+    ///
+    /// # async fn your_main() {
+    /// let db = SledConfig::default().open().expect("Couldn't open sled database");
+    /// let provider = SledBackend::from_db(db).start(4);
+    /// provider
+    ///     .update_flags("scope", "key", |flags| flags.persist.set(1))
+    ///     .await
+    ///     .expect("Couldn't update the flags");
+    /// # }
+    /// ```
+    pub async fn update_flags(
+        &self,
+        scope: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+        f: impl Fn(&mut ExpiryFlags) + Send + 'static,
+    ) -> Result<bool> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::UpdateFlags(
+                scope.as_ref().into(),
+                key.as_ref().into(),
+                Box::new(f),
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Expiry methods are not supported for the values stored without expiry flags
+    fn check_expiry(&self) -> Result<()> {
+        if self.expiry {
+            Ok(())
+        } else {
+            Err(BastehError::MethodNotSupported)
+        }
+    }
+
     async fn msg(&self, req: Request) -> Result<Response> {
         let (tx, rx) = tokio::sync::oneshot::channel();
 
@@ -96,21 +402,74 @@ impl SledBackend {
             .map(|tx| tx.clone())
             .unwrap()
             .try_send(Message { req, tx })
-            .map_err(BastehError::custom)?;
-        rx.await.map_err(BastehError::custom)?
+            .map_err(|err| match err {
+                TrySendError::Disconnected(_) => BastehError::BackendUnavailable,
+                err => BastehError::custom(err),
+            })?;
+        // The sender is dropped without a response if the worker is gone
+        rx.await.map_err(|_| BastehError::BackendUnavailable)?
     }
 }
 
 #[async_trait::async_trait]
 impl Provider for SledBackend {
-    async fn keys(&self, scope: &str) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
         match self.msg(Request::Keys(scope.into())).await? {
             Response::Iterator(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn set(&self, scope: &str, key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+    async fn keys_sorted(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        // Sled trees are already sorted
+        self.keys(scope).await
+    }
+
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, Result<Vec<u8>>> {
+        paged_stream(move |after: Option<Vec<u8>>| async move {
+            let keys = match self
+                .msg(Request::KeysPage(
+                    scope.into(),
+                    after.map(Into::into),
+                    KEYS_PAGE_SIZE,
+                ))
+                .await?
+            {
+                Response::Iterator(r) => r.collect::<Vec<_>>(),
+                _ => unreachable!(),
+            };
+            // A short page is the last one, otherwise the next page starts after its last key
+            let next = if keys.len() < KEYS_PAGE_SIZE {
+                None
+            } else {
+                keys.last().cloned()
+            };
+            Ok((keys, next))
+        })
+    }
+
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        match self.msg(Request::Scopes).await? {
+            Response::Iterator(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn size_bytes(&self, scope: &[u8]) -> basteh::Result<u64> {
+        match self.msg(Request::SizeBytes(scope.into())).await? {
+            Response::Size(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> basteh::Result<()> {
+        match self.msg(Request::DropScope(scope.into())).await? {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<()> {
         match self
             .msg(Request::Set(scope.into(), key.into(), value.into_owned()))
             .await?
@@ -120,16 +479,71 @@ impl Provider for SledBackend {
         }
     }
 
-    async fn get(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<bool> {
+        match self
+            .msg(Request::Upsert(
+                scope.into(),
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+        match self
+            .msg(Request::SetKeepTtl(
+                scope.into(),
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
         match self.msg(Request::Get(scope.into(), key.into())).await? {
             Response::Value(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        match self
+            .msg(Request::SetRaw(scope.into(), key.into(), value.to_vec()))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.msg(Request::GetRaw(scope.into(), key.into())).await? {
+            Response::Bytes(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn value_kind(&self, scope: &[u8], key: &[u8]) -> Result<Option<ValueKind>> {
+        match self
+            .msg(Request::ValueKind(scope.into(), key.into()))
+            .await?
+        {
+            Response::Kind(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
     async fn get_range(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         start: i64,
         end: i64,
@@ -143,9 +557,29 @@ impl Provider for SledBackend {
         }
     }
 
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> basteh::Result<Vec<Vec<OwnedValue>>> {
+        match self
+            .msg(Request::GetRangeMulti(
+                scope.into(),
+                requests
+                    .iter()
+                    .map(|(key, start, end)| ((*key).into(), *start, *end))
+                    .collect(),
+            ))
+            .await?
+        {
+            Response::ValueVecs(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
     async fn mutate(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         mutations: basteh::dev::Mutation,
     ) -> basteh::Result<i64> {
@@ -153,32 +587,39 @@ impl Provider for SledBackend {
             .msg(Request::MutateNumber(scope.into(), key.into(), mutations))
             .await?
         {
-            Response::Number(r) => Ok(r),
+            Response::Numbers((_, r)) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn push(&self, scope: &str, key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+    async fn mutate_returning(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: basteh::dev::Mutation,
+    ) -> basteh::Result<(i64, i64)> {
         match self
-            .msg(Request::Push(scope.into(), key.into(), value.into_owned()))
+            .msg(Request::MutateNumber(scope.into(), key.into(), mutations))
             .await?
         {
-            Response::Empty(r) => Ok(r),
+            Response::Numbers(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn push_multiple(
+    async fn hset(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
-        value: Vec<Value<'_>>,
+        field: &[u8],
+        value: Value<'_>,
     ) -> basteh::Result<()> {
         match self
-            .msg(Request::PushMulti(
+            .msg(Request::HSet(
                 scope.into(),
                 key.into(),
-                value.into_iter().map(|v| v.into_owned()).collect(),
+                field.into(),
+                value.into_owned(),
             ))
             .await?
         {
@@ -187,23 +628,46 @@ impl Provider for SledBackend {
         }
     }
 
-    async fn pop(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
-        match self.msg(Request::Pop(scope.into(), key.into())).await? {
-            Response::Value(r) => Ok(r),
+    async fn hincr(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        field: &[u8],
+        delta: i64,
+    ) -> basteh::Result<i64> {
+        match self
+            .msg(Request::HIncr(
+                scope.into(),
+                key.into(),
+                field.into(),
+                delta,
+            ))
+            .await?
+        {
+            Response::Number(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn remove(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
-        match self.msg(Request::Remove(scope.into(), key.into())).await? {
-            Response::Value(r) => Ok(r),
+    async fn set_bit(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        offset: u64,
+        value: bool,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::SetBit(scope.into(), key.into(), offset, value))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn contains_key(&self, scope: &str, key: &[u8]) -> basteh::Result<bool> {
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> basteh::Result<bool> {
         match self
-            .msg(Request::Contains(scope.into(), key.into()))
+            .msg(Request::HDel(scope.into(), key.into(), field.into()))
             .await?
         {
             Response::Bool(r) => Ok(r),
@@ -211,16 +675,28 @@ impl Provider for SledBackend {
         }
     }
 
-    async fn persist(&self, scope: &str, key: &[u8]) -> basteh::Result<()> {
-        match self.msg(Request::Persist(scope.into(), key.into())).await? {
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> basteh::Result<()> {
+        match self
+            .msg(Request::Push(scope.into(), key.into(), value.into_owned()))
+            .await?
+        {
             Response::Empty(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn expire(&self, scope: &str, key: &[u8], expire_in: Duration) -> basteh::Result<()> {
+    async fn push_multiple(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Vec<Value<'_>>,
+    ) -> basteh::Result<()> {
         match self
-            .msg(Request::Expire(scope.into(), key.into(), expire_in))
+            .msg(Request::PushMulti(
+                scope.into(),
+                key.into(),
+                value.into_iter().map(|v| v.into_owned()).collect(),
+            ))
             .await?
         {
             Response::Empty(r) => Ok(r),
@@ -228,36 +704,53 @@ impl Provider for SledBackend {
         }
     }
 
-    async fn expiry(&self, scope: &str, key: &[u8]) -> basteh::Result<Option<Duration>> {
-        match self.msg(Request::Expiry(scope.into(), key.into())).await? {
-            Response::Duration(r) => Ok(r),
+    async fn push_returning_len(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+    ) -> basteh::Result<u64> {
+        match self
+            .msg(Request::PushReturningLen(
+                scope.into(),
+                key.into(),
+                value.into_owned(),
+            ))
+            .await?
+        {
+            Response::Count(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn extend(&self, scope: &str, key: &[u8], duration: Duration) -> Result<()> {
+    async fn mutate_list(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: ListMutation,
+    ) -> basteh::Result<u64> {
         match self
-            .msg(Request::Extend(scope.into(), key.into(), duration))
+            .msg(Request::MutateList(scope.into(), key.into(), mutations))
             .await?
         {
-            Response::Empty(r) => Ok(r),
+            Response::Count(r) => Ok(r),
             _ => unreachable!(),
         }
     }
 
-    async fn set_expiring(
+    async fn push_capped(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         value: Value<'_>,
-        expire_in: Duration,
+        max_len: u64,
     ) -> basteh::Result<()> {
         match self
-            .msg(Request::SetExpiring(
+            .msg(Request::PushCapped(
                 scope.into(),
                 key.into(),
                 value.into_owned(),
-                expire_in,
+                max_len,
             ))
             .await?
         {
@@ -266,59 +759,998 @@ impl Provider for SledBackend {
         }
     }
 
-    async fn get_expiring(
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
+        match self.msg(Request::Pop(scope.into(), key.into())).await? {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn pop_blocking(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
-    ) -> basteh::Result<Option<(OwnedValue, Option<Duration>)>> {
+        timeout: Duration,
+    ) -> basteh::Result<Option<OwnedValue>> {
+        // There is no notification for the pushes, so the list is checked periodically
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(value) = self.pop(scope, key).await? {
+                return Ok(Some(value));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            runtime::sleep(POP_BLOCKING_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    async fn pop_push(
+        &self,
+        src_scope: &[u8],
+        src_key: &[u8],
+        dst_scope: &[u8],
+        dst_key: &[u8],
+    ) -> basteh::Result<Option<OwnedValue>> {
         match self
-            .msg(Request::GetExpiring(scope.into(), key.into()))
+            .msg(Request::PopPush(
+                src_scope.into(),
+                src_key.into(),
+                dst_scope.into(),
+                dst_key.into(),
+            ))
             .await?
         {
-            Response::ValueDuration(r) => Ok(r),
+            Response::Value(r) => Ok(r),
             _ => unreachable!(),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::time::Duration;
-
-    use basteh::dev::{OwnedValue, Value};
-    use basteh::test_utils::*;
-    use sled::IVec;
-    use zerocopy::{AsBytes, U16, U64};
-
-    use super::SledBackend;
-    use crate::inner::open_tree;
-    use crate::message::Request;
-    use crate::utils::{encode, get_current_timestamp};
-    use crate::{ExpiryFlags, SledConfig};
-
-    async fn open_database() -> sled::Db {
-        let mut tries = 0;
-        loop {
-            tries += 1;
-            if tries > 5 {
-                break;
-            };
-            let db = SledConfig::default().temporary(true).open();
-            if let Ok(db) = db {
-                return db;
-            } else {
-                // Wait for sometime and try again.
-                tokio::time::sleep(Duration::from_millis(500)).await;
-            }
+    async fn list_drain(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Vec<OwnedValue>> {
+        match self
+            .msg(Request::ListDrain(scope.into(), key.into()))
+            .await?
+        {
+            Response::ValueVec(r) => Ok(r),
+            _ => unreachable!(),
         }
-        panic!("Sled can not open the database files")
     }
 
-    #[tokio::test]
+    async fn incr_float(&self, scope: &[u8], key: &[u8], delta: f64) -> Result<f64> {
+        match self
+            .msg(Request::IncrFloat(scope.into(), key.into(), delta))
+            .await?
+        {
+            Response::Float(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn incr_big(&self, scope: &[u8], key: &[u8], delta: i128) -> Result<i128> {
+        match self
+            .msg(Request::IncrBig(scope.into(), key.into(), delta))
+            .await?
+        {
+            Response::BigNumber(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_if(&self, scope: &[u8], key: &[u8], value: i64, ord: Ordering) -> Result<bool> {
+        match self
+            .msg(Request::SetIf(scope.into(), key.into(), value, ord))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<OwnedValue>> {
+        match self.msg(Request::Remove(scope.into(), key.into())).await? {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> basteh::Result<bool> {
+        match self.msg(Request::Delete(scope.into(), key.into())).await? {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn remove_if(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expected: Value<'_>,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::RemoveIf(
+                scope.into(),
+                key.into(),
+                expected.into_owned(),
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> basteh::Result<bool> {
+        match self
+            .msg(Request::Contains(scope.into(), key.into()))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> basteh::Result<Vec<bool>> {
+        match self
+            .msg(Request::ContainsMulti(
+                scope.into(),
+                keys.iter().map(|key| (*key).into()).collect(),
+            ))
+            .await?
+        {
+            Response::BoolVec(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> basteh::Result<()> {
+        self.check_expiry()?;
+        match self.msg(Request::Persist(scope.into(), key.into())).await? {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> basteh::Result<()> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::Expire(scope.into(), key.into(), expire_in))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn expire_multiple(
+        &self,
+        scope: &[u8],
+        entries: &[(&[u8], Duration)],
+    ) -> basteh::Result<()> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::ExpireMulti(
+                scope.into(),
+                entries
+                    .iter()
+                    .map(|(key, expire_in)| ((*key).into(), *expire_in))
+                    .collect(),
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn modified_at(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<SystemTime>> {
+        match self
+            .msg(Request::ModifiedAt(scope.into(), key.into()))
+            .await?
+        {
+            Response::Time(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> basteh::Result<Option<Duration>> {
+        self.check_expiry()?;
+        match self.msg(Request::Expiry(scope.into(), key.into())).await? {
+            Response::Duration(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn extend(&self, scope: &[u8], key: &[u8], duration: Duration) -> Result<()> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::Extend(scope.into(), key.into(), duration))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> basteh::Result<()> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::SetExpiring(
+                scope.into(),
+                key.into(),
+                value.into_owned(),
+                expire_in,
+            ))
+            .await?
+        {
+            Response::Empty(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn swap_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> basteh::Result<Option<OwnedValue>> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::SwapExpiring(
+                scope.into(),
+                key.into(),
+                value.into_owned(),
+                expire_in,
+            ))
+            .await?
+        {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        delta: i64,
+        ttl: Duration,
+    ) -> basteh::Result<i64> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::IncrWithTtlFloor(
+                scope.into(),
+                key.into(),
+                delta,
+                ttl,
+            ))
+            .await?
+        {
+            Response::Number(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> basteh::Result<bool> {
+        match self
+            .msg(Request::SetVersioned(
+                scope.into(),
+                key.into(),
+                value.into_owned(),
+                version,
+            ))
+            .await?
+        {
+            Response::Bool(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+    ) -> basteh::Result<Option<(OwnedValue, Option<Duration>)>> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::GetExpiring(scope.into(), key.into()))
+            .await?
+        {
+            Response::ValueDuration(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> basteh::Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::GetExpiringMulti(
+                scope.into(),
+                keys.iter().map(|key| (*key).into()).collect(),
+            ))
+            .await?
+        {
+            Response::ValueDurationVec(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn snapshot(&self, scope: &[u8]) -> basteh::Result<Vec<SnapshotEntry>> {
+        match self.msg(Request::Snapshot(scope.into())).await? {
+            Response::Snapshot(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_and_extend(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expire_in: Duration,
+    ) -> basteh::Result<Option<OwnedValue>> {
+        self.check_expiry()?;
+        match self
+            .msg(Request::GetAndExtend(scope.into(), key.into(), expire_in))
+            .await?
+        {
+            Response::Value(r) => Ok(r),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::convert::TryInto;
+    use std::time::Duration;
+
+    use basteh::dev::{Clock, OwnedValue, Provider, SystemClock, Value, ValueKind};
+    use basteh::test_utils::*;
+    use basteh::{Basteh, BastehError};
+    use sled::IVec;
+    use zerocopy::{AsBytes, U16, U64};
+
+    use super::SledBackend;
+    use crate::format::{FORMAT_VERSION_KEY, METADATA_TREE, SELF_TEST_KEY};
+    use crate::inner::open_tree;
+    use crate::message::{Message, Request};
+    use crate::scopes::SCOPES_TREE;
+    use crate::utils::{encode, to_basteh};
+    use crate::value::SledValue;
+    use crate::Endian;
+    use crate::{ExpiryFlags, FormatVersionError, ScopeStrategy, SledConfig, FORMAT_VERSION};
+
+    async fn open_database() -> sled::Db {
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            if tries > 5 {
+                break;
+            };
+            let db = SledConfig::default().temporary(true).open();
+            if let Ok(db) = db {
+                return db;
+            } else {
+                // Wait for sometime and try again.
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        }
+        panic!("Sled can not open the database files")
+    }
+
+    /// A database written before the format version and the modes were recorded
+    async fn legacy_database() -> sled::Db {
+        let db = open_database().await;
+        let value = encode(Value::Number(10), &ExpiryFlags::new_persist(0));
+        open_tree(&db, b"legacy")
+            .unwrap()
+            .insert("key", value)
+            .unwrap();
+        db
+    }
+
+    #[tokio::test]
     async fn test_sled_store() {
         test_store(SledBackend::from_db(open_database().await).start(1)).await;
     }
 
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn test_sled_async_std() {
+        async_std::task::block_on(async {
+            let db = SledConfig::default().temporary(true).open().unwrap();
+            let store = Basteh::build()
+                .provider(SledBackend::from_db(db).start(1))
+                .finish();
+
+            store.set("key", "value").await.unwrap();
+            assert_eq!(
+                store.get::<String>("key").await.unwrap(),
+                Some("value".to_owned())
+            );
+            // Waits on the async-std timer
+            assert_eq!(
+                store
+                    .pop_blocking::<i64>("list", Duration::from_millis(100))
+                    .await
+                    .unwrap(),
+                None
+            );
+        });
+    }
+
+    #[test]
+    fn test_sled_error_conversion() {
+        fn unsupported() -> sled::Result<()> {
+            Err(sled::Error::Unsupported("unsupported".into()))
+        }
+
+        fn fails() -> basteh::Result<()> {
+            unsupported().map_err(to_basteh)?;
+            Ok(())
+        }
+
+        assert!(matches!(fails(), Err(BastehError::Custom(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sled_binary_scope() {
+        test_binary_scope(SledBackend::from_db(open_database().await).start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_nested_list() {
+        test_nested_list(SledBackend::from_db(open_database().await).start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_corrupted_list() {
+        let db = open_database().await;
+        let store = SledBackend::from_db(db.clone()).start(1);
+        let tree = open_tree(&db, b"corrupted").unwrap();
+
+        let list = Value::List(vec![
+            Value::Number(1),
+            Value::List(vec![Value::String("item".into())]),
+        ]);
+        let bytes = SledValue(list).to_bytes(Endian::Little);
+
+        // Truncated lists are read as bytes instead of panicking the backend thread, unless
+        // they're cut between the items
+        for len in 2..bytes.len() {
+            let mut value = bytes[..len].to_vec();
+            value.extend_from_slice(ExpiryFlags::new_persist(0).as_bytes());
+            tree.insert("list", value).unwrap();
+            match store.get_range(b"corrupted", b"list", 0, -1).await {
+                Ok(items) => assert_eq!(items, vec![OwnedValue::Number(1)]),
+                Err(err) => {
+                    assert!(matches!(err, BastehError::TypeConversion));
+                    assert_eq!(
+                        store.get(b"corrupted", b"list").await.unwrap(),
+                        Some(OwnedValue::Bytes(bytes[1..len].into()))
+                    );
+                }
+            }
+        }
+
+        // So are the ones nested too deep
+        let mut nested = Value::List(vec![]);
+        for _ in 0..100 {
+            nested = Value::List(vec![nested]);
+        }
+        tree.insert("list", encode(nested, &ExpiryFlags::new_persist(0)))
+            .unwrap();
+        assert!(matches!(
+            store.get_range(b"corrupted", b"list", 0, -1).await,
+            Err(BastehError::TypeConversion)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sled_modified_at() {
+        test_modified_at(
+            SledBackend::from_db(open_database().await)
+                .with_write_timestamps(true)
+                .start(1),
+        )
+        .await;
+
+        let provider = SledBackend::from_db(open_database().await).start(1);
+        assert!(matches!(
+            provider.modified_at(b"scope", b"key").await,
+            Err(BastehError::MethodNotSupported)
+        ));
+
+        // Databases holding data before the mode was recorded don't have write times
+        let provider = SledBackend::from_db(legacy_database().await)
+            .with_write_timestamps(true)
+            .start(1);
+        assert!(matches!(
+            provider.modified_at(b"legacy", b"key").await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        let store = Basteh::build().provider(provider).finish().scope("legacy");
+        assert_eq!(store.get::<i64>("key").await.unwrap(), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_sled_size_bytes() {
+        test_size_bytes(SledBackend::from_db(open_database().await).start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_scopes() {
+        test_scopes(SledBackend::from_db(open_database().await).start(1)).await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_drop_scope() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone()).start(1);
+        test_drop_scope(provider.clone()).await;
+
+        // The tree of the scope and its versions tree should be dropped as well
+        let store = Basteh::build()
+            .provider(provider)
+            .finish()
+            .scope("dropped_scope");
+        store.set("key", "value").await.unwrap();
+        store.set_versioned("key", "value", 1).await.unwrap();
+        assert!(db.tree_names().contains(&IVec::from("dropped_scope")));
+
+        store.drop_scope().await.unwrap();
+        assert!(!db
+            .tree_names()
+            .iter()
+            .any(|name| name.starts_with(b"dropped_scope")));
+    }
+
+    #[tokio::test]
+    async fn test_sled_is_deletion_enabled() {
+        let provider = SledBackend::from_db(open_database().await)
+            .perform_deletion(true)
+            .start(1);
+        assert!(provider.is_deletion_enabled());
+
+        let provider = SledBackend::from_db(open_database().await).start(1);
+        assert!(!provider.is_deletion_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_sled_queue_len() {
+        let provider = SledBackend::from_db(open_database().await).start(1);
+        provider
+            .set(b"scope", b"blocked", Value::Number(1))
+            .await
+            .unwrap();
+
+        // The worker waits in the flags update until the sender is dropped
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let block = provider.update_flags("scope", "blocked", move |_| {
+            rx.recv().ok();
+        });
+        test_queue_len(
+            provider.clone(),
+            || provider.queue_len(),
+            async move {
+                block.await.unwrap();
+            },
+            move || drop(tx),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_raw() {
+        let db = open_database().await;
+        let store = Basteh::build()
+            .provider(SledBackend::from_db(db.clone()).start(1))
+            .finish()
+            .scope("raw_scope");
+
+        // Raw values are written without the value kind and expiry flags
+        store.set_raw("key", b"raw value").await.unwrap();
+        let tree = db.open_tree("raw_scope").unwrap();
+        assert_eq!(tree.get("key").unwrap().unwrap().as_ref(), b"raw value");
+
+        tree.insert("external", b"external value").unwrap();
+        assert_eq!(
+            store.get_raw("external").await.unwrap(),
+            Some(b"external value".to_vec())
+        );
+        assert_eq!(store.get_raw("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sled_without_expiry() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone()).without_expiry().start(1);
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .finish()
+            .scope("no_expiry_scope");
+
+        // Only the encoded value is stored, without the expiry flags suffix
+        store.set("key", "value").await.unwrap();
+        let tree = db.open_tree("no_expiry_scope").unwrap();
+        assert_eq!(
+            tree.get("key").unwrap().unwrap().as_ref(),
+            [&[ValueKind::String as u8][..], b"value"].concat()
+        );
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+
+        store.push("list", 1).await.unwrap();
+        store.push("list", 2).await.unwrap();
+        assert_eq!(store.pop::<i64>("list").await.unwrap(), Some(2));
+        assert_eq!(store.mutate("number", |m| m.incr(5)).await.unwrap(), 5);
+        assert_eq!(store.mutate("number", |m| m.mul(2)).await.unwrap(), 10);
+
+        let scope = b"no_expiry_scope";
+        let dur = Duration::from_secs(10);
+        assert!(matches!(
+            provider.expire(scope, b"key", dur).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            provider.expiry(scope, b"key").await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            provider.persist(scope, b"key").await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            provider.extend(scope, b"key", dur).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            provider
+                .set_expiring(scope, b"key", Value::Number(1), dur)
+                .await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            provider.get_expiring(scope, b"key").await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(matches!(
+            provider.get_and_extend(scope, b"key", dur).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(!provider.is_deletion_enabled());
+
+        // Existing databases keep the mode they were created with
+        let provider = SledBackend::from_db(db.clone())
+            .perform_deletion(true)
+            .start(1);
+        assert_eq!(
+            provider.get(scope, b"key").await.unwrap(),
+            Some(OwnedValue::String("value".into()))
+        );
+        assert!(matches!(
+            provider.expire(scope, b"key", dur).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+        assert!(!provider.is_deletion_enabled());
+
+        // Databases holding data before the mode was recorded have the expiry flags
+        let provider = SledBackend::from_db(legacy_database().await)
+            .without_expiry()
+            .start(1);
+        provider.expire(b"legacy", b"key", dur).await.unwrap();
+        assert_eq!(
+            provider.get(b"legacy", b"key").await.unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sled_checksums() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone())
+            .with_checksums(true)
+            .start(1);
+        let store = Basteh::build()
+            .provider(provider)
+            .finish()
+            .scope("checksums_scope");
+
+        store.set("key", "value").await.unwrap();
+        store.expire("key", Duration::from_secs(10)).await.unwrap();
+        store.set("other", "value").await.unwrap();
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+
+        // Flip a byte of the stored value
+        let tree = db.open_tree("checksums_scope").unwrap();
+        let mut bytes = tree.get("key").unwrap().unwrap().to_vec();
+        bytes[1] ^= 0xff;
+        tree.insert("key", bytes).unwrap();
+        assert!(matches!(
+            store.get::<String>("key").await,
+            Err(BastehError::Corruption)
+        ));
+
+        // The mode is kept from the creation of the database
+        let provider = SledBackend::from_db(db).start(1);
+        let store = Basteh::build()
+            .provider(provider)
+            .finish()
+            .scope("checksums_scope");
+        assert_eq!(
+            store.get::<String>("other").await.unwrap(),
+            Some("value".to_owned())
+        );
+
+        // Databases holding data before the mode was recorded don't have checksums
+        let db = legacy_database().await;
+        let provider = SledBackend::from_db(db).with_checksums(true).start(1);
+        let store = Basteh::build().provider(provider).finish().scope("legacy");
+        assert_eq!(store.get::<i64>("key").await.unwrap(), Some(10));
+        store.set("key", 20).await.unwrap();
+        assert_eq!(store.get::<i64>("key").await.unwrap(), Some(20));
+    }
+
+    #[tokio::test]
+    async fn test_sled_reserved_scope() {
+        let db = open_database().await;
+        let store = Basteh::build()
+            .provider(SledBackend::from_db(db.clone()).start(1))
+            .finish();
+
+        store
+            .scope("versioned")
+            .set_versioned("key", "value", 1)
+            .await
+            .unwrap();
+
+        for name in [
+            "versioned__VERSIONS__",
+            "__BASTEH_METADATA__",
+            "__BASTEH_SCOPES__",
+            "__sled__default",
+        ] {
+            let scope = store.scope(name);
+            assert!(matches!(
+                scope.set("key", "value").await,
+                Err(BastehError::InvalidKey(found)) if found == name
+            ));
+            assert!(matches!(
+                scope.keys().await,
+                Err(BastehError::InvalidKey(_))
+            ));
+        }
+
+        // The versions are left untouched
+        assert!(!store
+            .scope("versioned")
+            .set_versioned("key", "stale", 1)
+            .await
+            .unwrap());
+
+        // All the scopes share a tree with the prefix strategy, so there are no reserved names
+        let store = Basteh::build()
+            .provider(
+                SledBackend::from_db(open_database().await)
+                    .with_scope_strategy(ScopeStrategy::Prefix)
+                    .start(1),
+            )
+            .finish()
+            .scope("__BASTEH_METADATA__");
+        store.set("key", "value").await.unwrap();
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sled_number_endianness() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone())
+            .with_number_endianness(Endian::Big)
+            .start(1);
+        let store = Basteh::build()
+            .provider(provider)
+            .finish()
+            .scope("endianness_scope");
+
+        store
+            .set("number", 0x0102_0304_0506_0708_i64)
+            .await
+            .unwrap();
+        store.set("list", vec![-2_i64]).await.unwrap();
+
+        // Read the bytes as another program would
+        let tree = db.open_tree("endianness_scope").unwrap();
+        let bytes = tree.get("number").unwrap().unwrap();
+        assert_eq!(bytes[0], ValueKind::Number as u8);
+        assert_eq!(
+            i64::from_be_bytes(bytes[1..9].try_into().unwrap()),
+            0x0102_0304_0506_0708
+        );
+        assert_eq!(&bytes[1..9], &[1, 2, 3, 4, 5, 6, 7, 8]);
+        // Kind and length of the item, then the number
+        let bytes = tree.get("list").unwrap().unwrap();
+        assert_eq!(&bytes[10..18], &(-2_i64).to_be_bytes());
+
+        assert_eq!(
+            store.get::<i64>("number").await.unwrap(),
+            Some(0x0102_0304_0506_0708)
+        );
+        assert_eq!(
+            store.mutate("number", |m| m.incr(1)).await.unwrap(),
+            0x0102_0304_0506_0709
+        );
+        assert_eq!(
+            store.get_range::<i64>("list", 0, -1).await.unwrap(),
+            vec![-2]
+        );
+
+        // The byte order is kept from the creation of the database
+        let provider = SledBackend::from_db(db).start(1);
+        let store = Basteh::build()
+            .provider(provider)
+            .finish()
+            .scope("endianness_scope");
+        assert_eq!(
+            store.get::<i64>("number").await.unwrap(),
+            Some(0x0102_0304_0506_0709)
+        );
+
+        // Databases holding data before the byte order was recorded use little-endian numbers
+        let provider = SledBackend::from_db(legacy_database().await)
+            .with_number_endianness(Endian::Big)
+            .start(1);
+        let store = Basteh::build().provider(provider).finish().scope("legacy");
+        assert_eq!(store.get::<i64>("key").await.unwrap(), Some(10));
+        assert_eq!(store.mutate("key", |m| m.incr(1)).await.unwrap(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_sled_update_flags() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone()).start(1);
+        let store = Basteh::build()
+            .provider(provider.clone())
+            .finish()
+            .scope("update_flags");
+
+        store
+            .set_expiring("key", "value", Duration::from_secs(100))
+            .await
+            .unwrap();
+        let tree = db.open_tree("update_flags").unwrap();
+        let before = tree.get("key").unwrap().unwrap();
+        let flags_len = ExpiryFlags::default().as_bytes().len();
+
+        assert!(provider
+            .update_flags("update_flags", "key", |flags| flags.persist.set(1))
+            .await
+            .unwrap());
+
+        // Only the persist flag is changed, the value and the nonce are kept
+        let after = tree.get("key").unwrap().unwrap();
+        let split = before.len() - flags_len;
+        assert_eq!(before[..split], after[..split]);
+        let (_, before_flags) = crate::decode(&before).unwrap();
+        let (_, after_flags) = crate::decode(&after).unwrap();
+        assert_eq!(after_flags.nonce, before_flags.nonce);
+        assert_eq!(after_flags.persist.get(), 1);
+        assert_eq!(store.expiry("key").await.unwrap(), None);
+        assert_eq!(
+            store.get::<String>("key").await.unwrap(),
+            Some("value".to_owned())
+        );
+
+        assert!(!provider
+            .update_flags("update_flags", "missing", |flags| flags.persist.set(1))
+            .await
+            .unwrap());
+
+        let provider = SledBackend::from_db(open_database().await)
+            .without_expiry()
+            .start(1);
+        assert!(matches!(
+            provider.update_flags("scope", "key", |_| {}).await,
+            Err(BastehError::MethodNotSupported)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sled_take_expired() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone()).start(1);
+        let store = Basteh::build().provider(provider.clone()).finish();
+
+        let scope = store.scope("take_expired");
+        scope.set("persistent", "value").await.unwrap();
+        scope
+            .set_expiring("expiring", "old value", Duration::from_secs(1))
+            .await
+            .unwrap();
+        scope.set("expiring", "last value").await.unwrap();
+        scope
+            .expire("expiring", Duration::from_secs(1))
+            .await
+            .unwrap();
+        store
+            .scope("take_expired2")
+            .set_expiring("key", 10, Duration::from_secs(1))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        let mut expired = provider.take_expired(false).await.unwrap();
+        expired.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            expired,
+            vec![
+                (
+                    b"take_expired".to_vec(),
+                    b"expiring".to_vec(),
+                    OwnedValue::String("last value".to_owned())
+                ),
+                (
+                    b"take_expired2".to_vec(),
+                    b"key".to_vec(),
+                    OwnedValue::Number(10)
+                ),
+            ]
+        );
+
+        // Expired items are kept unless they're taken with remove
+        assert_eq!(provider.take_expired(true).await.unwrap().len(), 2);
+        assert!(provider.take_expired(false).await.unwrap().is_empty());
+        assert_eq!(db.open_tree("take_expired").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sled_sweep_expired() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone()).start(1);
+        let store = Basteh::build().provider(provider.clone()).finish();
+
+        for scope in ["sweep_scope1", "sweep_scope2"] {
+            let store = store.scope(scope);
+            store.set("persistent", "value").await.unwrap();
+            store
+                .set_expiring("expiring", "value", Duration::from_secs(1))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // Only the expired item of the swept scope should be removed from the tree
+        assert_eq!(provider.sweep_expired("sweep_scope1").await.unwrap(), 1);
+        assert_eq!(db.open_tree("sweep_scope1").unwrap().len(), 1);
+        assert_eq!(db.open_tree("sweep_scope2").unwrap().len(), 2);
+        assert_eq!(provider.sweep_expired("sweep_scope1").await.unwrap(), 0);
+    }
+
     #[tokio::test]
     async fn test_sled_mutations() {
         test_mutations(SledBackend::from_db(open_database().await).start(1)).await;
@@ -360,6 +1792,142 @@ mod tests {
         assert!(!open_tree(&db, &scope).unwrap().contains_key(key).unwrap());
     }
 
+    #[tokio::test]
+    async fn test_sled_expiry_interval() {
+        let scope: IVec = "prefix".as_bytes().into();
+        let key: IVec = "key".as_bytes().into();
+        let value = OwnedValue::String(String::from("val"));
+        let db = open_database().await;
+        let store = SledBackend::from_db(db.clone())
+            .perform_deletion(true)
+            .with_expiry_interval(Duration::from_millis(50))
+            .start(1);
+        store
+            .msg(Request::SetExpiring(
+                scope.clone(),
+                key.clone(),
+                value,
+                Duration::from_secs(1),
+            ))
+            .await
+            .unwrap();
+        assert!(open_tree(&db, &scope).unwrap().contains_key(&key).unwrap());
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!open_tree(&db, &scope).unwrap().contains_key(&key).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sled_format_version() {
+        let db = open_database().await;
+        let value = encode(Value::Number(10), &ExpiryFlags::new_persist(0));
+        open_tree(&db, b"prefix")
+            .unwrap()
+            .insert("key", value)
+            .unwrap();
+
+        // Databases written before the version was recorded are migrated to the current one
+        let store = SledBackend::from_db(db.clone()).start(1);
+        assert_eq!(
+            store.get(b"prefix", b"key").await.unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+        let metadata = open_tree(&db, METADATA_TREE).unwrap();
+        assert_eq!(
+            metadata.get(FORMAT_VERSION_KEY).unwrap().unwrap().as_ref(),
+            &[FORMAT_VERSION]
+        );
+
+        // So are the ones marked with version 1, whose lists keep their strings as bytes
+        let mut legacy_list = vec![ValueKind::List as u8, ValueKind::Bytes as u8];
+        legacy_list.extend_from_slice(&4_u64.to_le_bytes());
+        legacy_list.extend_from_slice(b"item");
+        legacy_list.extend_from_slice(ExpiryFlags::new_persist(0).as_bytes());
+        open_tree(&db, b"prefix")
+            .unwrap()
+            .insert("list", legacy_list)
+            .unwrap();
+        metadata.insert(FORMAT_VERSION_KEY, &[1]).unwrap();
+        let store = SledBackend::from_db(db.clone()).start(1);
+        assert_eq!(
+            store.get_range(b"prefix", b"list", 0, -1).await.unwrap(),
+            vec![OwnedValue::Bytes("item".into())]
+        );
+        assert_eq!(
+            metadata.get(FORMAT_VERSION_KEY).unwrap().unwrap().as_ref(),
+            &[FORMAT_VERSION]
+        );
+
+        // New databases are marked with the current version right away
+        let empty = open_database().await;
+        SledBackend::from_db(empty.clone()).start(1);
+        assert_eq!(
+            open_tree(&empty, METADATA_TREE)
+                .unwrap()
+                .get(FORMAT_VERSION_KEY)
+                .unwrap()
+                .unwrap()
+                .as_ref(),
+            &[FORMAT_VERSION]
+        );
+
+        // Values written by another version are rejected instead of being misdecoded
+        metadata
+            .insert(FORMAT_VERSION_KEY, &[FORMAT_VERSION + 1])
+            .unwrap();
+        let store = SledBackend::from_db(db.clone())
+            .perform_deletion(true)
+            .scan_db_on_start(true)
+            .start(1);
+        match store.get(b"prefix", b"key").await {
+            Err(BastehError::Custom(err)) => assert_eq!(
+                err.downcast_ref::<FormatVersionError>(),
+                Some(&FormatVersionError {
+                    found: FORMAT_VERSION + 1,
+                    supported: FORMAT_VERSION,
+                })
+            ),
+            res => panic!("Expected a format version error, got {:?}", res),
+        }
+        assert!(store
+            .set(b"prefix", b"key", Value::Number(1))
+            .await
+            .is_err());
+        assert!(open_tree(&db, b"prefix")
+            .unwrap()
+            .contains_key("key")
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sled_start_checked() {
+        let db = open_database().await;
+
+        let store = SledBackend::from_db(db.clone()).start_checked(1).unwrap();
+        store
+            .set(b"prefix", b"key", Value::Number(10))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(b"prefix", b"key").await.unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+        let metadata = open_tree(&db, METADATA_TREE).unwrap();
+        assert!(!metadata.contains_key(SELF_TEST_KEY).unwrap());
+
+        // Unusable databases fail on start instead of on the first operation
+        metadata
+            .insert(FORMAT_VERSION_KEY, &[FORMAT_VERSION + 1])
+            .unwrap();
+        match SledBackend::from_db(db).start_checked(1) {
+            Err(BastehError::Custom(err)) => {
+                assert!(err.downcast_ref::<FormatVersionError>().is_some())
+            }
+            Err(err) => panic!("Expected a format version error, got {:?}", err),
+            Ok(_) => panic!("Expected a format version error"),
+        }
+    }
+
     #[tokio::test]
     async fn test_sled_scan_on_start() {
         let db = open_database().await;
@@ -374,7 +1942,7 @@ mod tests {
             &ExpiryFlags {
                 persist: U16::ZERO,
                 nonce: U64::new(1),
-                expires_at: U64::new(get_current_timestamp() - 1),
+                expires_at: U64::new(SystemClock.timestamp() - 1),
             },
         );
 
@@ -395,4 +1963,242 @@ mod tests {
         // Making sure actor stays alive
         drop(actor)
     }
+
+    #[tokio::test]
+    async fn test_sled_scan_many_trees() {
+        let db = open_database().await;
+
+        let expiring = encode(
+            Value::String("value".into()),
+            &ExpiryFlags::new_expiring(1, Duration::from_secs(2)),
+        );
+        let expired = encode(
+            Value::String("value".into()),
+            &ExpiryFlags {
+                persist: U16::ZERO,
+                nonce: U64::new(1),
+                expires_at: U64::new(SystemClock.timestamp() - 1),
+            },
+        );
+        let persistent = encode(Value::String("value".into()), &ExpiryFlags::new_persist(0));
+
+        let trees = (0..32)
+            .map(|i| db.open_tree(format!("tree{}", i)).unwrap())
+            .collect::<Vec<_>>();
+        for tree in trees.iter() {
+            for i in 0..100 {
+                tree.insert(format!("expiring{}", i), expiring.clone())
+                    .unwrap();
+                tree.insert(format!("expired{}", i), expired.clone())
+                    .unwrap();
+                tree.insert(format!("persistent{}", i), persistent.clone())
+                    .unwrap();
+            }
+        }
+
+        let actor = SledBackend::from_db(db.clone())
+            .scan_db_on_start(true)
+            .perform_deletion(true)
+            .start(1);
+
+        // Scan is done before start returns
+        for tree in trees.iter() {
+            assert_eq!(tree.len(), 200);
+            assert!(!tree.contains_key("expired0").unwrap());
+            assert!(tree.contains_key("expiring0").unwrap());
+        }
+
+        tokio::time::sleep(Duration::from_millis(3000)).await;
+        for tree in trees.iter() {
+            assert_eq!(tree.len(), 100);
+            assert!(tree.contains_key("persistent99").unwrap());
+        }
+
+        // Making sure actor stays alive
+        drop(actor)
+    }
+
+    #[tokio::test]
+    async fn test_sled_delete() {
+        let db = open_database().await;
+        let provider = SledBackend::from_db(db.clone()).start(1);
+        let tree = open_tree(&db, b"delete_scope").unwrap();
+
+        // Only the kind and the flags are decoded, so values which can't be read are deleted too
+        let mut undecodable = vec![ValueKind::List as u8, 0xff, 0xff, 0xff];
+        undecodable.extend_from_slice(ExpiryFlags::new_persist(0).as_bytes());
+        tree.insert("key", undecodable).unwrap();
+        assert!(provider.delete(b"delete_scope", b"key").await.unwrap());
+        assert!(!tree.contains_key("key").unwrap());
+
+        // Expired values are removed, but they're reported as missing
+        let expired = encode(
+            Value::Number(1),
+            &ExpiryFlags {
+                persist: U16::ZERO,
+                nonce: U64::new(1),
+                expires_at: U64::new(SystemClock.timestamp() - 1),
+            },
+        );
+        tree.insert("key", expired).unwrap();
+        assert!(!provider.delete(b"delete_scope", b"key").await.unwrap());
+        assert!(!tree.contains_key("key").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sled_backend_unavailable() {
+        // Without any worker the channel is disconnected
+        let provider = SledBackend::from_db(open_database().await).start(0);
+        assert!(matches!(
+            provider.get(b"scope", b"key").await,
+            Err(BastehError::BackendUnavailable)
+        ));
+
+        // A worker going away while handling the request drops the response sender
+        let (tx, rx) = crossbeam_channel::bounded::<Message>(1);
+        let worker = std::thread::spawn(move || drop(rx.recv()));
+        let mut provider = SledBackend::from_db(open_database().await);
+        provider.tx = Some(tx);
+        assert!(matches!(
+            provider.set(b"scope", b"key", Value::Number(1)).await,
+            Err(BastehError::BackendUnavailable)
+        ));
+        worker.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sled_prefix_strategy() {
+        let start = || async {
+            SledBackend::from_db(open_database().await)
+                .with_scope_strategy(ScopeStrategy::Prefix)
+                .start(1)
+        };
+        test_store(start().await).await;
+        test_binary_scope(start().await).await;
+        test_scopes(start().await).await;
+        test_size_bytes(start().await).await;
+        test_drop_scope(start().await).await;
+        test_expiry_store(start().await, 4).await;
+    }
+
+    #[tokio::test]
+    async fn test_sled_scope_strategy() {
+        // Scopes which are prefixes of each other, or contain the separator and its escape byte
+        let scopes: [&[u8]; 5] = [
+            b"scope",
+            b"scope\x00",
+            b"scope\x00key",
+            b"scope\x01",
+            b"scopes",
+        ];
+        let mut sizes = Vec::new();
+
+        for strategy in [ScopeStrategy::Tree, ScopeStrategy::Prefix] {
+            let db = open_database().await;
+            let provider = SledBackend::from_db(db.clone())
+                .with_scope_strategy(strategy)
+                .perform_deletion(true)
+                .start(1);
+            let store = Basteh::build().provider(provider).finish();
+
+            for (i, scope) in scopes.iter().enumerate() {
+                let scope = store.scope(*scope);
+                scope.set("key", i as i64).await.unwrap();
+                scope.set(format!("key{}", i), i as i64).await.unwrap();
+                scope
+                    .set_expiring("expiring", i as i64, Duration::from_secs(1))
+                    .await
+                    .unwrap();
+            }
+
+            for (i, scope) in scopes.iter().enumerate() {
+                let scope = store.scope(*scope);
+                assert_eq!(scope.get::<i64>("key").await.unwrap(), Some(i as i64));
+                assert_eq!(
+                    scope.keys_sorted().await.unwrap().collect::<Vec<_>>(),
+                    vec![
+                        b"expiring".to_vec(),
+                        b"key".to_vec(),
+                        format!("key{}", i).into_bytes()
+                    ]
+                );
+            }
+            assert_eq!(
+                store.scopes().await.unwrap().collect::<HashSet<_>>(),
+                scopes.iter().map(|scope| scope.to_vec()).collect()
+            );
+
+            // The expiry thread only removes the keys of their own scope
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            for (i, scope) in scopes.iter().enumerate() {
+                let scope = store.scope(*scope);
+                assert_eq!(
+                    scope.keys().await.unwrap().collect::<HashSet<_>>(),
+                    HashSet::from([b"key".to_vec(), format!("key{}", i).into_bytes()])
+                );
+            }
+
+            let mut strategy_sizes = Vec::new();
+            for scope in scopes.iter() {
+                strategy_sizes.push(store.scope(*scope).size_bytes().await.unwrap());
+            }
+            sizes.push(strategy_sizes);
+
+            store.scope(b"scope").drop_scope().await.unwrap();
+            assert!(store.scope(b"scope").keys().await.unwrap().next().is_none());
+            for scope in scopes[1..].iter() {
+                assert!(store.scope(*scope).contains_key("key").await.unwrap());
+            }
+
+            match strategy {
+                ScopeStrategy::Tree => assert!(db.tree_names().contains(&IVec::from("scopes"))),
+                ScopeStrategy::Prefix => {
+                    assert!(!db.tree_names().contains(&IVec::from("scopes")));
+                    assert_eq!(open_tree(&db, SCOPES_TREE).unwrap().len(), 8);
+                }
+            }
+        }
+
+        // Sizes don't include the prefixes
+        assert_eq!(sizes[0], sizes[1]);
+    }
+
+    #[tokio::test]
+    async fn test_sled_scope_strategy_recorded() {
+        let db = open_database().await;
+        let store = SledBackend::from_db(db.clone())
+            .with_scope_strategy(ScopeStrategy::Prefix)
+            .start(1);
+        store
+            .set(b"scope", b"key", Value::Number(10))
+            .await
+            .unwrap();
+
+        // Existing databases keep the strategy they were created with
+        let store = SledBackend::from_db(db.clone()).start(1);
+        assert_eq!(
+            store.get(b"scope", b"key").await.unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+        assert!(!db.tree_names().contains(&IVec::from("scope")));
+
+        // Databases holding data before the strategy was recorded have a tree per scope
+        let db = legacy_database().await;
+        let store = SledBackend::from_db(db.clone())
+            .with_scope_strategy(ScopeStrategy::Prefix)
+            .start(1);
+        assert_eq!(
+            store.get(b"legacy", b"key").await.unwrap(),
+            Some(OwnedValue::Number(10))
+        );
+        store
+            .set(b"legacy", b"other", Value::Number(20))
+            .await
+            .unwrap();
+        assert!(db
+            .open_tree("legacy")
+            .unwrap()
+            .contains_key("other")
+            .unwrap());
+    }
 }
@@ -1,41 +1,224 @@
-use std::time::SystemTime;
+use std::convert::TryInto;
+use std::time::{Duration, SystemTime};
 
-use basteh::dev::{Action, Mutation, Value};
+use basteh::dev::{Action, Clock, Mutation, SystemClock, Value, ValueKind};
+use basteh::{BastehError, Result};
 use zerocopy::{AsBytes, LayoutVerified};
 
-use crate::{flags::ExpiryFlags, value::SledValue};
+use crate::{
+    flags::{ExpiryFlags, PERSISTENT_FLAGS},
+    value::{Endian, SledValue},
+};
 
-pub(crate) fn get_current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
+const CHECKSUM_LEN: usize = 4;
+const TIMESTAMP_LEN: usize = 8;
+
+/// Convert the errors of sled, to be used as `.map_err(to_basteh)?`
+#[inline]
+pub(crate) fn to_basteh(err: sled::Error) -> BastehError {
+    BastehError::custom(err)
+}
+
+/// How the values are laid out in the trees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Encoding {
+    /// Values are followed by their `ExpiryFlags`, otherwise they're all persistent
+    pub(crate) expiry: bool,
+    /// Values end with a CRC32 of the bytes before it
+    pub(crate) checksums: bool,
+    /// Values are followed by the time they were written, in milliseconds since the unix epoch.
+    /// It's placed before the checksum.
+    pub(crate) timestamps: bool,
+    /// Byte order of the numbers, including the ones in lists and maps
+    pub(crate) numbers: Endian,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            expiry: true,
+            checksums: false,
+            timestamps: false,
+            numbers: Endian::Little,
+        }
+    }
+}
+
+impl Encoding {
+    /// Strip the checksum off the bytes, returns None if it doesn't match
+    #[inline]
+    fn checked(self, bytes: &[u8]) -> Option<&[u8]> {
+        if !self.checksums {
+            return Some(bytes);
+        }
+        let (bytes, checksum) = bytes.split_at(bytes.len().checked_sub(CHECKSUM_LEN)?);
+        (crc32fast::hash(bytes).to_le_bytes() == checksum).then_some(bytes)
+    }
+
+    /// Strip the checksum and the write time off the bytes, returns None if the checksum doesn't
+    /// match
+    #[inline]
+    fn verified(self, bytes: &[u8]) -> Option<&[u8]> {
+        let bytes = self.checked(bytes)?;
+        if !self.timestamps {
+            return Some(bytes);
+        }
+        Some(&bytes[..bytes.len().checked_sub(TIMESTAMP_LEN)?])
+    }
+
+    /// Same as `verified`, for mutable bytes
+    #[inline]
+    fn verified_mut(self, bytes: &mut [u8]) -> Option<&mut [u8]> {
+        let len = self.verified(bytes)?.len();
+        Some(&mut bytes[..len])
+    }
+
+    /// The time the value was written, returns None if it's not recorded or the checksum doesn't
+    /// match
+    pub(crate) fn written_at(self, bytes: &[u8]) -> Option<SystemTime> {
+        if !self.timestamps {
+            return None;
+        }
+        let bytes = self.checked(bytes)?;
+        let millis = bytes.get(bytes.len().checked_sub(TIMESTAMP_LEN)?..)?;
+        let millis = u64::from_le_bytes(millis.try_into().ok()?);
+        Some(SystemTime::UNIX_EPOCH + Duration::from_millis(millis))
+    }
+
+    /// Returns `Corruption` if the checksum of the stored bytes doesn't match
+    #[inline]
+    pub(crate) fn check(self, bytes: &[u8]) -> Result<()> {
+        match self.verified(bytes) {
+            Some(_) => Ok(()),
+            None => Err(BastehError::Corruption),
+        }
+    }
+
+    /// Write the checksum again after the bytes are changed in place, ex. by `decode_mut`
+    #[inline]
+    pub(crate) fn update_checksum(self, bytes: &mut [u8]) {
+        if self.checksums && bytes.len() >= CHECKSUM_LEN {
+            let (bytes, checksum) = bytes.split_at_mut(bytes.len() - CHECKSUM_LEN);
+            checksum.copy_from_slice(&crc32fast::hash(bytes).to_le_bytes());
+        }
+    }
+
+    /// Same as [`decode`], values stored without flags are returned as persistent. Values with a
+    /// mismatching checksum can't be decoded.
+    #[allow(clippy::type_complexity)]
+    #[inline]
+    pub(crate) fn decode(self, bytes: &[u8]) -> Option<(Value<'_>, &ExpiryFlags)> {
+        let bytes = self.verified(bytes)?;
+        if self.expiry {
+            decode_with(bytes, self.numbers)
+        } else {
+            Some((
+                SledValue::from_bytes(bytes, self.numbers)?.0,
+                &PERSISTENT_FLAGS,
+            ))
+        }
+    }
+
+    /// Same as [`decode_mut`], it returns None for values stored without flags. The checksum
+    /// should be updated with `update_checksum` after changing the flags.
+    #[allow(clippy::type_complexity)]
+    #[inline]
+    pub(crate) fn decode_mut(self, bytes: &mut [u8]) -> Option<(Value<'_>, &mut ExpiryFlags)> {
+        if self.expiry {
+            decode_mut(self.verified_mut(bytes)?, self.numbers)
+        } else {
+            None
+        }
+    }
+
+    /// Same as [`decode_kind`], values stored without flags are returned as persistent
+    #[inline]
+    pub(crate) fn decode_kind(self, bytes: &[u8]) -> Option<(ValueKind, &ExpiryFlags)> {
+        let bytes = self.verified(bytes)?;
+        if self.expiry {
+            decode_kind(bytes)
+        } else {
+            let kind = bytes.first().and_then(|v| ValueKind::from_u8(*v))?;
+            Some((kind, &PERSISTENT_FLAGS))
+        }
+    }
+
+    /// Same as [`encode`], the flags are dropped for values stored without them and the current
+    /// time is added if the write times are recorded
+    #[inline]
+    pub(crate) fn encode(self, value: Value<'_>, exp: &ExpiryFlags) -> Vec<u8> {
+        let mut bytes = if self.expiry {
+            encode_with(value, exp, self.numbers)
+        } else {
+            SledValue(value).to_bytes(self.numbers)
+        };
+        if self.timestamps {
+            let millis = SystemClock
+                .now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default();
+            bytes.extend_from_slice(&millis.to_le_bytes());
+        }
+        if self.checksums {
+            let checksum = crc32fast::hash(&bytes);
+            bytes.extend_from_slice(&checksum.to_le_bytes());
+        }
+        bytes
+    }
 }
 
 /// Takes an IVec and returns value bytes with its expiry flags as mutable
 #[allow(clippy::type_complexity)]
 #[inline]
-pub(crate) fn decode_mut(bytes: &mut [u8]) -> Option<(Value<'_>, &mut ExpiryFlags)> {
+pub(crate) fn decode_mut(
+    bytes: &mut [u8],
+    endian: Endian,
+) -> Option<(Value<'_>, &mut ExpiryFlags)> {
     let (val, exp): (&mut [u8], LayoutVerified<&mut [u8], ExpiryFlags>) =
         LayoutVerified::new_unaligned_from_suffix(bytes.as_mut())?;
-    Some((SledValue::from_bytes(val)?.0, exp.into_mut()))
+    Some((SledValue::from_bytes(val, endian)?.0, exp.into_mut()))
 }
 
-/// Takes an IVec and returns value bytes with its expiry flags
+/// Takes an IVec and returns value bytes with its expiry flags. Numbers are read as little-endian,
+/// the default of [`with_number_endianness`](crate::SledBackend::with_number_endianness).
 #[allow(clippy::type_complexity)]
 #[inline]
 pub fn decode(bytes: &[u8]) -> Option<(Value<'_>, &ExpiryFlags)> {
+    decode_with(bytes, Endian::Little)
+}
+
+#[allow(clippy::type_complexity)]
+#[inline]
+fn decode_with(bytes: &[u8], endian: Endian) -> Option<(Value<'_>, &ExpiryFlags)> {
     let (val, exp): (&[u8], LayoutVerified<&[u8], ExpiryFlags>) =
         LayoutVerified::new_unaligned_from_suffix(bytes.as_ref())?;
-    Some((SledValue::from_bytes(val)?.0, exp.into_ref()))
+    Some((SledValue::from_bytes(val, endian)?.0, exp.into_ref()))
+}
+
+/// Takes an IVec and returns the kind of its value with its expiry flags, without decoding the
+/// value itself
+#[inline]
+pub(crate) fn decode_kind(bytes: &[u8]) -> Option<(ValueKind, &ExpiryFlags)> {
+    let (val, exp): (&[u8], LayoutVerified<&[u8], ExpiryFlags>) =
+        LayoutVerified::new_unaligned_from_suffix(bytes)?;
+    let kind = val.first().and_then(|v| ValueKind::from_u8(*v))?;
+    Some((kind, exp.into_ref()))
 }
 
-/// Takes a value as bytes and an ExpiryFlags and turns them into bytes
+/// Takes a value as bytes and an ExpiryFlags and turns them into bytes. Numbers are written as
+/// little-endian, the default of
+/// [`with_number_endianness`](crate::SledBackend::with_number_endianness).
 #[allow(clippy::type_complexity)]
 #[inline]
 pub fn encode(value: Value<'_>, exp: &ExpiryFlags) -> Vec<u8> {
+    encode_with(value, exp, Endian::Little)
+}
+
+#[inline]
+fn encode_with(value: Value<'_>, exp: &ExpiryFlags, endian: Endian) -> Vec<u8> {
     let mut buff = vec![];
-    buff.extend_from_slice(&SledValue(value).to_bytes());
+    buff.extend_from_slice(&SledValue(value).to_bytes(endian));
     buff.extend_from_slice(exp.as_bytes());
     buff
 }
@@ -1,12 +1,70 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use basteh::dev::{Value, ValueKind};
 use bytes::Bytes;
 
+/// Byte order of the numbers stored in the database, set with
+/// [`with_number_endianness`](crate::SledBackend::with_number_endianness).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    /// Least significant byte first, the native order of most machines
+    #[default]
+    Little,
+    /// Most significant byte first(network order), the numbers sort in byte order if they're
+    /// not negative
+    Big,
+}
+
+impl Endian {
+    pub(crate) fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Self::Big,
+            _ => Self::Little,
+        }
+    }
+
+    pub(crate) fn as_byte(self) -> u8 {
+        match self {
+            Self::Little => 0,
+            Self::Big => 1,
+        }
+    }
+
+    fn read_i64(self, bytes: &[u8]) -> i64 {
+        let bytes = bytes.try_into().unwrap();
+        match self {
+            Self::Little => i64::from_le_bytes(bytes),
+            Self::Big => i64::from_be_bytes(bytes),
+        }
+    }
+
+    fn write_i64(self, n: i64) -> [u8; 8] {
+        match self {
+            Self::Little => n.to_le_bytes(),
+            Self::Big => n.to_be_bytes(),
+        }
+    }
+
+    fn read_i128(self, bytes: &[u8]) -> i128 {
+        let bytes = bytes.try_into().unwrap();
+        match self {
+            Self::Little => i128::from_le_bytes(bytes),
+            Self::Big => i128::from_be_bytes(bytes),
+        }
+    }
+
+    fn write_i128(self, n: i128) -> [u8; 16] {
+        match self {
+            Self::Little => n.to_le_bytes(),
+            Self::Big => n.to_be_bytes(),
+        }
+    }
+}
+
 pub struct SledValue<'a>(pub Value<'a>);
 
 impl<'a> SledValue<'a> {
-    pub(crate) fn from_bytes(data: &'a [u8]) -> Option<Self> {
+    pub(crate) fn from_bytes(data: &'a [u8], endian: Endian) -> Option<Self> {
         let kind = data.get(0).and_then(|v| ValueKind::from_u8(*v))?;
 
         Some(Self(match kind {
@@ -15,60 +73,44 @@ impl<'a> SledValue<'a> {
                     // Invalid data found, should we panic?
                     return None;
                 } else {
-                    Value::Number(i64::from_le_bytes(data[1..9].try_into().unwrap()))
+                    Value::Number(endian.read_i64(&data[1..9]))
                 }
             }
-            ValueKind::String => Value::String(String::from_utf8_lossy(&data[1..])),
-            ValueKind::Bytes => Value::Bytes(Bytes::copy_from_slice(&data[1..])),
-            ValueKind::List => {
-                let mut index = 1;
-                let mut values = Vec::new();
-
-                while index < data.len() {
-                    let kind = ValueKind::from_u8(data[index]).unwrap_or(ValueKind::Number);
-                    index += 1;
-
-                    let len = u64::from_le_bytes(data[index..(index + 8)].try_into().unwrap());
-                    index += 8;
-
-                    match kind {
-                        ValueKind::List => {
-                            panic!("List of lists is not supported");
-                        }
-                        ValueKind::Number => {
-                            let n =
-                                i64::from_le_bytes(data[index..(index + 8)].try_into().unwrap());
-                            index += 8;
-                            values.push(Value::Number(n));
-                        }
-                        ValueKind::Bytes => {
-                            let b = data[index..(index + len as usize)].to_vec();
-                            index += b.len();
-                            values.push(Value::Bytes(b.into()));
-                        }
-                        ValueKind::String => {
-                            let s = data[index..(index + len as usize)].to_vec();
-                            index += s.len();
-                            values.push(Value::String(
-                                String::from_utf8_lossy(&data[1..]).into_owned().into(),
-                            ));
-                        }
-                    }
+            ValueKind::BigNumber => {
+                if data.len() < std::mem::size_of::<i128>() + 1 {
+                    // Invalid data found, should we panic?
+                    return None;
+                } else {
+                    Value::BigNumber(endian.read_i128(&data[1..17]))
                 }
-
-                Value::List(values)
             }
+            ValueKind::String => Value::String(String::from_utf8_lossy(&data[1..])),
+            ValueKind::Bytes => Value::Bytes(Bytes::copy_from_slice(&data[1..])),
+            // Corrupted lists are read as bytes, so using them as lists fails instead of panicking
+            ValueKind::List => match decode_list(&data[1..], endian, 0) {
+                Some(list) => Value::List(list),
+                None => Value::Bytes(Bytes::copy_from_slice(&data[1..])),
+            },
+            ValueKind::Map => match decode_map(&data[1..], endian, 0) {
+                Some(map) => Value::Map(map),
+                None => Value::Bytes(Bytes::copy_from_slice(&data[1..])),
+            },
         }))
     }
 
-    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+    pub(crate) fn to_bytes(&self, endian: Endian) -> Vec<u8> {
         let mut res = Vec::new();
         let kind = self.0.kind() as u8;
         match &self.0 {
             Value::Number(n) => {
                 res.reserve(std::mem::size_of::<i64>() + 1);
                 res.push(kind);
-                res.extend_from_slice(&n.to_le_bytes())
+                res.extend_from_slice(&endian.write_i64(*n))
+            }
+            Value::BigNumber(n) => {
+                res.reserve(std::mem::size_of::<i128>() + 1);
+                res.push(kind);
+                res.extend_from_slice(&endian.write_i128(*n))
             }
             Value::Bytes(b) => {
                 res.reserve(b.len() + 1);
@@ -81,37 +123,122 @@ impl<'a> SledValue<'a> {
                 res.extend_from_slice(&s.as_bytes())
             }
             Value::List(l) => {
-                res.reserve(std::mem::size_of::<u64>() + 1);
                 res.push(ValueKind::List as u8);
-
-                for item in l {
-                    match item {
-                        Value::List(_) => {
-                            panic!("List of lists is not supported")
-                        }
-                        Value::Number(n) => {
-                            res.reserve(17);
-                            res.push(ValueKind::Number as u8);
-                            res.extend_from_slice(&4__u64.to_le_bytes());
-                            res.extend_from_slice(&n.to_le_bytes())
-                        }
-                        Value::Bytes(b) => {
-                            res.reserve(b.len() + 9);
-                            res.push(ValueKind::Bytes as u8);
-                            res.extend_from_slice(&(b.len() as u64).to_le_bytes());
-                            res.extend_from_slice(&b)
-                        }
-                        Value::String(s) => {
-                            res.reserve(s.len() + 9);
-                            res.push(ValueKind::Bytes as u8);
-                            res.extend_from_slice(&(s.len() as u64).to_le_bytes());
-                            res.extend_from_slice(&s.as_bytes())
-                        }
-                    }
-                }
+                encode_list(l, &mut res, endian);
+            }
+            Value::Map(m) => {
+                res.push(ValueKind::Map as u8);
+                encode_map(m, &mut res, endian);
             }
         }
 
         res
     }
 }
+
+/// Lists nested deeper than this are read as corrupted, so corrupted data can't overflow the stack
+const MAX_LIST_DEPTH: usize = 64;
+
+// List items are stored as kind(1 byte), length(8 bytes) and the data, nested lists use the same
+// encoding for their items with the total length of them as their length. Returns None if the data
+// is corrupted(ex. truncated).
+fn decode_list(mut data: &[u8], endian: Endian, depth: usize) -> Option<Vec<Value<'static>>> {
+    if depth > MAX_LIST_DEPTH {
+        return None;
+    }
+    let mut values = Vec::new();
+
+    while let Some((kind, rest)) = data.split_first() {
+        let kind = ValueKind::from_u8(*kind).unwrap_or(ValueKind::Number);
+
+        let len = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?);
+        let rest = &rest[8..];
+
+        // Numbers have a fixed size, their length is not reliable
+        let len = match kind {
+            ValueKind::Number => 8,
+            ValueKind::BigNumber => 16,
+            _ => usize::try_from(len).ok()?,
+        };
+        let item = rest.get(..len)?;
+        data = &rest[len..];
+
+        values.push(match kind {
+            ValueKind::List => Value::List(decode_list(item, endian, depth + 1)?),
+            ValueKind::Map => Value::Map(decode_map(item, endian, depth + 1)?),
+            ValueKind::Number => Value::Number(endian.read_i64(item)),
+            ValueKind::BigNumber => Value::BigNumber(endian.read_i128(item)),
+            ValueKind::Bytes => Value::Bytes(item.to_vec().into()),
+            ValueKind::String => Value::String(String::from_utf8_lossy(item).into_owned().into()),
+        });
+    }
+
+    Some(values)
+}
+
+// Maps are stored as a list of their fields(as bytes), each followed by its value
+fn decode_map(data: &[u8], endian: Endian, depth: usize) -> Option<Vec<(Vec<u8>, Value<'static>)>> {
+    let mut items = decode_list(data, endian, depth)?.into_iter();
+    let mut map = Vec::new();
+    while let (Some(Value::Bytes(field)), Some(value)) = (items.next(), items.next()) {
+        map.push((field.to_vec(), value));
+    }
+    Some(map)
+}
+
+fn encode_map(map: &[(Vec<u8>, Value)], res: &mut Vec<u8>, endian: Endian) {
+    for (field, value) in map {
+        res.reserve(field.len() + 9);
+        res.push(ValueKind::Bytes as u8);
+        res.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        res.extend_from_slice(field);
+        encode_list(std::slice::from_ref(value), res, endian);
+    }
+}
+
+fn encode_list(list: &[Value], res: &mut Vec<u8>, endian: Endian) {
+    for item in list {
+        match item {
+            Value::List(l) => {
+                res.push(ValueKind::List as u8);
+                let len_index = res.len();
+                res.extend_from_slice(&0_u64.to_le_bytes());
+                encode_list(l, res, endian);
+                let len = (res.len() - len_index - 8) as u64;
+                res[len_index..(len_index + 8)].copy_from_slice(&len.to_le_bytes());
+            }
+            Value::Map(m) => {
+                res.push(ValueKind::Map as u8);
+                let len_index = res.len();
+                res.extend_from_slice(&0_u64.to_le_bytes());
+                encode_map(m, res, endian);
+                let len = (res.len() - len_index - 8) as u64;
+                res[len_index..(len_index + 8)].copy_from_slice(&len.to_le_bytes());
+            }
+            Value::Number(n) => {
+                res.reserve(17);
+                res.push(ValueKind::Number as u8);
+                res.extend_from_slice(&4_u64.to_le_bytes());
+                res.extend_from_slice(&endian.write_i64(*n))
+            }
+            Value::BigNumber(n) => {
+                res.reserve(25);
+                res.push(ValueKind::BigNumber as u8);
+                res.extend_from_slice(&16_u64.to_le_bytes());
+                res.extend_from_slice(&endian.write_i128(*n))
+            }
+            Value::Bytes(b) => {
+                res.reserve(b.len() + 9);
+                res.push(ValueKind::Bytes as u8);
+                res.extend_from_slice(&(b.len() as u64).to_le_bytes());
+                res.extend_from_slice(b)
+            }
+            Value::String(s) => {
+                res.reserve(s.len() + 9);
+                res.push(ValueKind::String as u8);
+                res.extend_from_slice(&(s.len() as u64).to_le_bytes());
+                res.extend_from_slice(s.as_bytes())
+            }
+        }
+    }
+}
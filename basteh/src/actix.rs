@@ -0,0 +1,46 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, error::ErrorInternalServerError, web, FromRequest, HttpRequest};
+
+use crate::Basteh;
+
+/// Extract the [`Basteh`] registered in the app data, it can be registered either directly or
+/// wrapped in `web::Data`. Responds with an internal server error if none is registered.
+///
+/// ## Example
+/// ```rust
+/// use actix_web::{web, App, Error};
+/// use basteh::Basteh;
+///
+/// async fn index(store: Basteh) -> Result<String, Error> {
+///     let name = store.get::<String>("name").await.unwrap();
+///     Ok(name.unwrap_or_else(|| String::from("Violet")))
+/// }
+///
+/// fn make_app(store: Basteh) {
+///     let _app = App::new()
+///         .app_data(store)
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+impl FromRequest for Basteh {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let store = req.app_data::<Basteh>().or_else(|| {
+            req.app_data::<web::Data<Basteh>>()
+                .map(|data| data.get_ref())
+        });
+
+        match store {
+            Some(store) => ready(Ok(store.clone())),
+            None => {
+                log::error!(
+                    "Failed to extract Basteh, register it with App::app_data or wrap it in web::Data"
+                );
+                ready(Err(ErrorInternalServerError("Basteh is not configured")))
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::dev::{Mutation, OwnedValue, Value};
+use crate::error::{BastehError, Result};
+use crate::Basteh;
+
+/// The main methods of [`Basteh`](struct.Basteh.html) as a trait, so the code using the store can
+/// be generic over it and be tested with a mock instead of a real backend.
+///
+/// The methods have the same signatures and behavior as the ones of `Basteh`, except for the
+/// arguments being `Send`. The other methods of `Basteh` are not part of the trait, to keep the
+/// mocks short.
+///
+/// ## Example
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::convert::TryFrom;
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// use basteh::dev::{run_mutations, Mutation, OwnedValue, Value};
+/// use basteh::{BastehApi, BastehError, Result};
+///
+/// // The code under test only asks for the trait
+/// async fn count_visit(store: &impl BastehApi, page: &str) -> Result<i64> {
+///     store.mutate(page, |m| m.incr(1)).await
+/// }
+///
+/// // A mock keeping the values in a map, the methods the test doesn't need return an error
+/// #[derive(Default)]
+/// struct MockStore(Mutex<HashMap<Vec<u8>, OwnedValue>>);
+///
+/// #[async_trait::async_trait]
+/// impl BastehApi for MockStore {
+///     async fn keys(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+///         let keys = self.0.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+///         Ok(Box::new(keys.into_iter()))
+///     }
+///     async fn contains_key(&self, key: impl AsRef<[u8]> + Send) -> Result<bool> {
+///         Ok(self.0.lock().unwrap().contains_key(key.as_ref()))
+///     }
+///     async fn get<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+///     where
+///         T: TryFrom<OwnedValue> + Send,
+///         T::Error: Into<BastehError>,
+///     {
+///         let value = self.0.lock().unwrap().get(key.as_ref()).cloned();
+///         value.map(|v| T::try_from(v).map_err(Into::into)).transpose()
+///     }
+///     async fn set<'a>(
+///         &self,
+///         key: impl AsRef<[u8]> + Send,
+///         value: impl Into<Value<'a>> + Send,
+///     ) -> Result<()> {
+///         let value = value.into().into_owned();
+///         self.0.lock().unwrap().insert(key.as_ref().to_vec(), value);
+///         Ok(())
+///     }
+///     async fn set_expiring<'a>(
+///         &self,
+///         _: impl AsRef<[u8]> + Send,
+///         _: impl Into<Value<'a>> + Send,
+///         _: Duration,
+///     ) -> Result<()> {
+///         Err(BastehError::MethodNotSupported)
+///     }
+///     async fn remove<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+///     where
+///         T: TryFrom<OwnedValue> + Send,
+///         T::Error: Into<BastehError>,
+///     {
+///         let value = self.0.lock().unwrap().remove(key.as_ref());
+///         value.map(|v| T::try_from(v).map_err(Into::into)).transpose()
+///     }
+///     async fn expire(&self, _: impl AsRef<[u8]> + Send, _: Duration) -> Result<()> {
+///         Err(BastehError::MethodNotSupported)
+///     }
+///     async fn expiry(&self, _: impl AsRef<[u8]> + Send) -> Result<Option<Duration>> {
+///         Ok(None)
+///     }
+///     async fn persist(&self, _: impl AsRef<[u8]> + Send) -> Result<()> {
+///         Ok(())
+///     }
+///     async fn push<'a>(
+///         &self,
+///         _: impl AsRef<[u8]> + Send,
+///         _: impl Into<Value<'a>> + Send,
+///     ) -> Result<()> {
+///         Err(BastehError::MethodNotSupported)
+///     }
+///     async fn pop<T>(&self, _: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+///     where
+///         T: TryFrom<OwnedValue> + Send,
+///         T::Error: Into<BastehError>,
+///     {
+///         Err(BastehError::MethodNotSupported)
+///     }
+///     async fn get_range<T>(&self, _: impl AsRef<[u8]> + Send, _: i64, _: i64) -> Result<Vec<T>>
+///     where
+///         T: TryFrom<OwnedValue> + Send,
+///         T::Error: Into<BastehError>,
+///     {
+///         Err(BastehError::MethodNotSupported)
+///     }
+///     async fn mutate(
+///         &self,
+///         key: impl AsRef<[u8]> + Send,
+///         mutate_f: impl Fn(Mutation) -> Mutation + Send,
+///     ) -> Result<i64> {
+///         let mut map = self.0.lock().unwrap();
+///         let current = match map.get(key.as_ref()) {
+///             Some(OwnedValue::Number(n)) => *n,
+///             Some(value) => return Err(BastehError::NotANumber(value.kind())),
+///             None => 0,
+///         };
+///         let value = run_mutations(current, mutate_f(Mutation::new()))
+///             .ok_or(BastehError::InvalidNumber)?;
+///         map.insert(key.as_ref().to_vec(), OwnedValue::Number(value));
+///         Ok(value)
+///     }
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let store = MockStore::default();
+/// assert_eq!(count_visit(&store, "home").await.unwrap(), 1);
+/// assert_eq!(count_visit(&store, "home").await.unwrap(), 2);
+/// assert_eq!(store.get::<i64>("home").await.unwrap(), Some(2));
+///
+/// store.set("about", "text").await.unwrap();
+/// assert!(matches!(
+///     count_visit(&store, "about").await,
+///     Err(BastehError::NotANumber(_))
+/// ));
+/// # }
+/// ```
+#[async_trait::async_trait]
+pub trait BastehApi: Send + Sync {
+    /// Get all keys of the current scope, see [`Basteh::keys`](struct.Basteh.html#method.keys)
+    async fn keys(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>>;
+
+    /// Check if the key exists, see
+    /// [`Basteh::contains_key`](struct.Basteh.html#method.contains_key)
+    async fn contains_key(&self, key: impl AsRef<[u8]> + Send) -> Result<bool>;
+
+    /// Get the value of the key, see [`Basteh::get`](struct.Basteh.html#method.get)
+    async fn get<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>;
+
+    /// Set the value of the key, see [`Basteh::set`](struct.Basteh.html#method.set)
+    async fn set<'a>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        value: impl Into<Value<'a>> + Send,
+    ) -> Result<()>;
+
+    /// Set the value of the key and expire it after the duration, see
+    /// [`Basteh::set_expiring`](struct.Basteh.html#method.set_expiring)
+    async fn set_expiring<'a>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        value: impl Into<Value<'a>> + Send,
+        expires_in: Duration,
+    ) -> Result<()>;
+
+    /// Remove the key and return its value, see
+    /// [`Basteh::remove`](struct.Basteh.html#method.remove)
+    async fn remove<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>;
+
+    /// Expire the key after the duration, see [`Basteh::expire`](struct.Basteh.html#method.expire)
+    async fn expire(&self, key: impl AsRef<[u8]> + Send, expire_in: Duration) -> Result<()>;
+
+    /// Get the time left until the key expires, see
+    /// [`Basteh::expiry`](struct.Basteh.html#method.expiry)
+    async fn expiry(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<Duration>>;
+
+    /// Remove the expiry of the key, see [`Basteh::persist`](struct.Basteh.html#method.persist)
+    async fn persist(&self, key: impl AsRef<[u8]> + Send) -> Result<()>;
+
+    /// Push a value into the list of the key, see
+    /// [`Basteh::push`](struct.Basteh.html#method.push)
+    async fn push<'a>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        value: impl Into<Value<'a>> + Send,
+    ) -> Result<()>;
+
+    /// Pop the last value of the list of the key, see
+    /// [`Basteh::pop`](struct.Basteh.html#method.pop)
+    async fn pop<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>;
+
+    /// Get a range of the list of the key, see
+    /// [`Basteh::get_range`](struct.Basteh.html#method.get_range)
+    async fn get_range<T>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>;
+
+    /// Mutate the number of the key and return the result, see
+    /// [`Basteh::mutate`](struct.Basteh.html#method.mutate)
+    async fn mutate(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        mutate_f: impl Fn(Mutation) -> Mutation + Send,
+    ) -> Result<i64>;
+}
+
+#[async_trait::async_trait]
+impl BastehApi for Basteh {
+    async fn keys(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        Basteh::keys(self).await
+    }
+
+    async fn contains_key(&self, key: impl AsRef<[u8]> + Send) -> Result<bool> {
+        Basteh::contains_key(self, key).await
+    }
+
+    async fn get<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>,
+    {
+        Basteh::get(self, key).await
+    }
+
+    async fn set<'a>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        value: impl Into<Value<'a>> + Send,
+    ) -> Result<()> {
+        Basteh::set(self, key, value).await
+    }
+
+    async fn set_expiring<'a>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        value: impl Into<Value<'a>> + Send,
+        expires_in: Duration,
+    ) -> Result<()> {
+        Basteh::set_expiring(self, key, value, expires_in).await
+    }
+
+    async fn remove<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>,
+    {
+        Basteh::remove(self, key).await
+    }
+
+    async fn expire(&self, key: impl AsRef<[u8]> + Send, expire_in: Duration) -> Result<()> {
+        Basteh::expire(self, key, expire_in).await
+    }
+
+    async fn expiry(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<Duration>> {
+        Basteh::expiry(self, key).await
+    }
+
+    async fn persist(&self, key: impl AsRef<[u8]> + Send) -> Result<()> {
+        Basteh::persist(self, key).await
+    }
+
+    async fn push<'a>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        value: impl Into<Value<'a>> + Send,
+    ) -> Result<()> {
+        Basteh::push(self, key, value).await
+    }
+
+    async fn pop<T>(&self, key: impl AsRef<[u8]> + Send) -> Result<Option<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>,
+    {
+        Basteh::pop(self, key).await
+    }
+
+    async fn get_range<T>(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>>
+    where
+        T: TryFrom<OwnedValue> + Send,
+        T::Error: Into<BastehError>,
+    {
+        Basteh::get_range(self, key, start, end).await
+    }
+
+    async fn mutate(
+        &self,
+        key: impl AsRef<[u8]> + Send,
+        mutate_f: impl Fn(Mutation) -> Mutation + Send,
+    ) -> Result<i64> {
+        Basteh::mutate(self, key, mutate_f).await
+    }
+}
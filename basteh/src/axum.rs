@@ -0,0 +1,40 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+use crate::Basteh;
+
+/// Extract [`Basteh`] from the router's state, a `Basteh` inserted in the request extensions
+/// (ex. by a middleware scoping the store per user) takes precedence over the one in the state.
+///
+/// ## Example
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use basteh::Basteh;
+///
+/// async fn index(store: Basteh) -> String {
+///     let name = store.get::<String>("name").await.unwrap();
+///     name.unwrap_or_else(|| String::from("Violet"))
+/// }
+///
+/// fn make_router(store: Basteh) -> Router {
+///     Router::new().route("/", get(index)).with_state(store)
+/// }
+/// ```
+impl<S> FromRequestParts<S> for Basteh
+where
+    Basteh: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Basteh>() {
+            Some(store) => Ok(store.clone()),
+            None => Ok(Basteh::from_ref(state)),
+        }
+    }
+}
@@ -1,11 +1,25 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::convert::{AsRef, TryFrom, TryInto};
+use std::future::Future;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use crate::dev::{BastehBuilder, OwnedValue, Provider};
+use bytes::Bytes;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::bloom::Bloom;
+#[cfg(feature = "serde")]
+use crate::codec::{Codec, JsonCodec, SerdeBasteh};
+use crate::dev::{BastehBuilder, BoxStream, OwnedValue, Provider, SnapshotEntry};
+use crate::entry::Entry;
 use crate::error::Result;
-use crate::mutation::Mutation;
-use crate::value::Value;
+use crate::mutation::{run_mutations, ListAction, ListMutation, Mutation};
+use crate::range::resolve_range_strict;
+use crate::readonly::ReadonlyBasteh;
+use crate::typed::TypedStore;
+use crate::value::{Value, ValueKind};
 use crate::BastehError;
 
 /// Takes the underlying backend and provides common methods for it
@@ -31,330 +45,2123 @@ use crate::BastehError;
 ///
 #[derive(Clone)]
 pub struct Basteh {
-    pub(crate) scope: Arc<str>,
+    pub(crate) scope: Arc<[u8]>,
     pub(crate) provider: Arc<dyn Provider>,
+    pub(crate) key_normalizer: Option<KeyNormalizer>,
+    pub(crate) max_value_size: Option<usize>,
+    pub(crate) max_key_size: Option<usize>,
+    pub(crate) strict_mutate: bool,
+    #[cfg(feature = "timeout")]
+    pub(crate) timeout: Option<Duration>,
 }
 
+/// The largest bit offset accepted by `set_bit` and `get_bit`, the same as redis
+const MAX_BIT_OFFSET: u64 = u32::MAX as u64;
+
+pub(crate) type KeyNormalizer = Arc<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
 impl Basteh {
     /// Returns the Basteh builder struct
     pub fn build() -> BastehBuilder {
         BastehBuilder::default()
     }
 
-    /// Return a new Basteh struct for the specified scope. Calling twice will just change
-    /// the current scope.
+    /// Return a new Basteh struct for the specified scope. Calling twice will just change
+    /// the current scope.
+    ///
+    /// Scopes may or may not be implemented as key prefixes but should provide
+    /// some guarantees to not mutate other scopes. Scopes are binary-safe, but some backends
+    /// (like redb) only accept valid utf-8 scopes.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// let cache = store.scope("cache");
+    /// cache.set("age", "60").await;
+    /// #     "set"
+    /// # }
+    /// ```
+    pub fn scope(&self, scope: impl AsRef<[u8]>) -> Basteh {
+        Basteh {
+            scope: scope.as_ref().into(),
+            ..self.clone()
+        }
+    }
+
+    /// Return a new Basteh struct which passes every key through the provided function before
+    /// handing it to the backend, it can be used to normalize keys(ex. lowercasing them).
+    /// Calling twice will replace the previous normalizer, the default is to use keys as is.
+    ///
+    /// The normalizer is applied to all the key-taking methods, and it's kept when changing scopes.
+    ///
+    /// ## Note
+    /// Only the normalized keys are stored, so `keys` will return the normalized version of them
+    /// and not the originals.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// let store = store.with_key_normalizer(|key| key.to_ascii_lowercase());
+    /// store.set("KEY", "value").await;
+    /// let val = store.get::<String>("key").await; // Some("value")
+    /// #     "set"
+    /// # }
+    /// ```
+    pub fn with_key_normalizer<F>(&self, normalizer: F) -> Basteh
+    where
+        F: Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static,
+    {
+        Basteh {
+            key_normalizer: Some(Arc::new(normalizer)),
+            ..self.clone()
+        }
+    }
+
+    /// Return a new Basteh struct which fails every operation taking longer than `timeout` with
+    /// [`BastehError::Timeout`](enum.BastehError.html#variant.Timeout). Calling twice will replace
+    /// the previous timeout, the default is to wait as long as the backend takes.
+    ///
+    /// The timeout is applied to each call to the backend and it's kept when changing scopes.
+    ///
+    /// ## Note
+    /// The operation is only abandoned on our side, so a timed-out write may still take effect
+    /// in the backend.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let store = store.with_timeout(Duration::from_millis(200));
+    /// match store.get::<String>("key").await {
+    ///     Err(BastehError::Timeout) => Ok("Backend is too slow".to_string()),
+    ///     res => Ok(res?.unwrap_or_default()),
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "timeout")]
+    pub fn with_timeout(&self, timeout: Duration) -> Basteh {
+        Basteh {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Return a read-only view of this Basteh, sharing the same provider, scope and key
+    /// normalizer, which only exposes the methods that don't change the store.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, ReadonlyBasteh};
+    /// #
+    /// fn make_handler_state(store: &Basteh) -> ReadonlyBasteh {
+    ///     store.scope("config").readonly()
+    /// }
+    /// ```
+    pub fn readonly(&self) -> ReadonlyBasteh {
+        ReadonlyBasteh::new(self.clone())
+    }
+
+    /// Return a map-like view of this Basteh, storing values of type `V` under keys of type `K`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, TypedStore};
+    /// #
+    /// fn make_counters(store: &Basteh) -> TypedStore<&'static str, i64> {
+    ///     store.scope("counters").typed()
+    /// }
+    /// ```
+    pub fn typed<K, V>(&self) -> TypedStore<K, V> {
+        TypedStore::new(self.clone())
+    }
+
+    /// Return the bloom filter named `name`, see [`Bloom`](struct.Bloom.html) for the false
+    /// positives and the backend requirements.
+    ///
+    /// Filters are stored in a scope reserved for them next to the current one, so they don't
+    /// show up in its keys.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<bool, BastehError> {
+    /// let seen = store.bloom("seen_urls");
+    /// seen.add("https://example.com").await?;
+    /// let maybe_seen = seen.maybe_contains("https://example.com").await?;
+    /// #     Ok(maybe_seen)
+    /// # }
+    /// ```
+    pub fn bloom(&self, name: impl AsRef<[u8]>) -> Bloom {
+        let key = self.normalized_key(name.as_ref()).into_owned();
+        Bloom::new(
+            self.scope([self.scope.as_ref(), b"\0__bloom__"].concat()),
+            key,
+        )
+    }
+
+    /// [`set_serde`](Basteh::set_serde) and [`get_serde`](Basteh::get_serde) for the default
+    /// JSON codec.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError, MessagePackCodec};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let store = store.with_codec(MessagePackCodec);
+    /// store.set_serde("points", &vec![20, 25, 30]).await?;
+    /// let points = store.get_serde::<Vec<u32>>("points").await?;
+    /// #     Ok(format!("{:?}", points))
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn with_codec<C: Codec>(&self, codec: C) -> SerdeBasteh<C> {
+        SerdeBasteh::new(self.clone(), codec)
+    }
+
+    #[inline]
+    pub(crate) async fn timed<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        #[cfg(feature = "timeout")]
+        if let Some(timeout) = self.timeout {
+            return tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| BastehError::Timeout)?;
+        }
+        fut.await
+    }
+
+    #[inline]
+    fn key<'k>(&self, key: &'k [u8]) -> Result<Cow<'k, [u8]>> {
+        let key = self.normalized_key(key);
+        self.check_key(&key)?;
+        Ok(key)
+    }
+
+    #[inline]
+    fn normalized_key<'k>(&self, key: &'k [u8]) -> Cow<'k, [u8]> {
+        match &self.key_normalizer {
+            Some(normalizer) => Cow::Owned(normalizer(key)),
+            None => Cow::Borrowed(key),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn check_key(&self, key: &[u8]) -> Result<()> {
+        match self.max_key_size {
+            Some(max) if key.len() > max => Err(BastehError::KeyTooLarge {
+                size: key.len(),
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn check_size(&self, size: usize) -> Result<()> {
+        match self.max_value_size {
+            Some(max) if size > max => Err(BastehError::ValueTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get all keys matching the requested pattern(not implemented yet)
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.keys().await;
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn keys(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.timed(self.provider.keys(self.scope.as_ref())).await
+    }
+
+    /// Get all keys of the scope as a stream, fetching them lazily in pages on the backends which
+    /// support it(sled, redb and redis), so big scopes are not held in memory at once.
+    ///
+    /// ## Note
+    /// The keys are not sorted, and keys written or removed while the stream is consumed may or
+    /// may not be included. On redis it's based on `SCAN`, which may return a key more than once.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use futures_util::TryStreamExt;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<usize, BastehError> {
+    /// let mut keys = store.keys_stream();
+    /// let mut count = 0;
+    /// while let Some(key) = keys.try_next().await? {
+    ///     count += 1;
+    /// }
+    /// #     Ok(count)
+    /// # }
+    /// ```
+    pub fn keys_stream(&self) -> BoxStream<'_, Result<Vec<u8>>> {
+        self.provider.keys_stream(self.scope.as_ref())
+    }
+
+    /// Get all the scopes holding at least one key, in no particular order. Unlike the other
+    /// methods it's not limited to the current scope.
+    ///
+    /// ## Note
+    /// On redis it scans the whole database, so it's slow on big databases and scopes mapped to
+    /// other databases are not included.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<usize, BastehError> {
+    /// let scopes = store.scopes().await?.count();
+    /// #     Ok(scopes)
+    /// # }
+    /// ```
+    pub async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.timed(self.provider.scopes()).await
+    }
+
+    /// Get all keys in lexicographic byte order, unlike `keys` the order is the same for all
+    /// backends.
+    ///
+    /// ## Note
+    /// Backends which don't keep their keys sorted(like redis and memory) collect all the keys of
+    /// the scope and sort them in memory before returning, so it can be expensive for large scopes.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<Vec<Vec<u8>>, BastehError> {
+    /// let first_page = store.keys_sorted().await?.take(20).collect();
+    /// #     Ok(first_page)
+    /// # }
+    /// ```
+    pub async fn keys_sorted(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.timed(self.provider.keys_sorted(self.scope.as_ref()))
+            .await
+    }
+
+    /// Get the approximate number of bytes used by the keys and values of the scope.
+    ///
+    /// ## Note
+    /// The result is only an estimate for capacity planning and differs between backends, sled
+    /// and redb report the encoded size of the entries, redis reports `MEMORY USAGE` of each key
+    /// including its own overhead and memory reports the size of the payloads.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let used = store.scope("cache").size_bytes().await?;
+    /// #     Ok(used.to_string())
+    /// # }
+    /// ```
+    pub async fn size_bytes(&self) -> Result<u64> {
+        self.timed(self.provider.size_bytes(self.scope.as_ref()))
+            .await
+    }
+
+    /// Remove the scope with all of its keys.
+    ///
+    /// ## Note
+    /// Unlike removing the keys one by one, backends keeping each scope in its own storage also
+    /// drop that storage(the tree in sled, the tables in redb) to reclaim its metadata. Redis and
+    /// memory have nothing to reclaim, so for them it only removes the keys. The scope can still
+    /// be used afterwards, its storage is created again on demand.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// store.scope("old_cache").drop_scope().await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn drop_scope(&self) -> Result<()> {
+        self.timed(self.provider.drop_scope(self.scope.as_ref()))
+            .await
+    }
+
+    /// Load all the keys of the current scope into the fast tier of a
+    /// [`layered`](struct.BastehBuilder.html#method.layered) store, so the first reads of them
+    /// don't wait for the slow tier. Other providers fail with
+    /// [`BastehError::MethodNotSupported`](enum.BastehError.html#variant.MethodNotSupported).
+    ///
+    /// ## Memory
+    /// The whole scope is copied into the fast tier and stays there until it's removed, so it
+    /// should fit in its memory, a memory backend holds all the values of the scope at once after
+    /// it. Keys are read one by one, so it's not bound by
+    /// [`with_timeout`](struct.Basteh.html#method.with_timeout) and values written to the slow
+    /// tier behind the store's back while it runs may be loaded in their old version.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn startup(store: Basteh) -> Result<(), BastehError> {
+    /// store.scope("settings").preload().await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn preload(&self) -> Result<()> {
+        self.provider.preload(self.scope.as_ref()).await
+    }
+
+    /// Saves a single key-value on store, use bytes for bytes
+    ///
+    /// ## Note
+    ///
+    /// Calling set operations twice on the same key, overwrites it's value and
+    /// clear the expiry on that key(if it exist).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// # use bytes::Bytes;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.set("name", "Violet").await; // String
+    /// store.set("age", 20).await; // Number
+    /// store.set("points", vec![20__u32, 25, 30]).await; // Lists
+    /// store.set("data", Bytes::from_static(b"123456")).await; // Or bytes
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn set<'a>(&self, key: impl AsRef<[u8]>, value: impl Into<Value<'a>>) -> Result<()> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(
+            self.provider
+                .set(self.scope.as_ref(), &self.key(key.as_ref())?, value),
+        )
+        .await
+    }
+
+    /// Same as [`set`](Basteh::set), but returns true if the key was inserted and false if an
+    /// existing value was replaced. The check and the write happen atomically, expired keys
+    /// count as missing.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// if store.upsert("profile:42", "Violet").await? {
+    ///     // A new profile was created
+    /// }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn upsert<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'a>>,
+    ) -> Result<bool> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(
+            self.provider
+                .upsert(self.scope.as_ref(), &self.key(key.as_ref())?, value),
+        )
+        .await
+    }
+
+    /// Stores the bytes as they are, bypassing basteh's encoding(value kinds and expiry flags),
+    /// so they can be read by other programs sharing the database.
+    ///
+    /// It's meant for interop and migrations. Sled and redb write the bytes without the kind byte
+    /// and the expiry flags, so they can only be read back with [`get_raw`](Basteh::get_raw).
+    /// Redis writes them as a plain string and memory, which has no raw representation, keeps
+    /// them as a bytes value.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// store.set_raw("legacy", b"written for another program").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn set_raw(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Result<()> {
+        self.check_size(value.as_ref().len())?;
+        self.timed(self.provider.set_raw(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            value.as_ref(),
+        ))
+        .await
+    }
+
+    /// Serialize the value as JSON and store it, use [`with_codec`](Basteh::with_codec) for
+    /// other formats.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::collections::HashMap;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// let mut points = HashMap::new();
+    /// points.insert("math", 20);
+    /// store.set_serde("points", &points).await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub async fn set_serde<T: Serialize + ?Sized>(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: &T,
+    ) -> Result<()> {
+        self.with_codec(JsonCodec).set_serde(key, value).await
+    }
+
+    /// Sets a value on store with expiry on the key
+    /// It should be prefered over calling set and expire as backends may define
+    /// a more optimized way to do both operations at once.
+    ///
+    /// Calling set operations twice on the same key, overwrites it's value and
+    /// clear the expiry on that key(if it exist).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// # use std::time::Duration;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.set_expiring("name", "Violet", Duration::from_secs(10)).await;
+    /// #     "set"
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    /// Beside the normal errors caused by the Basteh itself, it will result in error if
+    /// expiry provider is not set.(no_expiry is called on builder)
+    pub async fn set_expiring(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'_>>,
+        expires_in: Duration,
+    ) -> Result<()> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(self.provider.set_expiring(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            value,
+            expires_in,
+        ))
+        .await
+    }
+
+    /// Atomically replaces the value of the key with one expiring after `expires_in`, and returns
+    /// the value it replaced, or None if the key didn't exist. The previous expiry of the key is
+    /// dropped, even if the key was persistent.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::time::Duration;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Rotate the token, the old one is returned to be revoked
+    /// let old_token = store
+    ///     .swap_expiring::<String>("token", "new-token", Duration::from_secs(3600))
+    ///     .await?;
+    /// #     Ok(old_token.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn swap_expiring<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'_>>,
+        expires_in: Duration,
+    ) -> Result<Option<T>> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(self.provider.swap_expiring(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            value,
+            expires_in,
+        ))
+        .await?
+        .map(|value| convert_value(key.as_ref(), value))
+        .transpose()
+    }
+
+    /// Stores a generic serializable value on store, keeping the current expiry of the key.
+    ///
+    /// Unlike `set`, which makes the key persistent, the key will expire at the same time as
+    /// before the call. Keys which don't exist or are persistent stay persistent.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Update the session without extending it
+    /// store.set_keepttl("session", "new data").await?;
+    /// #     Ok("set".to_string())
+    /// # }
+    /// ```
+    pub async fn set_keepttl(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'_>>,
+    ) -> Result<()> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(
+            self.provider
+                .set_keepttl(self.scope.as_ref(), &self.key(key.as_ref())?, value),
+        )
+        .await
+    }
+
+    /// Stores a value only if `version` is greater than the version of the last versioned write
+    /// for this key, returns false if the write is rejected as stale.
+    ///
+    /// It can be used for last-write-wins conflict resolution between multiple writers, using a
+    /// logical clock or a timestamp as the version. Versions are kept even if the key is removed
+    /// or overwritten with the other methods.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<bool, BastehError> {
+    /// let written = store.set_versioned("name", "Violet", 10).await?;
+    /// #     Ok(written)
+    /// # }
+    /// ```
+    pub async fn set_versioned(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'_>>,
+        version: u64,
+    ) -> Result<bool> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(self.provider.set_versioned(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            value,
+            version,
+        ))
+        .await
+    }
+
+    /// Gets a single value from store(use `get_range` for lists)
+    ///
+    /// Values which can't be converted to `T` fail with
+    /// [`BastehError::Conversion`](enum.BastehError.html#variant.Conversion), naming the key, `T`
+    /// and the kind of the stored value.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let val = store.get::<String>("key").await?;
+    /// #     Ok(val.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn get<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &'a self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.timed(
+            self.provider
+                .get(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .map(|value| convert_value(key.as_ref(), value))
+        .transpose()
+    }
+
+    /// Gets the bytes of the key as they are stored, bypassing basteh's encoding, so values
+    /// written by other programs sharing the database can be read.
+    ///
+    /// Values written by basteh are returned with their encoding(ex. the kind byte in sled and
+    /// redb) on backends encoding their values, see [`set_raw`](Basteh::set_raw).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<Vec<u8>, BastehError> {
+    /// let bytes = store.get_raw("legacy").await?;
+    /// #     Ok(bytes.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn get_raw(&self, key: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>> {
+        self.timed(
+            self.provider
+                .get_raw(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
+    }
+
+    /// Gets the bytes or string value of the key as a stream of chunks, so big values can be
+    /// written elsewhere(ex. to a response) without holding all of them. Returns None if the key
+    /// doesn't exist, other kinds of values fail with
+    /// [`BastehError::TypeConversion`](enum.BastehError.html#variant.TypeConversion).
+    ///
+    /// The timeout set by `with_timeout` only applies to opening the stream.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// use futures_util::TryStreamExt;
+    ///
+    /// # async fn index(store: Basteh) -> Result<usize, BastehError> {
+    /// let mut size = 0;
+    /// if let Some(mut chunks) = store.get_stream("video").await? {
+    ///     while let Some(chunk) = chunks.try_next().await? {
+    ///         size += chunk.len();
+    ///     }
+    /// }
+    /// #     Ok(size)
+    /// # }
+    /// ```
+    ///
+    /// ## Note
+    /// Only redis reads the value in parts(with `GETRANGE`), so a value changed while it's being
+    /// streamed may be mixed up there, and numbers are streamed as their digits as redis stores
+    /// them as strings. Sled, redb and memory read the whole value and hold it
+    /// in memory until the stream is dropped, the same goes for the values of a
+    /// `CompressingProvider`.
+    pub async fn get_stream(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<BoxStream<'static, Result<Bytes>>>> {
+        self.timed(
+            self.provider
+                .get_stream(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
+    }
+
+    /// Get the kind of the value stored for the key without converting it, returns None if the
+    /// key doesn't exist.
+    ///
+    /// ## Note
+    /// Sled, redb and memory read the kind alone, redis stores numbers, strings and bytes the same
+    /// way so it fetches those values to tell them apart(the same way `get` does).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError, ValueKind};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// if store.value_kind("visitors").await? == Some(ValueKind::List) {
+    ///     let visitors = store.get_range::<String>("visitors", 0, -1).await?;
+    /// #     return Ok(visitors.join(","));
+    /// }
+    /// #     Ok(String::new())
+    /// # }
+    /// ```
+    pub async fn value_kind(&self, key: impl AsRef<[u8]>) -> Result<Option<ValueKind>> {
+        self.timed(
+            self.provider
+                .value_kind(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
+    }
+
+    /// Get a value stored as JSON and deserialize it, use [`with_codec`](Basteh::with_codec) for
+    /// other formats.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::collections::HashMap;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// let points = store.get_serde::<HashMap<String, u32>>("points").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub async fn get_serde<T: DeserializeOwned>(&self, key: impl AsRef<[u8]>) -> Result<Option<T>> {
+        self.with_codec(JsonCodec).get_serde(key).await
+    }
+
+    /// Get any value rendered as JSON, for debugging or API responses. Bytes are encoded as base64
+    /// strings, lists as arrays and maps as objects, see the `TryFrom<OwnedValue>` implementation
+    /// of `serde_json::Value`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let json = store.get_json("points").await?;
+    /// #     Ok(json.unwrap_or_default().to_string())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub async fn get_json(&self, key: impl AsRef<[u8]>) -> Result<Option<serde_json::Value>> {
+        self.get(key).await
+    }
+
+    /// Gets a single value by looking it up in each of the scopes in order, the value from the
+    /// first scope containing the key is returned and the rest are not checked.
+    ///
+    /// The scopes replace the current scope of the Basteh(like calling [`scope`](Basteh::scope)
+    /// for each of them), so the current scope is only checked if it's in the list.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Use the user's config and fall back to the defaults
+    /// let val = store
+    ///     .get_cascading::<String>("theme", ["user_config", "default_config"])
+    ///     .await?;
+    /// #     Ok(val.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn get_cascading<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        scopes: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Option<T>> {
+        let key = self.key(key.as_ref())?;
+        for scope in scopes {
+            if let Some(value) = self.timed(self.provider.get(scope.as_ref(), &key)).await? {
+                return value.try_into().map(Some).map_err(Into::into);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Gets a list of values from store, start/end works like redis with support for negative indexes
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<Vec<String>, BastehError> {
+    /// let val = store.get_range::<String>("key", 0, -1).await?;
+    /// #     Ok(val)
+    /// # }
+    /// ```
+    pub async fn get_range<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &'a self,
+        key: impl AsRef<[u8]>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>> {
+        self.timed(self.provider.get_range(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            start,
+            end,
+        ))
+        .await?
+        .into_iter()
+        .map(|v| v.try_into().map_err(Into::into))
+        .collect::<Result<Vec<_>>>()
+    }
+
+    /// Same as `get_range` for multiple lists, each request being a key with its start and end.
+    /// The result has the same order as `requests`, with empty vectors for the missing keys.
+    /// Backends which support it read all the ranges in a single roundtrip.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let lists = store
+    ///     .get_range_multiple::<String>([("recent", -10, -1), ("pinned", 0, -1)])
+    ///     .await?;
+    /// for list in lists {
+    ///     println!("{:?}", list);
+    /// }
+    /// #     Ok("loaded".to_string())
+    /// # }
+    /// ```
+    pub async fn get_range_multiple<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        requests: impl IntoIterator<Item = (impl AsRef<[u8]>, i64, i64)>,
+    ) -> Result<Vec<Vec<T>>> {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys = requests
+            .iter()
+            .map(|(key, _, _)| self.key(key.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        let requests = keys
+            .iter()
+            .zip(requests.iter())
+            .map(|(key, (_, start, end))| (key.as_ref(), *start, *end))
+            .collect::<Vec<_>>();
+        self.timed(
+            self.provider
+                .get_range_multiple(self.scope.as_ref(), &requests),
+        )
+        .await?
+        .into_iter()
+        .map(|list| {
+            list.into_iter()
+                .map(|v| v.try_into().map_err(Into::into))
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect()
+    }
+
+    /// Same as `get_range`, but returns the items in reverse order, from `end` back to `start`.
+    ///
+    /// ## Note
+    /// Unlike redis' `ZREVRANGE`, the indexes are not counted from the end of the list, they select
+    /// the same items as `get_range` does(so `-10, -1` is the last 10 items, newest first). The
+    /// range is reversed after it's fetched, so there's no benefit in striding inside the backend,
+    /// use [`Iterator::step_by`] on the result for that.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<Vec<String>, BastehError> {
+    /// // The last 10 items, starting from the last one
+    /// let latest = store.get_range_rev::<String>("key", -10, -1).await?;
+    /// // Every other item of the list, in reverse order
+    /// let every_other = store
+    ///     .get_range_rev::<String>("key", 0, -1)
+    ///     .await?
+    ///     .into_iter()
+    ///     .step_by(2)
+    ///     .collect::<Vec<_>>();
+    /// #     Ok(every_other)
+    /// # }
+    /// ```
+    pub async fn get_range_rev<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>> {
+        let mut list = self.get_range(key, start, end).await?;
+        list.reverse();
+        Ok(list)
+    }
+
+    /// Same as `get_range`, but fails with
+    /// [`BastehError::OutOfRange`](enum.BastehError.html#variant.OutOfRange) if `start` or `end`
+    /// fall outside the list instead of clamping them. Missing keys are treated as empty lists.
+    ///
+    /// ## Note
+    /// The whole list is fetched from the backend to check the indexes against its length.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<Vec<String>, BastehError> {
+    /// // Exactly the first 10 items, or an error if there are less
+    /// let val = store.get_range_strict::<String>("key", 0, 9).await?;
+    /// #     Ok(val)
+    /// # }
+    /// ```
+    pub async fn get_range_strict<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>> {
+        let list = self
+            .timed(
+                self.provider
+                    .get_range(self.scope.as_ref(), &self.key(key.as_ref())?, 0, -1),
+            )
+            .await?;
+        let (skip, take) =
+            resolve_range_strict(list.len(), start, end).ok_or(BastehError::OutOfRange)?;
+
+        list.into_iter()
+            .skip(skip)
+            .take(take)
+            .map(|v| v.try_into().map_err(Into::into))
+            .collect()
+    }
+
+    /// Same as `get` but it also gets expiry.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let val = store.get_expiring::<String>("key").await?;
+    /// #     Ok(val.map(|v|v.0).unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn get_expiring<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &'a self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<(T, Option<Duration>)>> {
+        self.timed(
+            self.provider
+                .get_expiring(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .map(|(v, e)| v.try_into().map(|v| (v, e)).map_err(Into::into))
+        .transpose()
+    }
+
+    /// Same as `get_expiring` for multiple keys, the result has the same order as `keys` with
+    /// None for the missing ones. Backends which support it read all the keys in a single
+    /// roundtrip.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let sessions = store
+    ///     .get_expiring_multiple::<String>(["session:1", "session:2"])
+    ///     .await?;
+    /// for (value, expiry) in sessions.into_iter().flatten() {
+    ///     println!("{} expires in {:?}", value, expiry);
+    /// }
+    /// #     Ok("loaded".to_string())
+    /// # }
+    /// ```
+    pub async fn get_expiring_multiple<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        keys: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Vec<Option<(T, Option<Duration>)>>> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys = keys
+            .iter()
+            .map(|key| self.key(key.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.timed(
+            self.provider
+                .get_expiring_multiple(self.scope.as_ref(), &keys),
+        )
+        .await?
+        .into_iter()
+        .map(|item| {
+            item.map(|(v, e)| v.try_into().map(|v| (v, e)).map_err(Into::into))
+                .transpose()
+        })
+        .collect()
+    }
+
+    /// Get all the keys of the scope with their values and the time left until they expire, in no
+    /// particular order, to be backed up or restored later with [`import`](Basteh::import).
+    ///
+    /// The keys are returned as they're stored, after the key normalizer if there is one.
+    ///
+    /// ## Consistency
+    /// * memory: the values are copied under a single lock, the expiries are read right after.
+    /// * redb: the whole scope is read in a single read transaction.
+    /// * sled: the keys are listed first and their values are read in a single transaction, so
+    ///   keys added in between may be missing.
+    /// * redis: best effort, the keys are listed with `SCAN` and read in batches, so keys written
+    ///   while the snapshot is taken may or may not be included. Lists and maps are not supported.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<usize, BastehError> {
+    /// let backup = store.scope("sessions").snapshot_scope().await?;
+    /// for (key, value, expiry) in &backup {
+    ///     println!("{:?} = {:?}, expires in {:?}", key, value, expiry);
+    /// }
+    /// #     Ok(backup.len())
+    /// # }
+    /// ```
+    pub async fn snapshot_scope(&self) -> Result<Vec<SnapshotEntry>> {
+        self.timed(self.provider.snapshot(self.scope.as_ref()))
+            .await
+    }
+
+    /// Write the entries of a [`snapshot_scope`](Basteh::snapshot_scope) into the scope, keys
+    /// with an expiry are set with `set_expiring` and the others with `set`.
+    ///
+    /// The keys are written as they are, bypassing the key normalizer, and the entries are
+    /// written one by one, so a failed import may be left half done. Existing keys which are not
+    /// in the entries are kept.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// let backup = store.scope("sessions").snapshot_scope().await?;
+    /// store.scope("sessions_copy").import(backup).await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn import(&self, entries: impl IntoIterator<Item = SnapshotEntry>) -> Result<()> {
+        for (key, value, expiry) in entries {
+            let value = value.as_value();
+            self.check_key(&key)?;
+            self.check_size(value.size())?;
+            match expiry {
+                Some(expiry) => {
+                    self.timed(
+                        self.provider
+                            .set_expiring(self.scope.as_ref(), &key, value, expiry),
+                    )
+                    .await?
+                }
+                None => {
+                    self.timed(self.provider.set(self.scope.as_ref(), &key, value))
+                        .await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the value and expires the key after `expire_in`, replacing its current expiry, like
+    /// redis' `GETEX`. Missing keys return None and are left untouched.
+    ///
+    /// It's meant for sliding expirations(ex. sessions refreshed on each read), backends which
+    /// support it do both in a single atomic operation.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::time::Duration;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let session = store
+    ///     .get_and_extend::<String>("session", Duration::from_secs(1800))
+    ///     .await?;
+    /// #     Ok(session.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn get_and_extend<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        expire_in: Duration,
+    ) -> Result<Option<T>> {
+        self.timed(self.provider.get_and_extend(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            expire_in,
+        ))
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+        .map_err(Into::into)
+    }
+
+    /// Push a single value into the list stored for this key
+    ///
+    /// Calling set operations twice on the same key, overwrites it's value and
+    /// clear the expiry on that key(if it exist).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.set("age", vec![10]).await;
+    /// store.set("name", "Violet").await;
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn push<'a>(&self, key: impl AsRef<[u8]>, value: impl Into<Value<'a>>) -> Result<()> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(
+            self.provider
+                .push(self.scope.as_ref(), &self.key(key.as_ref())?, value),
+        )
+        .await
+    }
+
+    /// Push a single value into the list stored for this key and drop the oldest items so at
+    /// most `max_len` of the newest ones are kept, in one atomic step. It's useful to keep a
+    /// bounded log of the recent events.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// store.push_capped("audit", "user logged in", 100).await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn push_capped<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'a>>,
+        max_len: u64,
+    ) -> Result<()> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(self.provider.push_capped(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            value,
+            max_len,
+        ))
+        .await
+    }
+
+    /// Push a single value into the list stored for this key and return the length of the list
+    /// after the push, without a separate call to read it.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// let queued = store.push_len("jobs", "send_email").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn push_len<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl Into<Value<'a>>,
+    ) -> Result<u64> {
+        let value = value.into();
+        self.check_size(value.size())?;
+        self.timed(self.provider.push_returning_len(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            value,
+        ))
+        .await
+    }
+
+    /// Apply several edits to the list stored for this key in one atomic step and return the
+    /// length of the list afterwards. The edits are applied in the order they're added, a
+    /// missing key is an empty list and the key is removed if the list ends up empty.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<u64, BastehError> {
+    /// // Keep the last 100 events
+    /// let len = store
+    ///     .mutate_list("events", |l| l.push("user logged in").trim(-100, -1))
+    ///     .await?;
+    /// #     Ok(len)
+    /// # }
+    /// ```
+    ///
+    /// ## Note
+    /// Redis runs the edits as a `MULTI` transaction of `RPUSH`, `RPOP` and `LTRIM` commands.
+    pub async fn mutate_list(
+        &self,
+        key: impl AsRef<[u8]>,
+        mutate_f: impl Fn(ListMutation) -> ListMutation,
+    ) -> Result<u64> {
+        let mutations = mutate_f(ListMutation::new());
+        for act in mutations.iter() {
+            if let ListAction::Push(value) = act {
+                self.check_size(value.as_value().size())?;
+            }
+        }
+        self.timed(self.provider.mutate_list(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            mutations,
+        ))
+        .await
+    }
+
+    /// Push all the given values into the list stored for this key
+    ///
+    /// Calling set operations twice on the same key, overwrites it's value and
+    /// clear the expiry on that key(if it exist).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.set("age", vec![10]).await;
+    /// store.set("name", "Violet").await;
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn push_mutiple<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        values: impl Iterator<Item = impl Into<Value<'a>>>,
+    ) -> Result<()> {
+        let values: Vec<Value> = values.map(|v| v.into()).collect();
+        self.check_size(values.iter().map(Value::size).sum())?;
+        self.timed(self.provider.push_multiple(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            values,
+        ))
+        .await
+    }
+
+    /// Replace the list stored for this key with the given values, it is the same as calling
+    /// `set` with a list but makes the intention clear.
+    ///
+    /// Calling set operations twice on the same key, overwrites it's value and
+    /// clear the expiry on that key(if it exist).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.set_list("names", ["Violet", "Ivy"]).await;
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn set_list<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        values: impl IntoIterator<Item = impl Into<Value<'a>>>,
+    ) -> Result<()> {
+        let value = Value::List(values.into_iter().map(Into::into).collect());
+        self.check_size(value.size())?;
+        self.timed(
+            self.provider
+                .set(self.scope.as_ref(), &self.key(key.as_ref())?, value),
+        )
+        .await
+    }
+
+    /// Append all the given values to the end of the list stored for this key, creating
+    /// the list if it doesn't exist. Unlike `set_list`, the existing items are kept.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.set_list("names", ["Violet"]).await;
+    /// store.extend_list("names", ["Ivy", "Rose"]).await; // ["Violet", "Ivy", "Rose"]
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn extend_list<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        values: impl IntoIterator<Item = impl Into<Value<'a>>>,
+    ) -> Result<()> {
+        let values: Vec<Value> = values.into_iter().map(Into::into).collect();
+        self.check_size(values.iter().map(Value::size).sum())?;
+        self.timed(self.provider.push_multiple(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            values,
+        ))
+        .await
+    }
+
+    /// Pop the last value from the list stored for this key
+    ///
+    /// The key is removed when its last item is popped, so `contains_key` returns false for
+    /// emptied lists on all the backends, the same as for keys that were never set. Items which
+    /// can't be converted to `T` fail with
+    /// [`BastehError::Conversion`](enum.BastehError.html#variant.Conversion), the same as `get`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let val = store.pop::<String>("key").await?;
+    /// #     Ok(val.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn pop<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &'a self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.timed(
+            self.provider
+                .pop(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .map(|value| convert_value(key.as_ref(), value))
+        .transpose()
+    }
+
+    /// Same as [`pop`](Basteh::pop), but waits up to `timeout` for a value to be pushed if the
+    /// list is empty, returning None if nothing was pushed in time. It's meant for the consumers
+    /// of work queues.
+    ///
+    /// Redis uses `BRPOP` on a dedicated connection, other backends may check the list
+    /// periodically while waiting. The timeout set by `with_timeout` doesn't apply to this
+    /// method, as waiting is expected.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::time::Duration;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let job = store.pop_blocking::<String>("jobs", Duration::from_secs(5)).await?;
+    /// #     Ok(job.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn pop_blocking<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        timeout: Duration,
+    ) -> Result<Option<T>> {
+        self.provider
+            .pop_blocking(self.scope.as_ref(), &self.key(key.as_ref())?, timeout)
+            .await?
+            .map(TryInto::try_into)
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Atomically pop the last value from the list stored for `src_key` and push it to the end of
+    /// the list stored for `dst_key`, returning the moved value.
+    ///
+    /// It returns None without changing anything if the source list is empty or doesn't exist,
+    /// and the destination list is created if it doesn't exist.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Move a job to the processing list, so it's not lost if the worker dies
+    /// let job = store.pop_push::<String>("jobs", "processing").await?;
+    /// #     Ok(job.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn pop_push<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        src_key: impl AsRef<[u8]>,
+        dst_key: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.timed(self.provider.pop_push(
+            self.scope.as_ref(),
+            &self.key(src_key.as_ref())?,
+            self.scope.as_ref(),
+            &self.key(dst_key.as_ref())?,
+        ))
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+        .map_err(Into::into)
+    }
+
+    /// Atomically remove the list stored for the key and return all of its items.
+    ///
+    /// It returns an empty vec if the key doesn't exist, and fails without removing anything if
+    /// the key has a value of another type.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Take all of the queued events at once, so none of them is handled twice
+    /// let events = store.list_drain::<String>("events").await?;
+    /// #     Ok(events.join(","))
+    /// # }
+    /// ```
+    pub async fn list_drain<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Vec<T>> {
+        self.timed(
+            self.provider
+                .list_drain(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .into_iter()
+        .map(|v| v.try_into().map_err(Into::into))
+        .collect()
+    }
+
+    /// Sets a field of the map stored for the key, creating the map if the key doesn't exist.
+    ///
+    /// Maps are meant for small records, they're stored as redis hashes on redis and as a single
+    /// value on the other backends. It fails with
+    /// [`BastehError::TypeConversion`](enum.BastehError.html#variant.TypeConversion) if the key
+    /// has a value of another kind.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// store.hset("user:1", "name", "Violet").await?;
+    /// store.hset("user:1", "age", 20).await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn hset<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        field: impl AsRef<[u8]>,
+        value: impl Into<Value<'a>>,
+    ) -> Result<()> {
+        let value = value.into();
+        self.check_size(field.as_ref().len() + value.size())?;
+        self.timed(self.provider.hset(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            field.as_ref(),
+            value,
+        ))
+        .await
+    }
+
+    /// Adds `delta` to the number in a field of the map stored for the key and returns the
+    /// result, creating the map and the field if they don't exist. It's useful to keep several
+    /// counters grouped under one key, ex. the stats of a user.
+    ///
+    /// It fails with [`BastehError::TypeConversion`](enum.BastehError.html#variant.TypeConversion)
+    /// if the key has a value of another kind and with
+    /// [`BastehError::NotANumber`](enum.BastehError.html#variant.NotANumber) if the field is not a
+    /// number.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<i64, BastehError> {
+    /// let visits = store.hincr("stats:user:1", "visits", 1).await?;
+    /// #     Ok(visits)
+    /// # }
+    /// ```
+    ///
+    /// ## Note
+    /// Redis uses `HINCRBY`, so a field holding a string of digits is incremented there but fails
+    /// with `NotANumber` on the other backends.
+    pub async fn hincr(
+        &self,
+        key: impl AsRef<[u8]>,
+        field: impl AsRef<[u8]>,
+        delta: i64,
+    ) -> Result<i64> {
+        self.timed(self.provider.hincr(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            field.as_ref(),
+            delta,
+        ))
+        .await
+    }
+
+    /// Gets a field of the map stored for the key, returns None if the key or the field doesn't
+    /// exist.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let name = store.hget::<String>("user:1", "name").await?;
+    /// #     Ok(name.unwrap_or_default())
+    /// # }
+    /// ```
+    pub async fn hget<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        field: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.timed(self.provider.hget(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            field.as_ref(),
+        ))
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+        .map_err(Into::into)
+    }
+
+    /// Removes a field of the map stored for the key and returns true if it existed, the key is
+    /// removed with its last field.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<bool, BastehError> {
+    /// let removed = store.hdel("user:1", "age").await?;
+    /// #     Ok(removed)
+    /// # }
+    /// ```
+    pub async fn hdel(&self, key: impl AsRef<[u8]>, field: impl AsRef<[u8]>) -> Result<bool> {
+        self.timed(self.provider.hdel(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            field.as_ref(),
+        ))
+        .await
+    }
+
+    /// Gets all the fields of the map stored for the key with their values, returns an empty vec
+    /// if the key doesn't exist.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<usize, BastehError> {
+    /// let fields = store.hgetall::<String>("user:1").await?;
+    /// #     Ok(fields.len())
+    /// # }
+    /// ```
+    pub async fn hgetall<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Vec<(Vec<u8>, T)>> {
+        self.timed(
+            self.provider
+                .hgetall(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .into_iter()
+        .map(|(field, v)| Ok((field, v.try_into().map_err(Into::into)?)))
+        .collect()
+    }
+
+    /// Set the bit at `offset` of the bytes stored for the key and return its previous value,
+    /// bits are counted from the most significant bit of the first byte and the bytes are
+    /// extended with zeros as needed, the same as redis' `SETBIT`.
+    ///
+    /// Offsets are limited to 2^32 - 1 like redis, larger ones fail with
+    /// [`BastehError::OutOfRange`](enum.BastehError.html#variant.OutOfRange). It fails with a type
+    /// conversion error if the key has a value other than bytes(note that redis can't tell bytes
+    /// and strings apart, so it accepts strings as well).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<bool, BastehError> {
+    /// // Mark the user 42 as active today
+    /// store.set_bit("active:2023-01-01", 42, true).await?;
+    /// let active = store.get_bit("active:2023-01-01", 42).await?;
+    /// #     Ok(active)
+    /// # }
+    /// ```
+    pub async fn set_bit(&self, key: impl AsRef<[u8]>, offset: u64, value: bool) -> Result<bool> {
+        if offset > MAX_BIT_OFFSET {
+            return Err(BastehError::OutOfRange);
+        }
+        self.timed(self.provider.set_bit(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            offset,
+            value,
+        ))
+        .await
+    }
+
+    /// Get the bit at `offset` of the bytes stored for the key, bits of missing keys and past the
+    /// end of the bytes are false. See [`set_bit`](Basteh::set_bit).
+    pub async fn get_bit(&self, key: impl AsRef<[u8]>, offset: u64) -> Result<bool> {
+        if offset > MAX_BIT_OFFSET {
+            return Err(BastehError::OutOfRange);
+        }
+        self.timed(
+            self.provider
+                .get_bit(self.scope.as_ref(), &self.key(key.as_ref())?, offset),
+        )
+        .await
+    }
+
+    /// Mutate a numeric value in the store, missing keys are treated as 0.
+    ///
+    /// Mutating a value which is not a number fails with
+    /// [`BastehError::NotANumber`](enum.BastehError.html#variant.NotANumber) and leaves the value
+    /// untouched, except on backends storing numbers as strings(like redis) which overwrite it
+    /// when the mutations start with `set`. Use
+    /// [`BastehBuilder::strict_mutate`](struct.BastehBuilder.html#method.strict_mutate) to fail
+    /// consistently on all backends. Note that such backends can't tell numeric strings apart from
+    /// numbers, so those are mutated as numbers.
+    ///
+    /// ## Note
+    /// The closure will called in-place(outside the backend store) and only the collected mutations
+    /// will be passed.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::Basteh;
+    /// # use std::cmp::Ordering;
+    /// #
+    /// # async fn index<'a>(store: Basteh) -> &'a str {
+    /// store.mutate("age", |v| v.incr(5)).await;
+    /// // Or conditionally set it to 100
+    /// store.mutate("age", |v| v.if_(Ordering::Greater, 100, |m| m.set(100))).await;
+    /// #     "set"
+    /// # }
+    /// ```
+    pub async fn mutate(
+        &self,
+        key: impl AsRef<[u8]>,
+        mutate_f: impl Fn(Mutation) -> Mutation,
+    ) -> Result<i64> {
+        self.timed(self.provider.mutate(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            mutate_f(Mutation::new()).strict(self.strict_mutate),
+        ))
+        .await
+    }
+
+    /// Same as [`mutate`](Basteh::mutate), but returns the value before the mutations as well, as
+    /// `(old, new)`. The old value of a missing key is 0.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let (old, new) = store.mutate_returning("age", |v| v.incr(5)).await?;
+    /// #     Ok(format!("{} -> {}", old, new))
+    /// # }
+    /// ```
+    pub async fn mutate_returning(
+        &self,
+        key: impl AsRef<[u8]>,
+        mutate_f: impl Fn(Mutation) -> Mutation,
+    ) -> Result<(i64, i64)> {
+        self.timed(self.provider.mutate_returning(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            mutate_f(Mutation::new()).strict(self.strict_mutate),
+        ))
+        .await
+    }
+
+    /// Increment a floating point value by `delta` and return the result, missing keys are
+    /// treated as 0.
     ///
-    /// Scopes may or may not be implemented as key prefixes but should provide
-    /// some guarantees to not mutate other scopes.
+    /// Values are stored as strings(like redis does), numbers and numeric strings are both
+    /// accepted and any other value fails with
+    /// [`BastehError::NotANumber`](enum.BastehError.html#variant.NotANumber) and is left untouched.
+    ///
+    /// ## Precision
+    /// The result is an `f64`, so it's only precise up to ~15 significant digits and repeated
+    /// increments accumulate rounding errors(ex. adding 0.1 ten times is not exactly 1.0). Redis
+    /// computes the result with a `long double` and may round slightly differently than the other
+    /// backends, compare results with a tolerance and use [`mutate`](Basteh::mutate) with
+    /// integers(ex. cents) when exact values are needed.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::Basteh;
     /// #
     /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// let cache = store.scope("cache");
-    /// cache.set("age", "60").await;
+    /// let balance = store.incr_float("balance", 2.5).await;
     /// #     "set"
     /// # }
     /// ```
-    pub fn scope(&self, scope: &str) -> Basteh {
-        Basteh {
-            scope: scope.into(),
-            provider: self.provider.clone(),
-        }
+    pub async fn incr_float(&self, key: impl AsRef<[u8]>, delta: f64) -> Result<f64> {
+        self.timed(
+            self.provider
+                .incr_float(self.scope.as_ref(), &self.key(key.as_ref())?, delta),
+        )
+        .await
     }
 
-    /// Get all keys matching the requested pattern(not implemented yet)
+    /// Increment a number by `delta` as a 128-bit integer and return the result, missing keys are
+    /// treated as 0 and the expiry of existing keys is kept.
+    ///
+    /// Results which fit in an `i64` are stored as numbers, and the others as
+    /// [`Value::BigNumber`](dev/enum.Value.html#variant.BigNumber), they can be read with
+    /// `get::<i128>`. Values which are not numbers fail with
+    /// [`BastehError::NotANumber`](enum.BastehError.html#variant.NotANumber) and results
+    /// overflowing an `i128` with
+    /// [`BastehError::InvalidNumber`](enum.BastehError.html#variant.InvalidNumber).
+    ///
+    /// ## Note
+    /// [`mutate`](Basteh::mutate) and the other `i64` methods fail with `NotANumber` once the value
+    /// has grown past `i64::MAX`. Redis stores big numbers as strings and adds them with a lua
+    /// script, so its own `INCRBY` can't be used on them either.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::Basteh;
     /// #
     /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// store.keys().await;
+    /// let total = store.incr_big("total_bytes", 1 << 70).await;
     /// #     "set"
     /// # }
     /// ```
-    pub async fn keys(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
-        self.provider.keys(self.scope.as_ref()).await
+    pub async fn incr_big(&self, key: impl AsRef<[u8]>, delta: i128) -> Result<i128> {
+        self.timed(
+            self.provider
+                .incr_big(self.scope.as_ref(), &self.key(key.as_ref())?, delta),
+        )
+        .await
     }
 
-    /// Saves a single key-value on store, use bytes for bytes
+    /// Store the number only if it compares to the current value as `ord` and return whether it
+    /// was stored, ex. `Ordering::Greater` only stores numbers greater than the current one.
     ///
-    /// ## Note
+    /// A missing key is stored for `Greater` and `Less` but not for `Equal`, and the expiry of an
+    /// existing key is kept. Values which are not numbers fail with
+    /// [`BastehError::NotANumber`](enum.BastehError.html#variant.NotANumber) and are left untouched.
     ///
-    /// Calling set operations twice on the same key, overwrites it's value and
-    /// clear the expiry on that key(if it exist).
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::cmp::Ordering;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Keep the highest number of concurrent users seen
+    /// let updated = store.set_if("peak_users", 42, Ordering::Greater).await?;
+    /// #     Ok(format!("{}", updated))
+    /// # }
+    /// ```
+    pub async fn set_if(&self, key: impl AsRef<[u8]>, value: i64, ord: Ordering) -> Result<bool> {
+        self.timed(
+            self.provider
+                .set_if(self.scope.as_ref(), &self.key(key.as_ref())?, value, ord),
+        )
+        .await
+    }
+
+    /// Add `delta` to the number stored for the key and return the result, setting the expiry
+    /// only when the key is created, ex. for fixed window rate limits.
+    ///
+    /// A missing key is set to `delta` expiring after `ttl`, while an existing one is incremented
+    /// and keeps its expiry, so later calls never extend it. Persistent keys get `ttl` as well.
+    /// Values which are not numbers fail with
+    /// [`BastehError::NotANumber`](enum.BastehError.html#variant.NotANumber) and are left untouched.
     ///
     /// ## Example
     /// ```rust
-    /// # use basteh::Basteh;
-    /// # use bytes::Bytes;
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::time::Duration;
     /// #
-    /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// store.set("name", "Violet").await; // String
-    /// store.set("age", 20).await; // Number
-    /// store.set("points", vec![20__u32, 25, 30]).await; // Lists
-    /// store.set("data", Bytes::from_static(b"123456")).await; // Or bytes
-    /// #     "set"
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // Count the requests of the client in the current minute
+    /// let requests = store
+    ///     .incr_with_ttl_floor("requests:127.0.0.1", 1, Duration::from_secs(60))
+    ///     .await?;
+    /// #     Ok(format!("{}", requests))
     /// # }
     /// ```
-    pub async fn set<'a>(&self, key: impl AsRef<[u8]>, value: impl Into<Value<'a>>) -> Result<()> {
-        self.provider
-            .set(self.scope.as_ref(), key.as_ref(), value.into())
-            .await
+    pub async fn incr_with_ttl_floor(
+        &self,
+        key: impl AsRef<[u8]>,
+        delta: i64,
+        ttl: Duration,
+    ) -> Result<i64> {
+        self.timed(self.provider.incr_with_ttl_floor(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            delta,
+            ttl,
+        ))
+        .await
     }
 
-    /// Sets a value on store with expiry on the key
-    /// It should be prefered over calling set and expire as backends may define
-    /// a more optimized way to do both operations at once.
+    /// Compute the result of the mutations against the current value without storing it, an
+    /// absent key is treated as 0 and is not created.
     ///
-    /// Calling set operations twice on the same key, overwrites it's value and
-    /// clear the expiry on that key(if it exist).
+    /// ## Note
+    /// The value is read and mutated outside the backend store, so the result may differ from
+    /// what [`mutate`](Basteh::mutate) returns if the value is changed in the meantime.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::Basteh;
-    /// # use std::time::Duration;
     /// #
     /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// store.set_expiring("name", "Violet", Duration::from_secs(10)).await;
+    /// let next_age = store.preview_mutate("age", |v| v.incr(5)).await;
     /// #     "set"
     /// # }
     /// ```
-    ///
-    /// ## Errors
-    /// Beside the normal errors caused by the Basteh itself, it will result in error if
-    /// expiry provider is not set.(no_expiry is called on builder)
-    pub async fn set_expiring(
+    pub async fn preview_mutate(
         &self,
         key: impl AsRef<[u8]>,
-        value: impl Into<Value<'_>>,
-        expires_in: Duration,
-    ) -> Result<()> {
-        self.provider
-            .set_expiring(
-                self.scope.as_ref(),
-                key.as_ref().into(),
-                value.into(),
-                expires_in,
+        mutate_f: impl Fn(Mutation) -> Mutation,
+    ) -> Result<i64> {
+        let value = match self
+            .timed(
+                self.provider
+                    .get(self.scope.as_ref(), &self.key(key.as_ref())?),
             )
-            .await
+            .await?
+        {
+            Some(OwnedValue::Number(n)) => n,
+            Some(value) => return Err(BastehError::NotANumber(value.kind())),
+            None => 0,
+        };
+
+        run_mutations(value, mutate_f(Mutation::new())).ok_or(BastehError::InvalidNumber)
     }
 
-    /// Gets a single value from store(use `get_range` for lists)
+    /// Get an [`Entry`](struct.Entry.html) for the key, to read, insert or modify its value
+    /// in a single chain.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<i64, BastehError> {
+    /// let visits = store
+    ///     .entry("visits")
+    ///     .and_modify(|m| m.incr(1))
+    ///     .or_insert::<i64>(1)
+    ///     .await?;
+    /// #     Ok(visits)
+    /// # }
+    /// ```
+    pub fn entry(&self, key: impl AsRef<[u8]>) -> Entry<'_> {
+        Entry::new(self, key.as_ref().to_vec())
+    }
+
+    /// Removes a key value pair from store, returning the value if exist.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::{Basteh, BastehError};
     /// #
     /// # async fn index(store: Basteh) -> Result<String, BastehError> {
-    /// let val = store.get::<String>("key").await?;
-    /// #     Ok(val.unwrap_or_default())
+    /// store.remove::<String>("key").await?;
+    /// #     Ok("deleted".to_string())
     /// # }
     /// ```
-    pub async fn get<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
-        &'a self,
+    pub async fn remove<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
         key: impl AsRef<[u8]>,
     ) -> Result<Option<T>> {
-        self.provider
-            .get(self.scope.as_ref(), key.as_ref().into())
-            .await?
-            .map(TryInto::try_into)
-            .transpose()
-            .map_err(Into::into)
+        self.timed(
+            self.provider
+                .remove(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+        .map_err(Into::into)
     }
 
-    /// Gets a list of values from store, start/end works like redis with support for negative indexes
+    /// Removes a key value pair from store, returning whether it existed. Unlike
+    /// [`remove`](Self::remove), the value is not read or transferred from the backend, so it's
+    /// cheaper for large values which are discarded anyway.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::{Basteh, BastehError};
     /// #
-    /// # async fn index(store: Basteh) -> Result<Vec<String>, BastehError> {
-    /// let val = store.get_range::<String>("key", 0, -1).await?;
-    /// #     Ok(val)
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// if store.delete("report").await? {
+    ///     return Ok("deleted".to_string());
+    /// }
+    /// #     Ok("missing".to_string())
     /// # }
     /// ```
-    pub async fn get_range<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
-        &'a self,
-        key: impl AsRef<[u8]>,
-        start: i64,
-        end: i64,
-    ) -> Result<Vec<T>> {
-        self.provider
-            .get_range(self.scope.as_ref(), key.as_ref().into(), start, end)
-            .await?
-            .into_iter()
-            .map(|v| v.try_into().map_err(Into::into))
-            .collect::<Result<Vec<_>>>()
-            .map_err(Into::into)
+    pub async fn delete(&self, key: impl AsRef<[u8]>) -> Result<bool> {
+        self.timed(
+            self.provider
+                .delete(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
     }
 
-    /// Same as `get` but it also gets expiry.
+    /// Gets a value and removes it in a single atomic operation, like redis' `GETDEL`, it's useful
+    /// to consume one-shot values like tokens.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::{Basteh, BastehError};
     /// #
     /// # async fn index(store: Basteh) -> Result<String, BastehError> {
-    /// let val = store.get_expiring::<String>("key").await?;
-    /// #     Ok(val.map(|v|v.0).unwrap_or_default())
+    /// let token = store.get_del::<String>("reset_token").await?;
+    /// #     Ok(token.unwrap_or_default())
     /// # }
     /// ```
-    pub async fn get_expiring<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
-        &'a self,
+    pub async fn get_del<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
         key: impl AsRef<[u8]>,
-    ) -> Result<Option<(T, Option<Duration>)>> {
-        self.provider
-            .get_expiring(self.scope.as_ref(), key.as_ref().into())
-            .await?
-            .map(|(v, e)| v.try_into().map(|v| (v, e)).map_err(Into::into))
-            .transpose()
+    ) -> Result<Option<T>> {
+        self.timed(
+            self.provider
+                .get_del(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+        .map_err(Into::into)
     }
 
-    /// Push a single value into the list stored for this key
-    ///
-    /// Calling set operations twice on the same key, overwrites it's value and
-    /// clear the expiry on that key(if it exist).
+    /// Removes the key only if it still holds the expected value, and returns whether it was
+    /// removed. It's useful to release a lock or a claim only if it wasn't taken over by someone
+    /// else in the meantime.
     ///
     /// ## Example
     /// ```rust
-    /// # use basteh::Basteh;
+    /// # use basteh::{Basteh, BastehError};
     /// #
-    /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// store.set("age", vec![10]).await;
-    /// store.set("name", "Violet").await;
-    /// #     "set"
+    /// # async fn index(store: Basteh) -> Result<bool, BastehError> {
+    /// let released = store.remove_if("job_lock", "worker-1").await?;
+    /// #     Ok(released)
     /// # }
     /// ```
-    pub async fn push<'a>(&self, key: impl AsRef<[u8]>, value: impl Into<Value<'a>>) -> Result<()> {
-        self.provider
-            .push(self.scope.as_ref(), key.as_ref(), value.into())
-            .await
+    pub async fn remove_if<'a>(
+        &self,
+        key: impl AsRef<[u8]>,
+        expected: impl Into<Value<'a>>,
+    ) -> Result<bool> {
+        self.timed(self.provider.remove_if(
+            self.scope.as_ref(),
+            &self.key(key.as_ref())?,
+            expected.into(),
+        ))
+        .await
     }
 
-    /// Push all the given values into the list stored for this key
+    /// Removes all the keys of the scope matching a redis-like glob pattern and returns how many
+    /// were removed.
     ///
-    /// Calling set operations twice on the same key, overwrites it's value and
-    /// clear the expiry on that key(if it exist).
+    /// `*` matches any number of bytes, `?` a single byte, `[abc]`/`[a-z]`/`[^a]` a single byte
+    /// of the set and `\` escapes the next byte. The pattern is matched against the stored keys,
+    /// so it's not passed through the [key normalizer](Basteh::with_key_normalizer).
+    ///
+    /// ## Atomicity
+    /// The matching keys are found first and then removed, so it's not atomic against concurrent
+    /// writers, keys written in the meantime may or may not be removed.
     ///
     /// ## Example
     /// ```rust
-    /// # use basteh::Basteh;
+    /// # use basteh::{Basteh, BastehError};
     /// #
-    /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// store.set("age", vec![10]).await;
-    /// store.set("name", "Violet").await;
-    /// #     "set"
+    /// # async fn index(store: Basteh) -> Result<u64, BastehError> {
+    /// let removed = store.remove_matching("temp:*").await?;
+    /// #     Ok(removed)
     /// # }
     /// ```
-    pub async fn push_mutiple<'a>(
-        &self,
-        key: impl AsRef<[u8]>,
-        values: impl Iterator<Item = impl Into<Value<'a>>>,
-    ) -> Result<()> {
-        self.provider
-            .push_multiple(
-                self.scope.as_ref(),
-                key.as_ref(),
-                values.map(|v| v.into()).collect(),
-            )
+    pub async fn remove_matching(&self, pattern: &str) -> Result<u64> {
+        self.timed(self.provider.remove_matching(self.scope.as_ref(), pattern))
             .await
     }
 
-    /// Pop all the value from the list stored for this key
+    /// Checks if store contains a key.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::{Basteh, BastehError};
     /// #
     /// # async fn index(store: Basteh) -> Result<String, BastehError> {
-    /// let val = store.get::<String>("key").await?;
-    /// #     Ok(val.unwrap_or_default())
+    /// let exist = store.contains_key("key").await?;
+    /// #     Ok("deleted".to_string())
     /// # }
     /// ```
-    pub async fn pop<'a, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
-        &'a self,
-        key: impl AsRef<[u8]>,
-    ) -> Result<Option<T>> {
-        self.provider
-            .pop(self.scope.as_ref(), key.as_ref().into())
-            .await?
-            .map(TryInto::try_into)
-            .transpose()
-            .map_err(Into::into)
+    pub async fn contains_key(&self, key: impl AsRef<[u8]>) -> Result<bool> {
+        self.timed(
+            self.provider
+                .contains_key(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
     }
 
-    /// Mutate a numeric value in the store. It may overwrite the value if it's not a number.
+    /// Mark the key as recently used without reading its value, returns whether the key exists.
     ///
-    /// ## Note
-    /// The closure will called in-place(outside the backend store) and only the collected mutations
-    /// will be passed.
+    /// Backends evicting keys by recency(like redis with an LRU policy, or memory with a limit on
+    /// the number of keys) keep touched keys around longer, for the others it's the same as
+    /// `contains_key`.
     ///
     /// ## Example
     /// ```rust
-    /// # use basteh::Basteh;
-    /// # use std::cmp::Ordering;
+    /// # use basteh::{Basteh, BastehError};
     /// #
-    /// # async fn index<'a>(store: Basteh) -> &'a str {
-    /// store.mutate("age", |v| v.incr(5)).await;
-    /// // Or conditionally set it to 100
-    /// store.mutate("age", |v| v.if_(Ordering::Greater, 100, |m| m.set(100))).await;
-    /// #     "set"
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// // The page was served from the CDN, keep the cached copy warm
+    /// store.touch("page:/index").await?;
+    /// #     Ok("touched".to_string())
     /// # }
     /// ```
-    pub async fn mutate(
-        &self,
-        key: impl AsRef<[u8]>,
-        mutate_f: impl Fn(Mutation) -> Mutation,
-    ) -> Result<i64> {
-        self.provider
-            .mutate(
-                self.scope.as_ref(),
-                key.as_ref().into(),
-                mutate_f(Mutation::new()),
-            )
-            .await
+    pub async fn touch(&self, key: impl AsRef<[u8]>) -> Result<bool> {
+        self.timed(
+            self.provider
+                .touch(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
     }
 
-    /// Removes a key value pair from store, returning the value if exist.
+    /// Checks if store contains all the keys, it is true if no keys are given.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::{Basteh, BastehError};
     /// #
     /// # async fn index(store: Basteh) -> Result<String, BastehError> {
-    /// store.remove::<String>("key").await?;
-    /// #     Ok("deleted".to_string())
+    /// let exist = store.contains_all(["name", "age"]).await?;
+    /// #     Ok("checked".to_string())
     /// # }
     /// ```
-    pub async fn remove<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+    pub async fn contains_all(
         &self,
-        key: impl AsRef<[u8]>,
-    ) -> Result<Option<T>> {
-        self.provider
-            .remove(self.scope.as_ref(), key.as_ref().into())
-            .await?
-            .map(TryInto::try_into)
-            .transpose()
-            .map_err(Into::into)
+        keys: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<bool> {
+        Ok(self.exists_multiple(keys).await?.into_iter().all(|e| e))
     }
 
-    /// Checks if store contains a key.
+    /// Checks if store contains any of the keys, it is false if no keys are given.
     ///
     /// ## Example
     /// ```rust
     /// # use basteh::{Basteh, BastehError};
     /// #
     /// # async fn index(store: Basteh) -> Result<String, BastehError> {
-    /// let exist = store.contains_key("key").await?;
-    /// #     Ok("deleted".to_string())
+    /// let exist = store.contains_any(["name", "nickname"]).await?;
+    /// #     Ok("checked".to_string())
     /// # }
     /// ```
-    pub async fn contains_key(&self, key: impl AsRef<[u8]>) -> Result<bool> {
-        self.provider
-            .contains_key(self.scope.as_ref(), key.as_ref().into())
+    pub async fn contains_any(
+        &self,
+        keys: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<bool> {
+        Ok(self.exists_multiple(keys).await?.into_iter().any(|e| e))
+    }
+
+    async fn exists_multiple(
+        &self,
+        keys: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Vec<bool>> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys = keys
+            .iter()
+            .map(|key| self.key(key.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        let keys = keys.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.timed(self.provider.exists_multiple(self.scope.as_ref(), &keys))
             .await
     }
 
@@ -374,8 +2181,50 @@ impl Basteh {
     /// # }
     /// ```
     pub async fn expire(&self, key: impl AsRef<[u8]>, expire_in: Duration) -> Result<()> {
-        self.provider
-            .expire(self.scope.as_ref(), key.as_ref().into(), expire_in)
+        self.timed(
+            self.provider
+                .expire(self.scope.as_ref(), &self.key(key.as_ref())?, expire_in),
+        )
+        .await
+    }
+
+    /// Sets the expiry of several keys at once, each to its own duration. Like
+    /// [`expire`](Self::expire), it won't result in error if some of the keys don't exist.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// # use std::time::Duration;
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// store
+    ///     .expire_multiple([
+    ///         ("session", Duration::from_secs(3600)),
+    ///         ("csrf_token", Duration::from_secs(300)),
+    ///     ])
+    ///     .await?;
+    /// #     Ok("deleted".to_string())
+    /// # }
+    /// ```
+    pub async fn expire_multiple(
+        &self,
+        entries: impl IntoIterator<Item = (impl AsRef<[u8]>, Duration)>,
+    ) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let keys = entries
+            .iter()
+            .map(|(key, _)| self.key(key.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        let entries = keys
+            .iter()
+            .zip(entries.iter())
+            .map(|(key, (_, expire_in))| (key.as_ref(), *expire_in))
+            .collect::<Vec<_>>();
+        self.timed(self.provider.expire_multiple(self.scope.as_ref(), &entries))
             .await
     }
 
@@ -400,9 +2249,37 @@ impl Basteh {
     /// # }
     /// ```
     pub async fn expiry(&self, key: impl AsRef<[u8]>) -> Result<Option<Duration>> {
-        self.provider
-            .expiry(self.scope.as_ref(), key.as_ref().into())
-            .await
+        self.timed(
+            self.provider
+                .expiry(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
+    }
+
+    /// Gets the last time the value of the key was written, it will return none if the key
+    /// doesn't exist or the time it was written is not known.
+    ///
+    /// Changing the expiry of a key doesn't count as a write. It's only supported by the backends
+    /// which can record the write times, and is usually opt-in(ex. sled and redb backends'
+    /// `with_write_timestamps`).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// if let Some(modified) = store.modified_at("report").await? {
+    ///     println!("Report was last written at {:?}", modified);
+    /// }
+    /// #     Ok("checked".to_string())
+    /// # }
+    /// ```
+    pub async fn modified_at(&self, key: impl AsRef<[u8]>) -> Result<Option<SystemTime>> {
+        self.timed(
+            self.provider
+                .modified_at(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
     }
 
     /// Extends expiry for a key, it won't result in error if the key doesn't exist.
@@ -421,9 +2298,11 @@ impl Basteh {
     /// # }
     /// ```
     pub async fn extend(&self, key: impl AsRef<[u8]>, expire_in: Duration) -> Result<()> {
-        self.provider
-            .extend(self.scope.as_ref(), key.as_ref().into(), expire_in)
-            .await
+        self.timed(
+            self.provider
+                .extend(self.scope.as_ref(), &self.key(key.as_ref())?, expire_in),
+        )
+        .await
     }
 
     /// Clears expiry from the provided key, making it persistent.
@@ -441,8 +2320,28 @@ impl Basteh {
     /// # }
     /// ```
     pub async fn persist(&self, key: impl AsRef<[u8]>) -> Result<()> {
-        self.provider
-            .persist(self.scope.as_ref(), key.as_ref().into())
-            .await
+        self.timed(
+            self.provider
+                .persist(self.scope.as_ref(), &self.key(key.as_ref())?),
+        )
+        .await
     }
 }
+
+/// Convert the value read for the key, failed conversions are reported with the key, the type
+/// and the kind of the value
+fn convert_value<T>(key: &[u8], value: OwnedValue) -> Result<T>
+where
+    T: TryFrom<OwnedValue>,
+    T::Error: Into<BastehError>,
+{
+    let found = value.kind();
+    T::try_from(value).map_err(|err| match err.into() {
+        BastehError::TypeConversion => BastehError::Conversion {
+            key: String::from_utf8_lossy(key).into_owned(),
+            expected: std::any::type_name::<T>(),
+            found,
+        },
+        err => err,
+    })
+}
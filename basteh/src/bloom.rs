@@ -0,0 +1,211 @@
+use std::convert::TryFrom;
+use std::f64::consts::LN_2;
+
+use bytes::BytesMut;
+
+use crate::error::Result;
+use crate::Basteh;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// The expected number of items and the acceptable false positive rate of a bloom filter, which
+/// decide its size. See [`Basteh::bloom`](struct.Basteh.html#method.bloom).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomParams {
+    /// Number of the items the filter is expected to hold
+    pub capacity: u64,
+    /// Rate of the false positives while the filter holds up to `capacity` items, it should be
+    /// between 0 and 1
+    pub error_rate: f64,
+}
+
+impl Default for BloomParams {
+    /// 10,000 items with 1% of false positives, about 12KB of bits
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            error_rate: 0.01,
+        }
+    }
+}
+
+impl BloomParams {
+    /// Number of the bits of the filter
+    pub fn bits(&self) -> u64 {
+        let capacity = self.capacity.max(1) as f64;
+        let error_rate = self.error_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        ((-capacity * error_rate.ln()) / (LN_2 * LN_2))
+            .ceil()
+            .max(8.0) as u64
+    }
+
+    /// Number of the bits set for each item
+    pub fn hashes(&self) -> u32 {
+        let bits_per_item = self.bits() as f64 / self.capacity.max(1) as f64;
+        (bits_per_item * LN_2).round().clamp(1.0, 32.0) as u32
+    }
+
+    /// Offsets of the bits set for the item. They only depend on the item and the params, so the
+    /// filters can be persisted and shared between processes.
+    pub fn positions(&self, item: &[u8]) -> impl Iterator<Item = u64> {
+        let bits = self.bits();
+        // Double hashing, the second hash is made odd so it never gets stuck on a single bit
+        let first = fnv1a(item, FNV_OFFSET);
+        let second = fnv1a(item, first) | 1;
+        (0..u64::from(self.hashes()))
+            .map(move |i| first.wrapping_add(i.wrapping_mul(second)) % bits)
+    }
+}
+
+fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Read the bit at `offset` of the bytes, counting from the most significant bit of the first
+/// byte like redis' `GETBIT`. Bits past the end are 0.
+pub fn read_bit(bytes: &[u8], offset: u64) -> bool {
+    let mask = 0x80 >> (offset % 8);
+    usize::try_from(offset / 8)
+        .ok()
+        .and_then(|index| bytes.get(index))
+        .is_some_and(|byte| byte & mask != 0)
+}
+
+/// Write the bit at `offset` of the bytes and return its previous value, the bytes are extended
+/// with zeros if they're shorter, like redis' `SETBIT`.
+///
+/// ## Example
+/// ```rust
+/// use basteh::dev::{read_bit, write_bit};
+/// use bytes::BytesMut;
+///
+/// let mut bytes = BytesMut::new();
+/// assert!(!write_bit(&mut bytes, 9, true));
+/// assert_eq!(bytes.as_ref(), &[0x00, 0x40]);
+/// assert!(read_bit(&bytes, 9));
+/// assert!(!read_bit(&bytes, 100));
+/// ```
+pub fn write_bit(bytes: &mut BytesMut, offset: u64, value: bool) -> bool {
+    let index = (offset / 8) as usize;
+    let mask = 0x80 >> (offset % 8);
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+    let previous = bytes[index] & mask != 0;
+    if value {
+        bytes[index] |= mask;
+    } else {
+        bytes[index] &= !mask;
+    }
+    previous
+}
+
+/// A bloom filter stored in the [`Basteh`](struct.Basteh.html) it's made from, returned by
+/// [`Basteh::bloom`](struct.Basteh.html#method.bloom).
+///
+/// It answers "have I seen this item" without storing the items, in a fixed amount of space.
+/// [`maybe_contains`](Bloom::maybe_contains) never returns false for an added item, but it may
+/// return true for an item which was never added, with a rate close to the `error_rate` of the
+/// filter while it holds up to `capacity` items, and higher after that. Items can't be removed.
+///
+/// ## Note
+/// Redis uses the `BF.*` commands of the RedisBloom module if it's loaded, with `capacity` and
+/// `error_rate` passed to `BF.INSERT`, and falls back to `SETBIT`/`GETBIT` otherwise. The other
+/// backends store the bits as a bytes value, using [`set_bit`](struct.Basteh.html#method.set_bit)
+/// and [`get_bit`](struct.Basteh.html#method.get_bit). The two layouts are not compatible, so a
+/// redis server shouldn't start or stop loading the module while a filter is in use, and the
+/// params of a filter shouldn't be changed after items are added to it.
+#[derive(Clone)]
+pub struct Bloom {
+    store: Basteh,
+    key: Vec<u8>,
+    params: BloomParams,
+}
+
+impl Bloom {
+    pub(crate) fn new(store: Basteh, key: Vec<u8>) -> Self {
+        Self {
+            store,
+            key,
+            params: BloomParams::default(),
+        }
+    }
+
+    /// Size the filter for `capacity` items with `error_rate` false positives, defaults to
+    /// 10,000 items and 1%.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, Bloom};
+    /// #
+    /// fn make_filter(store: &Basteh) -> Bloom {
+    ///     store.bloom("seen_urls").with_params(1_000_000, 0.001)
+    /// }
+    /// ```
+    pub fn with_params(mut self, capacity: u64, error_rate: f64) -> Self {
+        self.params = BloomParams {
+            capacity,
+            error_rate,
+        };
+        self
+    }
+
+    /// The params the filter is sized with
+    pub fn params(&self) -> &BloomParams {
+        &self.params
+    }
+
+    /// Add an item to the filter
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<(), BastehError> {
+    /// store.bloom("seen_urls").add("https://example.com").await?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub async fn add(&self, item: impl AsRef<[u8]>) -> Result<()> {
+        self.store.check_key(&self.key)?;
+        self.store
+            .timed(self.store.provider.bloom_add(
+                self.store.scope.as_ref(),
+                &self.key,
+                item.as_ref(),
+                &self.params,
+            ))
+            .await
+    }
+
+    /// Returns false if the item was never added, and true if it was probably added.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<bool, BastehError> {
+    /// let seen = store
+    ///     .bloom("seen_urls")
+    ///     .maybe_contains("https://example.com")
+    ///     .await?;
+    /// #     Ok(seen)
+    /// # }
+    /// ```
+    pub async fn maybe_contains(&self, item: impl AsRef<[u8]>) -> Result<bool> {
+        self.store.check_key(&self.key)?;
+        self.store
+            .timed(self.store.provider.bloom_maybe_contains(
+                self.store.scope.as_ref(),
+                &self.key,
+                item.as_ref(),
+                &self.params,
+            ))
+            .await
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{dev::Provider, Basteh};
+use crate::{dev::Provider, Basteh, LayeredProvider};
 
 pub const GLOBAL_SCOPE: &str = "Basteh_GLOBAL_SCOPE";
 
@@ -16,6 +16,10 @@ pub const GLOBAL_SCOPE: &str = "Basteh_GLOBAL_SCOPE";
 #[derive(Default)]
 pub struct BastehBuilder<S = ()> {
     provider: Option<S>,
+    scope: Option<Arc<[u8]>>,
+    max_value_size: Option<usize>,
+    max_key_size: Option<usize>,
+    strict_mutate: bool,
 }
 
 impl BastehBuilder {
@@ -28,16 +32,84 @@ impl BastehBuilder {
     {
         BastehBuilder {
             provider: Some(provider),
+            scope: self.scope,
+            max_value_size: self.max_value_size,
+            max_key_size: self.max_key_size,
+            strict_mutate: self.strict_mutate,
         }
     }
+
+    #[must_use = "Builder must be used by calling finish"]
+    /// This method can be used to set a [`LayeredProvider`](../struct.LayeredProvider.html)
+    /// made of a fast provider in front of a slow one, it will overwrite the store.
+    pub fn layered<F, S>(self, fast: F, slow: S) -> BastehBuilder<LayeredProvider<F, S>>
+    where
+        F: Provider + 'static,
+        S: Provider + 'static,
+    {
+        self.provider(LayeredProvider::new(fast, slow))
+    }
+}
+
+impl<S> BastehBuilder<S> {
+    #[must_use = "Builder must be used by calling finish"]
+    /// Set the scope of the built [`Basteh`](../struct.Basteh.html) instead of
+    /// [`GLOBAL_SCOPE`](../constant.GLOBAL_SCOPE.html), the same as calling
+    /// [`scope`](../struct.Basteh.html#method.scope) on it.
+    pub fn default_scope(mut self, scope: impl AsRef<[u8]>) -> Self {
+        self.scope = Some(scope.as_ref().into());
+        self
+    }
+
+    #[must_use = "Builder must be used by calling finish"]
+    /// Reject values bigger than `bytes` with
+    /// [`BastehError::ValueTooLarge`](../enum.BastehError.html#variant.ValueTooLarge) before
+    /// they reach the backend, the default is to not limit the values.
+    ///
+    /// The limit is checked against the payload of each write, numbers take 8 bytes and lists
+    /// are the sum of their items, so pushing to a list only checks the pushed items.
+    pub fn max_value_size(mut self, bytes: usize) -> Self {
+        self.max_value_size = Some(bytes);
+        self
+    }
+
+    #[must_use = "Builder must be used by calling finish"]
+    /// Reject keys longer than `bytes` with
+    /// [`BastehError::KeyTooLarge`](../enum.BastehError.html#variant.KeyTooLarge) before they reach
+    /// the backend, the default is to not limit the keys.
+    ///
+    /// The limit is checked against the keys as they're sent to the backend, after the key
+    /// normalizer if there is one, the scope is not counted.
+    pub fn max_key_size(mut self, bytes: usize) -> Self {
+        self.max_key_size = Some(bytes);
+        self
+    }
+
+    #[must_use = "Builder must be used by calling finish"]
+    /// Make [`mutate`](../struct.Basteh.html#method.mutate) fail with
+    /// [`BastehError::NotANumber`](../enum.BastehError.html#variant.NotANumber) on every backend
+    /// when the existing value is not a number, the default is `false`.
+    ///
+    /// Without it, backends storing numbers as strings(like redis) overwrite such values when the
+    /// mutations start with `set`, the others fail the same way.
+    pub fn strict_mutate(mut self, strict: bool) -> Self {
+        self.strict_mutate = strict;
+        self
+    }
 }
 
 impl<S: Provider + 'static> BastehBuilder<S> {
     /// Build the Basteh
     pub fn finish(self) -> Basteh {
         Basteh {
-            scope: GLOBAL_SCOPE.into(),
+            scope: self.scope.unwrap_or_else(|| GLOBAL_SCOPE.as_bytes().into()),
             provider: Arc::new(self.provider.unwrap()),
+            key_normalizer: None,
+            max_value_size: self.max_value_size,
+            max_key_size: self.max_key_size,
+            strict_mutate: self.strict_mutate,
+            #[cfg(feature = "timeout")]
+            timeout: None,
         }
     }
 }
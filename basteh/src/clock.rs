@@ -0,0 +1,74 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+/// A source of the current time used by the providers for expiry, so the expiry logic can be
+/// tested without waiting for the real time to pass.
+pub trait Clock: Send + Sync {
+    /// Get the current time
+    fn now(&self) -> SystemTime;
+
+    /// Get the current time as seconds since the unix epoch
+    fn timestamp(&self) -> u64 {
+        self.now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// The real clock, reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock which only moves when it's told to, clones share the same time.
+///
+/// ## Example
+/// ```rust
+/// use std::time::Duration;
+/// use basteh::dev::{Clock, MockClock};
+///
+/// let clock = MockClock::default();
+/// let start = clock.timestamp();
+/// clock.advance(Duration::from_secs(10));
+/// assert_eq!(clock.timestamp(), start + 10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock(Arc<Mutex<SystemTime>>);
+
+impl MockClock {
+    /// Make a new clock starting at `now`
+    pub fn new(now: SystemTime) -> Self {
+        Self(Arc::new(Mutex::new(now)))
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+
+    /// Set the current time of the clock
+    pub fn set(&self, now: SystemTime) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+impl Default for MockClock {
+    /// Make a new clock starting at the current system time
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap()
+    }
+}
@@ -0,0 +1,118 @@
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{BastehError, Result};
+use crate::Basteh;
+
+/// A serialization format used by [`SerdeBasteh`](struct.SerdeBasteh.html) to turn values into
+/// bytes and back.
+///
+/// ## Example
+/// ```rust
+/// use basteh::{BastehError, Codec};
+/// use serde::{de::DeserializeOwned, Serialize};
+///
+/// struct PrettyJson;
+///
+/// impl Codec for PrettyJson {
+///     fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, BastehError> {
+///         serde_json::to_vec_pretty(value).map_err(BastehError::custom)
+///     }
+///
+///     fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, BastehError> {
+///         serde_json::from_slice(data).map_err(BastehError::custom)
+///     }
+/// }
+/// ```
+pub trait Codec {
+    /// Serialize the value into bytes
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>>;
+
+    /// Deserialize a value from the bytes written by [`encode`](Codec::encode)
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T>;
+}
+
+/// The default codec, storing values as JSON
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(BastehError::custom)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        serde_json::from_slice(data).map_err(BastehError::custom)
+    }
+}
+
+/// A codec storing values as MessagePack, structs are written as maps so fields can be added or
+/// reordered without breaking the stored values.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePackCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(BastehError::custom)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        rmp_serde::from_slice(data).map_err(BastehError::custom)
+    }
+}
+
+/// A view of a [`Basteh`](struct.Basteh.html) storing serializable values with the codec `C`,
+/// returned by [`Basteh::with_codec`](struct.Basteh.html#method.with_codec).
+///
+/// Values are stored as bytes, so they can be read back with `get::<Bytes>` as well.
+///
+/// ## Example
+/// ```rust
+/// # use basteh::{Basteh, BastehError, JsonCodec};
+/// # use serde::{Deserialize, Serialize};
+/// #
+/// #[derive(Serialize, Deserialize)]
+/// struct User {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// # async fn index(store: Basteh) -> Result<String, BastehError> {
+/// let users = store.scope("users").with_codec(JsonCodec);
+/// users.set_serde("violet", &User { name: "Violet".into(), age: 20 }).await?;
+/// let user = users.get_serde::<User>("violet").await?;
+/// #     Ok(user.map(|u| u.name).unwrap_or_default())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SerdeBasteh<C> {
+    store: Basteh,
+    codec: C,
+}
+
+impl<C: Codec> SerdeBasteh<C> {
+    pub(crate) fn new(store: Basteh, codec: C) -> Self {
+        Self { store, codec }
+    }
+
+    /// Serialize the value with the codec and store it
+    pub async fn set_serde<T: Serialize + ?Sized>(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: &T,
+    ) -> Result<()> {
+        let data = self.codec.encode(value)?;
+        self.store.set(key, Bytes::from(data)).await
+    }
+
+    /// Get the value and deserialize it with the codec
+    pub async fn get_serde<T: DeserializeOwned>(&self, key: impl AsRef<[u8]>) -> Result<Option<T>> {
+        self.store
+            .get::<Bytes>(key)
+            .await?
+            .map(|data| self.codec.decode(&data))
+            .transpose()
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    cmp::Ordering,
+    time::{Duration, SystemTime},
+};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    dev::{
+        BloomParams, BoxStream, Capabilities, ListMutation, Mutation, OwnedValue, Provider,
+        SnapshotEntry, Value, ValueKind,
+    },
+    error::{BastehError, Result},
+};
+
+// Header of the values written by CompressingProvider: magic, original kind, compressed flag
+const MAGIC: &[u8] = b"\xffBZ\x01";
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// A provider compressing large strings and bytes with lz4 before handing them to the inner
+/// provider and decompressing them on the way back.
+///
+/// Values larger than `threshold` bytes are compressed and stored as bytes tagged with a small
+/// header, so compressed and uncompressed values can live side by side in the same store.
+/// Numbers, lists and values smaller than the threshold are passed through untouched(except
+/// for bytes which happen to start with the header, which are tagged too).
+///
+/// ## Lists
+/// List values and their items are never compressed, `push`, `pop` and `get_range` work on the
+/// raw items as they're stored in the inner provider.
+///
+/// ## Example
+/// ```rust
+/// use basteh::{dev::Provider, Basteh, CompressingProvider};
+///
+/// fn make_basteh(provider: impl Provider + 'static) -> Basteh {
+///     let provider = CompressingProvider::new(provider, 1024);
+///     Basteh::build().provider(provider).finish()
+/// }
+/// ```
+pub struct CompressingProvider<P> {
+    provider: P,
+    threshold: usize,
+}
+
+impl<P> CompressingProvider<P> {
+    /// Create a new compressing provider, compressing values larger than `threshold` bytes
+    pub fn new(provider: P, threshold: usize) -> Self {
+        Self {
+            provider,
+            threshold,
+        }
+    }
+
+    fn encode<'a>(&self, value: Value<'a>) -> Value<'a> {
+        let (kind, data): (_, &[u8]) = match &value {
+            Value::String(s) if s.len() > self.threshold => (ValueKind::String, s.as_bytes()),
+            Value::Bytes(b) if b.len() > self.threshold || b.starts_with(MAGIC) => {
+                (ValueKind::Bytes, b.as_ref())
+            }
+            _ => return value,
+        };
+
+        let mut encoded = Vec::with_capacity(HEADER_LEN + data.len());
+        encoded.extend_from_slice(MAGIC);
+        encoded.push(kind as u8);
+        if data.len() > self.threshold {
+            encoded.push(1);
+            encoded.extend_from_slice(&lz4_flex::compress_prepend_size(data));
+        } else {
+            encoded.push(0);
+            encoded.extend_from_slice(data);
+        }
+
+        Value::Bytes(Bytes::from(encoded))
+    }
+}
+
+fn decode(value: OwnedValue) -> Result<OwnedValue> {
+    let data = match value {
+        OwnedValue::Bytes(ref b) if b.len() >= HEADER_LEN && b.starts_with(MAGIC) => b,
+        value => return Ok(value),
+    };
+
+    let kind = ValueKind::from_u8(data[MAGIC.len()]);
+    let payload = &data[HEADER_LEN..];
+    let payload = if data[MAGIC.len() + 1] == 1 {
+        lz4_flex::decompress_size_prepended(payload).map_err(BastehError::custom)?
+    } else {
+        payload.to_vec()
+    };
+
+    match kind {
+        Some(ValueKind::String) => String::from_utf8(payload)
+            .map(OwnedValue::String)
+            .map_err(BastehError::custom),
+        Some(ValueKind::Bytes) => Ok(OwnedValue::Bytes(BytesMut::from(&payload[..]))),
+        _ => Err(BastehError::TypeConversion),
+    }
+}
+
+// Kind of the value before encoding, without decompressing it
+fn decode_kind(value: &OwnedValue) -> ValueKind {
+    match value {
+        OwnedValue::Bytes(b) if b.len() >= HEADER_LEN && b.starts_with(MAGIC) => {
+            ValueKind::from_u8(b[MAGIC.len()]).unwrap_or(ValueKind::Bytes)
+        }
+        value => value.kind(),
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> Provider for CompressingProvider<P>
+where
+    P: Provider,
+{
+    fn capabilities(&self) -> Capabilities {
+        self.provider.capabilities()
+    }
+
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.provider.keys(scope).await
+    }
+
+    async fn keys_sorted(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.provider.keys_sorted(scope).await
+    }
+
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, Result<Vec<u8>>> {
+        self.provider.keys_stream(scope)
+    }
+
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.provider.scopes().await
+    }
+
+    async fn size_bytes(&self, scope: &[u8]) -> Result<u64> {
+        self.provider.size_bytes(scope).await
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> Result<()> {
+        self.provider.drop_scope(scope).await
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.provider.set(scope, key, self.encode(value)).await
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.provider
+            .set_keepttl(scope, key, self.encode(value))
+            .await
+    }
+
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<bool> {
+        self.provider.upsert(scope, key, self.encode(value)).await
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.provider.get(scope, key).await?.map(decode).transpose()
+    }
+
+    async fn value_kind(&self, scope: &[u8], key: &[u8]) -> Result<Option<ValueKind>> {
+        // Encoded values are stored as bytes, so their header should be checked
+        match self.provider.value_kind(scope, key).await? {
+            Some(ValueKind::Bytes) => Ok(self
+                .provider
+                .get(scope, key)
+                .await?
+                .map(|value| decode_kind(&value))),
+            kind => Ok(kind),
+        }
+    }
+
+    async fn get_range(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        self.provider.get_range(scope, key, start, end).await
+    }
+
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> Result<Vec<Vec<OwnedValue>>> {
+        self.provider.get_range_multiple(scope, requests).await
+    }
+
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.provider.push(scope, key, value).await
+    }
+
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        self.provider.push_multiple(scope, key, value).await
+    }
+
+    async fn push_capped(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        max_len: u64,
+    ) -> Result<()> {
+        self.provider.push_capped(scope, key, value, max_len).await
+    }
+
+    async fn push_returning_len(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<u64> {
+        self.provider.push_returning_len(scope, key, value).await
+    }
+
+    async fn mutate_list(&self, scope: &[u8], key: &[u8], mutations: ListMutation) -> Result<u64> {
+        self.provider.mutate_list(scope, key, mutations).await
+    }
+
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.provider.pop(scope, key).await
+    }
+
+    async fn pop_blocking(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        timeout: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        self.provider.pop_blocking(scope, key, timeout).await
+    }
+
+    async fn pop_push(
+        &self,
+        src_scope: &[u8],
+        src_key: &[u8],
+        dst_scope: &[u8],
+        dst_key: &[u8],
+    ) -> Result<Option<OwnedValue>> {
+        self.provider
+            .pop_push(src_scope, src_key, dst_scope, dst_key)
+            .await
+    }
+
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        self.provider.set_raw(scope, key, value).await
+    }
+
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.provider.get_raw(scope, key).await
+    }
+
+    async fn remove_if(&self, scope: &[u8], key: &[u8], expected: Value<'_>) -> Result<bool> {
+        // Compression is deterministic, so the stored value matches the encoded one
+        self.provider
+            .remove_if(scope, key, self.encode(expected))
+            .await
+    }
+
+    async fn remove_matching(&self, scope: &[u8], pattern: &str) -> Result<u64> {
+        self.provider.remove_matching(scope, pattern).await
+    }
+
+    async fn incr_float(&self, scope: &[u8], key: &[u8], delta: f64) -> Result<f64> {
+        self.provider.incr_float(scope, key, delta).await
+    }
+
+    async fn incr_big(&self, scope: &[u8], key: &[u8], delta: i128) -> Result<i128> {
+        self.provider.incr_big(scope, key, delta).await
+    }
+
+    async fn set_if(&self, scope: &[u8], key: &[u8], value: i64, ord: Ordering) -> Result<bool> {
+        self.provider.set_if(scope, key, value, ord).await
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        delta: i64,
+        ttl: Duration,
+    ) -> Result<i64> {
+        self.provider
+            .incr_with_ttl_floor(scope, key, delta, ttl)
+            .await
+    }
+
+    async fn list_drain(&self, scope: &[u8], key: &[u8]) -> Result<Vec<OwnedValue>> {
+        self.provider.list_drain(scope, key).await
+    }
+
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        self.provider.mutate(scope, key, mutations).await
+    }
+
+    async fn hset(&self, scope: &[u8], key: &[u8], field: &[u8], value: Value<'_>) -> Result<()> {
+        self.provider
+            .hset(scope, key, field, self.encode(value))
+            .await
+    }
+
+    async fn hincr(&self, scope: &[u8], key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        self.provider.hincr(scope, key, field, delta).await
+    }
+
+    async fn hget(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<Option<OwnedValue>> {
+        self.provider
+            .hget(scope, key, field)
+            .await?
+            .map(decode)
+            .transpose()
+    }
+
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<bool> {
+        self.provider.hdel(scope, key, field).await
+    }
+
+    async fn hgetall(&self, scope: &[u8], key: &[u8]) -> Result<Vec<(Vec<u8>, OwnedValue)>> {
+        self.provider
+            .hgetall(scope, key)
+            .await?
+            .into_iter()
+            .map(|(field, value)| Ok((field, decode(value)?)))
+            .collect()
+    }
+
+    // set_bit and get_bit are left to the default implementations, so the bits are written to
+    // the decompressed bytes. Bloom filters are only accessed through these methods, so they're
+    // stored uncompressed.
+    async fn bloom_add(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<()> {
+        self.provider.bloom_add(scope, key, item, params).await
+    }
+
+    async fn bloom_maybe_contains(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<bool> {
+        self.provider
+            .bloom_maybe_contains(scope, key, item, params)
+            .await
+    }
+
+    async fn mutate_returning(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: Mutation,
+    ) -> Result<(i64, i64)> {
+        self.provider.mutate_returning(scope, key, mutations).await
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.provider
+            .remove(scope, key)
+            .await?
+            .map(decode)
+            .transpose()
+    }
+
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        self.provider.delete(scope, key).await
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        self.provider.contains_key(scope, key).await
+    }
+
+    async fn touch(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        self.provider.touch(scope, key).await
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> Result<Vec<bool>> {
+        self.provider.exists_multiple(scope, keys).await
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        self.provider.persist(scope, key).await
+    }
+
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        self.provider.expire(scope, key, expire_in).await
+    }
+
+    async fn expire_multiple(&self, scope: &[u8], entries: &[(&[u8], Duration)]) -> Result<()> {
+        self.provider.expire_multiple(scope, entries).await
+    }
+
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
+        self.provider.expiry(scope, key).await
+    }
+
+    async fn modified_at(&self, scope: &[u8], key: &[u8]) -> Result<Option<SystemTime>> {
+        self.provider.modified_at(scope, key).await
+    }
+
+    async fn extend(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        self.provider.extend(scope, key, expire_in).await
+    }
+
+    async fn set_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> Result<()> {
+        self.provider
+            .set_expiring(scope, key, self.encode(value), expire_in)
+            .await
+    }
+
+    async fn swap_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        self.provider
+            .swap_expiring(scope, key, self.encode(value), expire_in)
+            .await?
+            .map(decode)
+            .transpose()
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> Result<bool> {
+        self.provider
+            .set_versioned(scope, key, self.encode(value), version)
+            .await
+    }
+
+    async fn get_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+    ) -> Result<Option<(OwnedValue, Option<Duration>)>> {
+        self.provider
+            .get_expiring(scope, key)
+            .await?
+            .map(|(value, expiry)| Ok((decode(value)?, expiry)))
+            .transpose()
+    }
+
+    async fn preload(&self, scope: &[u8]) -> Result<()> {
+        self.provider.preload(scope).await
+    }
+
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        self.provider
+            .get_expiring_multiple(scope, keys)
+            .await?
+            .into_iter()
+            .map(|item| {
+                item.map(|(value, expiry)| Ok((decode(value)?, expiry)))
+                    .transpose()
+            })
+            .collect()
+    }
+
+    async fn snapshot(&self, scope: &[u8]) -> Result<Vec<SnapshotEntry>> {
+        self.provider
+            .snapshot(scope)
+            .await?
+            .into_iter()
+            .map(|(key, value, expiry)| Ok((key, decode(value)?, expiry)))
+            .collect()
+    }
+
+    async fn get_and_extend(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        self.provider
+            .get_and_extend(scope, key, expire_in)
+            .await?
+            .map(decode)
+            .transpose()
+    }
+}
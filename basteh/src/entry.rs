@@ -0,0 +1,123 @@
+use std::convert::{TryFrom, TryInto};
+use std::future::Future;
+
+use crate::dev::{Mutation, OwnedValue, Value};
+use crate::error::{BastehError, Result};
+use crate::Basteh;
+
+type Modifier<'a> = Box<dyn Fn(Mutation) -> Mutation + Send + Sync + 'a>;
+
+/// A view into a single key of a [`Basteh`](struct.Basteh.html), returned by
+/// [`Basteh::entry`](struct.Basteh.html#method.entry).
+///
+/// ## Note
+/// Entries are a composition of `get`, `set` and `mutate` calls, so they're not atomic and the
+/// value may be changed by others between the calls.
+#[must_use = "Entry does nothing unless resolved by calling or_insert or or_insert_with"]
+pub struct Entry<'a> {
+    store: &'a Basteh,
+    key: Vec<u8>,
+    modifier: Option<Modifier<'a>>,
+}
+
+impl<'a> Entry<'a> {
+    pub(crate) fn new(store: &'a Basteh, key: Vec<u8>) -> Self {
+        Self {
+            store,
+            key,
+            modifier: None,
+        }
+    }
+
+    /// The key of this entry
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Mutate the value if it already exists, the mutation is applied when the entry is resolved
+    /// by [`or_insert`](Entry::or_insert) or [`or_insert_with`](Entry::or_insert_with), and the
+    /// existing value should be a number.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<i64, BastehError> {
+    /// // Start counting from 1 or increase the current count
+    /// let visits = store
+    ///     .entry("visits")
+    ///     .and_modify(|m| m.incr(1))
+    ///     .or_insert::<i64>(1)
+    ///     .await?;
+    /// #     Ok(visits)
+    /// # }
+    /// ```
+    pub fn and_modify(mut self, f: impl Fn(Mutation) -> Mutation + Send + Sync + 'a) -> Self {
+        self.modifier = Some(Box::new(f));
+        self
+    }
+
+    /// Return the value of the entry, inserting `default` if the key doesn't exist.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let name = store.entry("name").or_insert::<String>("Violet").await?;
+    /// #     Ok(name)
+    /// # }
+    /// ```
+    pub async fn or_insert<'v, T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        self,
+        default: impl Into<Value<'v>>,
+    ) -> Result<T> {
+        self.or_insert_with(|| async { default }).await
+    }
+
+    /// Return the value of the entry, inserting the result of `f` if the key doesn't exist.
+    /// The function is only called if the key doesn't exist.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use basteh::{Basteh, BastehError};
+    /// #
+    /// # async fn index(store: Basteh) -> Result<String, BastehError> {
+    /// let name = store
+    ///     .entry("name")
+    ///     .or_insert_with::<String, _, _, _>(|| async { String::from("Violet") })
+    ///     .await?;
+    /// #     Ok(name)
+    /// # }
+    /// ```
+    pub async fn or_insert_with<
+        'v,
+        T: TryFrom<OwnedValue, Error = impl Into<BastehError>>,
+        F,
+        Fut,
+        V,
+    >(
+        self,
+        f: F,
+    ) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+        V: Into<Value<'v>>,
+    {
+        let value = match self.store.get::<OwnedValue>(&self.key).await? {
+            Some(_) if self.modifier.is_some() => {
+                let modifier = self.modifier.unwrap();
+                OwnedValue::Number(self.store.mutate(&self.key, modifier).await?)
+            }
+            Some(value) => value,
+            None => {
+                let value = f().await.into().into_owned();
+                self.store.set(&self.key, value.as_value()).await?;
+                value
+            }
+        };
+
+        value.try_into().map_err(Into::into)
+    }
+}
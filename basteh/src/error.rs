@@ -2,6 +2,8 @@ use std::{convert::Infallible, error::Error};
 
 use thiserror::Error;
 
+use crate::dev::ValueKind;
+
 /// Error type that will be returned from all fallible methods of basteh.
 ///
 /// implementers should generally use Custom variant for their own errors.
@@ -13,9 +15,53 @@ pub enum BastehError {
     /// States that the retrieved number is invalid
     #[error("BastehError: Value is not a valid number or mutation will result in overflow")]
     InvalidNumber,
+    /// States that the stored value can't be used as a number, it carries the kind of the value
+    /// found under the key
+    #[error("BastehError: Expected a number but the key holds a value of kind {0:?}")]
+    NotANumber(ValueKind),
     /// States that the retrieved number is invalid
     #[error("BastehError: Invalid type requested from backend")]
     TypeConversion,
+    /// States that the value of the key can't be converted to the requested type, returned by
+    /// [`Basteh::get`](struct.Basteh.html#method.get) and
+    /// [`Basteh::pop`](struct.Basteh.html#method.pop) instead of `TypeConversion`. It carries the
+    /// key(lossily decoded as utf8), the name of the requested type and the kind of the value.
+    #[error("BastehError: Can't convert the value of key {key:?} from {found:?} to {expected}")]
+    Conversion {
+        key: String,
+        expected: &'static str,
+        found: ValueKind,
+    },
+    /// States that the requested indexes are out of the list's range, returned by
+    /// [`Basteh::get_range_strict`](struct.Basteh.html#method.get_range_strict)
+    #[error("BastehError: Index is out of the list's range")]
+    OutOfRange,
+    /// States that the backend didn't finish the operation in the time set by
+    /// [`Basteh::with_timeout`](struct.Basteh.html#method.with_timeout)
+    #[error("BastehError: Operation timed out")]
+    Timeout,
+    /// States that the value is bigger than the limit set by
+    /// [`BastehBuilder::max_value_size`](struct.BastehBuilder.html#method.max_value_size),
+    /// it carries the size of the value and the limit
+    #[error("BastehError: Value of {size} bytes exceeds the limit of {max} bytes")]
+    ValueTooLarge { size: usize, max: usize },
+    /// States that the key is longer than the limit set by
+    /// [`BastehBuilder::max_key_size`](struct.BastehBuilder.html#method.max_key_size), it
+    /// carries the size of the key and the limit
+    #[error("BastehError: Key of {size} bytes exceeds the limit of {max} bytes")]
+    KeyTooLarge { size: usize, max: usize },
+    /// States that a stored value doesn't match its checksum, the data is corrupted
+    #[error("BastehError: Stored value is corrupted")]
+    Corruption,
+    /// States that the scope or key can't be used as it collides with the names a backend uses for
+    /// its internal data(ex. the expiry tables of redb), it carries the name(lossily decoded as
+    /// utf8)
+    #[error("BastehError: {0:?} can't be used as a key or scope")]
+    InvalidKey(String),
+    /// States that the backend can't take requests anymore, ex. its worker threads are gone,
+    /// unlike the other errors it's not about the data and retrying won't help
+    #[error("BastehError: Backend is not available")]
+    BackendUnavailable,
     /// An error from the underlying backend
     #[error("BastehError: {:?}", self)]
     Custom(Box<dyn Error + Send>),
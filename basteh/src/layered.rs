@@ -0,0 +1,460 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures_util::TryStreamExt;
+
+use crate::{
+    dev::{
+        BloomParams, BoxStream, Capabilities, Mutation, OwnedValue, Provider, SnapshotEntry, Value,
+    },
+    error::{BastehError, Result},
+};
+
+/// A read-through provider combining a fast provider(ex. in memory) in front of a slow one
+/// (ex. a persistent database).
+///
+/// Reads are first tried against the fast provider, on a miss the value is read from the slow
+/// provider and is populated into the fast one. Writes go to both of them, slow provider first.
+///
+/// ## Supported operations
+/// Only `keys` and `keys_stream`(served by the slow provider), `get`, `set`, `remove`,
+/// `contains_key`, `exists_multiple` and `preload` are supported for now, the other
+/// operations(lists, mutations and expiry) return
+/// [`MethodNotSupported`](crate::BastehError::MethodNotSupported) as their semantics across two
+/// providers are not defined yet.
+///
+/// The writes built on top of a stored value(ex. `hset`, `set_bit`, `set_keepttl` or
+/// `set_versioned`) are applied to the slow provider alone, and the key is then dropped from the
+/// fast one, so the next read fetches the new value.
+///
+/// ## Negative caching
+/// By default every read of a missing key reaches the slow provider. With
+/// [`with_negative_ttl`](LayeredProvider::with_negative_ttl) the misses are remembered for a
+/// while, and reads of those keys return nothing without asking the slow provider again.
+///
+/// Writes through the layered provider forget the misses of their keys, but a key created in the
+/// slow provider by someone else(ex. another process sharing the database) keeps being reported
+/// as missing until its miss is older than the ttl.
+///
+/// ## Preloading
+/// [`Basteh::preload`](crate::Basteh::preload) copies a whole scope from the slow provider into
+/// the fast one, so its first reads are as fast as the next ones. The fast provider should have
+/// room for all of it.
+///
+/// ## Example
+/// ```rust
+/// use basteh::{dev::Provider, Basteh};
+///
+/// fn make_basteh(fast: impl Provider + 'static, slow: impl Provider + 'static) -> Basteh {
+///     Basteh::build().layered(fast, slow).finish()
+/// }
+/// ```
+pub struct LayeredProvider<F, S> {
+    fast: F,
+    slow: S,
+    negative_ttl: Option<Duration>,
+    misses: Mutex<HashMap<MissKey, Instant>>,
+}
+
+type MissKey = (Vec<u8>, Vec<u8>);
+
+// Misses older than the ttl are swept once there are more than this many of them
+const MAX_MISSES: usize = 1024;
+
+impl<F, S> LayeredProvider<F, S> {
+    pub fn new(fast: F, slow: S) -> Self {
+        Self {
+            fast,
+            slow,
+            negative_ttl: None,
+            misses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remember the keys missing from the slow provider for `ttl`, so reading them again in that
+    /// window doesn't reach the slow provider. See [negative caching](#negative-caching) for the
+    /// staleness it brings.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use basteh::{dev::Provider, Basteh, LayeredProvider};
+    ///
+    /// fn make_basteh(fast: impl Provider + 'static, slow: impl Provider + 'static) -> Basteh {
+    ///     let provider = LayeredProvider::new(fast, slow).with_negative_ttl(Duration::from_secs(5));
+    ///     Basteh::build().provider(provider).finish()
+    /// }
+    /// ```
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    fn is_known_missing(&self, scope: &[u8], key: &[u8]) -> bool {
+        let ttl = match self.negative_ttl {
+            Some(ttl) => ttl,
+            None => return false,
+        };
+        let mut misses = self.misses.lock().unwrap();
+        // The lookup needs owned keys, as tuples of slices can't borrow from the stored ones
+        let miss = (scope.to_vec(), key.to_vec());
+        match misses.get(&miss) {
+            Some(at) if at.elapsed() < ttl => true,
+            Some(_) => {
+                misses.remove(&miss);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn remember_missing(&self, scope: &[u8], key: &[u8]) {
+        let ttl = match self.negative_ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        let mut misses = self.misses.lock().unwrap();
+        if misses.len() >= MAX_MISSES {
+            misses.retain(|_, at| at.elapsed() < ttl);
+        }
+        misses.insert((scope.to_vec(), key.to_vec()), Instant::now());
+    }
+
+    fn forget_missing(&self, scope: &[u8], key: &[u8]) {
+        if self.negative_ttl.is_some() {
+            self.misses
+                .lock()
+                .unwrap()
+                .remove(&(scope.to_vec(), key.to_vec()));
+        }
+    }
+}
+
+impl<F: Provider, S> LayeredProvider<F, S> {
+    // Called after a key is written to the slow provider alone, so its next read isn't served
+    // from the stale copy or the miss
+    async fn invalidate(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        self.forget_missing(scope, key);
+        self.fast.delete(scope, key).await.map(|_| ())
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, S> Provider for LayeredProvider<F, S>
+where
+    F: Provider,
+    S: Provider,
+{
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            expiry: false,
+            mutations: false,
+            nested_lists: false,
+        }
+    }
+
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.slow.keys(scope).await
+    }
+
+    async fn keys_sorted(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.slow.keys_sorted(scope).await
+    }
+
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, Result<Vec<u8>>> {
+        self.slow.keys_stream(scope)
+    }
+
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.slow.scopes().await
+    }
+
+    async fn size_bytes(&self, scope: &[u8]) -> Result<u64> {
+        self.slow.size_bytes(scope).await
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> Result<()> {
+        self.slow.drop_scope(scope).await?;
+        self.misses
+            .lock()
+            .unwrap()
+            .retain(|(miss_scope, _), _| miss_scope != scope);
+        self.fast.drop_scope(scope).await
+    }
+
+    async fn preload(&self, scope: &[u8]) -> Result<()> {
+        let mut keys = self.slow.keys_stream(scope);
+        while let Some(key) = keys.try_next().await? {
+            // Keys removed since they were listed are skipped
+            if let Some(value) = self.slow.get(scope, &key).await? {
+                self.fast.set(scope, &key, value.as_value()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.slow.set(scope, key, value.clone()).await?;
+        self.forget_missing(scope, key);
+        self.fast.set(scope, key, value).await
+    }
+
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<bool> {
+        let inserted = self.slow.upsert(scope, key, value.clone()).await?;
+        self.forget_missing(scope, key);
+        self.fast.set(scope, key, value).await?;
+        Ok(inserted)
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.slow.set_keepttl(scope, key, value).await?;
+        self.invalidate(scope, key).await
+    }
+
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        self.slow.set_raw(scope, key, value).await?;
+        self.invalidate(scope, key).await
+    }
+
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> Result<Option<Vec<u8>>> {
+        // The fast tier keeps encoded values, raw bytes only live in the slow one
+        self.slow.get_raw(scope, key).await
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> Result<bool> {
+        let written = self.slow.set_versioned(scope, key, value, version).await?;
+        self.invalidate(scope, key).await?;
+        Ok(written)
+    }
+
+    async fn hset(&self, scope: &[u8], key: &[u8], field: &[u8], value: Value<'_>) -> Result<()> {
+        self.slow.hset(scope, key, field, value).await?;
+        self.invalidate(scope, key).await
+    }
+
+    async fn hincr(&self, scope: &[u8], key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        let result = self.slow.hincr(scope, key, field, delta).await?;
+        self.invalidate(scope, key).await?;
+        Ok(result)
+    }
+
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<bool> {
+        let removed = self.slow.hdel(scope, key, field).await?;
+        self.invalidate(scope, key).await?;
+        Ok(removed)
+    }
+
+    async fn set_bit(&self, scope: &[u8], key: &[u8], offset: u64, value: bool) -> Result<bool> {
+        let previous = self.slow.set_bit(scope, key, offset, value).await?;
+        self.invalidate(scope, key).await?;
+        Ok(previous)
+    }
+
+    async fn bloom_add(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<()> {
+        self.slow.bloom_add(scope, key, item, params).await?;
+        self.invalidate(scope, key).await
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        if let Some(value) = self.fast.get(scope, key).await? {
+            return Ok(Some(value));
+        }
+        if self.is_known_missing(scope, key) {
+            return Ok(None);
+        }
+
+        let value = self.slow.get(scope, key).await?;
+        if let Some(ref value) = value {
+            self.fast.set(scope, key, value.as_value()).await?;
+        } else {
+            self.remember_missing(scope, key);
+        }
+        Ok(value)
+    }
+
+    async fn get_range(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _start: i64,
+        _end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn push(&self, _scope: &[u8], _key: &[u8], _value: Value<'_>) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn push_multiple(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Vec<Value<'_>>,
+    ) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn pop(&self, _scope: &[u8], _key: &[u8]) -> Result<Option<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn mutate(&self, _scope: &[u8], _key: &[u8], _mutations: Mutation) -> Result<i64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = self.slow.remove(scope, key).await?;
+        self.fast.remove(scope, key).await?;
+        Ok(value)
+    }
+
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let deleted = self.slow.delete(scope, key).await?;
+        self.fast.delete(scope, key).await?;
+        Ok(deleted)
+    }
+
+    async fn get_del(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = self.slow.get_del(scope, key).await?;
+        self.fast.delete(scope, key).await?;
+        Ok(value)
+    }
+
+    async fn remove_matching(&self, scope: &[u8], pattern: &str) -> Result<u64> {
+        let removed = self.slow.remove_matching(scope, pattern).await?;
+        self.fast.remove_matching(scope, pattern).await?;
+        Ok(removed)
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        if self.fast.contains_key(scope, key).await? {
+            return Ok(true);
+        }
+        if self.is_known_missing(scope, key) {
+            return Ok(false);
+        }
+
+        let exists = self.slow.contains_key(scope, key).await?;
+        if !exists {
+            self.remember_missing(scope, key);
+        }
+        Ok(exists)
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> Result<Vec<bool>> {
+        let mut res = self.fast.exists_multiple(scope, keys).await?;
+
+        // Indexes of the keys which should be asked from the slow provider
+        let unknown = keys
+            .iter()
+            .zip(res.iter())
+            .enumerate()
+            .filter(|(_, (key, exists))| !**exists && !self.is_known_missing(scope, key))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            let missing = unknown.iter().map(|i| keys[*i]).collect::<Vec<_>>();
+            let found = self.slow.exists_multiple(scope, &missing).await?;
+            for (i, exists) in unknown.into_iter().zip(found) {
+                res[i] = exists;
+                if !exists {
+                    self.remember_missing(scope, keys[i]);
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    async fn persist(&self, _scope: &[u8], _key: &[u8]) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn expire(&self, _scope: &[u8], _key: &[u8], _expire_in: Duration) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn expire_multiple(&self, _scope: &[u8], _entries: &[(&[u8], Duration)]) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn expiry(&self, _scope: &[u8], _key: &[u8]) -> Result<Option<Duration>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn modified_at(&self, scope: &[u8], key: &[u8]) -> Result<Option<SystemTime>> {
+        self.slow.modified_at(scope, key).await
+    }
+
+    async fn extend(&self, _scope: &[u8], _key: &[u8], _expire_in: Duration) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn set_expiring(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Value<'_>,
+        _expire_in: Duration,
+    ) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn swap_expiring(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Value<'_>,
+        _expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _delta: i64,
+        _ttl: Duration,
+    ) -> Result<i64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn get_and_extend(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn get_expiring(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+    ) -> Result<Option<(OwnedValue, Option<Duration>)>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    async fn snapshot(&self, scope: &[u8]) -> Result<Vec<SnapshotEntry>> {
+        self.slow.snapshot(scope).await
+    }
+}
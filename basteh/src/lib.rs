@@ -1,25 +1,68 @@
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "actix-web")]
+mod actix;
+mod api;
+#[cfg(feature = "axum")]
+mod axum;
 mod basteh;
+mod bloom;
 mod builder;
+mod clock;
+#[cfg(feature = "serde")]
+mod codec;
+#[cfg(feature = "compression")]
+mod compression;
+mod entry;
 mod error;
+mod layered;
 mod mutation;
+mod paging;
+mod pattern;
 mod provider;
+mod range;
+mod readonly;
+pub mod session;
+mod typed;
 mod value;
+#[cfg(feature = "write_behind")]
+mod write_behind;
 
+pub use crate::api::BastehApi;
 pub use crate::basteh::Basteh;
-pub use crate::value::{OwnedValue, Value};
+pub use crate::bloom::Bloom;
+pub use crate::entry::Entry;
+pub use crate::readonly::ReadonlyBasteh;
+pub use crate::value::{OwnedValue, Value, ValueKind};
 pub use builder::GLOBAL_SCOPE;
+#[cfg(feature = "msgpack")]
+pub use codec::MessagePackCodec;
+#[cfg(feature = "serde")]
+pub use codec::{Codec, JsonCodec, SerdeBasteh};
+#[cfg(feature = "compression")]
+pub use compression::CompressingProvider;
 pub use error::{BastehError, Result};
+pub use layered::LayeredProvider;
+pub use typed::TypedStore;
+#[cfg(feature = "write_behind")]
+pub use write_behind::WriteBehindProvider;
 
 /// Set of traits and structs used for storage backend development
 pub mod dev {
+    pub use crate::bloom::{read_bit, write_bit, BloomParams};
     pub use crate::builder::BastehBuilder;
-    pub use crate::mutation::{Action, Mutation};
-    pub use crate::provider::Provider;
+    pub use crate::clock::{Clock, MockClock, SystemClock};
+    pub use crate::mutation::{
+        add_big, add_float, add_number, run_list_mutations, run_mutations, should_set, Action,
+        ListAction, ListMutation, Mutation,
+    };
+    pub use crate::paging::paged_stream;
+    pub use crate::pattern::glob_match;
+    pub use crate::provider::{Capabilities, Provider, SnapshotEntry, STREAM_CHUNK_SIZE};
+    pub use crate::range::{resolve_range, resolve_range_strict};
     pub use crate::value::{OwnedValue, Value, ValueKind};
+    pub use futures_util::stream::BoxStream;
 }
 
-#[doc(hidden)]
 #[cfg(feature = "test_utils")]
 pub mod test_utils;
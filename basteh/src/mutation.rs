@@ -1,5 +1,11 @@
 use std::cmp::Ordering;
 
+use crate::{
+    error::{BastehError, Result},
+    range::resolve_range,
+    value::{OwnedValue, Value, ValueKind},
+};
+
 #[derive(Debug)]
 pub enum Action {
     Set(i64),
@@ -11,18 +17,36 @@ pub enum Action {
     IfElse(Ordering, i64, Mutation, Mutation),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Mutation {
     actions: Vec<Action>,
+    strict: bool,
 }
 
 impl Mutation {
-    pub(crate) fn new() -> Self {
+    /// An empty list of mutations, it can be passed to the closures given to
+    /// [`Basteh::mutate`](crate::Basteh::mutate) to collect the mutations, for example in mocks of
+    /// [`BastehApi`](crate::BastehApi).
+    pub fn new() -> Self {
         Mutation {
             actions: Vec::new(),
+            strict: false,
         }
     }
 
+    /// Whether the mutations must fail with
+    /// [`BastehError::NotANumber`](crate::BastehError::NotANumber) if the existing value is not a
+    /// number, set by [`BastehBuilder::strict_mutate`](crate::dev::BastehBuilder::strict_mutate).
+    /// Providers which may otherwise overwrite such values should check it.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub(crate) fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
     pub fn set(mut self, val: i64) -> Self {
         self.actions.push(Action::Set(val));
         self
@@ -83,3 +107,201 @@ impl Mutation {
         self.actions.len()
     }
 }
+
+/// Run the mutations against the value locally, returns None if any of the operations overflows
+/// or divides by zero.
+pub fn run_mutations(mut value: i64, mutations: Mutation) -> Option<i64> {
+    for act in mutations.into_iter() {
+        match act {
+            Action::Set(rhs) => {
+                value = rhs;
+            }
+            Action::Incr(rhs) => {
+                value = value.checked_add(rhs)?;
+            }
+            Action::Decr(rhs) => {
+                value = value.checked_sub(rhs)?;
+            }
+            Action::Mul(rhs) => {
+                value = value.checked_mul(rhs)?;
+            }
+            Action::Div(rhs) => {
+                value = value.checked_div(rhs)?;
+            }
+            Action::If(ord, rhs, sub) => {
+                if value.cmp(&rhs) == ord {
+                    value = run_mutations(value, sub)?;
+                }
+            }
+            Action::IfElse(ord, rhs, sub, sub2) => {
+                if value.cmp(&rhs) == ord {
+                    value = run_mutations(value, sub)?;
+                } else {
+                    value = run_mutations(value, sub2)?;
+                }
+            }
+        }
+    }
+    Some(value)
+}
+
+/// Add `delta` to the float held by the value and return the result, used by providers
+/// implementing `incr_float`.
+///
+/// Floats are stored as strings(the same way redis does), numbers are converted and missing
+/// values are treated as 0. It fails with `NotANumber` if the value can't be read as a float and
+/// with `InvalidNumber` if the result is NaN or infinite.
+pub fn add_float(value: Option<Value<'_>>, delta: f64) -> Result<f64> {
+    let current = match value {
+        None => 0.0,
+        Some(Value::Number(n)) => n as f64,
+        Some(Value::BigNumber(n)) => n as f64,
+        Some(Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| BastehError::NotANumber(ValueKind::String))?,
+        Some(value) => return Err(BastehError::NotANumber(value.kind())),
+    };
+
+    let result = current + delta;
+    if result.is_finite() {
+        Ok(result)
+    } else {
+        Err(BastehError::InvalidNumber)
+    }
+}
+
+/// Add `delta` to the number held by the value and return the result, used by providers
+/// implementing `incr_big`.
+///
+/// Missing values are treated as 0 and both numbers and big numbers are accepted, the result
+/// should be stored as `OwnedValue::from(result)` so it's kept as a number while it fits in an `i64`.
+/// It fails with `NotANumber` for other values and with `InvalidNumber` if the result overflows
+/// an `i128`.
+///
+/// ## Example
+/// ```rust
+/// use basteh::dev::{add_big, OwnedValue, Value};
+///
+/// let result = add_big(Some(Value::Number(i64::MAX)), 1).unwrap();
+/// assert_eq!(result, i64::MAX as i128 + 1);
+/// assert_eq!(OwnedValue::from(result), OwnedValue::BigNumber(result));
+/// assert_eq!(OwnedValue::from(add_big(None, 5).unwrap()), OwnedValue::Number(5));
+/// ```
+pub fn add_big(value: Option<Value<'_>>, delta: i128) -> Result<i128> {
+    let current = match value {
+        None => 0,
+        Some(Value::Number(n)) => i128::from(n),
+        Some(Value::BigNumber(n)) => n,
+        Some(value) => return Err(BastehError::NotANumber(value.kind())),
+    };
+
+    current.checked_add(delta).ok_or(BastehError::InvalidNumber)
+}
+
+/// Add `delta` to the number held by the value and return the result, used by providers
+/// implementing `hincr`.
+///
+/// Missing values are treated as 0. It fails with `NotANumber` if the value is not a number and
+/// with `InvalidNumber` if it's a big number or the result overflows an `i64`.
+pub fn add_number(value: Option<Value<'_>>, delta: i64) -> Result<i64> {
+    match value {
+        None => Ok(delta),
+        Some(Value::Number(n)) => n.checked_add(delta).ok_or(BastehError::InvalidNumber),
+        Some(Value::BigNumber(_)) => Err(BastehError::InvalidNumber),
+        Some(value) => Err(BastehError::NotANumber(value.kind())),
+    }
+}
+
+/// Check whether the number `value` should replace the current one when compared as `ord`, used
+/// by providers implementing `set_if`.
+///
+/// It is replaced when `value.cmp(&current) == ord`, a missing value is treated as the extreme
+/// so it's replaced for `Greater` and `Less`, but never for `Equal`. It fails with `NotANumber`
+/// if the current value is not a number.
+pub fn should_set(current: Option<Value<'_>>, value: i64, ord: Ordering) -> Result<bool> {
+    match current {
+        None => Ok(ord != Ordering::Equal),
+        Some(Value::Number(n)) => Ok(value.cmp(&n) == ord),
+        Some(current) => Err(BastehError::NotANumber(current.kind())),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ListAction {
+    Push(OwnedValue),
+    Pop,
+    Trim(i64, i64),
+}
+
+/// Edits of a list applied together by [`Basteh::mutate_list`](crate::Basteh::mutate_list), in
+/// the order they're added.
+#[derive(Debug, Default)]
+pub struct ListMutation {
+    actions: Vec<ListAction>,
+}
+
+impl ListMutation {
+    pub fn new() -> Self {
+        ListMutation {
+            actions: Vec::new(),
+        }
+    }
+
+    /// Push the value to the end of the list
+    pub fn push<'a>(mut self, value: impl Into<Value<'a>>) -> Self {
+        self.actions
+            .push(ListAction::Push(value.into().into_owned()));
+        self
+    }
+
+    /// Remove the last item of the list, if there is any
+    pub fn pop(mut self) -> Self {
+        self.actions.push(ListAction::Pop);
+        self
+    }
+
+    /// Keep only the items between `start` and `end`(inclusive, negative ones counting from the
+    /// end of the list) like redis' `LTRIM`, ex. `trim(-100, -1)` keeps the last 100 items.
+    pub fn trim(mut self, start: i64, end: i64) -> Self {
+        self.actions.push(ListAction::Trim(start, end));
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ListAction> {
+        self.actions.iter()
+    }
+}
+
+/// Run the list mutations against the items of a list locally, used by providers implementing
+/// `mutate_list`. The mutations are only borrowed, so it can be retried(ex. in a transaction).
+///
+/// ## Example
+/// ```rust
+/// use basteh::dev::{run_list_mutations, ListMutation, OwnedValue};
+///
+/// let mut list = vec![OwnedValue::Number(1), OwnedValue::Number(2)];
+/// let mutations = ListMutation::new().push(3).pop().push(4).trim(-2, -1);
+/// run_list_mutations(&mut list, &mutations);
+/// assert_eq!(list, vec![OwnedValue::Number(2), OwnedValue::Number(4)]);
+/// ```
+pub fn run_list_mutations(list: &mut Vec<OwnedValue>, mutations: &ListMutation) {
+    for act in mutations.iter() {
+        match act {
+            ListAction::Push(value) => list.push(value.clone()),
+            ListAction::Pop => {
+                list.pop();
+            }
+            ListAction::Trim(start, end) => {
+                // An end before the start of the list empties it, like LTRIM
+                if *end < 0 && end.unsigned_abs() > list.len() as u64 {
+                    list.clear();
+                    continue;
+                }
+                let (skip, take) = resolve_range(list.len(), *start, *end);
+                list.truncate(skip.saturating_add(take));
+                list.drain(..skip.min(list.len()));
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::future::Future;
+
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+
+use crate::error::{BastehError, Result};
+
+enum Page<C> {
+    First,
+    Next(C),
+    Done,
+}
+
+/// Build a stream of keys fetched lazily in pages, used by providers implementing `keys_stream`.
+///
+/// `fetch` is called with the cursor returned with the previous page(None for the first one) and
+/// should return the keys of the page and the cursor of the next one, or None after the last page.
+/// Only a single page is held in memory at a time.
+pub fn paged_stream<'a, C, F, Fut>(fetch: F) -> BoxStream<'a, Result<Vec<u8>>>
+where
+    C: Send + 'a,
+    F: FnMut(Option<C>) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<(Vec<Vec<u8>>, Option<C>)>> + Send + 'a,
+{
+    stream::try_unfold((Page::First, fetch), |(page, mut fetch)| async move {
+        let cursor = match page {
+            Page::First => None,
+            Page::Next(cursor) => Some(cursor),
+            Page::Done => return Ok::<_, BastehError>(None),
+        };
+
+        let (keys, next) = fetch(cursor).await?;
+        let page = next.map_or(Page::Done, Page::Next);
+        Ok(Some((
+            stream::iter(keys.into_iter().map(Ok)),
+            (page, fetch),
+        )))
+    })
+    .try_flatten()
+    .boxed()
+}
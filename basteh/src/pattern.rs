@@ -0,0 +1,83 @@
+/// Check if the key matches a redis-like glob `pattern`.
+///
+/// `*` matches any number of bytes, `?` matches a single byte, `[abc]`, `[a-z]` and `[^a]` match
+/// a single byte in(or not in, with `^`) the set, and `\` escapes the next byte.
+pub fn glob_match(pattern: &[u8], key: &[u8]) -> bool {
+    let (mut p, mut k) = (0, 0);
+    // Where to resume after a mismatch, right after the last `*` and the key index it stopped at
+    let mut backtrack = None;
+
+    while k < key.len() {
+        if p < pattern.len() {
+            if pattern[p] == b'*' {
+                backtrack = Some((p + 1, k));
+                p += 1;
+                continue;
+            }
+            if let Some(len) = match_byte(&pattern[p..], key[k]) {
+                p += len;
+                k += 1;
+                continue;
+            }
+        }
+
+        // Let the last `*` match one more byte and try again
+        match backtrack {
+            Some((star_p, star_k)) => {
+                backtrack = Some((star_p, star_k + 1));
+                p = star_p;
+                k = star_k + 1;
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Match a byte against the first token of the pattern, returning the length of the token
+fn match_byte(pattern: &[u8], byte: u8) -> Option<usize> {
+    match pattern[0] {
+        b'?' => Some(1),
+        b'\\' if pattern.len() > 1 => (pattern[1] == byte).then_some(2),
+        b'[' => match_class(pattern, byte),
+        c => (c == byte).then_some(1),
+    }
+}
+
+fn match_class(pattern: &[u8], byte: u8) -> Option<usize> {
+    let mut i = 1;
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    loop {
+        match pattern.get(i) {
+            // Not a class without the closing bracket, so it's matched literally
+            None => return (byte == b'[').then_some(1),
+            Some(b']') => break,
+            Some(b'\\') if i + 1 < pattern.len() => {
+                matched |= pattern[i + 1] == byte;
+                i += 2;
+            }
+            Some(&start) if pattern.get(i + 1) == Some(&b'-') => match pattern.get(i + 2) {
+                Some(&end) if end != b']' => {
+                    matched |= start.min(end) <= byte && byte <= start.max(end);
+                    i += 3;
+                }
+                _ => {
+                    matched |= start == byte;
+                    i += 1;
+                }
+            },
+            Some(&c) => {
+                matched |= c == byte;
+                i += 1;
+            }
+        }
+    }
+
+    (matched != negate).then_some(i + 1)
+}
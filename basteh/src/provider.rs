@@ -1,68 +1,605 @@
-use std::time::Duration;
+use std::{
+    cmp::Ordering,
+    time::{Duration, SystemTime},
+};
 
-use crate::{dev::OwnedValue, error::Result, mutation::Mutation, value::Value};
+use bytes::{Bytes, BytesMut};
+use futures_util::stream::{self, BoxStream, StreamExt};
+
+use crate::{
+    bloom::{read_bit, write_bit, BloomParams},
+    dev::{OwnedValue, ValueKind},
+    error::{BastehError, Result},
+    mutation::{add_number, ListMutation, Mutation},
+    pattern::glob_match,
+    value::Value,
+};
+
+/// Size of the chunks of the values streamed by `get_stream`, unless the provider reads them in
+/// its own chunks
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A key of a scope with its value and the time left until it expires, as returned by
+/// `snapshot`
+pub type SnapshotEntry = (Vec<u8>, OwnedValue, Option<Duration>);
+
+/// Groups of methods which a provider may not support as a whole, the conformance suite in
+/// `test_utils` skips the tests of the unsupported ones. Everything is supported by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Keys can be set to expire, with `expire`, `set_expiring` and the likes
+    pub expiry: bool,
+    /// Numbers can be changed in place, with `mutate` and the likes
+    pub mutations: bool,
+    /// Lists can hold other lists as their items
+    pub nested_lists: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            expiry: true,
+            mutations: true,
+            nested_lists: true,
+        }
+    }
+}
 
 /// It is usefull for when store and expiry are implemented for the same struct,
 /// and should be implemented in those cases even if there can't be any optimization,
 /// as it will prevent some runtime checks for expiry validity.
 #[async_trait::async_trait]
 pub trait Provider: Send + Sync {
+    /// The groups of methods supported by the provider, providers which return
+    /// [`MethodNotSupported`](BastehError::MethodNotSupported) for any of them should override it.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
     /// Set a key-value pair, if the key already exist, value should be overwritten
-    async fn keys(&self, scope: &str) -> Result<Box<dyn Iterator<Item = Vec<u8>>>>;
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>>;
+
+    /// Get all the keys of the scope in lexicographic byte order. Providers keeping their keys
+    /// sorted should override this to return `keys` as is, by default all the keys are collected
+    /// and sorted in memory.
+    async fn keys_sorted(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        let mut keys = self.keys(scope).await?.collect::<Vec<_>>();
+        keys.sort_unstable();
+        Ok(Box::new(keys.into_iter()))
+    }
+
+    /// Get all the keys of the scope as a stream, in no particular order. Providers should fetch
+    /// the keys in pages(see [`paged_stream`](crate::dev::paged_stream)) so big scopes are not
+    /// held in memory at once, by default all the keys are collected with `keys`.
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, Result<Vec<u8>>> {
+        stream::once(async move { self.keys(scope).await.map(Iterator::collect::<Vec<_>>) })
+            .flat_map(|keys| match keys {
+                Ok(keys) => stream::iter(keys.into_iter().map(Ok)).left_stream(),
+                Err(err) => stream::once(async move { Err(err) }).right_stream(),
+            })
+            .boxed()
+    }
+
+    /// Get the scopes holding at least one key, in no particular order. Providers which can't
+    /// enumerate their scopes should leave it unsupported.
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Get the approximate number of bytes used by the keys and values of the scope, it's meant
+    /// for capacity planning so providers may report their encoded sizes or include their own
+    /// overhead.
+    async fn size_bytes(&self, _scope: &[u8]) -> Result<u64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Remove the scope with all of its keys, providers keeping each scope in its own storage(ex.
+    /// a tree or a table) should drop that storage as well. By default all the keys are removed
+    /// one by one.
+    async fn drop_scope(&self, scope: &[u8]) -> Result<()> {
+        let keys = self.keys(scope).await?.collect::<Vec<_>>();
+        for key in keys {
+            self.remove(scope, &key).await?;
+        }
+        Ok(())
+    }
+
+    /// Load all the keys of the scope into the cache of the provider, for the providers keeping
+    /// one in front of another storage(ex. [`LayeredProvider`](crate::LayeredProvider)). The
+    /// others have nothing to load, so it's unsupported by default.
+    async fn preload(&self, _scope: &[u8]) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
 
     /// Set a key-value pair, if the key already exist, value should be overwritten
-    async fn set(&self, scope: &str, key: &[u8], value: Value<'_>) -> Result<()>;
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()>;
+
+    /// Set a key-value pair keeping the current expiry of the key, if the key doesn't exist or
+    /// is persistent, it should be persistent after the call.
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        let expiry = self.expiry(scope, key).await?;
+        self.set(scope, key, value).await?;
+        match expiry {
+            Some(expiry) => self.expire(scope, key, expiry).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Atomically set a key-value pair like `set`, and return true if the key didn't exist before
+    /// or false if its value was replaced. Expired keys should count as missing.
+    async fn upsert(&self, _scope: &[u8], _key: &[u8], _value: Value<'_>) -> Result<bool> {
+        Err(BastehError::MethodNotSupported)
+    }
 
     /// Get a single value for specified key, it should return None if the value does not exist
-    async fn get(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>>;
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>>;
+
+    /// Get the bytes or string value of the key as a stream of chunks, it should return None if
+    /// the key doesn't exist and fail with a type conversion error for other kinds of values.
+    /// Providers which can read parts of a value should override it, by default the value is read
+    /// with `get` and split into chunks of [`STREAM_CHUNK_SIZE`](crate::dev::STREAM_CHUNK_SIZE).
+    async fn get_stream(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+    ) -> Result<Option<BoxStream<'static, Result<Bytes>>>> {
+        let bytes = match self.get(scope, key).await? {
+            Some(OwnedValue::Bytes(bytes)) => bytes.freeze(),
+            Some(OwnedValue::String(string)) => Bytes::from(string),
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => return Ok(None),
+        };
+
+        let chunks = (0..bytes.len())
+            .step_by(STREAM_CHUNK_SIZE)
+            .map(move |start| Ok(bytes.slice(start..bytes.len().min(start + STREAM_CHUNK_SIZE))));
+        Ok(Some(stream::iter(chunks).boxed()))
+    }
+
+    /// Set the bytes of the key as they are, without basteh's encoding, so they can be read by
+    /// programs not using basteh. By default they're stored as a bytes value with `set`,
+    /// providers encoding their values should override it.
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        self.set(scope, key, Value::Bytes(Bytes::copy_from_slice(value)))
+            .await
+    }
+
+    /// Get the bytes of the key as they are stored, without decoding them, it should return None
+    /// if the key doesn't exist. By default bytes and string values are returned as is and other
+    /// values fail with a type conversion error, providers encoding their values should override
+    /// it.
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self.get(scope, key).await? {
+            Some(OwnedValue::Bytes(bytes)) => Ok(Some(bytes.to_vec())),
+            Some(OwnedValue::String(string)) => Ok(Some(string.into_bytes())),
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the value for a key and expire it after `expire_in`, replacing its current expiry. It
+    /// should return None without changing anything if the key doesn't exist. By default it calls
+    /// `get` and `expire`, providers which can do both atomically should override it.
+    async fn get_and_extend(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        let val = self.get(scope, key).await?;
+        if val.is_some() {
+            self.expire(scope, key, expire_in).await?;
+        }
+        Ok(val)
+    }
+
+    /// Get the kind of the value for specified key, it should return None if the value does not
+    /// exist. By default the whole value is fetched, providers which can read the kind alone
+    /// should override it.
+    async fn value_kind(&self, scope: &[u8], key: &[u8]) -> Result<Option<ValueKind>> {
+        Ok(self.get(scope, key).await?.map(|value| value.kind()))
+    }
 
     /// Get a list of values for specified key, it should return an empty vector if the value does not exist
     async fn get_range(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         start: i64,
         end: i64,
     ) -> Result<Vec<OwnedValue>>;
 
+    /// Get a range of the list of each key, each request being a key with its start and end. The
+    /// result should have the same length and order as `requests`, with empty vectors for the
+    /// missing keys. Providers which can read multiple lists in a single roundtrip should override
+    /// this, by default it calls get_range for each request.
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> Result<Vec<Vec<OwnedValue>>> {
+        let mut res = Vec::with_capacity(requests.len());
+        for (key, start, end) in requests {
+            res.push(self.get_range(scope, key, *start, *end).await?);
+        }
+        Ok(res)
+    }
+
     /// Push a value into the list associated with this key, if the key has a value of
     /// another type, it should return error
-    async fn push(&self, scope: &str, key: &[u8], value: Value<'_>) -> Result<()>;
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()>;
 
     /// Push multiple values into the list associated with this key, if the key has a value of
     /// another type, it should return error
-    async fn push_multiple(&self, scope: &str, key: &[u8], value: Vec<Value<'_>>) -> Result<()>;
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()>;
 
     /// Pop a value from the list associated with this key, if the key has a value of
-    /// another type, it should return error
-    async fn pop(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>>;
+    /// another type, it should return error. Lists are removed when their last item is popped,
+    /// like redis does, so emptied lists can't be told apart from missing keys.
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>>;
+
+    /// Same as `pop`, but waits up to `timeout` for an item to be pushed if the list is empty or
+    /// doesn't exist, like redis' `BRPOP`. It should return None if nothing was pushed in time.
+    async fn pop_blocking(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _timeout: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically pop a value from the end of the source list and push it to the end of the
+    /// destination list, returning the moved value. It should return None without writing anything
+    /// if the source list is empty or doesn't exist, and return error if any of the keys has a
+    /// value of another type. The source is removed if it's emptied, the same as `pop`.
+    async fn pop_push(
+        &self,
+        _src_scope: &[u8],
+        _src_key: &[u8],
+        _dst_scope: &[u8],
+        _dst_key: &[u8],
+    ) -> Result<Option<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically remove the list associated with this key and return all of its items. It should
+    /// return an empty vec if the key doesn't exist, and return error without removing anything
+    /// if the key has a value of another type.
+    async fn list_drain(&self, _scope: &[u8], _key: &[u8]) -> Result<Vec<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically push a value into the list associated with this key and trim the list to its
+    /// last `max_len` items, like redis' `RPUSH` followed by `LTRIM`. It should return error if
+    /// the key has a value of another type. With a `max_len` of zero the list is emptied, so it's
+    /// removed.
+    async fn push_capped(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Value<'_>,
+        _max_len: u64,
+    ) -> Result<()> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Push a value into the list associated with this key and return the length of the list
+    /// after the push, like redis' `RPUSH`. The length should be read in the same atomic step as
+    /// the push, and it should return error if the key has a value of another type.
+    async fn push_returning_len(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Value<'_>,
+    ) -> Result<u64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Apply the list mutations to the list associated with this key in one atomic step and
+    /// return the length of the list afterwards. A missing key is an empty list, the key should
+    /// be removed if the list ends up empty, and it should return error if the key has a value of
+    /// another type.
+    async fn mutate_list(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _mutations: ListMutation,
+    ) -> Result<u64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Set a field of the map stored for the key, creating the map if the key doesn't exist and
+    /// keeping the expiry of the key. It should fail with a type conversion error if the key has
+    /// a value of another kind. By default the map is read with `get` and written back with
+    /// `set_keepttl`, providers which can do it atomically should override it.
+    async fn hset(&self, scope: &[u8], key: &[u8], field: &[u8], value: Value<'_>) -> Result<()> {
+        let mut map = match self.get(scope, key).await? {
+            Some(OwnedValue::Map(map)) => map,
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => Vec::new(),
+        };
+
+        let value = value.into_owned();
+        match map.iter_mut().find(|(f, _)| f == field) {
+            Some((_, v)) => *v = value,
+            None => map.push((field.to_vec(), value)),
+        }
+        self.set_keepttl(scope, key, OwnedValue::Map(map).as_value())
+            .await
+    }
+
+    /// Add `delta` to the number in a field of the map stored for the key and return the result,
+    /// like redis' `HINCRBY`. Missing keys and fields start at 0 and the expiry of the key is
+    /// kept. It should fail with a type conversion error if the key has a value of another kind,
+    /// and with `NotANumber` if the field is not a number(see
+    /// [`add_number`](crate::dev::add_number)). By default the map is read with `get` and written
+    /// back with `set_keepttl`, providers which can do it atomically should override it.
+    async fn hincr(&self, scope: &[u8], key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        let mut map = match self.get(scope, key).await? {
+            Some(OwnedValue::Map(map)) => map,
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => Vec::new(),
+        };
+
+        let result = match map.iter_mut().find(|(f, _)| f == field) {
+            Some((_, v)) => {
+                let result = add_number(Some(v.as_value()), delta)?;
+                *v = OwnedValue::Number(result);
+                result
+            }
+            None => {
+                map.push((field.to_vec(), OwnedValue::Number(delta)));
+                delta
+            }
+        };
+        self.set_keepttl(scope, key, OwnedValue::Map(map).as_value())
+            .await?;
+        Ok(result)
+    }
+
+    /// Get a field of the map stored for the key, it should return None if the key or the field
+    /// doesn't exist, and fail with a type conversion error if the key has a value of another
+    /// kind. By default the map is read with `get`.
+    async fn hget(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<Option<OwnedValue>> {
+        match self.get(scope, key).await? {
+            Some(OwnedValue::Map(map)) => {
+                Ok(map.into_iter().find(|(f, _)| f == field).map(|(_, v)| v))
+            }
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a field of the map stored for the key and return true if it existed, the key
+    /// should be removed with its last field. By default the map is read with `get` and written
+    /// back with `set_keepttl`, providers which can do it atomically should override it.
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<bool> {
+        let mut map = match self.get(scope, key).await? {
+            Some(OwnedValue::Map(map)) => map,
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => return Ok(false),
+        };
+
+        let len = map.len();
+        map.retain(|(f, _)| f != field);
+        if map.len() == len {
+            Ok(false)
+        } else if map.is_empty() {
+            self.remove(scope, key).await.map(|_| true)
+        } else {
+            self.set_keepttl(scope, key, OwnedValue::Map(map).as_value())
+                .await
+                .map(|_| true)
+        }
+    }
+
+    /// Get all the fields of the map stored for the key with their values, it should return an
+    /// empty vec if the key doesn't exist. By default the map is read with `get`.
+    async fn hgetall(&self, scope: &[u8], key: &[u8]) -> Result<Vec<(Vec<u8>, OwnedValue)>> {
+        match self.get(scope, key).await? {
+            Some(OwnedValue::Map(map)) => Ok(map),
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Set the bit at `offset` of the bytes stored for the key and return its previous value,
+    /// like redis' `SETBIT`(see [`write_bit`](crate::dev::write_bit)). Missing keys start as empty
+    /// bytes and the expiry of the key is kept. It should fail with a type conversion error if the
+    /// key has a value of another kind. By default the bytes are read with `get` and written back
+    /// with `set_keepttl`, providers which can do it atomically should override it.
+    async fn set_bit(&self, scope: &[u8], key: &[u8], offset: u64, value: bool) -> Result<bool> {
+        let mut bytes = match self.get(scope, key).await? {
+            Some(OwnedValue::Bytes(bytes)) => bytes,
+            Some(_) => return Err(BastehError::TypeConversion),
+            None => BytesMut::new(),
+        };
+
+        let previous = write_bit(&mut bytes, offset, value);
+        self.set_keepttl(scope, key, Value::Bytes(bytes.freeze()))
+            .await?;
+        Ok(previous)
+    }
+
+    /// Get the bit at `offset` of the bytes stored for the key, it should return false if the key
+    /// doesn't exist or the bytes are shorter. By default the bytes are read with `get`.
+    async fn get_bit(&self, scope: &[u8], key: &[u8], offset: u64) -> Result<bool> {
+        match self.get(scope, key).await? {
+            Some(OwnedValue::Bytes(bytes)) => Ok(read_bit(&bytes, offset)),
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(false),
+        }
+    }
+
+    /// Add an item to the bloom filter stored for the key, sized by `params`. By default the bits
+    /// of the item's [`positions`](crate::dev::BloomParams::positions) are set with `set_bit`.
+    async fn bloom_add(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<()> {
+        for offset in params.positions(item) {
+            self.set_bit(scope, key, offset, true).await?;
+        }
+        Ok(())
+    }
+
+    /// Check if an item may have been added to the bloom filter stored for the key, it should
+    /// never return false for an added item. By default the bytes are read with `get` and the
+    /// bits of the item's [`positions`](crate::dev::BloomParams::positions) are checked.
+    async fn bloom_maybe_contains(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<bool> {
+        match self.get(scope, key).await? {
+            Some(OwnedValue::Bytes(bytes)) => Ok(params
+                .positions(item)
+                .all(|offset| read_bit(&bytes, offset))),
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(false),
+        }
+    }
 
     /// Mutate and get a value for specified key, it should set the value to 0 if it doesn't exist
-    async fn mutate(&self, scope: &str, key: &[u8], mutations: Mutation) -> Result<i64>;
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64>;
+
+    /// Same as `mutate`, but returns the value before the mutations as well, as `(old, new)`.
+    /// The old value of a missing key is 0. It should be done atomically, so there is no
+    /// default implementation.
+    async fn mutate_returning(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _mutations: Mutation,
+    ) -> Result<(i64, i64)> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically add `delta` to the float stored for the key and return the result, it should
+    /// treat missing keys as 0 and store the result as a string. See
+    /// [`add_float`](crate::dev::add_float) for the shared conversion rules.
+    async fn incr_float(&self, _scope: &[u8], _key: &[u8], _delta: f64) -> Result<f64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically add `delta` to the number stored for the key and return the result, it should
+    /// treat missing keys as 0 and keep the expiry of existing ones. See
+    /// [`add_big`](crate::dev::add_big) for the shared conversion rules.
+    async fn incr_big(&self, _scope: &[u8], _key: &[u8], _delta: i128) -> Result<i128> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically store the number `value` if it compares to the stored number as `ord`, and
+    /// return whether it was written. See [`should_set`](crate::dev::should_set) for the shared
+    /// comparison rules, the expiry of an existing key should be kept.
+    async fn set_if(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: i64,
+        _ord: Ordering,
+    ) -> Result<bool> {
+        Err(BastehError::MethodNotSupported)
+    }
 
     /// Delete the key from storage, if the key doesn't exist, it shouldn't return an error
-    async fn remove(&self, scope: &str, key: &[u8]) -> Result<Option<OwnedValue>>;
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>>;
+
+    /// Delete the key and return whether it existed, like redis' `DEL`. Unlike `remove`, the value
+    /// shouldn't be read or sent back. By default it calls `remove` and drops the value.
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        Ok(self.remove(scope, key).await?.is_some())
+    }
+
+    /// Get the value for a key and delete it atomically, like redis' `GETDEL`. By default it calls
+    /// `remove`, which already returns the removed value.
+    async fn get_del(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.remove(scope, key).await
+    }
+
+    /// Atomically delete the key if it holds `expected`, and return whether it was deleted.
+    /// Expired keys shouldn't match.
+    async fn remove_if(&self, _scope: &[u8], _key: &[u8], _expected: Value<'_>) -> Result<bool> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Remove all the keys of the scope matching the redis-like glob `pattern`(see
+    /// [`glob_match`](crate::dev::glob_match)) and return how many were removed. By default the
+    /// keys are collected and the matching ones are removed one by one.
+    async fn remove_matching(&self, scope: &[u8], pattern: &str) -> Result<u64> {
+        let keys = self
+            .keys(scope)
+            .await?
+            .filter(|key| glob_match(pattern.as_bytes(), key))
+            .collect::<Vec<_>>();
+        let mut removed = 0;
+        for key in keys {
+            if self.remove(scope, &key).await?.is_some() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
 
     /// Check if key exist in storage
-    async fn contains_key(&self, scope: &str, key: &[u8]) -> Result<bool>;
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool>;
+
+    /// Mark the key as recently used without reading its value and return whether it exists.
+    /// Providers evicting keys by recency(LRU) should bump the key's last access, for the others
+    /// it's the same as `contains_key`, which is the default.
+    async fn touch(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        self.contains_key(scope, key).await
+    }
+
+    /// Check if each of the keys exist in storage, the result should have the same length and
+    /// order as `keys`. Providers which can check multiple keys in a single roundtrip should
+    /// override this, by default it calls contains_key for each key.
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> Result<Vec<bool>> {
+        let mut res = Vec::with_capacity(keys.len());
+        for key in keys {
+            res.push(self.contains_key(scope, key).await?);
+        }
+        Ok(res)
+    }
 
     /// Remove all expiry requests from a key and make it persistent,
     /// the persistenty can be overwriten by calling expire on the key.
-    async fn persist(&self, scope: &str, key: &[u8]) -> Result<()>;
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()>;
 
     /// Sets an expiry for a key, the key may or may not be removed based on
     /// implementation, but it should be guaranteed that it won't appear in
     /// get based methods or contains checks after the period specified.
-    async fn expire(&self, scope: &str, key: &[u8], expire_in: Duration) -> Result<()>;
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()>;
+
+    /// Sets the expiry of each key to its own duration. Providers which can set them in a single
+    /// roundtrip should override this, by default it calls expire for each key.
+    async fn expire_multiple(&self, scope: &[u8], entries: &[(&[u8], Duration)]) -> Result<()> {
+        for (key, expire_in) in entries {
+            self.expire(scope, key, *expire_in).await?;
+        }
+        Ok(())
+    }
 
     /// Gets expiry for a key, returning None means it doesn't have an expiry,
     /// if the provider can't return an expiry, it should return an error instead.
     /// The result of this function can have some error, but it should be documented.
-    async fn expiry(&self, scope: &str, key: &[u8]) -> Result<Option<Duration>>;
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>>;
+
+    /// Get the last time the value of the key was written, it should return None if the key
+    /// doesn't exist or its write time is not known. Expiry changes are not writes.
+    async fn modified_at(&self, _scope: &[u8], _key: &[u8]) -> Result<Option<SystemTime>> {
+        Err(BastehError::MethodNotSupported)
+    }
 
     /// Extend expiry for a key for another duration of time.
     /// If the key doesn't have an expiry, it should be equivalent of calling expire.
-    async fn extend(&self, scope: &str, key: &[u8], expire_in: Duration) -> Result<()> {
+    async fn extend(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
         let expiry = self.expiry(scope.clone(), key.clone()).await?;
         self.expire(scope, key, expiry.unwrap_or_default() + expire_in)
             .await
@@ -72,7 +609,7 @@ pub trait Provider: Send + Sync {
     /// both the value and the expiry for that key.
     async fn set_expiring(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
         value: Value<'_>,
         expire_in: Duration,
@@ -81,11 +618,51 @@ pub trait Provider: Send + Sync {
         self.expire(scope, key, expire_in).await
     }
 
+    /// Atomically set the value expiring after `expire_in` and return the value it replaced, or
+    /// None if the key didn't exist or was expired.
+    async fn swap_expiring(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Value<'_>,
+        _expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Atomically add `delta` to the number stored for the key and return the result, without
+    /// ever extending its expiry. A missing or expired key is set to `delta` expiring after
+    /// `ttl`, an existing key keeps its expiry, and gets `ttl` only if it's persistent. Values
+    /// which are not numbers should fail with `NotANumber`.
+    async fn incr_with_ttl_floor(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _delta: i64,
+        _ttl: Duration,
+    ) -> Result<i64> {
+        Err(BastehError::MethodNotSupported)
+    }
+
+    /// Set a key-value pair only if `version` is greater than the version stored with the last
+    /// versioned write for this key, returning whether the value was written. Versions should be
+    /// kept when the key is removed or overwritten by other methods, so stale writes are still
+    /// rejected.
+    async fn set_versioned(
+        &self,
+        _scope: &[u8],
+        _key: &[u8],
+        _value: Value<'_>,
+        _version: u64,
+    ) -> Result<bool> {
+        Err(BastehError::MethodNotSupported)
+    }
+
     /// Get the value and expiry for a key, it is possible to return None if the key doesn't exist,
     /// or return None for the expiry if the key is persistent.
     async fn get_expiring(
         &self,
-        scope: &str,
+        scope: &[u8],
         key: &[u8],
     ) -> Result<Option<(OwnedValue, Option<Duration>)>> {
         let val = self.get(scope.clone(), key.clone()).await?;
@@ -97,4 +674,34 @@ pub trait Provider: Send + Sync {
             None => Ok(None),
         }
     }
+
+    /// Get the values and expiries of the keys, the result should have the same length and order
+    /// as `keys` with None for the missing ones. Providers which can read multiple keys in a
+    /// single roundtrip should override this, by default it calls get_expiring for each key.
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        let mut res = Vec::with_capacity(keys.len());
+        for key in keys {
+            res.push(self.get_expiring(scope, key).await?);
+        }
+        Ok(res)
+    }
+
+    /// Get all the keys of the scope with their values and expiries, in no particular order.
+    /// Providers which can read the whole scope at once should override this, by default the
+    /// keys are listed and read with get_expiring_multiple, so keys written in between may or may
+    /// not be included.
+    async fn snapshot(&self, scope: &[u8]) -> Result<Vec<SnapshotEntry>> {
+        let keys = self.keys(scope).await?.collect::<Vec<_>>();
+        let key_refs = keys.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let values = self.get_expiring_multiple(scope, &key_refs).await?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|(value, expiry)| (key, value, expiry)))
+            .collect())
+    }
 }
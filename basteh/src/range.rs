@@ -0,0 +1,41 @@
+use std::convert::TryInto;
+
+/// Resolve redis-like `start` and `end` indexes(inclusive, negative ones counting from the end
+/// of the list) of a list with `len` items to the number of items to skip and take. Indexes out
+/// of the list's range are clamped.
+pub fn resolve_range(len: usize, start: i64, end: i64) -> (usize, usize) {
+    let start: usize = start
+        .try_into()
+        .unwrap_or_else(|_| len.saturating_sub(start.unsigned_abs() as usize));
+
+    // An end before the first item means an empty range rather than the first item
+    let end: Option<usize> = match end.try_into() {
+        Ok(end) => Some(end),
+        Err(_) => len.checked_sub(end.unsigned_abs() as usize),
+    };
+
+    let take: usize = end
+        .and_then(|end| end.checked_sub(start))
+        .and_then(|end| end.checked_add(1))
+        .unwrap_or(0);
+
+    (start, take)
+}
+
+/// Same as [`resolve_range`], but returns None if any of the indexes is out of the list's range
+/// instead of clamping it.
+pub fn resolve_range_strict(len: usize, start: i64, end: i64) -> Option<(usize, usize)> {
+    let in_range = |index: i64| {
+        if index >= 0 {
+            (index as u64) < len as u64
+        } else {
+            index.unsigned_abs() <= len as u64
+        }
+    };
+
+    if in_range(start) && in_range(end) {
+        Some(resolve_range(len, start, end))
+    } else {
+        None
+    }
+}
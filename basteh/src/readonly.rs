@@ -0,0 +1,186 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::dev::{BoxStream, OwnedValue, ValueKind};
+use crate::error::{BastehError, Result};
+use crate::Basteh;
+
+/// A read-only view of a [`Basteh`](struct.Basteh.html), returned by
+/// [`Basteh::readonly`](struct.Basteh.html#method.readonly).
+///
+/// It uses the same provider, scope and key normalizer as the `Basteh` it's made from, but only
+/// exposes the methods which don't change the store, so it can be handed to the components which
+/// shouldn't write.
+///
+/// ## Example
+/// ```rust
+/// # use basteh::{ReadonlyBasteh, BastehError};
+/// #
+/// async fn index(store: ReadonlyBasteh) -> Result<String, BastehError> {
+///     let val = store.get::<String>("key").await?;
+///     Ok(val.unwrap_or_default())
+/// }
+/// ```
+///
+/// Writing through it doesn't compile
+/// ```rust,compile_fail
+/// # use basteh::ReadonlyBasteh;
+/// #
+/// async fn index(store: ReadonlyBasteh) {
+///     store.set("key", "value").await;
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ReadonlyBasteh {
+    store: Basteh,
+}
+
+impl ReadonlyBasteh {
+    pub(crate) fn new(store: Basteh) -> Self {
+        Self { store }
+    }
+
+    /// Get all keys of the current scope, same as [`Basteh::keys`](struct.Basteh.html#method.keys)
+    pub async fn keys(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.store.keys().await
+    }
+
+    /// Get all keys of the current scope as a stream, same as
+    /// [`Basteh::keys_stream`](struct.Basteh.html#method.keys_stream)
+    pub fn keys_stream(&self) -> BoxStream<'_, Result<Vec<u8>>> {
+        self.store.keys_stream()
+    }
+
+    /// Get all keys in lexicographic byte order, same as
+    /// [`Basteh::keys_sorted`](struct.Basteh.html#method.keys_sorted)
+    pub async fn keys_sorted(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.store.keys_sorted().await
+    }
+
+    /// Get the approximate number of bytes used by the scope, same as
+    /// [`Basteh::size_bytes`](struct.Basteh.html#method.size_bytes)
+    pub async fn size_bytes(&self) -> Result<u64> {
+        self.store.size_bytes().await
+    }
+
+    /// Gets a single value from store, same as [`Basteh::get`](struct.Basteh.html#method.get)
+    pub async fn get<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.store.get(key).await
+    }
+
+    /// Gets the bytes of the key as they are stored, same as
+    /// [`Basteh::get_raw`](struct.Basteh.html#method.get_raw)
+    pub async fn get_raw(&self, key: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>> {
+        self.store.get_raw(key).await
+    }
+
+    /// Get the kind of the value stored for the key, same as
+    /// [`Basteh::value_kind`](struct.Basteh.html#method.value_kind)
+    pub async fn value_kind(&self, key: impl AsRef<[u8]>) -> Result<Option<ValueKind>> {
+        self.store.value_kind(key).await
+    }
+
+    /// Gets a single value from the first scope containing the key, same as
+    /// [`Basteh::get_cascading`](struct.Basteh.html#method.get_cascading)
+    pub async fn get_cascading<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        scopes: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Option<T>> {
+        self.store.get_cascading(key, scopes).await
+    }
+
+    /// Gets a list of values from store, same as
+    /// [`Basteh::get_range`](struct.Basteh.html#method.get_range)
+    pub async fn get_range<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>> {
+        self.store.get_range(key, start, end).await
+    }
+
+    /// Gets ranges of multiple lists, same as
+    /// [`Basteh::get_range_multiple`](struct.Basteh.html#method.get_range_multiple)
+    pub async fn get_range_multiple<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        requests: impl IntoIterator<Item = (impl AsRef<[u8]>, i64, i64)>,
+    ) -> Result<Vec<Vec<T>>> {
+        self.store.get_range_multiple(requests).await
+    }
+
+    /// Gets a field of the map stored for the key, same as
+    /// [`Basteh::hget`](struct.Basteh.html#method.hget)
+    pub async fn hget<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        field: impl AsRef<[u8]>,
+    ) -> Result<Option<T>> {
+        self.store.hget(key, field).await
+    }
+
+    /// Gets all the fields of the map stored for the key, same as
+    /// [`Basteh::hgetall`](struct.Basteh.html#method.hgetall)
+    pub async fn hgetall<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Vec<(Vec<u8>, T)>> {
+        self.store.hgetall(key).await
+    }
+
+    /// Gets a list of values from store in reverse order, same as
+    /// [`Basteh::get_range_rev`](struct.Basteh.html#method.get_range_rev)
+    pub async fn get_range_rev<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>> {
+        self.store.get_range_rev(key, start, end).await
+    }
+
+    /// Gets a list of values from store failing on out of range indexes, same as
+    /// [`Basteh::get_range_strict`](struct.Basteh.html#method.get_range_strict)
+    pub async fn get_range_strict<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<T>> {
+        self.store.get_range_strict(key, start, end).await
+    }
+
+    /// Gets a value and its expiry, same as
+    /// [`Basteh::get_expiring`](struct.Basteh.html#method.get_expiring)
+    pub async fn get_expiring<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<(T, Option<Duration>)>> {
+        self.store.get_expiring(key).await
+    }
+
+    /// Gets the values and expiries of multiple keys, same as
+    /// [`Basteh::get_expiring_multiple`](struct.Basteh.html#method.get_expiring_multiple)
+    pub async fn get_expiring_multiple<T: TryFrom<OwnedValue, Error = impl Into<BastehError>>>(
+        &self,
+        keys: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Vec<Option<(T, Option<Duration>)>>> {
+        self.store.get_expiring_multiple(keys).await
+    }
+
+    /// Checks if store contains a key, same as
+    /// [`Basteh::contains_key`](struct.Basteh.html#method.contains_key)
+    pub async fn contains_key(&self, key: impl AsRef<[u8]>) -> Result<bool> {
+        self.store.contains_key(key).await
+    }
+
+    /// Gets expiry for the provided key, same as
+    /// [`Basteh::expiry`](struct.Basteh.html#method.expiry)
+    pub async fn expiry(&self, key: impl AsRef<[u8]>) -> Result<Option<Duration>> {
+        self.store.expiry(key).await
+    }
+}
@@ -0,0 +1,136 @@
+//! A framework-agnostic session store built on top of [`Basteh`].
+//!
+//! Sessions are stored as opaque bytes with an expiry, so adapters for session middlewares
+//! (ex. actix-session or tower-sessions) can wrap [`SessionStore`] and use their own
+//! serialization. For the common case of string maps, [`SessionStore::load_state`] and
+//! [`SessionStore::store_state`] serialize a [`SessionState`] to a compact binary format.
+use std::{collections::HashMap, convert::TryInto, time::Duration};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{error::Result, Basteh, BastehError, OwnedValue};
+
+/// The state of a session as a map of string keys to string values
+pub type SessionState = HashMap<String, String>;
+
+/// A session store keeping the session data of each session id with an expiry.
+///
+/// ## Example
+/// ```rust
+/// use std::time::Duration;
+/// use basteh::{session::SessionStore, Basteh, BastehError};
+///
+/// async fn login(store: Basteh) -> Result<(), BastehError> {
+///     let sessions = SessionStore::new(store.scope("sessions"));
+///
+///     sessions
+///         .store("session_id", "user=1", Duration::from_secs(3600))
+///         .await?;
+///     assert!(sessions.load("session_id").await?.is_some());
+/// #     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SessionStore {
+    store: Basteh,
+}
+
+impl SessionStore {
+    /// Create a new session store, sessions are stored in the scope of the given store so it's
+    /// usually a good idea to give them a dedicated scope.
+    pub fn new(store: Basteh) -> Self {
+        Self { store }
+    }
+
+    /// Load the data of the session, returns `None` if the session doesn't exist or is expired
+    pub async fn load(&self, id: impl AsRef<[u8]>) -> Result<Option<Bytes>> {
+        // Backends storing everything as strings(like redis) read digit-only data back as numbers
+        match self.store.get::<OwnedValue>(id).await? {
+            Some(OwnedValue::Bytes(data)) => Ok(Some(data.freeze())),
+            Some(OwnedValue::String(data)) => Ok(Some(data.into())),
+            Some(OwnedValue::Number(n)) => Ok(Some(n.to_string().into())),
+            Some(OwnedValue::BigNumber(n)) => Ok(Some(n.to_string().into())),
+            Some(_) => Err(BastehError::TypeConversion),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the data of the session, replacing the previous data and expiring it after `ttl`
+    pub async fn store(
+        &self,
+        id: impl AsRef<[u8]>,
+        data: impl Into<Bytes>,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.store.set_expiring(id, data.into(), ttl).await
+    }
+
+    /// Expire the session after `ttl` without touching its data
+    pub async fn update_ttl(&self, id: impl AsRef<[u8]>, ttl: Duration) -> Result<()> {
+        self.store.expire(id, ttl).await
+    }
+
+    /// Remove the session, doesn't fail if it doesn't exist
+    pub async fn remove(&self, id: impl AsRef<[u8]>) -> Result<()> {
+        self.store.delete(id).await.map(|_| ())
+    }
+
+    /// Load the session and deserialize it as a [`SessionState`]
+    ///
+    /// ## Errors
+    /// Returns [`BastehError::TypeConversion`] if the stored data was not written by
+    /// [`store_state`](SessionStore::store_state).
+    pub async fn load_state(&self, id: impl AsRef<[u8]>) -> Result<Option<SessionState>> {
+        self.load(id)
+            .await?
+            .map(|data| decode_state(&data))
+            .transpose()
+    }
+
+    /// Serialize the [`SessionState`] and store it, expiring it after `ttl`
+    pub async fn store_state(
+        &self,
+        id: impl AsRef<[u8]>,
+        state: &SessionState,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.store(id, encode_state(state), ttl).await
+    }
+}
+
+// The state is encoded as a sequence of length-prefixed(u32, big endian) keys and values
+fn encode_state(state: &SessionState) -> Bytes {
+    let len = state.iter().map(|(k, v)| 8 + k.len() + v.len()).sum();
+    let mut buf = BytesMut::with_capacity(len);
+    for (key, value) in state {
+        for part in [key, value] {
+            buf.put_u32(part.len() as u32);
+            buf.put_slice(part.as_bytes());
+        }
+    }
+    buf.freeze()
+}
+
+fn decode_state(mut data: &[u8]) -> Result<SessionState> {
+    fn read_part(data: &mut &[u8]) -> Result<String> {
+        if data.len() < 4 {
+            return Err(BastehError::TypeConversion);
+        }
+        let (len, rest) = data.split_at(4);
+        let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        if rest.len() < len {
+            return Err(BastehError::TypeConversion);
+        }
+        let (part, rest) = rest.split_at(len);
+        *data = rest;
+        String::from_utf8(part.to_vec()).map_err(|_| BastehError::TypeConversion)
+    }
+
+    let mut state = SessionState::new();
+    while !data.is_empty() {
+        let key = read_part(&mut data)?;
+        let value = read_part(&mut data)?;
+        state.insert(key, value);
+    }
+    Ok(state)
+}
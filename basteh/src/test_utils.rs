@@ -1,9 +1,71 @@
-use std::{cmp::Ordering, collections::HashSet, time::Duration};
+//! Test suites shared by the backends of this repository, behind the `test_utils` feature.
+//!
+//! Backends living outside of it can be checked against the same suites with [`conformance`].
+
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    convert::TryFrom,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering as AtomicOrdering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
 use bytes::Bytes;
+use futures_util::{FutureExt, TryStreamExt};
 
 use crate::{dev::*, *};
 
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////    Conformance     ///////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Runs the store, nested list, expiry and mutation suites against the provider, skipping the
+/// ones its [`capabilities`](Provider::capabilities) say are not supported. It panics on the first
+/// failed check.
+///
+/// The suites cover the optional methods of [`Provider`] too, the ones left to their default
+/// implementation fail with [`MethodNotSupported`](BastehError::MethodNotSupported). Many scopes
+/// and keys are written, so the provider should start empty and not be shared with anything else.
+///
+/// `delay_secs` is the expiry set by the expiry suites before waiting for the keys to be gone, it
+/// should be longer than the delay the provider has in removing the expired keys.
+///
+/// ## Example
+/// Not compiled here, as basteh doesn't depend on any backend, the same test lives in
+/// basteh-memory.
+/// ```ignore
+/// use basteh::test_utils::conformance;
+/// use basteh_memory::MemoryBackend;
+///
+/// #[tokio::test]
+/// async fn test_memory_conformance() {
+///     conformance(MemoryBackend::start_default(), 2).await;
+/// }
+/// ```
+pub async fn conformance<P>(provider: P, delay_secs: u64)
+where
+    P: 'static + Provider,
+{
+    let capabilities = provider.capabilities();
+    let store = Basteh::build().provider(provider).finish();
+
+    store_suite(store.clone()).await;
+    if capabilities.nested_lists {
+        nested_list_suite(store.clone()).await;
+    }
+    if capabilities.expiry {
+        expiry_suite(store.clone(), delay_secs).await;
+        expiry_store_suite(store.clone(), delay_secs).await;
+    }
+    if capabilities.mutations {
+        mutation_suite(store).await;
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////    Basteh tests     ////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -121,500 +183,3696 @@ pub async fn test_store_list(store: Basteh) {
     assert_eq!(get_vec, vec!["World".to_string()]);
 }
 
-pub async fn test_store<P>(store: P)
-where
-    P: 'static + Provider,
-{
-    let store = Basteh::build().provider(store).finish();
+pub async fn test_store_remove_if(store: Basteh) {
+    let key = "remove_if_key";
 
-    tokio::join!(
-        test_store_methods(store.clone()),
-        test_store_bytes(store.clone()),
-        test_store_numbers(store.clone()),
-        test_store_keys(store.clone()),
-        test_store_list(store.clone())
+    assert!(!store.remove_if(key, "worker-1").await.unwrap());
+
+    // Values not matching the expected one are left alone
+    store.set(key, "worker-2").await.unwrap();
+    assert!(!store.remove_if(key, "worker-1").await.unwrap());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("worker-2".to_string())
     );
+    assert!(store.remove_if(key, "worker-2").await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+
+    store.set(key, 10).await.unwrap();
+    assert!(!store.remove_if(key, 11).await.unwrap());
+    assert!(store.remove_if(key, 10).await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+
+    store.set(key, [1_i64, 2]).await.unwrap();
+    assert!(!store.remove_if(key, [1_i64, 3]).await.unwrap());
+    assert!(!store.remove_if(key, [1_i64]).await.unwrap());
+    assert!(store.contains_key(key).await.unwrap());
+    assert!(store.remove_if(key, [1_i64, 2]).await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
 }
 
-////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-//////////////////////////////////////////////////    Expiration tests     /////////////////////////////////////////////////
-////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-
-/// Testing the expiry basics by setting a key and expiry, then waiting for it to expire
-pub async fn test_expiry_basics(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expiring_key";
-    let value = "val";
-
-    // Provider for invalid key should return None
-    assert!(store.expiry(key).await.unwrap().is_none());
+pub async fn test_store_pop_blocking(store: Basteh) {
+    let key = "pop_blocking_key";
 
-    // Testing set and get before expiry
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
+    // Nothing is pushed, so it should give up after the timeout
     assert_eq!(
-        store.get::<String>(key).await.unwrap(),
-        Some(value.to_owned())
+        store
+            .pop_blocking::<String>(key, Duration::from_millis(200))
+            .await
+            .unwrap(),
+        None
     );
 
-    // The exact duration depends on the implementation
-    let exp = store.expiry(key).await.unwrap().unwrap();
-    assert!(exp.as_secs() > 0);
-    assert!(exp.as_secs() <= delay_secs);
-
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    // Items already in the list are returned right away
+    store.push(key, "first").await.unwrap();
+    assert_eq!(
+        store
+            .pop_blocking::<String>(key, Duration::from_secs(5))
+            .await
+            .unwrap(),
+        Some("first".to_string())
+    );
 
-    // Check if extended item has been expired
-    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    // Items pushed while waiting are picked up
+    let (popped, _) = tokio::join!(
+        store.pop_blocking::<String>(key, Duration::from_secs(5)),
+        async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            store.push(key, "second").await.unwrap();
+        }
+    );
+    assert_eq!(popped.unwrap(), Some("second".to_string()));
+    assert_eq!(
+        store.get_range::<String>(key, 0, -1).await.unwrap(),
+        Vec::<String>::new()
+    );
 }
 
-/// Testing extending functionality by setting an expiry and extending it later,
-/// The key shouldn't be expired before the sum of default expiry and extended time
-pub async fn test_expiry_extend(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "extended_expiring_key";
-    let value = "val";
-
-    // Testing set and get before expiry
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
+pub async fn test_store_pop_push(store: Basteh) {
+    let src = "pop_push_src";
+    let dst = "pop_push_dst";
 
-    // Testing extending the expiry time
-    // The exact duration depends on the implementation
-    store.extend(key, delay).await.unwrap();
+    store.set_list(src, [1_i64, 2, 3]).await.unwrap();
+    store.set_list(dst, [10_i64]).await.unwrap();
 
-    let exp = store.expiry(key).await.unwrap().unwrap();
-    assert!(exp.as_secs() >= delay_secs);
-    assert!(exp.as_secs() <= delay_secs * 2);
+    assert_eq!(store.pop_push::<i64>(src, dst).await.unwrap(), Some(3));
+    assert_eq!(store.pop_push::<i64>(src, dst).await.unwrap(), Some(2));
+    assert_eq!(store.get_range::<i64>(src, 0, -1).await.unwrap(), vec![1]);
+    assert_eq!(
+        store.get_range::<i64>(dst, 0, -1).await.unwrap(),
+        vec![10, 3, 2]
+    );
 
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    // The destination is created if it doesn't exist
+    let new_dst = "pop_push_new_dst";
+    assert_eq!(store.pop_push::<i64>(src, new_dst).await.unwrap(), Some(1));
+    assert_eq!(
+        store.get_range::<i64>(new_dst, 0, -1).await.unwrap(),
+        vec![1]
+    );
 
-    // Check if extended item still exist
+    // Empty and missing sources don't move anything
+    assert_eq!(store.pop_push::<i64>(src, dst).await.unwrap(), None);
     assert_eq!(
-        store.get::<String>(key).await.unwrap(),
-        Some(value.to_owned())
+        store
+            .pop_push::<i64>("pop_push_missing", dst)
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(!store.contains_key("pop_push_missing").await.unwrap());
+    assert_eq!(
+        store.get_range::<i64>(dst, 0, -1).await.unwrap(),
+        vec![10, 3, 2]
     );
 
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    // Rotating a list in place
+    assert_eq!(store.pop_push::<i64>(dst, dst).await.unwrap(), Some(2));
+    assert_eq!(
+        store.get_range::<i64>(dst, 0, -1).await.unwrap(),
+        vec![10, 3, 2]
+    );
 
-    // Check if extended item has been expired
-    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    // Type errors shouldn't move anything
+    let not_a_list = "pop_push_not_a_list";
+    store.set(not_a_list, "value").await.unwrap();
+    assert!(matches!(
+        store.pop_push::<i64>(not_a_list, dst).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert!(matches!(
+        store.pop_push::<i64>(dst, not_a_list).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert_eq!(
+        store.get_range::<i64>(dst, 0, -1).await.unwrap(),
+        vec![10, 3, 2]
+    );
+    assert_eq!(
+        store.get::<String>(not_a_list).await.unwrap(),
+        Some("value".to_owned())
+    );
 }
 
-/// Testing persist, by setting an expiry for a key and making it persistant later
-pub async fn test_expiry_persist(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "persistant_key";
-    let value = "val";
-
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
-    assert!(store.persist(key).await.is_ok());
-
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+pub async fn test_store_wrong_type(store: Basteh) {
+    let key = "wrong_type_string";
+    store.set(key, "value").await.unwrap();
+
+    // List operations on other kinds of values fail the same way on all the backends
+    assert!(matches!(
+        store.push(key, 1).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert!(matches!(
+        store.extend_list(key, [1_i64, 2]).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert!(matches!(
+        store.pop::<i64>(key).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert!(matches!(
+        store.get_range::<i64>(key, 0, -1).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert!(matches!(
+        store.list_drain::<i64>(key).await,
+        Err(BastehError::TypeConversion)
+    ));
+
+    // Number operations report the kind they found instead
+    assert!(matches!(
+        store.mutate(key, |m| m.incr(1)).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
 
-    // Check if persistent key is still there
     assert_eq!(
         store.get::<String>(key).await.unwrap(),
-        Some(value.to_owned())
+        Some("value".to_owned())
     );
 }
 
-/// Testing persist, by setting an expiry for a key and making it persistant later
-pub async fn test_mutate_sould_not_change_expiry(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "mutated_key";
-    let value = 1200;
+pub async fn test_store_set_extend_list(store: Basteh) {
+    let key = "set_extend_list_key";
 
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
+    // Extending a list that doesn't exist should create it
+    store.extend_list(key, [1_i64, 2]).await.unwrap();
+    let get_vec = store.get_range::<i64>(key, 0, -1).await.unwrap();
+    assert_eq!(get_vec, vec![1, 2]);
 
-    assert!(store.mutate(key, |m| m.incr(1300)).await.is_ok());
+    // Extending a non-empty list should append to it
+    store.extend_list(key, vec![3_i64, 4]).await.unwrap();
+    let get_vec = store.get_range::<i64>(key, 0, -1).await.unwrap();
+    assert_eq!(get_vec, vec![1, 2, 3, 4]);
 
-    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(2500));
+    // Setting the list should replace all the existing items
+    store.set_list(key, [10_i64, 20, 30]).await.unwrap();
+    let get_vec = store.get_range::<i64>(key, 0, -1).await.unwrap();
+    assert_eq!(get_vec, vec![10, 20, 30]);
 
-    // Check if persistent expiry have changed
-    assert!(store.expiry(key).await.unwrap().is_some());
+    // And extending again should append to the replaced list
+    store.extend_list(key, [40_i64]).await.unwrap();
+    let get_vec = store.get_range::<i64>(key, 0, -1).await.unwrap();
+    assert_eq!(get_vec, vec![10, 20, 30, 40]);
 }
 
-/// Testing if calling set after expire, clears expiration from the key
-pub async fn test_expiry_set_clearing(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "set_after_expire_key";
-    let value = "val";
+pub async fn test_store_key_normalizer(store: Basteh) {
+    let store = store
+        .scope("NORMALIZER_SCOPE")
+        .with_key_normalizer(|key| key.to_ascii_lowercase());
 
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
-    assert!(store.set(key, value).await.is_ok());
+    assert!(store.set("KEY", "value").await.is_ok());
 
-    tokio::time::sleep(Duration::from_secs((delay_secs) + 1)).await;
+    // Both versions should hit the same entry
+    assert_eq!(
+        store.get::<String>("KEY").await.unwrap(),
+        Some("value".to_owned())
+    );
+    assert_eq!(
+        store.get::<String>("key").await.unwrap(),
+        Some("value".to_owned())
+    );
 
-    // Check if calling set twice cleaerd the expire
+    assert!(store.set("Key", "value2").await.is_ok());
     assert_eq!(
-        store.get::<String>(key).await.unwrap(),
-        Some(value.to_owned())
+        store.get::<String>("kEY").await.unwrap(),
+        Some("value2".to_owned())
+    );
+
+    // Only the normalized key is stored
+    let keys = store.keys().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(keys, vec![b"key".to_vec()]);
+
+    assert!(store.contains_key("KEY").await.unwrap());
+    assert_eq!(
+        store.remove::<String>("KeY").await.unwrap(),
+        Some("value2".to_owned())
     );
+    assert!(!store.contains_key("key").await.unwrap());
 }
 
-/// Testing if second call to expire overrides the first one
-/// The key should expire in this test, as we set a shorter period second time
-pub async fn test_expiry_override_shorter(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expire_override_shorter_key";
-    let value = "val";
+pub async fn test_store_entry(store: Basteh) {
+    let key = "entry_key";
 
-    // Test if second call to expire overwrites the first expiry
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay * 5).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
+    // Absent key should be inserted
+    let value = store.entry(key).or_insert::<String>("first").await.unwrap();
+    assert_eq!(value, "first");
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("first".to_owned())
+    );
 
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    // Present key should be kept
+    let value = store
+        .entry(key)
+        .or_insert::<String>("second")
+        .await
+        .unwrap();
+    assert_eq!(value, "first");
+    let value = store
+        .entry(key)
+        .or_insert_with::<String, _, _, &str>(|| async { panic!("Should not be called") })
+        .await;
+    assert_eq!(value.unwrap(), "first");
 
-    // Check if calling set twice cleaerd the expire
-    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    // Insert with a function
+    let key = "entry_key_with";
+    let value = store
+        .entry(key)
+        .or_insert_with::<i64, _, _, _>(|| async { 10 })
+        .await
+        .unwrap();
+    assert_eq!(value, 10);
+
+    // Modify only existing values
+    let key = "entry_counter";
+    for expected in 1..=3 {
+        let value = store
+            .entry(key)
+            .and_modify(|m| m.incr(1))
+            .or_insert::<i64>(1)
+            .await
+            .unwrap();
+        assert_eq!(value, expected);
+    }
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(3));
 }
 
-/// Testing if second call to expire overrides the first one
-/// The key shouldn't expire in this test, as we set a longer period second time
-pub async fn test_expiry_override_longer(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expire_override_longer_key";
-    let value = "val";
-
-    // Test if second call to expire overwrites the first expiry
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
-    assert!(store.expire(key, delay * 5).await.is_ok());
+pub async fn test_store_versioned(store: Basteh) {
+    let key = "versioned_key";
 
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    assert!(store.set_versioned(key, "v5", 5).await.unwrap());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("v5".to_owned())
+    );
 
-    // Check if calling set twice cleaerd the expire
+    // Out of order lower versions and the same version are rejected
+    assert!(!store.set_versioned(key, "v3", 3).await.unwrap());
+    assert!(!store.set_versioned(key, "v5 again", 5).await.unwrap());
     assert_eq!(
         store.get::<String>(key).await.unwrap(),
-        Some(value.to_owned())
+        Some("v5".to_owned())
     );
-}
 
-// delay_secs is the duration of time we set for expiry and we wait to see
-// the result, it should depend on how much delay an implementer has between
-// getting a command and executing it
-pub async fn test_expiry<P>(provider: P, delay_secs: u64)
-where
-    P: 'static + Provider,
-{
-    let store = Basteh::build().provider(provider).finish();
+    // Higher versions are written
+    assert!(store.set_versioned(key, 10, 10).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(10));
 
-    tokio::join!(
-        test_expiry_basics(store.clone(), delay_secs),
-        test_mutate_sould_not_change_expiry(store.clone(), delay_secs,),
-        test_expiry_extend(store.clone(), delay_secs),
-        test_expiry_persist(store.clone(), delay_secs),
-        test_expiry_set_clearing(store.clone(), delay_secs),
-        test_expiry_override_shorter(store.clone(), delay_secs),
-        test_expiry_override_longer(store, delay_secs)
+    // Versions survive removing the key
+    store.remove::<i64>(key).await.unwrap();
+    assert!(!store.set_versioned(key, "v7", 7).await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+    assert!(store.set_versioned(key, vec!["v", "11"], 11).await.unwrap());
+    assert_eq!(
+        store.get_range::<String>(key, 0, -1).await.unwrap(),
+        vec!["v".to_owned(), "11".to_owned()]
     );
+
+    // Largest versions shouldn't lose precision
+    assert!(store.set_versioned(key, 1, u64::MAX - 1).await.unwrap());
+    assert!(!store.set_versioned(key, 2, u64::MAX - 2).await.unwrap());
+    assert!(store.set_versioned(key, 3, u64::MAX).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(3));
 }
 
-////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-///////////////////////////////////////////////    Basteh-Expiration tests     //////////////////////////////////////////////
-////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+pub async fn test_store_contains_multiple(store: Basteh) {
+    let present1 = "contains_multiple_present1";
+    let present2 = "contains_multiple_present2";
+    let absent = "contains_multiple_absent";
 
-pub async fn test_expiry_store_basics(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expiry_store_key";
-    let value = "value";
+    store.set(present1, "value").await.unwrap();
+    store.set(present2, 2).await.unwrap();
 
-    // Test set and get expiring
-    assert!(store.set_expiring(key, value, delay).await.is_ok());
+    assert!(store.contains_all([present1, present2]).await.unwrap());
+    assert!(!store
+        .contains_all([present1, absent, present2])
+        .await
+        .unwrap());
+    assert!(!store.contains_all([absent]).await.unwrap());
 
-    let (v, e) = store.get_expiring::<String>(key).await.unwrap().unwrap();
-    assert_eq!(&v, &value);
-    assert!(e.unwrap().as_secs() > 0);
-    assert!(e.unwrap().as_secs() <= delay_secs);
+    assert!(store.contains_any([absent, present2]).await.unwrap());
+    assert!(store.contains_any([present1, present2]).await.unwrap());
+    assert!(!store.contains_any([absent]).await.unwrap());
 
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    // Empty sets of keys
+    assert!(store.contains_all(Vec::<&str>::new()).await.unwrap());
+    assert!(!store.contains_any(Vec::<&str>::new()).await.unwrap());
 
-    // Check if first item expired as expected
-    assert_eq!(store.get_expiring::<String>(key).await.unwrap(), None);
+    store.remove::<i64>(present2).await.unwrap();
+    assert!(!store.contains_all([present1, present2]).await.unwrap());
+    assert!(store.contains_any([present1, present2]).await.unwrap());
 }
 
-/// Testing if second call to expire overrides the first one
-/// The key should expire in this test, as we set a shorter period second time
-pub async fn test_expiry_store_override_shorter(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expire_store_override_shorter_key";
-    let value = "value";
+pub async fn test_store_readonly(store: Basteh) {
+    let store = store.scope("readonly_scope");
+    let readonly = store.readonly();
 
-    // Test if second call to set expiring, overwrites expiry for key
-    assert!(store.set_expiring(key, value, delay).await.is_ok());
-    assert!(store.set_expiring(key, value, delay * 2).await.is_ok());
-    let exp = store.expiry(key).await.unwrap().unwrap();
-    assert!(exp.as_secs() > delay_secs);
-    assert!(exp.as_secs() <= delay_secs * 2);
+    store.set("readonly_key", "value").await.unwrap();
+    store.set_list("readonly_list", [1_i64, 2]).await.unwrap();
+    store
+        .set_expiring("readonly_expiring", 10, Duration::from_secs(100))
+        .await
+        .unwrap();
 
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    assert_eq!(
+        readonly.get::<String>("readonly_key").await.unwrap(),
+        Some("value".to_owned())
+    );
+    assert_eq!(
+        readonly
+            .get_range::<i64>("readonly_list", 0, -1)
+            .await
+            .unwrap(),
+        vec![1, 2]
+    );
+    assert!(readonly.contains_key("readonly_key").await.unwrap());
+    assert!(!readonly.contains_key("readonly_missing").await.unwrap());
 
-    // Check if the second call to set overwrites expiry
+    let mut keys = readonly.keys().await.unwrap().collect::<Vec<_>>();
+    keys.sort();
     assert_eq!(
-        store.get::<String>(key).await.unwrap(),
-        Some(value.to_owned())
+        keys,
+        vec![
+            b"readonly_expiring".to_vec(),
+            b"readonly_key".to_vec(),
+            b"readonly_list".to_vec()
+        ]
+    );
+
+    assert_eq!(readonly.expiry("readonly_key").await.unwrap(), None);
+    assert!(readonly
+        .expiry("readonly_expiring")
+        .await
+        .unwrap()
+        .is_some());
+    let (value, expiry) = readonly
+        .get_expiring::<i64>("readonly_expiring")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, 10);
+    assert!(expiry.is_some());
+
+    // Later writes through the original handle are visible
+    store.set("readonly_key", "new value").await.unwrap();
+    assert_eq!(
+        readonly.get::<String>("readonly_key").await.unwrap(),
+        Some("new value".to_owned())
     );
 }
 
-/// Testing if second call to expire overrides the first one
-/// The key shouldn't expire in this test, as we set a longer period second time
-pub async fn test_expiry_store_override_longer(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expire_store_override_longer_key";
-    let value = "value";
+pub async fn test_store_get_cascading(store: Basteh) {
+    let key = "cascading_key";
+    let scopes = ["cascading_user", "cascading_team", "cascading_default"];
 
-    // Test if second call to set expiring, overwrites expiry for key
-    assert!(store.set_expiring(key, value, delay * 2).await.is_ok());
+    // Missing in all the scopes
+    assert_eq!(
+        store.get_cascading::<String>(key, scopes).await.unwrap(),
+        None
+    );
+
+    // Only in the last fallback scope
+    store
+        .scope("cascading_default")
+        .set(key, "default")
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_cascading::<String>(key, scopes).await.unwrap(),
+        Some("default".to_owned())
+    );
+
+    // The earlier scopes take precedence
+    store
+        .scope("cascading_team")
+        .set(key, "team")
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_cascading::<String>(key, scopes).await.unwrap(),
+        Some("team".to_owned())
+    );
+    assert_eq!(
+        store
+            .readonly()
+            .get_cascading::<String>(key, &scopes[2..])
+            .await
+            .unwrap(),
+        Some("default".to_owned())
+    );
+
+    // The current scope is not checked unless it's in the list
+    store.set(key, "current").await.unwrap();
+    assert_eq!(
+        store
+            .get_cascading::<String>(key, ["cascading_user"])
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+pub async fn test_store_keys_sorted(store: Basteh) {
+    let store = store.scope("keys_sorted_scope");
+    let keys: [&[u8]; 6] = [b"b", b"ab", b"\xff", b"a", b"B", b"\x00"];
+
+    for key in keys {
+        store.set(key, "value").await.unwrap();
+    }
+
+    let mut expected = keys.iter().map(|k| k.to_vec()).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(
+        store.keys_sorted().await.unwrap().collect::<Vec<_>>(),
+        expected
+    );
+    assert_eq!(
+        store
+            .readonly()
+            .keys_sorted()
+            .await
+            .unwrap()
+            .collect::<Vec<_>>(),
+        expected
+    );
+}
+
+pub async fn test_store_get_range_rev(store: Basteh) {
+    let key = "get_range_rev_key";
+    store.set_list(key, [1_i64, 2, 3, 4, 5]).await.unwrap();
+
+    assert_eq!(
+        store.get_range_rev::<i64>(key, 0, -1).await.unwrap(),
+        vec![5, 4, 3, 2, 1]
+    );
+    assert_eq!(
+        store.get_range_rev::<i64>(key, 1, 3).await.unwrap(),
+        vec![4, 3, 2]
+    );
+    assert_eq!(
+        store.get_range_rev::<i64>(key, -2, -1).await.unwrap(),
+        vec![5, 4]
+    );
+    assert_eq!(
+        store
+            .readonly()
+            .get_range_rev::<i64>(key, 3, 10)
+            .await
+            .unwrap(),
+        vec![5, 4]
+    );
+    assert!(store
+        .get_range_rev::<i64>("get_range_rev_missing", 0, -1)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+pub async fn test_store_get_range_multiple(store: Basteh) {
+    let (first, second, third) = ("range_multi_1", "range_multi_2", "range_multi_3");
+    store.set_list(first, [1_i64, 2, 3, 4, 5]).await.unwrap();
+    store.set_list(second, [10_i64, 20]).await.unwrap();
+    store.set_list(third, [7_i64, 8, 9]).await.unwrap();
+
+    let requests = [
+        (third, -2, -1),
+        (first, 1, 3),
+        (second, 0, -1),
+        (first, 4, 10),
+    ];
+    let ranges = store
+        .get_range_multiple::<i64>(requests.iter().copied())
+        .await
+        .unwrap();
+    assert_eq!(ranges.len(), requests.len());
+    for ((key, start, end), range) in requests.iter().zip(ranges) {
+        assert_eq!(
+            range,
+            store.get_range::<i64>(key, *start, *end).await.unwrap()
+        );
+    }
+    assert_eq!(
+        store
+            .readonly()
+            .get_range_multiple::<i64>([(second, 1, 1), ("range_multi_missing", 0, -1)])
+            .await
+            .unwrap(),
+        vec![vec![20], vec![]]
+    );
+
+    store.set("range_multi_number", 1).await.unwrap();
+    assert!(store
+        .get_range_multiple::<i64>([(first, 0, -1), ("range_multi_number", 0, -1)])
+        .await
+        .is_err());
+}
+
+pub async fn test_store_get_range_missing(store: Basteh) {
+    let key = "get_range_missing_key";
+
+    // A missing key reads as an empty list
+    assert_eq!(
+        store.get_range::<i64>(key, 0, -1).await.unwrap(),
+        Vec::<i64>::new()
+    );
+    assert_eq!(
+        store.get_range::<i64>(key, -3, 5).await.unwrap(),
+        Vec::<i64>::new()
+    );
+    assert_eq!(
+        store
+            .readonly()
+            .get_range::<String>(key, 0, -1)
+            .await
+            .unwrap(),
+        Vec::<String>::new()
+    );
+
+    // Anything other than a list is still an error
+    store.set("get_range_missing_number", 1).await.unwrap();
+    assert!(store
+        .get_range::<i64>("get_range_missing_number", 0, -1)
+        .await
+        .is_err());
+}
+
+pub async fn test_store_get_range_strict(store: Basteh) {
+    let key = "get_range_strict_key";
+    store.set_list(key, [1_i64, 2, 3, 4]).await.unwrap();
+
+    // In range, negative indexes count from the end
+    assert_eq!(
+        store.get_range_strict::<i64>(key, 1, 2).await.unwrap(),
+        vec![2, 3]
+    );
+    assert_eq!(
+        store.get_range_strict::<i64>(key, -4, -1).await.unwrap(),
+        vec![1, 2, 3, 4]
+    );
+    assert_eq!(
+        store.get_range_strict::<i64>(key, 3, 3).await.unwrap(),
+        vec![4]
+    );
+
+    // Out of range in strict mode, clamped in lenient mode
+    assert!(matches!(
+        store.get_range_strict::<i64>(key, 2, 10).await,
+        Err(BastehError::OutOfRange)
+    ));
+    assert_eq!(
+        store.get_range::<i64>(key, 2, 10).await.unwrap(),
+        vec![3, 4]
+    );
+    assert!(matches!(
+        store.get_range_strict::<i64>(key, -5, -1).await,
+        Err(BastehError::OutOfRange)
+    ));
+    assert_eq!(
+        store.get_range::<i64>(key, -5, -1).await.unwrap(),
+        vec![1, 2, 3, 4]
+    );
+    assert!(matches!(
+        store.get_range_strict::<i64>(key, 4, 4).await,
+        Err(BastehError::OutOfRange)
+    ));
+    assert!(matches!(
+        store.get_range_strict::<i64>(key, i64::MIN, -1).await,
+        Err(BastehError::OutOfRange)
+    ));
+    assert_eq!(
+        store.get_range::<i64>(key, i64::MIN, -1).await.unwrap(),
+        vec![1, 2, 3, 4]
+    );
+    assert!(store
+        .get_range::<i64>(key, 0, i64::MIN)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Missing keys are empty lists
+    assert!(matches!(
+        store
+            .get_range_strict::<i64>("get_range_strict_missing", 0, 0)
+            .await,
+        Err(BastehError::OutOfRange)
+    ));
+}
+
+pub async fn test_store_push_capped(store: Basteh) {
+    let key = "push_capped_key";
+
+    for i in 1..=5_i64 {
+        store.push_capped(key, i, 3).await.unwrap();
+    }
+    // Only the newest items are kept
+    assert_eq!(
+        store.get_range::<i64>(key, 0, -1).await.unwrap(),
+        vec![3, 4, 5]
+    );
+
+    // Lists already over the cap are trimmed on the next push
+    store.set_list(key, [1_i64, 2, 3, 4]).await.unwrap();
+    store.push_capped(key, 5, 2).await.unwrap();
+    assert_eq!(
+        store.get_range::<i64>(key, 0, -1).await.unwrap(),
+        vec![4, 5]
+    );
+
+    // A cap of zero empties the list, which removes it
+    store.push_capped(key, 6, 0).await.unwrap();
+    assert!(!store.contains_key(key).await.unwrap());
+
+    store.set("push_capped_string", "value").await.unwrap();
+    assert!(matches!(
+        store.push_capped("push_capped_string", 1, 3).await,
+        Err(BastehError::TypeConversion)
+    ));
+}
+
+pub async fn test_store_mutate_list(store: Basteh) {
+    let key = "mutate_list_key";
+
+    store.set_list(key, [1_i64, 2, 3]).await.unwrap();
+    // Push and trim in one call
+    let len = store
+        .mutate_list(key, |l| l.push(4).trim(-3, -1))
+        .await
+        .unwrap();
+    assert_eq!(len, 3);
+    assert_eq!(
+        store.get_range::<i64>(key, 0, -1).await.unwrap(),
+        vec![2, 3, 4]
+    );
+
+    let len = store
+        .mutate_list(key, |l| l.pop().pop().push(5))
+        .await
+        .unwrap();
+    assert_eq!(len, 2);
+    assert_eq!(
+        store.get_range::<i64>(key, 0, -1).await.unwrap(),
+        vec![2, 5]
+    );
+
+    // Missing keys are empty lists
+    let len = store
+        .mutate_list("mutate_list_missing", |l| l.pop().push("a").push("b"))
+        .await
+        .unwrap();
+    assert_eq!(len, 2);
+
+    // Emptied lists are removed
+    let len = store.mutate_list(key, |l| l.trim(1, 0)).await.unwrap();
+    assert_eq!(len, 0);
+    assert!(!store.contains_key(key).await.unwrap());
+
+    store.set("mutate_list_string", "value").await.unwrap();
+    assert!(matches!(
+        store.mutate_list("mutate_list_string", |l| l.push(1)).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert_eq!(
+        store
+            .get::<String>("mutate_list_string")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("value")
+    );
+}
+
+pub async fn test_store_bits(store: Basteh) {
+    let key = "bits_key";
+
+    assert!(!store.get_bit(key, 9).await.unwrap());
+    assert!(!store.set_bit(key, 9, true).await.unwrap());
+    assert!(store.get_bit(key, 9).await.unwrap());
+    assert!(!store.get_bit(key, 8).await.unwrap());
+    assert!(!store.get_bit(key, 1000).await.unwrap());
+    assert!(store.set_bit(key, 9, false).await.unwrap());
+    assert!(!store.get_bit(key, 9).await.unwrap());
+
+    assert!(matches!(
+        store.set_bit(key, u64::MAX, true).await,
+        Err(BastehError::OutOfRange)
+    ));
+
+    store.set_list("bits_list", [1_i64]).await.unwrap();
+    assert!(matches!(
+        store.set_bit("bits_list", 1, true).await,
+        Err(BastehError::TypeConversion)
+    ));
+}
+
+pub async fn test_store_bloom(store: Basteh) {
+    let bloom = store.bloom("bloom_filter").with_params(1000, 0.01);
+    assert!(!bloom.maybe_contains("item0").await.unwrap());
+
+    for i in 0..200 {
+        bloom.add(format!("item{}", i)).await.unwrap();
+    }
+
+    // No false negatives
+    for i in 0..200 {
+        assert!(bloom.maybe_contains(format!("item{}", i)).await.unwrap());
+    }
+
+    // False positives are allowed, but they should be rare
+    let mut false_positives = 0;
+    for i in 0..1000 {
+        if bloom.maybe_contains(format!("other{}", i)).await.unwrap() {
+            false_positives += 1;
+        }
+    }
+    assert!(false_positives < 50);
+
+    // Filters are kept out of the keys of the scope
+    assert!(!store.contains_key("bloom_filter").await.unwrap());
+}
+
+pub async fn test_store_push_len(store: Basteh) {
+    let key = "push_len_key";
+
+    for i in 1..=5_u64 {
+        assert_eq!(store.push_len(key, i as i64).await.unwrap(), i);
+    }
+    assert_eq!(
+        store.get_range::<i64>(key, 0, -1).await.unwrap(),
+        vec![1, 2, 3, 4, 5]
+    );
+
+    store.set("push_len_string", "value").await.unwrap();
+    assert!(matches!(
+        store.push_len("push_len_string", 1).await,
+        Err(BastehError::TypeConversion)
+    ));
+}
+
+pub async fn test_store_delete(store: Basteh) {
+    let key = "delete_key";
+
+    assert!(!store.delete(key).await.unwrap());
+    store.set(key, vec![0_u8; 64 * 1024]).await.unwrap();
+    assert!(store.delete(key).await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+    assert!(!store.delete(key).await.unwrap());
+
+    store.push(key, 1).await.unwrap();
+    assert!(store.delete(key).await.unwrap());
+    assert!(store.get::<i64>(key).await.unwrap().is_none());
+}
+
+pub async fn test_store_upsert(store: Basteh) {
+    let key = "upsert_key";
+
+    assert!(store.upsert(key, "first").await.unwrap());
+    assert!(!store.upsert(key, "second").await.unwrap());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("second".to_owned())
+    );
+
+    // Like set, the expiry is cleared
+    store.expire(key, Duration::from_secs(60)).await.unwrap();
+    assert!(!store.upsert(key, 10).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(10));
+    assert!(store.expiry(key).await.unwrap().is_none());
+
+    // Removed keys are inserted again
+    store.remove::<i64>(key).await.unwrap();
+    assert!(store.upsert(key, vec![1_i64, 2]).await.unwrap());
+    assert!(!store.upsert(key, vec![3_i64]).await.unwrap());
+    assert_eq!(store.get_range::<i64>(key, 0, -1).await.unwrap(), vec![3]);
+}
+
+pub async fn test_store_snapshot(store: Basteh) {
+    let store = store.scope("snapshot_scope");
+    store.set("string", "value").await.unwrap();
+    store.set("number", 42).await.unwrap();
+    store
+        .set("bytes", Bytes::from_static(b"\x00\x01"))
+        .await
+        .unwrap();
+    store
+        .set_expiring("expiring", "soon", Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    let sorted = |mut entries: Vec<SnapshotEntry>| {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    };
+    let snapshot = sorted(store.snapshot_scope().await.unwrap());
+    let keys = snapshot.iter().map(|e| e.0.as_slice()).collect::<Vec<_>>();
+    assert_eq!(keys, vec![&b"bytes"[..], b"expiring", b"number", b"string"]);
+    assert!(snapshot[1].2.is_some());
+
+    store.drop_scope().await.unwrap();
+    assert!(store.snapshot_scope().await.unwrap().is_empty());
+
+    store.import(snapshot.clone()).await.unwrap();
+    let restored = sorted(store.snapshot_scope().await.unwrap());
+    assert_eq!(restored.len(), snapshot.len());
+    for (restored, original) in restored.iter().zip(&snapshot) {
+        assert_eq!(restored.0, original.0);
+        assert_eq!(restored.1, original.1);
+        assert_eq!(restored.2.is_some(), original.2.is_some());
+    }
+    assert!(restored[1].2.unwrap() <= snapshot[1].2.unwrap());
+}
+
+pub async fn test_store_list_drain(store: Basteh) {
+    let key = "list_drain_key";
+    store.set_list(key, [1_i64, 2, 3]).await.unwrap();
+
+    assert_eq!(store.list_drain::<i64>(key).await.unwrap(), vec![1, 2, 3]);
+    assert!(!store.contains_key(key).await.unwrap());
+    assert!(store.list_drain::<i64>(key).await.unwrap().is_empty());
+
+    // Non-list values are kept
+    let key = "list_drain_string_key";
+    store.set(key, "value").await.unwrap();
+    assert!(store.list_drain::<i64>(key).await.is_err());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("value".to_string())
+    );
+}
+
+pub async fn test_store_get_stream(store: Basteh) {
+    let key = "get_stream_key";
+    let value = (0..STREAM_CHUNK_SIZE * 3 + 100)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    store.set(key, Bytes::from(value.clone())).await.unwrap();
+
+    let chunks = store
+        .get_stream(key)
+        .await
+        .unwrap()
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), value);
+
+    store.set("get_stream_string", "value").await.unwrap();
+    let chunks = store
+        .get_stream("get_stream_string")
+        .await
+        .unwrap()
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), b"value");
+
+    assert!(store
+        .get_stream("get_stream_missing")
+        .await
+        .unwrap()
+        .is_none());
+
+    store.set_list("get_stream_list", [1_i64, 2]).await.unwrap();
+    assert!(matches!(
+        store.get_stream("get_stream_list").await,
+        Err(BastehError::TypeConversion)
+    ));
+}
+
+pub async fn test_store_conversion_error(store: Basteh) {
+    let key = "conversion_key";
+    store.set(key, "not a number").await.unwrap();
+
+    let err = store.get::<i64>(key).await.unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains(key));
+    assert!(message.contains("i64"));
+    assert!(message.contains("String"));
+    assert!(matches!(
+        err,
+        BastehError::Conversion {
+            key: ref err_key,
+            expected: "i64",
+            found: ValueKind::String,
+        } if err_key == key
+    ));
+
+    let key = "conversion_list";
+    store
+        .push(key, Bytes::from_static(b"\x00\xff"))
+        .await
+        .unwrap();
+    assert!(matches!(
+        store.pop::<u8>(key).await,
+        Err(BastehError::Conversion {
+            expected: "u8",
+            found: ValueKind::Bytes,
+            ..
+        })
+    ));
+}
+
+pub async fn test_store_value_kind(store: Basteh) {
+    store.set("value_kind_number", 10).await.unwrap();
+    store.set("value_kind_string", "value").await.unwrap();
+    store
+        .set("value_kind_bytes", Bytes::from_static(b"\x00\xff"))
+        .await
+        .unwrap();
+    store.set_list("value_kind_list", [1_i64, 2]).await.unwrap();
+
+    assert_eq!(
+        store.value_kind("value_kind_number").await.unwrap(),
+        Some(ValueKind::Number)
+    );
+    assert_eq!(
+        store.value_kind("value_kind_string").await.unwrap(),
+        Some(ValueKind::String)
+    );
+    assert_eq!(
+        store.value_kind("value_kind_bytes").await.unwrap(),
+        Some(ValueKind::Bytes)
+    );
+    assert_eq!(
+        store.value_kind("value_kind_list").await.unwrap(),
+        Some(ValueKind::List)
+    );
+    assert_eq!(store.value_kind("value_kind_missing").await.unwrap(), None);
+    assert_eq!(
+        store
+            .readonly()
+            .value_kind("value_kind_number")
+            .await
+            .unwrap(),
+        Some(ValueKind::Number)
+    );
+}
+
+pub async fn test_store_touch(store: Basteh) {
+    let key = "touch_key";
+    store.set(key, "value").await.unwrap();
+
+    assert!(store.touch(key).await.unwrap());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("value".to_owned())
+    );
+    assert!(!store.touch("touch_missing_key").await.unwrap());
+    assert!(!store.contains_key("touch_missing_key").await.unwrap());
+}
+
+pub async fn test_store_incr_float(store: Basteh) {
+    let key = "incr_float_key";
+
+    let mut result = 0.0;
+    for _ in 0..10 {
+        result = store.incr_float(key, 0.1).await.unwrap();
+    }
+    assert!((result - 1.0).abs() < 1e-9);
+    assert!((store.incr_float(key, -2.5).await.unwrap() + 1.5).abs() < 1e-9);
+
+    store.set("incr_float_number", 3).await.unwrap();
+    assert!((store.incr_float("incr_float_number", 0.5).await.unwrap() - 3.5).abs() < 1e-9);
+
+    store.set("incr_float_string", "value").await.unwrap();
+    assert!(store.incr_float("incr_float_string", 0.5).await.is_err());
+    assert_eq!(
+        store.get::<String>("incr_float_string").await.unwrap(),
+        Some("value".to_owned())
+    );
+
+    store.set_list("incr_float_list", [1_i64, 2]).await.unwrap();
+    assert!(store.incr_float("incr_float_list", 0.5).await.is_err());
+}
+
+pub async fn test_store_big_number(store: Basteh) {
+    let key = "big_number_key";
+    let big = i64::MAX as i128 + 1;
+
+    store.set(key, i64::MAX).await.unwrap();
+    assert_eq!(store.incr_big(key, 1).await.unwrap(), big);
+    assert_eq!(store.get::<i128>(key).await.unwrap(), Some(big));
+    assert!(store.get::<i64>(key).await.is_err());
+    assert!(matches!(
+        store.mutate(key, |m| m.incr(1)).await,
+        Err(BastehError::NotANumber(_))
+    ));
+
+    // Back in the range of i64, it's a number again
+    assert_eq!(store.incr_big(key, -1).await.unwrap(), i64::MAX as i128);
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(i64::MAX));
+
+    assert_eq!(
+        store.incr_big("big_number_missing", -big).await.unwrap(),
+        -big
+    );
+    assert_eq!(
+        store
+            .incr_big("big_number_missing", i128::MIN + big)
+            .await
+            .unwrap(),
+        i128::MIN
+    );
+    assert!(matches!(
+        store.incr_big("big_number_missing", -1).await,
+        Err(BastehError::InvalidNumber)
+    ));
+    assert_eq!(
+        store.get::<i128>("big_number_missing").await.unwrap(),
+        Some(i128::MIN)
+    );
+
+    store.set("big_number_set", i128::MAX).await.unwrap();
+    assert_eq!(
+        store.get::<i128>("big_number_set").await.unwrap(),
+        Some(i128::MAX)
+    );
+    assert_eq!(
+        store.get::<String>("big_number_set").await.unwrap(),
+        Some(i128::MAX.to_string())
+    );
+
+    store.push("big_number_list", big).await.unwrap();
+    assert_eq!(
+        store.pop::<i128>("big_number_list").await.unwrap(),
+        Some(big)
+    );
+
+    store.set("big_number_string", "value").await.unwrap();
+    assert!(matches!(
+        store.incr_big("big_number_string", 1).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+}
+
+pub async fn test_store_typed(store: Basteh) {
+    let store = store.scope("typed");
+    let typed = store.typed::<String, i64>();
+
+    typed.insert("key1".to_owned(), 10).await.unwrap();
+    typed.insert("key2".to_owned(), -20).await.unwrap();
+    assert_eq!(typed.get("key1".to_owned()).await.unwrap(), Some(10));
+    assert_eq!(typed.get("key2".to_owned()).await.unwrap(), Some(-20));
+    assert_eq!(typed.get("missing".to_owned()).await.unwrap(), None);
+
+    assert_eq!(typed.remove("key1".to_owned()).await.unwrap(), Some(10));
+    assert_eq!(typed.get("key1".to_owned()).await.unwrap(), None);
+
+    store.set("mismatch", "not a number").await.unwrap();
+    assert!(matches!(
+        typed.get("mismatch".to_owned()).await,
+        Err(BastehError::Conversion { .. })
+    ));
+    store.set_list("mismatch_list", [1_i64, 2]).await.unwrap();
+    assert!(matches!(
+        store.typed::<&str, i64>().get("mismatch_list").await,
+        Err(BastehError::Conversion { .. })
+    ));
+}
+
+pub async fn test_store_remove_matching(store: Basteh) {
+    let store = store.scope("remove_matching");
+
+    for key in [
+        "temp:1",
+        "temp:22",
+        "temp:",
+        "tempo",
+        "cache:temp:1",
+        "Temp:1",
+    ] {
+        store.set(key, "value").await.unwrap();
+    }
+    assert_eq!(store.remove_matching("temp:*").await.unwrap(), 3);
+
+    let mut keys = store.keys().await.unwrap().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            b"Temp:1".to_vec(),
+            b"cache:temp:1".to_vec(),
+            b"tempo".to_vec()
+        ]
+    );
+
+    assert_eq!(store.remove_matching("[Tt]emp?1").await.unwrap(), 1);
+    assert_eq!(store.remove_matching("missing*").await.unwrap(), 0);
+    assert!(store.contains_key("tempo").await.unwrap());
+}
+
+pub async fn test_store_get_del(store: Basteh) {
+    let key = "get_del";
+
+    store.set(key, "token").await.unwrap();
+    assert_eq!(
+        store.get_del::<String>(key).await.unwrap(),
+        Some("token".to_owned())
+    );
+    assert!(!store.contains_key(key).await.unwrap());
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    assert_eq!(store.get_del::<String>(key).await.unwrap(), None);
+
+    store.set_list(key, [1_i64, 2]).await.unwrap();
+    assert_eq!(
+        store.get_del::<OwnedValue>(key).await.unwrap(),
+        Some(OwnedValue::List(vec![
+            OwnedValue::Number(1),
+            OwnedValue::Number(2)
+        ]))
+    );
+    assert!(!store.contains_key(key).await.unwrap());
+}
+
+pub async fn test_store_hincr(store: Basteh) {
+    let key = "hincr_key";
+    store.hset(key, "name", "basteh").await.unwrap();
+    store.hset(key, "other", 10).await.unwrap();
+
+    assert_eq!(store.hincr(key, "count", 5).await.unwrap(), 5);
+    assert_eq!(store.hincr(key, "count", -2).await.unwrap(), 3);
+    assert_eq!(store.hget::<i64>(key, "count").await.unwrap(), Some(3));
+
+    // The other fields are untouched
+    assert_eq!(store.hget::<i64>(key, "other").await.unwrap(), Some(10));
+    assert_eq!(
+        store.hget::<String>(key, "name").await.unwrap(),
+        Some("basteh".to_owned())
+    );
+
+    // Missing keys are created
+    assert_eq!(store.hincr("hincr_missing", "count", 1).await.unwrap(), 1);
+
+    assert!(matches!(
+        store.hincr(key, "name", 1).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    store.hset(key, "max", i64::MAX).await.unwrap();
+    assert!(matches!(
+        store.hincr(key, "max", 1).await,
+        Err(BastehError::InvalidNumber)
+    ));
+    assert_eq!(store.hget::<i64>(key, "max").await.unwrap(), Some(i64::MAX));
+
+    store.set("hincr_string", "value").await.unwrap();
+    assert!(matches!(
+        store.hincr("hincr_string", "count", 1).await,
+        Err(BastehError::TypeConversion)
+    ));
+}
+
+pub async fn test_store_map(store: Basteh) {
+    let key = "map_key";
+
+    store.hset(key, "name", "basteh").await.unwrap();
+    store.hset(key, "count", 1).await.unwrap();
+    store.hset(key, b"\x00\xff", "binary").await.unwrap();
+    assert_eq!(store.value_kind(key).await.unwrap(), Some(ValueKind::Map));
+
+    assert_eq!(
+        store.hget::<String>(key, "name").await.unwrap(),
+        Some("basteh".to_owned())
+    );
+    assert_eq!(store.hget::<i64>(key, "count").await.unwrap(), Some(1));
+    assert_eq!(store.hget::<i64>(key, "missing").await.unwrap(), None);
+    assert_eq!(store.hget::<i64>("missing", "count").await.unwrap(), None);
+
+    // Setting an existing field overwrites it
+    store.hset(key, "count", 2).await.unwrap();
+    assert_eq!(store.hget::<i64>(key, "count").await.unwrap(), Some(2));
+
+    let mut fields = store.hgetall::<OwnedValue>(key).await.unwrap();
+    fields.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        fields,
+        vec![
+            (
+                b"\x00\xff".to_vec(),
+                OwnedValue::String("binary".to_owned())
+            ),
+            (b"count".to_vec(), OwnedValue::Number(2)),
+            (b"name".to_vec(), OwnedValue::String("basteh".to_owned())),
+        ]
+    );
+
+    assert!(store.hdel(key, "name").await.unwrap());
+    assert!(!store.hdel(key, "name").await.unwrap());
+    assert_eq!(store.hget::<String>(key, "name").await.unwrap(), None);
+    assert_eq!(store.hgetall::<OwnedValue>(key).await.unwrap().len(), 2);
+
+    // Deleting the last field removes the key
+    assert!(store.hdel(key, "count").await.unwrap());
+    assert!(store.hdel(key, b"\x00\xff").await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+    assert!(store.hgetall::<OwnedValue>(key).await.unwrap().is_empty());
+    assert!(!store.hdel(key, "count").await.unwrap());
+
+    // Hash methods on other kinds of values fail
+    store.set("map_string", "value").await.unwrap();
+    assert!(matches!(
+        store.hset("map_string", "field", 1).await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert!(matches!(
+        store.hget::<i64>("map_string", "field").await,
+        Err(BastehError::TypeConversion)
+    ));
+    assert_eq!(
+        store.get::<String>("map_string").await.unwrap(),
+        Some("value".to_owned())
+    );
+}
+
+pub async fn test_store_keys_stream(store: Basteh) {
+    // More keys than a single page holds on the backends fetching them in pages
+    let store = store.scope("keys_stream");
+    let count = 2500;
+    for i in 0..count {
+        store.set(format!("key_{}", i), i as i64).await.unwrap();
+    }
+
+    let mut keys = store.keys_stream();
+    let mut seen = HashSet::new();
+    while let Some(key) = keys.try_next().await.unwrap() {
+        seen.insert(key);
+    }
+    assert_eq!(seen.len(), count);
+    for i in 0..count {
+        assert!(seen.contains(format!("key_{}", i).as_bytes()));
+    }
+
+    assert!(store
+        .scope("keys_stream_empty")
+        .keys_stream()
+        .try_next()
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub async fn test_store_emptied_list(store: Basteh) {
+    let key = "emptied_list";
+
+    store.set_list(key, [1_i64, 2]).await.unwrap();
+    assert_eq!(store.pop::<i64>(key).await.unwrap(), Some(2));
+    assert!(store.contains_key(key).await.unwrap());
+
+    // Popping the last item removes the key
+    assert_eq!(store.pop::<i64>(key).await.unwrap(), Some(1));
+    assert!(!store.contains_key(key).await.unwrap());
+    assert_eq!(store.value_kind(key).await.unwrap(), None);
+    assert!(store.get_range::<i64>(key, 0, -1).await.unwrap().is_empty());
+
+    // Popping a missing key doesn't create it
+    assert_eq!(store.pop::<i64>(key).await.unwrap(), None);
+    assert!(!store.contains_key(key).await.unwrap());
+
+    // Same for the source of pop_push, unless it's rotated in place
+    let dst = "emptied_list_dst";
+    store.set_list(key, [1_i64]).await.unwrap();
+    assert_eq!(store.pop_push::<i64>(key, key).await.unwrap(), Some(1));
+    assert!(store.contains_key(key).await.unwrap());
+    assert_eq!(store.pop_push::<i64>(key, dst).await.unwrap(), Some(1));
+    assert!(!store.contains_key(key).await.unwrap());
+    assert_eq!(store.get_range::<i64>(dst, 0, -1).await.unwrap(), vec![1]);
+}
+
+pub async fn test_store<P>(store: P)
+where
+    P: 'static + Provider,
+{
+    store_suite(Basteh::build().provider(store).finish()).await;
+}
+
+async fn store_suite(store: Basteh) {
+    tokio::join!(
+        test_store_methods(store.clone()),
+        test_store_bytes(store.clone()),
+        test_store_numbers(store.clone()),
+        test_store_keys(store.clone()),
+        test_store_list(store.clone()),
+        test_store_set_extend_list(store.clone()),
+        test_store_wrong_type(store.clone()),
+        test_store_pop_push(store.clone()),
+        test_store_pop_blocking(store.clone()),
+        test_store_remove_if(store.clone()),
+        test_store_key_normalizer(store.clone()),
+        test_store_entry(store.clone()),
+        test_store_versioned(store.clone()),
+        test_store_contains_multiple(store.clone()),
+        test_store_readonly(store.clone()),
+        test_store_get_cascading(store.clone()),
+        test_store_keys_sorted(store.clone()),
+        test_store_get_range_strict(store.clone()),
+        test_store_get_range_rev(store.clone()),
+        test_store_get_range_missing(store.clone()),
+        test_store_get_range_multiple(store.clone()),
+        test_store_list_drain(store.clone()),
+        test_store_push_capped(store.clone()),
+        test_store_push_len(store.clone()),
+        test_store_mutate_list(store.clone()),
+        test_store_delete(store.clone()),
+        test_store_upsert(store.clone()),
+        test_store_snapshot(store.clone()),
+        test_store_bits(store.clone()),
+        test_store_bloom(store.clone()),
+        test_store_conversion_error(store.clone()),
+        test_store_get_stream(store.clone()),
+        test_store_value_kind(store.clone()),
+        test_store_touch(store.clone()),
+        test_store_incr_float(store.clone()),
+        test_store_big_number(store.clone()),
+        test_store_typed(store.clone()),
+        test_store_remove_matching(store.clone()),
+        test_store_emptied_list(store.clone()),
+        test_store_get_del(store.clone()),
+        test_store_map(store.clone()),
+        test_store_hincr(store.clone()),
+        test_store_keys_stream(store)
+    );
+}
+
+/// Testing lists holding other lists, it's not part of test_store as redis can't store them.
+pub async fn test_nested_list<P>(store: P)
+where
+    P: 'static + Provider,
+{
+    nested_list_suite(Basteh::build().provider(store).finish()).await;
+}
+
+async fn nested_list_suite(store: Basteh) {
+    let key = "nested_list_key";
+
+    let sublist = OwnedValue::List(vec![
+        OwnedValue::String("inner".to_owned()),
+        OwnedValue::Bytes(bytes::BytesMut::from(&b"\x00\xff"[..])),
+        OwnedValue::List(vec![]),
+    ]);
+    let value = OwnedValue::List(vec![
+        OwnedValue::Number(1),
+        sublist.clone(),
+        OwnedValue::String("outer".to_owned()),
+        OwnedValue::List(vec![OwnedValue::List(vec![OwnedValue::Number(-2)])]),
+    ]);
+
+    store.set(key, value.as_value()).await.unwrap();
+    assert_eq!(
+        store.get::<OwnedValue>(key).await.unwrap(),
+        Some(value.clone())
+    );
+    assert_eq!(
+        store.get_range::<OwnedValue>(key, 1, 1).await.unwrap(),
+        vec![sublist.clone()]
+    );
+
+    // Pushing a list adds it as a single item
+    store.push(key, sublist.as_value()).await.unwrap();
+    assert_eq!(
+        store
+            .get_range::<OwnedValue>(key, 0, -1)
+            .await
+            .unwrap()
+            .len(),
+        5
+    );
+    assert_eq!(store.pop::<OwnedValue>(key).await.unwrap(), Some(sublist));
+    assert_eq!(store.get::<OwnedValue>(key).await.unwrap(), Some(value));
+}
+
+/// Testing the write times, it's not part of test_store as it's opt-in for the backends.
+pub async fn test_modified_at<P>(provider: P)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build().provider(provider).finish();
+    let key = "modified_at_key";
+
+    assert_eq!(store.modified_at(key).await.unwrap(), None);
+
+    let before = SystemTime::now() - Duration::from_secs(1);
+    store.set(key, "value").await.unwrap();
+    let written = store.modified_at(key).await.unwrap().unwrap();
+    assert!(written >= before && written <= SystemTime::now());
+
+    // Changing the expiry is not a write
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    store.expire(key, Duration::from_secs(60)).await.unwrap();
+    assert_eq!(store.modified_at(key).await.unwrap(), Some(written));
+
+    store.set(key, "new value").await.unwrap();
+    assert!(store.modified_at(key).await.unwrap().unwrap() > written);
+
+    store.remove::<String>(key).await.unwrap();
+    assert_eq!(store.modified_at(key).await.unwrap(), None);
+}
+
+/// Testing the reported size of a scope, it's not part of test_store as the other tests write to
+/// the same scope concurrently.
+pub async fn test_size_bytes<P>(store: P)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build().provider(store).finish().scope("size_bytes");
+    assert_eq!(store.size_bytes().await.unwrap(), 0);
+
+    let values = [("key1", "a".repeat(100)), ("key2", "b".repeat(1000))];
+    let payload = values.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>() as u64;
+    for (key, value) in values.iter() {
+        store.set(key, value).await.unwrap();
+    }
+    assert!(store.size_bytes().await.unwrap() >= payload);
+    assert_eq!(
+        store.size_bytes().await.unwrap(),
+        store.readonly().size_bytes().await.unwrap()
+    );
+
+    store.remove::<String>("key2").await.unwrap();
+    assert!(store.size_bytes().await.unwrap() < payload);
+    store.remove::<String>("key1").await.unwrap();
+    assert_eq!(store.size_bytes().await.unwrap(), 0);
+}
+
+pub async fn test_drop_scope<P>(store: P)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build().provider(store).finish();
+    let scope = store.scope("drop_scope");
+    let other = store.scope("drop_scope_other");
+
+    scope.set("key1", "value").await.unwrap();
+    scope
+        .set_expiring("key2", 10, Duration::from_secs(60))
+        .await
+        .unwrap();
+    scope.set_versioned("key3", "value", 1).await.unwrap();
+    other.set("key1", "value").await.unwrap();
+
+    scope.drop_scope().await.unwrap();
+    assert_eq!(scope.keys().await.unwrap().count(), 0);
+    assert_eq!(scope.get::<String>("key1").await.unwrap(), None);
+    assert_eq!(
+        other.get::<String>("key1").await.unwrap(),
+        Some("value".to_owned())
+    );
+
+    // Dropping a missing scope is not an error, and dropped scopes can be used again
+    scope.drop_scope().await.unwrap();
+    scope.set("key1", "new value").await.unwrap();
+    assert_eq!(
+        scope.get::<String>("key1").await.unwrap(),
+        Some("new value".to_owned())
+    );
+    assert!(scope.set_versioned("key3", "value", 1).await.unwrap());
+}
+
+/// Testing the length of the request queue reported by `queue_len`, for the backends started with
+/// a single worker thread.
+///
+/// `block` is a request keeping the worker busy until `release` is called, so the requests sent
+/// meanwhile wait in the queue.
+pub async fn test_queue_len<P, F>(
+    provider: P,
+    queue_len: impl Fn() -> usize,
+    block: F,
+    release: impl FnOnce(),
+) where
+    P: 'static + Provider,
+    F: Future<Output = ()>,
+{
+    let store = Basteh::build().provider(provider).finish();
+    assert_eq!(queue_len(), 0);
+
+    // The request is sent on its first poll, once the worker takes it nothing else is taken
+    let mut block = Box::pin(block);
+    assert!((&mut block).now_or_never().is_none());
+    while queue_len() != 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    let mut requests = (0..10)
+        .map(|i| Box::pin(store.set(format!("key{}", i), i)))
+        .collect::<Vec<_>>();
+    for request in &mut requests {
+        assert!(request.now_or_never().is_none());
+    }
+    assert_eq!(queue_len(), 10);
+
+    release();
+    block.await;
+    for request in requests {
+        request.await.unwrap();
+    }
+    assert_eq!(queue_len(), 0);
+    assert_eq!(store.get::<i64>("key9").await.unwrap(), Some(9));
+}
+
+pub async fn test_scopes<P>(store: P)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build().provider(store).finish();
+    let names = ["scopes_one", "scopes_two", "scopes_three"];
+    for name in names {
+        store.scope(name).set("key", "value").await.unwrap();
+    }
+    // Dropped and emptied scopes are not listed
+    store
+        .scope("scopes_dropped")
+        .set("key", "value")
+        .await
+        .unwrap();
+    store.scope("scopes_dropped").drop_scope().await.unwrap();
+    store
+        .scope("scopes_emptied")
+        .set("key", "value")
+        .await
+        .unwrap();
+    store
+        .scope("scopes_emptied")
+        .remove::<String>("key")
+        .await
+        .unwrap();
+
+    let scopes = store.scopes().await.unwrap().collect::<HashSet<_>>();
+    for name in names {
+        assert!(scopes.contains(name.as_bytes()));
+    }
+    assert!(!scopes.contains(b"scopes_dropped".as_ref()));
+    assert!(!scopes.contains(b"scopes_emptied".as_ref()));
+}
+
+/// Testing scopes which are not valid utf-8, it's not part of test_store as some backends only
+/// support utf-8 scopes.
+pub async fn test_binary_scope<P>(store: P)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build().provider(store).finish();
+    let scope1 = store.scope(b"\xff\xfe\x00scope");
+    let scope2 = store.scope(b"\xff\xfe\x01scope");
+
+    scope1.set("binary_key", "value1").await.unwrap();
+    scope2.set("binary_key", "value2").await.unwrap();
+
+    assert_eq!(
+        scope1.get::<String>("binary_key").await.unwrap(),
+        Some("value1".to_owned())
+    );
+    assert_eq!(
+        scope2.get::<String>("binary_key").await.unwrap(),
+        Some("value2".to_owned())
+    );
+    assert_eq!(
+        scope1.keys().await.unwrap().collect::<Vec<_>>(),
+        vec![b"binary_key".to_vec()]
+    );
+
+    scope1.remove::<String>("binary_key").await.unwrap();
+    assert!(!scope1.contains_key("binary_key").await.unwrap());
+    assert!(scope2.contains_key("binary_key").await.unwrap());
+    assert!(!store.contains_key("binary_key").await.unwrap());
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////////////    Expiration tests     /////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Testing the expiry basics by setting a key and expiry, then waiting for it to expire
+pub async fn test_expiry_basics(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expiring_key";
+    let value = "val";
+
+    // Provider for invalid key should return None
+    assert!(store.expiry(key).await.unwrap().is_none());
+
+    // Testing set and get before expiry
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+
+    // The exact duration depends on the implementation
+    let exp = store.expiry(key).await.unwrap().unwrap();
+    assert!(exp.as_secs() > 0);
+    assert!(exp.as_secs() <= delay_secs);
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if extended item has been expired
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+}
+
+/// Testing extending functionality by setting an expiry and extending it later,
+/// The key shouldn't be expired before the sum of default expiry and extended time
+pub async fn test_expiry_extend(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "extended_expiring_key";
+    let value = "val";
+
+    // Testing set and get before expiry
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+
+    // Testing extending the expiry time
+    // The exact duration depends on the implementation
+    store.extend(key, delay).await.unwrap();
+
+    let exp = store.expiry(key).await.unwrap().unwrap();
+    assert!(exp.as_secs() >= delay_secs);
+    assert!(exp.as_secs() <= delay_secs * 2);
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if extended item still exist
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if extended item has been expired
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+}
+
+/// Testing persist, by setting an expiry for a key and making it persistant later
+pub async fn test_expiry_persist(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "persistant_key";
+    let value = "val";
+
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+    assert!(store.persist(key).await.is_ok());
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if persistent key is still there
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+}
+
+/// Testing persist, by setting an expiry for a key and making it persistant later
+pub async fn test_mutate_sould_not_change_expiry(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "mutated_key";
+    let value = 1200;
+
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+
+    assert!(store.mutate(key, |m| m.incr(1300)).await.is_ok());
+
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(2500));
+
+    // Check if persistent expiry have changed
+    assert!(store.expiry(key).await.unwrap().is_some());
+
+    // Setting the value with a mutation shouldn't clear the expiry either
+    assert_eq!(store.mutate(key, |m| m.set(10)).await.unwrap(), 10);
+    assert!(store.expiry(key).await.unwrap().is_some());
+    assert_eq!(store.mutate(key, |m| m.set(5).incr(1)).await.unwrap(), 6);
+    assert!(store.expiry(key).await.unwrap().is_some());
+}
+
+/// Testing if calling set after expire, clears expiration from the key
+pub async fn test_expiry_set_clearing(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "set_after_expire_key";
+    let value = "val";
+
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+    assert!(store.set(key, value).await.is_ok());
+
+    tokio::time::sleep(Duration::from_secs((delay_secs) + 1)).await;
+
+    // Check if calling set twice cleaerd the expire
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+}
+
+/// Testing if set_keepttl changes the value without touching the expiry
+pub async fn test_expiry_set_keepttl(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "set_keepttl_key";
+    let persistent_key = "set_keepttl_persistent_key";
+
+    assert!(store.set(key, "old").await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+    assert!(store.set_keepttl(key, "new").await.is_ok());
+
+    let exp = store.expiry(key).await.unwrap().unwrap();
+    assert!(exp.as_secs() > 0);
+    assert!(exp.as_secs() <= delay_secs);
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("new".to_owned())
+    );
+
+    // Missing and persistent keys stay persistent
+    assert!(store.set_keepttl(persistent_key, "old").await.is_ok());
+    assert!(store.set_keepttl(persistent_key, "new").await.is_ok());
+    assert_eq!(store.expiry(persistent_key).await.unwrap(), None);
+
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    assert_eq!(
+        store.get::<String>(persistent_key).await.unwrap(),
+        Some("new".to_owned())
+    );
+}
+
+/// Testing if second call to expire overrides the first one
+/// The key should expire in this test, as we set a shorter period second time
+pub async fn test_expiry_override_shorter(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_override_shorter_key";
+    let value = "val";
+
+    // Test if second call to expire overwrites the first expiry
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay * 5).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if calling set twice cleaerd the expire
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+}
+
+/// Testing if second call to expire overrides the first one
+/// The key shouldn't expire in this test, as we set a longer period second time
+pub async fn test_expiry_override_longer(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_override_longer_key";
+    let value = "val";
+
+    // Test if second call to expire overwrites the first expiry
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+    assert!(store.expire(key, delay * 5).await.is_ok());
+
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if calling set twice cleaerd the expire
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+}
+
+// delay_secs is the duration of time we set for expiry and we wait to see
+// the result, it should depend on how much delay an implementer has between
+// getting a command and executing it
+pub async fn test_expiry<P>(provider: P, delay_secs: u64)
+where
+    P: 'static + Provider,
+{
+    expiry_suite(Basteh::build().provider(provider).finish(), delay_secs).await;
+}
+
+async fn expiry_suite(store: Basteh, delay_secs: u64) {
+    tokio::join!(
+        test_expiry_basics(store.clone(), delay_secs),
+        test_mutate_sould_not_change_expiry(store.clone(), delay_secs,),
+        test_expiry_extend(store.clone(), delay_secs),
+        test_expiry_persist(store.clone(), delay_secs),
+        test_expiry_set_clearing(store.clone(), delay_secs),
+        test_expiry_set_keepttl(store.clone(), delay_secs),
+        test_expiry_override_shorter(store.clone(), delay_secs),
+        test_expiry_override_longer(store, delay_secs)
+    );
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////////    Basteh-Expiration tests     //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub async fn test_expiry_store_basics(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expiry_store_key";
+    let value = "value";
+
+    // Test set and get expiring
+    assert!(store.set_expiring(key, value, delay).await.is_ok());
+
+    let (v, e) = store.get_expiring::<String>(key).await.unwrap().unwrap();
+    assert_eq!(&v, &value);
+    assert!(e.unwrap().as_secs() > 0);
+    assert!(e.unwrap().as_secs() <= delay_secs);
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if first item expired as expected
+    assert_eq!(store.get_expiring::<String>(key).await.unwrap(), None);
+}
+
+/// Testing if second call to expire overrides the first one
+/// The key should expire in this test, as we set a shorter period second time
+pub async fn test_expiry_store_override_shorter(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_store_override_shorter_key";
+    let value = "value";
+
+    // Test if second call to set expiring, overwrites expiry for key
+    assert!(store.set_expiring(key, value, delay).await.is_ok());
+    assert!(store.set_expiring(key, value, delay * 2).await.is_ok());
+    let exp = store.expiry(key).await.unwrap().unwrap();
+    assert!(exp.as_secs() > delay_secs);
+    assert!(exp.as_secs() <= delay_secs * 2);
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if the second call to set overwrites expiry
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+}
+
+/// Testing if second call to expire overrides the first one
+/// The key shouldn't expire in this test, as we set a longer period second time
+pub async fn test_expiry_store_override_longer(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_store_override_longer_key";
+    let value = "value";
+
+    // Test if second call to set expiring, overwrites expiry for key
+    assert!(store.set_expiring(key, value, delay * 2).await.is_ok());
     assert!(store.set_expiring(key, value, delay).await.is_ok());
     let exp = store.expiry(key).await.unwrap().unwrap();
     assert!(exp.as_secs() > 0);
     assert!(exp.as_secs() <= delay_secs);
 
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Check if the second call to set overwrites expiry
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+}
+
+/// Testing if mutation after expiry works as expected
+pub async fn test_expiry_store_mutate_after_expiry(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_store_mutate_after_expiry_key";
+    let value = 1000;
+
+    // Set a number and set expiry for it
+    assert!(store.set(key, value).await.is_ok());
+    assert!(store.expire(key, delay).await.is_ok());
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+
+    // Mutate the number
+    store.mutate(key, |m| m.incr(100)).await.unwrap();
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(100))
+}
+
+/// Testing if get_and_extend replaces the expiry of the key
+pub async fn test_expiry_store_get_and_extend(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_store_get_and_extend_key";
+    let value = "value";
+
+    assert!(store.set_expiring(key, value, delay).await.is_ok());
+    assert_eq!(
+        store
+            .get_and_extend::<String>(key, delay * 3)
+            .await
+            .unwrap(),
+        Some(value.to_owned())
+    );
+    let exp = store.expiry(key).await.unwrap().unwrap();
+    assert!(exp.as_secs() > delay_secs * 2);
+    assert!(exp.as_secs() <= delay_secs * 3);
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some(value.to_owned())
+    );
+
+    // Missing keys are not created
+    let missing = "expire_store_get_and_extend_missing";
+    assert_eq!(
+        store
+            .get_and_extend::<String>(missing, delay)
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(!store.contains_key(missing).await.unwrap());
+}
+
+pub async fn test_expiry_store_swap_expiring(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "expire_store_swap_expiring_key";
+
+    store.set(key, "old").await.unwrap();
+    assert!(store.expiry(key).await.unwrap().is_none());
+    assert_eq!(
+        store
+            .swap_expiring::<String>(key, "new", delay * 2)
+            .await
+            .unwrap(),
+        Some("old".to_owned())
+    );
+    let exp = store.expiry(key).await.unwrap().unwrap();
+    assert!(exp.as_secs() > delay_secs);
+    assert!(exp.as_secs() <= delay_secs * 2);
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("new".to_owned())
+    );
+
+    // Missing keys are created
+    let missing = "expire_store_swap_expiring_missing";
+    assert_eq!(
+        store
+            .swap_expiring::<String>(missing, "value", delay)
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(store.expiry(missing).await.unwrap().is_some());
+
+    // Adding some error to the delay, for the implementers sake
+    tokio::time::sleep(Duration::from_secs(delay_secs * 2 + 1)).await;
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    assert_eq!(store.get::<String>(missing).await.unwrap(), None);
+}
+
+pub async fn test_expiry_store_incr_with_ttl_floor(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let key = "incr_ttl_floor_key";
+
+    assert_eq!(store.incr_with_ttl_floor(key, 1, delay).await.unwrap(), 1);
+    assert!(store.expiry(key).await.unwrap().unwrap() <= delay);
+
+    // Later increments grow the counter without extending the expiry
+    assert_eq!(
+        store.incr_with_ttl_floor(key, 2, delay * 10).await.unwrap(),
+        3
+    );
+    assert!(store.expiry(key).await.unwrap().unwrap() <= delay);
+
+    tokio::time::sleep(delay * 2).await;
+    assert_eq!(store.get::<i64>(key).await.unwrap(), None);
+
+    // The counter starts over once it's expired
+    assert_eq!(store.incr_with_ttl_floor(key, 1, delay).await.unwrap(), 1);
+
+    // Persistent keys get the ttl as well
+    let persistent = "incr_ttl_floor_persistent";
+    store.set(persistent, 10).await.unwrap();
+    assert_eq!(
+        store
+            .incr_with_ttl_floor(persistent, 1, delay)
+            .await
+            .unwrap(),
+        11
+    );
+    assert!(store.expiry(persistent).await.unwrap().is_some());
+
+    let string = "incr_ttl_floor_string";
+    store.set(string, "value").await.unwrap();
+    assert!(matches!(
+        store.incr_with_ttl_floor(string, 1, delay).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    assert_eq!(
+        store.get::<String>(string).await.unwrap(),
+        Some("value".to_owned())
+    );
+}
+
+pub async fn test_expiry_store_expire_multiple(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let keys = ["expire_multi_1", "expire_multi_2", "expire_multi_3"];
+
+    for key in keys {
+        store.set(key, "value").await.unwrap();
+    }
+
+    // Missing keys are ignored
+    store
+        .expire_multiple([
+            (keys[0], delay),
+            (keys[1], delay * 3),
+            (keys[2], delay * 10),
+            ("expire_multi_missing", delay),
+        ])
+        .await
+        .unwrap();
+
+    let first = store.expiry(keys[0]).await.unwrap().unwrap();
+    let second = store.expiry(keys[1]).await.unwrap().unwrap();
+    let third = store.expiry(keys[2]).await.unwrap().unwrap();
+    assert!(first <= delay);
+    assert!(second > delay && second <= delay * 3);
+    assert!(third > delay * 3 && third <= delay * 10);
+    assert!(!store.contains_key("expire_multi_missing").await.unwrap());
+
+    tokio::time::sleep(delay * 2).await;
+    assert_eq!(store.get::<String>(keys[0]).await.unwrap(), None);
+    assert_eq!(
+        store.get::<String>(keys[1]).await.unwrap(),
+        Some("value".to_owned())
+    );
+    assert_eq!(
+        store.get::<String>(keys[2]).await.unwrap(),
+        Some("value".to_owned())
+    );
+}
+
+pub async fn test_expiry_store_get_expiring_multiple(store: Basteh, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
+    let keys = [
+        "get_expiring_multi_1",
+        "get_expiring_multi_missing",
+        "get_expiring_multi_2",
+        "get_expiring_multi_3",
+    ];
+
+    store.set_expiring(keys[0], "first", delay).await.unwrap();
+    store
+        .set_expiring(keys[2], "second", delay * 10)
+        .await
+        .unwrap();
+    store.set(keys[3], "third").await.unwrap();
+
+    let res = store.get_expiring_multiple::<String>(keys).await.unwrap();
+    assert_eq!(res.len(), 4);
+
+    let (first, first_exp) = res[0].clone().unwrap();
+    assert_eq!(first, "first");
+    assert!(first_exp.unwrap() <= delay);
+    assert!(first_exp.unwrap() > delay / 2);
+
+    assert_eq!(res[1], None);
+
+    let (second, second_exp) = res[2].clone().unwrap();
+    assert_eq!(second, "second");
+    assert!(second_exp.unwrap() <= delay * 10);
+    assert!(second_exp.unwrap() > delay * 9);
+
+    assert_eq!(res[3], Some(("third".to_owned(), None)));
+
+    // Expired keys are missing, the others are still in place
+    tokio::time::sleep(delay * 2).await;
+    let res = store.get_expiring_multiple::<String>(keys).await.unwrap();
+    assert_eq!(res[0], None);
+    assert_eq!(res[2].as_ref().map(|(v, _)| v.as_str()), Some("second"));
+    assert_eq!(res[3], Some(("third".to_owned(), None)));
+
+    assert!(store
+        .get_expiring_multiple::<String>(Vec::<&str>::new())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+// delay_secs is the duration of time we set for expiry and we wait to see
+// the result, it should depend on how much delay an implementer has between
+// getting a command and executing it
+pub async fn test_expiry_store<P>(provider: P, delay_secs: u64)
+where
+    P: 'static + Provider,
+{
+    expiry_store_suite(Basteh::build().provider(provider).finish(), delay_secs).await;
+}
+
+async fn expiry_store_suite(store: Basteh, delay_secs: u64) {
+    tokio::join!(
+        test_expiry_store_basics(store.clone(), delay_secs),
+        test_expiry_store_override_shorter(store.clone(), delay_secs),
+        test_expiry_store_override_longer(store.clone(), delay_secs),
+        test_expiry_store_mutate_after_expiry(store.clone(), delay_secs),
+        test_expiry_store_get_and_extend(store.clone(), delay_secs),
+        test_expiry_store_swap_expiring(store.clone(), delay_secs),
+        test_expiry_store_incr_with_ttl_floor(store.clone(), delay_secs),
+        test_expiry_store_expire_multiple(store.clone(), delay_secs),
+        test_expiry_store_get_expiring_multiple(store, delay_secs),
+    );
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////////////    Mutation tests     //////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub async fn test_mutate_numbers(store: Basteh) {
+    let key = "mutate_key";
+
+    // Increase by 1600, key doesn't exist so it should be considered 0
+    let mut_res = store.mutate(key, |m| m.incr(1600)).await;
+    assert_eq!(mut_res.unwrap(), 1600);
+
+    let get_res = store.get(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(1600));
+
+    // Decrease by 200
+    let mut_res = store.mutate(key, |m| m.decr(200)).await;
+    assert_eq!(mut_res.unwrap(), 1400);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(1400));
+
+    // Mutiply by 2
+    let mut_res = store.mutate(key, |m| m.mul(2)).await;
+    assert_eq!(mut_res.unwrap(), 2800);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(2800));
+
+    // Divide by 4
+    let mut_res = store.mutate(key, |m| m.div(4)).await;
+    assert_eq!(mut_res.unwrap(), 700);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(700));
+
+    // Set to 100
+    let mut_res = store.mutate(key, |m| m.set(100)).await;
+    assert_eq!(mut_res.unwrap(), 100);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(100));
+
+    // Conditional if
+    let mut_res = store
+        .mutate(key, |m| m.if_(Ordering::Equal, 100, |m| m.set(200)))
+        .await;
+    assert_eq!(mut_res.unwrap(), 200);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(200));
+
+    // Conditional if else
+    let mut_res = store
+        .mutate(key, |m| {
+            m.if_else(Ordering::Greater, 200, |m| m.decr(100), |m| m.decr(50))
+        })
+        .await;
+    assert_eq!(mut_res.unwrap(), 150);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(150));
+
+    // Multi level conditionals
+    let mutation = |m: Mutation| {
+        m.if_(Ordering::Greater, 100, |m| {
+            m.if_(Ordering::Less, 200, |m| {
+                m.if_else(Ordering::Greater, 150, |m| m.set(125), |m| m.set(175))
+            })
+        })
+    };
+    let mut_res = store.mutate(key, mutation).await;
+    assert_eq!(mut_res.unwrap(), 175);
+
+    let get_res = store.get::<i64>(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(175));
+
+    // Multi level conditionals
+    let mut_res = store.mutate(key, mutation).await;
+    assert_eq!(mut_res.unwrap(), 125);
+
+    let get_res = store.get(key).await;
+    assert!(get_res.is_ok());
+    assert_eq!(get_res.unwrap(), Some(125));
+}
+
+async fn test_mutate_edge_cases(store: Basteh) {
+    let key = "mutate_edge_key";
+
+    // No mutations, no value, should be set to 0
+    let mut_res = store.mutate(key, |m| m).await;
+    assert_eq!(mut_res.unwrap(), 0);
+
+    // Check if it is actually set to 0
+    let get_res = store.get(key).await;
+    assert_eq!(get_res.unwrap(), Some(0));
+
+    store.set(key, 100).await.ok();
+
+    // No mutations, with value, should keep the value
+    let mut_res = store.mutate(key, |m| m).await;
+    assert_eq!(mut_res.unwrap(), 100);
+
+    // Check if it is actually kept the value
+    let get_res = store.get(key).await;
+    assert_eq!(get_res.unwrap(), Some(100));
+
+    store.set(key, "Hi").await.ok();
+
+    // No mutations, with string value
+    let mut_res = store.mutate(key, |m| m).await;
+    assert!(mut_res.is_err());
+
+    // Check if it is actually kept the value
+    let get_res = store.get::<String>(key).await;
+    assert_eq!(get_res.unwrap(), Some("Hi".to_string()));
+
+    // No mutations, with string value
+    let mut_res = store.mutate(key, |m| m.incr(100)).await;
+    assert!(mut_res.is_err());
+
+    // Check if it is actually kept the value
+    let get_res = store.get::<String>(key).await;
+    assert_eq!(get_res.unwrap(), Some("Hi".to_string()));
+}
+
+async fn test_mutate_non_number(store: Basteh) {
+    let key = "mutate_non_number_key";
+
+    store.set(key, "Hi").await.unwrap();
+    assert!(matches!(
+        store.mutate(key, |m| m.incr(1)).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    assert!(matches!(
+        store.mutate(key, |m| m).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    // Setting doesn't depend on the current value, but in strict mode it shouldn't overwrite it
+    let strict = Basteh {
+        strict_mutate: true,
+        ..store.clone()
+    };
+    assert!(matches!(
+        strict.mutate(key, |m| m.set(5)).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    assert!(matches!(
+        strict.mutate(key, |m| m.set(5).incr(1)).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("Hi".to_owned())
+    );
+
+    // Not a valid utf-8 string, so it can't be confused with one
+    store
+        .set(key, Bytes::from_static(b"\xff\x00\xff"))
+        .await
+        .unwrap();
+    assert!(matches!(
+        store.mutate(key, |m| m.incr(1).mul(2)).await,
+        Err(BastehError::NotANumber(ValueKind::Bytes))
+    ));
+
+    store.remove::<Bytes>(key).await.unwrap();
+    store.push(key, 10).await.unwrap();
+    assert!(matches!(
+        store.mutate(key, |m| m.decr(1)).await,
+        Err(BastehError::NotANumber(ValueKind::List))
+    ));
+    assert!(matches!(
+        strict.mutate(key, |m| m.set(1)).await,
+        Err(BastehError::NotANumber(ValueKind::List))
+    ));
+    assert_eq!(store.get_range::<i64>(key, 0, -1).await.unwrap(), vec![10]);
+}
+
+async fn test_mutate_preview(store: Basteh) {
+    let key = "mutate_preview_key";
+
+    // Absent keys are treated as 0 and are not created
+    assert_eq!(store.preview_mutate(key, |m| m.incr(5)).await.unwrap(), 5);
+    assert!(!store.contains_key(key).await.unwrap());
+
+    store.set(key, 10).await.unwrap();
+    assert_eq!(
+        store
+            .preview_mutate(key, |m| m.mul(3).if_(Ordering::Greater, 20, |m| m.decr(5)))
+            .await
+            .unwrap(),
+        25
+    );
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(10));
+
+    // Same errors as mutate
+    assert!(matches!(
+        store.preview_mutate(key, |m| m.mul(i64::MAX)).await,
+        Err(BastehError::InvalidNumber)
+    ));
+    store.set(key, "Hi").await.unwrap();
+    assert!(matches!(
+        store.preview_mutate(key, |m| m.incr(1)).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+}
+
+async fn test_mutate_returning(store: Basteh) {
+    let key = "mutate_returning_key";
+
+    store.set(key, 10).await.unwrap();
+    assert_eq!(
+        store.mutate_returning(key, |m| m.incr(5)).await.unwrap(),
+        (10, 15)
+    );
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(15));
+
+    // Absent keys are treated as 0
+    let key = "mutate_returning_absent_key";
+    assert_eq!(
+        store
+            .mutate_returning(key, |m| m.incr(2).mul(3))
+            .await
+            .unwrap(),
+        (0, 6)
+    );
+
+    store.set(key, "Hi").await.unwrap();
+    assert!(matches!(
+        store.mutate_returning(key, |m| m.incr(1)).await,
+        Err(BastehError::NotANumber(_))
+    ));
+}
+
+async fn test_set_if(store: Basteh) {
+    let key = "set_if_key";
+
+    // Missing keys are written unless equality is expected
+    assert!(!store.set_if(key, 10, Ordering::Equal).await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+    assert!(store.set_if(key, 10, Ordering::Greater).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(10));
+
+    // Greater only updates when the new value exceeds the stored one
+    assert!(!store.set_if(key, 5, Ordering::Greater).await.unwrap());
+    assert!(!store.set_if(key, 10, Ordering::Greater).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(10));
+    assert!(store.set_if(key, 15, Ordering::Greater).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(15));
+
+    assert!(!store.set_if(key, 20, Ordering::Less).await.unwrap());
+    assert!(store.set_if(key, -3, Ordering::Less).await.unwrap());
+    assert!(store.set_if(key, -3, Ordering::Equal).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(-3));
+
+    // The expiry is kept
+    store.expire(key, Duration::from_secs(60)).await.unwrap();
+    assert!(store.set_if(key, 0, Ordering::Greater).await.unwrap());
+    assert!(store.expiry(key).await.unwrap().is_some());
+
+    // Large numbers are compared exactly, even where they don't fit in a double
+    store.set(key, 9007199254740992_i64).await.unwrap();
+    assert!(!store
+        .set_if(key, 9007199254740993, Ordering::Equal)
+        .await
+        .unwrap());
+    assert!(store
+        .set_if(key, 9007199254740993, Ordering::Greater)
+        .await
+        .unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(9007199254740993));
+    store.set(key, i64::MAX).await.unwrap();
+    assert!(!store
+        .set_if(key, i64::MAX, Ordering::Greater)
+        .await
+        .unwrap());
+    assert!(store
+        .set_if(key, i64::MAX - 1, Ordering::Less)
+        .await
+        .unwrap());
+    assert!(store.set_if(key, i64::MIN, Ordering::Less).await.unwrap());
+    assert!(!store.set_if(key, -1, Ordering::Less).await.unwrap());
+    assert!(store.set_if(key, -1, Ordering::Greater).await.unwrap());
+    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(-1));
+
+    // Decimals are not numbers either
+    store.set(key, "1.5").await.unwrap();
+    assert!(matches!(
+        store.set_if(key, 1, Ordering::Greater).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+
+    store.set(key, "Hi").await.unwrap();
+    assert!(matches!(
+        store.set_if(key, 1, Ordering::Greater).await,
+        Err(BastehError::NotANumber(ValueKind::String))
+    ));
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("Hi".to_owned())
+    );
+}
+
+async fn test_mutate_backend_error() {
+    let store = Basteh::build().provider(FailingProvider).finish();
+
+    assert!(matches!(
+        store.mutate("mutate_failing_key", |m| m.incr(1)).await,
+        Err(BastehError::Custom(_))
+    ));
+}
+
+async fn test_mutate_list(store: Basteh) {
+    store.push("mutate_list", "value").await.unwrap();
+
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some("value".to_string()));
+
+    store
+        .push_mutiple(
+            "mutate_list",
+            vec!["value1", "value2", "value3", "value4"].into_iter(),
+        )
+        .await
+        .unwrap();
+
+    store.push("mutate_list", 100).await.unwrap();
+    store
+        .push("mutate_list", Bytes::from_static(b"\0100\0"))
+        .await
+        .unwrap();
+
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some(Bytes::from_static(b"\0100\0")));
 
-    // Check if the second call to set overwrites expiry
-    assert_eq!(store.get::<String>(key).await.unwrap(), None);
-}
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some(100));
 
-/// Testing if mutation after expiry works as expected
-pub async fn test_expiry_store_mutate_after_expiry(store: Basteh, delay_secs: u64) {
-    let delay = Duration::from_secs(delay_secs);
-    let key = "expire_store_mutate_after_expiry_key";
-    let value = 1000;
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some("value4".to_string()));
 
-    // Set a number and set expiry for it
-    assert!(store.set(key, value).await.is_ok());
-    assert!(store.expire(key, delay).await.is_ok());
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some("value3".to_string()));
 
-    // Adding some error to the delay, for the implementers sake
-    tokio::time::sleep(Duration::from_secs(delay_secs + 1)).await;
+    store.push("mutate_list", "value5").await.unwrap();
 
-    // Mutate the number
-    store.mutate(key, |m| m.incr(100)).await.unwrap();
-    assert_eq!(store.get::<i64>(key).await.unwrap(), Some(100))
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some("value5".to_string()));
+
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some("value2".to_string()));
+
+    let pop_value = store.pop("mutate_list").await.unwrap();
+    assert_eq!(pop_value, Some("value1".to_string()));
+
+    let pop_value = store.pop::<String>("mutate_list").await.unwrap();
+    assert_eq!(pop_value, None);
 }
 
 // delay_secs is the duration of time we set for expiry and we wait to see
 // the result, it should depend on how much delay an implementer has between
 // getting a command and executing it
-pub async fn test_expiry_store<P>(provider: P, delay_secs: u64)
+pub async fn test_mutations<P>(provider: P)
 where
     P: 'static + Provider,
 {
-    let store = Basteh::build().provider(provider).finish();
+    mutation_suite(Basteh::build().provider(provider).finish()).await;
+}
+
+async fn mutation_suite(store: Basteh) {
+    tokio::join!(
+        test_mutate_numbers(store.clone()),
+        test_mutate_edge_cases(store.clone()),
+        test_mutate_list(store.clone()),
+        test_mutate_non_number(store.clone()),
+        test_mutate_preview(store.clone()),
+        test_mutate_returning(store.clone()),
+        test_set_if(store.clone()),
+        test_mutate_backend_error(),
+    );
+}
+
+/// A provider failing every operation with a custom error, used to simulate an unavailable backend
+pub struct FailingProvider;
+
+impl FailingProvider {
+    fn error() -> BastehError {
+        BastehError::custom(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "Backend is not available",
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl Provider for FailingProvider {
+    async fn keys(&self, _: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        Err(Self::error())
+    }
+
+    async fn set(&self, _: &[u8], _: &[u8], _: Value<'_>) -> Result<()> {
+        Err(Self::error())
+    }
+
+    async fn get(&self, _: &[u8], _: &[u8]) -> Result<Option<OwnedValue>> {
+        Err(Self::error())
+    }
+
+    async fn get_range(&self, _: &[u8], _: &[u8], _: i64, _: i64) -> Result<Vec<OwnedValue>> {
+        Err(Self::error())
+    }
+
+    async fn push(&self, _: &[u8], _: &[u8], _: Value<'_>) -> Result<()> {
+        Err(Self::error())
+    }
+
+    async fn push_multiple(&self, _: &[u8], _: &[u8], _: Vec<Value<'_>>) -> Result<()> {
+        Err(Self::error())
+    }
+
+    async fn pop(&self, _: &[u8], _: &[u8]) -> Result<Option<OwnedValue>> {
+        Err(Self::error())
+    }
+
+    async fn mutate(&self, _: &[u8], _: &[u8], _: Mutation) -> Result<i64> {
+        Err(Self::error())
+    }
+
+    async fn remove(&self, _: &[u8], _: &[u8]) -> Result<Option<OwnedValue>> {
+        Err(Self::error())
+    }
+
+    async fn contains_key(&self, _: &[u8], _: &[u8]) -> Result<bool> {
+        Err(Self::error())
+    }
+
+    async fn persist(&self, _: &[u8], _: &[u8]) -> Result<()> {
+        Err(Self::error())
+    }
+
+    async fn expire(&self, _: &[u8], _: &[u8], _: Duration) -> Result<()> {
+        Err(Self::error())
+    }
+
+    async fn expiry(&self, _: &[u8], _: &[u8]) -> Result<Option<Duration>> {
+        Err(Self::error())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////////////    Layered tests     ///////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Testing the read-through behavior of the layered provider, the providers should be clones
+/// sharing the same underlying storage so we can inspect each tier separately.
+pub async fn test_layered<F, S>(fast: F, slow: S)
+where
+    F: 'static + Provider + Clone,
+    S: 'static + Provider + Clone,
+{
+    let fast_store = Basteh::build().provider(fast.clone()).finish();
+    let slow_store = Basteh::build().provider(slow.clone()).finish();
+    assert_eq!(
+        LayeredProvider::new(fast.clone(), slow.clone()).capabilities(),
+        Capabilities {
+            expiry: false,
+            mutations: false,
+            nested_lists: false,
+        }
+    );
+    let store = Basteh::build().layered(fast, slow).finish();
+
+    let key = "layered_key";
+
+    // Only available in the slow tier
+    slow_store.set(key, "slow value").await.unwrap();
+    assert!(!fast_store.contains_key(key).await.unwrap());
+
+    // A miss in the fast tier should pull the value from the slow tier and populate the fast one
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("slow value".to_owned())
+    );
+    assert_eq!(
+        fast_store.get::<String>(key).await.unwrap(),
+        Some("slow value".to_owned())
+    );
+
+    // Subsequent reads should be served by the fast tier
+    slow_store.remove::<String>(key).await.unwrap();
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("slow value".to_owned())
+    );
+    assert!(store.contains_key(key).await.unwrap());
+
+    // Batched checks should fall back to the slow tier for the keys missing in the fast one
+    slow_store.set("layered_slow_only", "value").await.unwrap();
+    assert!(store
+        .contains_all([key, "layered_slow_only"])
+        .await
+        .unwrap());
+    assert!(!store
+        .contains_any(["layered_missing1", "layered_missing2"])
+        .await
+        .unwrap());
+
+    // Writes should go to both tiers
+    store.set(key, "new value").await.unwrap();
+    assert_eq!(
+        fast_store.get::<String>(key).await.unwrap(),
+        Some("new value".to_owned())
+    );
+    assert_eq!(
+        slow_store.get::<String>(key).await.unwrap(),
+        Some("new value".to_owned())
+    );
+
+    // And so should removes
+    assert_eq!(
+        store.remove::<String>(key).await.unwrap(),
+        Some("new value".to_owned())
+    );
+    assert!(!fast_store.contains_key(key).await.unwrap());
+    assert!(!slow_store.contains_key(key).await.unwrap());
+    assert!(!store.contains_key(key).await.unwrap());
+
+    // Upserts are checked against the slow tier, which is the source of truth
+    fast_store.set(key, "stale value").await.unwrap();
+    assert!(store.upsert(key, "upserted").await.unwrap());
+    assert!(!store.upsert(key, "upserted").await.unwrap());
+    assert_eq!(
+        fast_store.get::<String>(key).await.unwrap(),
+        Some("upserted".to_owned())
+    );
+    store.remove::<String>(key).await.unwrap();
+
+    // Writes applied to the slow tier alone drop the cached copy
+    store.set(key, "cached").await.unwrap();
+    store.set_keepttl(key, "kept").await.unwrap();
+    assert!(!fast_store.contains_key(key).await.unwrap());
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("kept".to_owned())
+    );
+    store.remove::<String>(key).await.unwrap();
+
+    // Raw bytes are written to and read from the slow tier
+    store.set(key, "cached").await.unwrap();
+    store.set_raw(key, b"raw value").await.unwrap();
+    assert!(!fast_store.contains_key(key).await.unwrap());
+    assert_eq!(
+        store.get_raw(key).await.unwrap(),
+        Some(b"raw value".to_vec())
+    );
+    assert_eq!(
+        slow_store.get_raw(key).await.unwrap(),
+        Some(b"raw value".to_vec())
+    );
+    assert!(store.delete(key).await.unwrap());
+
+    let map_key = "layered_map";
+    store.hset(map_key, "field", 1).await.unwrap();
+    assert_eq!(store.hget::<i64>(map_key, "field").await.unwrap(), Some(1));
+    assert_eq!(store.hincr(map_key, "field", 2).await.unwrap(), 3);
+    assert_eq!(store.hget::<i64>(map_key, "field").await.unwrap(), Some(3));
+    assert_eq!(
+        slow_store.hget::<i64>(map_key, "field").await.unwrap(),
+        Some(3)
+    );
+    assert!(store.hdel(map_key, "field").await.unwrap());
+    assert_eq!(store.hget::<i64>(map_key, "field").await.unwrap(), None);
+
+    let bits_key = "layered_bits";
+    assert!(!store.get_bit(bits_key, 3).await.unwrap());
+    store.set_bit(bits_key, 3, true).await.unwrap();
+    assert!(store.get_bit(bits_key, 3).await.unwrap());
+
+    // Unsupported operations should be reported
+    assert!(matches!(
+        store.expire(key, Duration::from_secs(1)).await,
+        Err(BastehError::MethodNotSupported)
+    ));
+    assert!(matches!(
+        store
+            .swap_expiring::<String>(key, "value", Duration::from_secs(1))
+            .await,
+        Err(BastehError::MethodNotSupported)
+    ));
+}
+
+/// A provider counting the reads(`get` and `contains_key`) reaching the wrapped provider, used to
+/// check which tier of a layered provider serves them
+#[derive(Clone)]
+pub struct CountingProvider<P> {
+    inner: P,
+    reads: Arc<AtomicUsize>,
+}
+
+impl<P> CountingProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            reads: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of the reads so far
+    pub fn reads(&self) -> usize {
+        self.reads.load(AtomicOrdering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Provider> Provider for CountingProvider<P> {
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.inner.keys(scope).await
+    }
+
+    fn keys_stream<'a>(&'a self, scope: &'a [u8]) -> BoxStream<'a, Result<Vec<u8>>> {
+        self.inner.keys_stream(scope)
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.inner.set(scope, key, value).await
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.reads.fetch_add(1, AtomicOrdering::SeqCst);
+        self.inner.get(scope, key).await
+    }
+
+    async fn get_range(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        self.inner.get_range(scope, key, start, end).await
+    }
+
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.inner.push(scope, key, value).await
+    }
+
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        self.inner.push_multiple(scope, key, value).await
+    }
+
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.inner.pop(scope, key).await
+    }
+
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        self.inner.mutate(scope, key, mutations).await
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.inner.remove(scope, key).await
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        self.reads.fetch_add(1, AtomicOrdering::SeqCst);
+        self.inner.contains_key(scope, key).await
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        self.inner.persist(scope, key).await
+    }
+
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        self.inner.expire(scope, key, expire_in).await
+    }
+
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
+        self.inner.expiry(scope, key).await
+    }
+}
+
+/// Testing preloading a scope into the fast tier, the providers should be clones sharing the
+/// same underlying storage.
+pub async fn test_layered_preload<F, S>(fast: F, slow: S)
+where
+    F: 'static + Provider + Clone,
+    S: 'static + Provider + Clone,
+{
+    let slow = CountingProvider::new(slow);
+    let fast_store = Basteh::build().provider(fast.clone()).finish();
+    let slow_store = Basteh::build().provider(slow.clone()).finish();
+    let store = Basteh::build().layered(fast, slow.clone()).finish();
+
+    let keys = ["preload_key1", "preload_key2", "preload_key3"];
+    let scope = slow_store.scope("preload");
+    for key in keys {
+        scope.set(key, key).await.unwrap();
+    }
+    slow_store
+        .scope("preload_other")
+        .set("key", "value")
+        .await
+        .unwrap();
+
+    store.scope("preload").preload().await.unwrap();
+    for key in keys {
+        assert_eq!(
+            fast_store
+                .scope("preload")
+                .get::<String>(key)
+                .await
+                .unwrap(),
+            Some(key.to_owned())
+        );
+    }
+    // Only the given scope is loaded
+    assert!(!fast_store
+        .scope("preload_other")
+        .contains_key("key")
+        .await
+        .unwrap());
+
+    // The reads are served by the fast tier
+    let reads = slow.reads();
+    for key in keys {
+        assert_eq!(
+            store.scope("preload").get::<String>(key).await.unwrap(),
+            Some(key.to_owned())
+        );
+    }
+    assert!(store.scope("preload").contains_all(keys).await.unwrap());
+    assert_eq!(slow.reads(), reads);
+
+    // An empty scope has nothing to load
+    store.scope("preload_empty").preload().await.unwrap();
+
+    // Providers without a cache can't preload
+    assert!(matches!(
+        slow_store.preload().await,
+        Err(BastehError::MethodNotSupported)
+    ));
+}
+
+/// Testing the negative caching of the layered provider, the providers should be clones sharing
+/// the same underlying storage.
+pub async fn test_layered_negative_ttl<F, S>(fast: F, slow: S)
+where
+    F: 'static + Provider + Clone,
+    S: 'static + Provider + Clone,
+{
+    let slow = CountingProvider::new(slow);
+    let slow_store = Basteh::build().provider(slow.clone()).finish();
+    let provider =
+        LayeredProvider::new(fast, slow.clone()).with_negative_ttl(Duration::from_secs(1));
+    let store = Basteh::build().provider(provider).finish();
+
+    let key = "negative_key";
+
+    // Only the first miss reaches the slow provider
+    let reads = slow.reads();
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    assert_eq!(slow.reads(), reads + 1);
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    assert!(!store.contains_key(key).await.unwrap());
+    assert_eq!(slow.reads(), reads + 1);
+
+    // Keys created behind the layered provider are stale until the ttl passes
+    slow_store.set(key, "value").await.unwrap();
+    assert_eq!(store.get::<String>(key).await.unwrap(), None);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(
+        store.get::<String>(key).await.unwrap(),
+        Some("value".to_owned())
+    );
+
+    // Writes through the layered provider are seen right away
+    let key = "negative_written_key";
+    assert!(!store.contains_key(key).await.unwrap());
+    store.set(key, "value").await.unwrap();
+    assert!(store.contains_key(key).await.unwrap());
+
+    // Including the ones applied to the slow tier alone
+    let map_key = "negative_map_key";
+    assert_eq!(store.hget::<i64>(map_key, "field").await.unwrap(), None);
+    store.hset(map_key, "field", 1).await.unwrap();
+    assert_eq!(store.hget::<i64>(map_key, "field").await.unwrap(), Some(1));
+
+    // Batched lookups remember the misses as well
+    slow_store.set("negative_slow_only", "value").await.unwrap();
+    assert!(store
+        .contains_all([key, "negative_slow_only"])
+        .await
+        .unwrap());
+    assert!(!store.contains_any(["negative_missing"]).await.unwrap());
+    let reads = slow.reads();
+    assert!(!store.contains_key("negative_missing").await.unwrap());
+    assert_eq!(slow.reads(), reads);
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////    Write-behind tests     ////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Testing the write-behind provider, the provider should be a clone sharing the same underlying
+/// storage so we can check what has been flushed into it.
+#[cfg(feature = "write_behind")]
+pub async fn test_write_behind<P>(provider: P)
+where
+    P: 'static + Provider + Clone,
+{
+    use crate::WriteBehindProvider;
+
+    let inner_store = Basteh::build().provider(provider.clone()).finish();
+
+    // Explicit flush, using the provider directly to keep a handle for flushing
+    let scope = GLOBAL_SCOPE.as_bytes();
+    let store = WriteBehindProvider::new(provider.clone(), Duration::from_secs(3600), 1024);
+
+    store.set(scope, b"wb_key1", "value1".into()).await.unwrap();
+    store.set(scope, b"wb_key2", "value2".into()).await.unwrap();
+    assert!(!inner_store.contains_key("wb_key1").await.unwrap());
+
+    // Buffered writes are visible through the write-behind provider itself
+    assert_eq!(
+        store.get(scope, b"wb_key1").await.unwrap(),
+        Some(OwnedValue::String("value1".into()))
+    );
+
+    store.flush().await.unwrap();
+    assert_eq!(
+        inner_store.get::<String>("wb_key1").await.unwrap(),
+        Some("value1".to_owned())
+    );
+    assert_eq!(
+        inner_store.get::<String>("wb_key2").await.unwrap(),
+        Some("value2".to_owned())
+    );
+
+    // Buffered removes
+    assert_eq!(
+        store.remove(scope, b"wb_key1").await.unwrap(),
+        Some(OwnedValue::String("value1".into()))
+    );
+    assert!(!store.contains_key(scope, b"wb_key1").await.unwrap());
+    assert!(inner_store.contains_key("wb_key1").await.unwrap());
+    assert_eq!(
+        store
+            .exists_multiple(scope, &[b"wb_key1", b"wb_key2", b"wb_key3"])
+            .await
+            .unwrap(),
+        vec![false, true, false]
+    );
+    store.flush().await.unwrap();
+    assert!(!inner_store.contains_key("wb_key1").await.unwrap());
+
+    // Flush on size threshold
+    let store = Basteh::build()
+        .provider(WriteBehindProvider::new(
+            provider.clone(),
+            Duration::from_secs(3600),
+            2,
+        ))
+        .finish();
+    store.set("wb_key3", "value3").await.unwrap();
+    assert!(!inner_store.contains_key("wb_key3").await.unwrap());
+    store.set("wb_key4", "value4").await.unwrap();
+    assert!(inner_store.contains_key("wb_key3").await.unwrap());
+    assert!(inner_store.contains_key("wb_key4").await.unwrap());
+
+    // Flush on interval
+    let store = Basteh::build()
+        .provider(WriteBehindProvider::new(
+            provider.clone(),
+            Duration::from_millis(200),
+            1024,
+        ))
+        .finish();
+    store.set("wb_key5", "value5").await.unwrap();
+    assert!(!inner_store.contains_key("wb_key5").await.unwrap());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(inner_store.contains_key("wb_key5").await.unwrap());
+
+    // Flush on drop
+    let store = Basteh::build()
+        .provider(WriteBehindProvider::new(
+            provider,
+            Duration::from_secs(3600),
+            1024,
+        ))
+        .finish();
+    store.set("wb_key6", "value6").await.unwrap();
+    assert!(!inner_store.contains_key("wb_key6").await.unwrap());
+    drop(store);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(inner_store.contains_key("wb_key6").await.unwrap());
+}
+
+/// A provider holding back the writes(`set` and `remove`) reaching the wrapped provider for a
+/// while, used to keep a flush of the write-behind provider in flight
+#[derive(Clone)]
+pub struct DelayedProvider<P> {
+    inner: P,
+    delay_ms: Arc<AtomicU64>,
+}
+
+impl<P> DelayedProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            delay_ms: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Set the delay of the writes starting from now
+    pub fn set_delay(&self, delay: Duration) {
+        self.delay_ms
+            .store(delay.as_millis() as u64, AtomicOrdering::SeqCst);
+    }
+
+    async fn delay(&self) {
+        let delay = self.delay_ms.load(AtomicOrdering::SeqCst);
+        if delay > 0 {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Provider> Provider for DelayedProvider<P> {
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.inner.keys(scope).await
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.delay().await;
+        self.inner.set(scope, key, value).await
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.inner.get(scope, key).await
+    }
+
+    async fn get_range(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        self.inner.get_range(scope, key, start, end).await
+    }
+
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.inner.push(scope, key, value).await
+    }
+
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        self.inner.push_multiple(scope, key, value).await
+    }
+
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.inner.pop(scope, key).await
+    }
+
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        self.inner.mutate(scope, key, mutations).await
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.delay().await;
+        self.inner.remove(scope, key).await
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        self.inner.contains_key(scope, key).await
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        self.inner.persist(scope, key).await
+    }
+
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        self.inner.expire(scope, key, expire_in).await
+    }
+
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
+        self.inner.expiry(scope, key).await
+    }
+}
+
+/// Testing flushes of the write-behind provider running concurrently with writes, reads and other
+/// flushes, the provider should be a clone sharing the same underlying storage.
+#[cfg(feature = "write_behind")]
+pub async fn test_write_behind_concurrent_flush<P>(provider: P)
+where
+    P: 'static + Provider + Clone,
+{
+    use crate::WriteBehindProvider;
+
+    let inner_store = Basteh::build().provider(provider.clone()).finish();
+    let delayed = DelayedProvider::new(provider);
+    let store = Arc::new(WriteBehindProvider::new(
+        delayed.clone(),
+        Duration::from_secs(3600),
+        1024,
+    ));
+    let scope = GLOBAL_SCOPE.as_bytes();
+    let key = b"wb_concurrent_key";
+
+    // The first flush is held back while writing the old value
+    store.set(scope, key, Value::Number(1)).await.unwrap();
+    delayed.set_delay(Duration::from_millis(300));
+    let first = tokio::spawn({
+        let store = store.clone();
+        async move { store.flush().await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // The value being flushed is still readable
+    assert_eq!(
+        store.get(scope, key).await.unwrap(),
+        Some(OwnedValue::Number(1))
+    );
+    assert!(store.contains_key(scope, key).await.unwrap());
+    assert!(!inner_store.contains_key("wb_concurrent_key").await.unwrap());
+
+    // A newer value flushed right away shouldn't be overwritten by the older one
+    delayed.set_delay(Duration::ZERO);
+    store.set(scope, key, Value::Number(2)).await.unwrap();
+    let second = tokio::spawn({
+        let store = store.clone();
+        async move { store.flush().await }
+    });
+    assert_eq!(
+        store.get(scope, key).await.unwrap(),
+        Some(OwnedValue::Number(2))
+    );
 
-    tokio::join!(
-        test_expiry_store_basics(store.clone(), delay_secs),
-        test_expiry_store_override_shorter(store.clone(), delay_secs),
-        test_expiry_store_override_longer(store.clone(), delay_secs),
-        test_expiry_store_mutate_after_expiry(store, delay_secs),
+    first.await.unwrap().unwrap();
+    second.await.unwrap().unwrap();
+    assert_eq!(
+        inner_store.get::<i64>("wb_concurrent_key").await.unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        store.get(scope, key).await.unwrap(),
+        Some(OwnedValue::Number(2))
+    );
+
+    // Same for the removes
+    store.remove(scope, key).await.unwrap();
+    delayed.set_delay(Duration::from_millis(300));
+    let first = tokio::spawn({
+        let store = store.clone();
+        async move { store.flush().await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!store.contains_key(scope, key).await.unwrap());
+    delayed.set_delay(Duration::ZERO);
+    store.set(scope, key, Value::Number(3)).await.unwrap();
+    store.flush().await.unwrap();
+    first.await.unwrap().unwrap();
+    assert_eq!(
+        inner_store.get::<i64>("wb_concurrent_key").await.unwrap(),
+        Some(3)
     );
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-///////////////////////////////////////////////////    Mutation tests     //////////////////////////////////////////////////
+/////////////////////////////////////////////////    Compression tests     /////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-pub async fn test_mutate_numbers(store: Basteh) {
-    let key = "mutate_key";
-
-    // Increase by 1600, key doesn't exist so it should be considered 0
-    let mut_res = store.mutate(key, |m| m.incr(1600)).await;
-    assert_eq!(mut_res.unwrap(), 1600);
-
-    let get_res = store.get(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(1600));
-
-    // Decrease by 200
-    let mut_res = store.mutate(key, |m| m.decr(200)).await;
-    assert_eq!(mut_res.unwrap(), 1400);
+/// Testing the compressing provider, the provider should be a clone sharing the same underlying
+/// storage so we can check the stored values.
+#[cfg(feature = "compression")]
+pub async fn test_compression<P>(provider: P)
+where
+    P: 'static + Provider + Clone,
+{
+    use crate::CompressingProvider;
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(1400));
+    let inner_store = Basteh::build().provider(provider.clone()).finish();
+    let store = Basteh::build()
+        .provider(CompressingProvider::new(provider, 64))
+        .finish();
 
-    // Mutiply by 2
-    let mut_res = store.mutate(key, |m| m.mul(2)).await;
-    assert_eq!(mut_res.unwrap(), 2800);
+    // Large compressible payloads
+    let payload = "{\"key\": \"value\"}".repeat(1024);
+    store.set("compressed_str", &payload).await.unwrap();
+    assert_eq!(
+        store.get::<String>("compressed_str").await.unwrap(),
+        Some(payload.clone())
+    );
+    let stored = inner_store
+        .get::<Bytes>("compressed_str")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.len() < payload.len());
+    assert_eq!(
+        store.value_kind("compressed_str").await.unwrap(),
+        Some(ValueKind::String)
+    );
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(2800));
+    let payload = Bytes::from(vec![7_u8; 4096]);
+    store
+        .set("compressed_bytes", payload.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get::<Bytes>("compressed_bytes").await.unwrap(),
+        Some(payload.clone())
+    );
+    assert_eq!(
+        store.remove::<Bytes>("compressed_bytes").await.unwrap(),
+        Some(payload)
+    );
 
-    // Divide by 4
-    let mut_res = store.mutate(key, |m| m.div(4)).await;
-    assert_eq!(mut_res.unwrap(), 700);
+    // Small values and numbers pass through untouched
+    store.set("small_str", "small").await.unwrap();
+    assert_eq!(
+        inner_store.get::<String>("small_str").await.unwrap(),
+        Some("small".to_owned())
+    );
+    assert_eq!(
+        store.get::<String>("small_str").await.unwrap(),
+        Some("small".to_owned())
+    );
+    store.set("number", 1000).await.unwrap();
+    assert_eq!(store.get::<i64>("number").await.unwrap(), Some(1000));
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(700));
+    // Uncompressed data written by other means is still readable
+    inner_store.set("raw_str", "raw").await.unwrap();
+    assert_eq!(
+        store.get::<String>("raw_str").await.unwrap(),
+        Some("raw".to_owned())
+    );
 
-    // Set to 100
-    let mut_res = store.mutate(key, |m| m.set(100)).await;
-    assert_eq!(mut_res.unwrap(), 100);
+    // Small bytes looking like a compressed value should survive the round trip
+    let tricky = Bytes::from_static(b"\xffBZ\x01\x01\x01garbage");
+    store.set("tricky", tricky.clone()).await.unwrap();
+    assert_eq!(store.get::<Bytes>("tricky").await.unwrap(), Some(tricky));
+}
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(100));
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////////////    Timeout tests     ///////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-    // Conditional if
-    let mut_res = store
-        .mutate(key, |m| m.if_(Ordering::Equal, 100, |m| m.set(200)))
-        .await;
-    assert_eq!(mut_res.unwrap(), 200);
+/// A provider sleeping for `delay` before handing every operation to the inner provider, used to
+/// simulate a slow backend
+pub struct SlowProvider<P> {
+    provider: P,
+    delay: Duration,
+}
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(200));
+impl<P> SlowProvider<P> {
+    pub fn new(provider: P, delay: Duration) -> Self {
+        Self { provider, delay }
+    }
+}
 
-    // Conditional if else
-    let mut_res = store
-        .mutate(key, |m| {
-            m.if_else(Ordering::Greater, 200, |m| m.decr(100), |m| m.decr(50))
-        })
-        .await;
-    assert_eq!(mut_res.unwrap(), 150);
+#[async_trait::async_trait]
+impl<P: Provider> Provider for SlowProvider<P> {
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.keys(scope).await
+    }
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(150));
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.set(scope, key, value).await
+    }
 
-    // Multi level conditionals
-    let mutation = |m: Mutation| {
-        m.if_(Ordering::Greater, 100, |m| {
-            m.if_(Ordering::Less, 200, |m| {
-                m.if_else(Ordering::Greater, 150, |m| m.set(125), |m| m.set(175))
-            })
-        })
-    };
-    let mut_res = store.mutate(key, mutation).await;
-    assert_eq!(mut_res.unwrap(), 175);
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.get(scope, key).await
+    }
 
-    let get_res = store.get::<i64>(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(175));
+    async fn get_range(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.get_range(scope, key, start, end).await
+    }
 
-    // Multi level conditionals
-    let mut_res = store.mutate(key, mutation).await;
-    assert_eq!(mut_res.unwrap(), 125);
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.push(scope, key, value).await
+    }
 
-    let get_res = store.get(key).await;
-    assert!(get_res.is_ok());
-    assert_eq!(get_res.unwrap(), Some(125));
-}
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.push_multiple(scope, key, value).await
+    }
 
-async fn test_mutate_edge_cases(store: Basteh) {
-    let key = "mutate_edge_key";
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.pop(scope, key).await
+    }
 
-    // No mutations, no value, should be set to 0
-    let mut_res = store.mutate(key, |m| m).await;
-    assert_eq!(mut_res.unwrap(), 0);
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.mutate(scope, key, mutations).await
+    }
 
-    // Check if it is actually set to 0
-    let get_res = store.get(key).await;
-    assert_eq!(get_res.unwrap(), Some(0));
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.remove(scope, key).await
+    }
 
-    store.set(key, 100).await.ok();
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.contains_key(scope, key).await
+    }
 
-    // No mutations, with value, should keep the value
-    let mut_res = store.mutate(key, |m| m).await;
-    assert_eq!(mut_res.unwrap(), 100);
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.persist(scope, key).await
+    }
 
-    // Check if it is actually kept the value
-    let get_res = store.get(key).await;
-    assert_eq!(get_res.unwrap(), Some(100));
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.expire(scope, key, expire_in).await
+    }
 
-    store.set(key, "Hi").await.ok();
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
+        tokio::time::sleep(self.delay).await;
+        self.provider.expiry(scope, key).await
+    }
+}
 
-    // No mutations, with string value
-    let mut_res = store.mutate(key, |m| m).await;
-    assert!(mut_res.is_err());
+/// Testing the per-operation timeouts, the provider is wrapped in a [`SlowProvider`] taking
+/// 200 milliseconds for each operation.
+#[cfg(feature = "timeout")]
+pub async fn test_timeout<P>(provider: P)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build()
+        .provider(SlowProvider::new(provider, Duration::from_millis(200)))
+        .finish();
+
+    let key = "timeout_key";
+
+    // Without a timeout slow operations just take their time
+    store.set(key, "value").await.unwrap();
+
+    let short = store.with_timeout(Duration::from_millis(50));
+    assert!(matches!(
+        short.get::<String>(key).await,
+        Err(BastehError::Timeout)
+    ));
+    assert!(matches!(
+        short.set(key, "other").await,
+        Err(BastehError::Timeout)
+    ));
+    assert!(matches!(
+        short.preview_mutate(key, |m| m.incr(1)).await,
+        Err(BastehError::Timeout)
+    ));
+
+    // The timeout is kept when changing the scope
+    assert!(matches!(
+        short.scope("other_scope").contains_key(key).await,
+        Err(BastehError::Timeout)
+    ));
+
+    let long = store.with_timeout(Duration::from_secs(5));
+    assert!(long.get::<String>(key).await.unwrap().is_some());
+    assert!(long.remove::<String>(key).await.is_ok());
+    assert!(!store.contains_key(key).await.unwrap());
+}
 
-    // Check if it is actually kept the value
-    let get_res = store.get::<String>(key).await;
-    assert_eq!(get_res.unwrap(), Some("Hi".to_string()));
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////////////    Actix-web tests     //////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-    // No mutations, with string value
-    let mut_res = store.mutate(key, |m| m.incr(100)).await;
-    assert!(mut_res.is_err());
+/// Testing the actix-web extractor with a tiny app reading a key through it, both with Basteh
+/// registered directly and wrapped in `web::Data`.
+#[cfg(feature = "actix-web")]
+pub async fn test_actix_web_extractor<P>(provider: P)
+where
+    P: 'static + Provider,
+{
+    use actix_web::{http::StatusCode, test, web, App};
+
+    async fn index(store: Basteh) -> String {
+        store
+            .get::<String>("actix_key")
+            .await
+            .unwrap()
+            .unwrap_or_default()
+    }
 
-    // Check if it is actually kept the value
-    let get_res = store.get::<String>(key).await;
-    assert_eq!(get_res.unwrap(), Some("Hi".to_string()));
+    let store = Basteh::build().provider(provider).finish();
+    store.set("actix_key", "actix value").await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(store.clone())
+            .route("/", web::get().to(index)),
+    )
+    .await;
+    let body = test::call_and_read_body(&app, test::TestRequest::get().to_request()).await;
+    assert_eq!(body, "actix value");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(store))
+            .route("/", web::get().to(index)),
+    )
+    .await;
+    let body = test::call_and_read_body(&app, test::TestRequest::get().to_request()).await;
+    assert_eq!(body, "actix value");
+
+    // Not registering Basteh should fail the extraction
+    let app = test::init_service(App::new().route("/", web::get().to(index))).await;
+    let res = test::call_service(&app, test::TestRequest::get().to_request()).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
 }
 
-async fn test_mutate_list(store: Basteh) {
-    store.push("mutate_list", "value").await.unwrap();
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////////////    Session tests     ///////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some("value".to_string()));
+pub async fn test_session_round_trip(sessions: session::SessionStore) {
+    let ttl = Duration::from_secs(60);
 
-    store
-        .push_mutiple(
-            "mutate_list",
-            vec!["value1", "value2", "value3", "value4"].into_iter(),
-        )
+    assert_eq!(sessions.load("round_trip").await.unwrap(), None);
+    sessions
+        .store("round_trip", "session data", ttl)
         .await
         .unwrap();
+    assert_eq!(
+        sessions.load("round_trip").await.unwrap(),
+        Some(Bytes::from_static(b"session data"))
+    );
 
-    store.push("mutate_list", 100).await.unwrap();
-    store
-        .push("mutate_list", Bytes::from_static(b"\0100\0"))
+    let mut state = session::SessionState::new();
+    state.insert("user".to_owned(), "1".to_owned());
+    state.insert("".to_owned(), "empty key".to_owned());
+    state.insert("theme".to_owned(), "".to_owned());
+    sessions
+        .store_state("round_trip_state", &state, ttl)
         .await
         .unwrap();
+    assert_eq!(
+        sessions.load_state("round_trip_state").await.unwrap(),
+        Some(state)
+    );
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some(Bytes::from_static(b"\0100\0")));
+    // Digit-only data is read back as it was stored
+    sessions.store("round_trip", "42", ttl).await.unwrap();
+    assert_eq!(
+        sessions.load("round_trip").await.unwrap(),
+        Some(Bytes::from_static(b"42"))
+    );
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some(100));
+    // Data which is not a serialized state
+    sessions.store("round_trip", "garbage", ttl).await.unwrap();
+    assert!(matches!(
+        sessions.load_state("round_trip").await,
+        Err(BastehError::TypeConversion)
+    ));
+
+    sessions.remove("round_trip").await.unwrap();
+    assert_eq!(sessions.load("round_trip").await.unwrap(), None);
+    // Removing a missing session is not an error
+    assert!(sessions.remove("round_trip").await.is_ok());
+}
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some("value4".to_string()));
+pub async fn test_session_expiry(sessions: session::SessionStore, delay_secs: u64) {
+    let delay = Duration::from_secs(delay_secs);
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some("value3".to_string()));
+    sessions.store("expiring", "data", delay).await.unwrap();
+    sessions.store("extended", "data", delay).await.unwrap();
+    sessions.update_ttl("extended", delay * 3).await.unwrap();
 
-    store.push("mutate_list", "value5").await.unwrap();
+    tokio::time::sleep(delay * 2).await;
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some("value5".to_string()));
+    assert_eq!(sessions.load("expiring").await.unwrap(), None);
+    assert_eq!(
+        sessions.load("extended").await.unwrap(),
+        Some(Bytes::from_static(b"data"))
+    );
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some("value2".to_string()));
+    // Storing again replaces the previous expiry
+    sessions.store("extended", "new data", delay).await.unwrap();
+    tokio::time::sleep(delay * 2).await;
+    assert_eq!(sessions.load("extended").await.unwrap(), None);
+}
 
-    let pop_value = store.pop("mutate_list").await.unwrap();
-    assert_eq!(pop_value, Some("value1".to_string()));
+pub async fn test_session_store<P>(provider: P, delay_secs: u64)
+where
+    P: 'static + Provider,
+{
+    let store = Basteh::build().provider(provider).finish();
+    let sessions = session::SessionStore::new(store.scope("sessions"));
 
-    let pop_value = store.pop::<String>("mutate_list").await.unwrap();
-    assert_eq!(pop_value, None);
+    tokio::join!(
+        test_session_round_trip(sessions.clone()),
+        test_session_expiry(sessions, delay_secs),
+    );
 }
 
-// delay_secs is the duration of time we set for expiry and we wait to see
-// the result, it should depend on how much delay an implementer has between
-// getting a command and executing it
-pub async fn test_mutations<P>(provider: P)
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////    Codec tests     ////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Testing the stored values rendered as JSON
+#[cfg(feature = "serde")]
+pub async fn test_json_values<P>(provider: P)
 where
     P: 'static + Provider,
 {
+    use serde_json::json;
+
+    let cases = vec![
+        (OwnedValue::Number(-10), json!(-10)),
+        (OwnedValue::String("value".to_owned()), json!("value")),
+        (
+            OwnedValue::Bytes(b"\x00\xffbytes"[..].into()),
+            json!("AP9ieXRlcw=="),
+        ),
+        (
+            OwnedValue::List(vec![
+                OwnedValue::Number(1),
+                OwnedValue::List(vec![OwnedValue::String("nested".to_owned())]),
+                OwnedValue::List(vec![]),
+            ]),
+            json!([1, ["nested"], []]),
+        ),
+        (
+            OwnedValue::Map(vec![
+                (b"name".to_vec(), OwnedValue::String("Violet".to_owned())),
+                (
+                    b"scores".to_vec(),
+                    OwnedValue::List(vec![OwnedValue::Number(20)]),
+                ),
+            ]),
+            json!({"name": "Violet", "scores": [20]}),
+        ),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(serde_json::Value::try_from(value).unwrap(), expected);
+    }
+
+    // Map fields can't be used as object keys if they're not utf-8
+    assert!(matches!(
+        serde_json::Value::try_from(OwnedValue::Map(vec![(
+            b"\xff".to_vec(),
+            OwnedValue::Number(1)
+        )])),
+        Err(BastehError::TypeConversion)
+    ));
+
     let store = Basteh::build().provider(provider).finish();
+    store.push("json_list", 1).await.unwrap();
+    store.push("json_list", "two").await.unwrap();
+    assert_eq!(
+        store.get_json("json_list").await.unwrap(),
+        Some(json!([1, "two"]))
+    );
+    store
+        .set("json_bytes", Bytes::from_static(b"bytes"))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_json("json_bytes").await.unwrap(),
+        Some(json!("Ynl0ZXM="))
+    );
+    assert_eq!(store.get_json("json_missing").await.unwrap(), None);
+}
 
-    tokio::join!(
-        test_mutate_numbers(store.clone()),
-        test_mutate_edge_cases(store.clone()),
-        test_mutate_list(store.clone()),
+/// Testing serializable values round-tripping through the JSON and MessagePack codecs.
+#[cfg(feature = "msgpack")]
+pub async fn test_codecs<P>(provider: P)
+where
+    P: 'static + Provider,
+{
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct User {
+        name: String,
+        age: u8,
+        tags: Vec<String>,
+        manager: Option<Box<User>>,
+    }
+
+    let store = Basteh::build().provider(provider).finish();
+    let user = User {
+        name: "Violet".to_owned(),
+        age: 20,
+        tags: vec!["admin".to_owned(), "ops".to_owned()],
+        manager: Some(Box::new(User {
+            name: "Lily".to_owned(),
+            age: 42,
+            tags: Vec::new(),
+            manager: None,
+        })),
+    };
+
+    // The default codec is JSON
+    store.set_serde("json_user", &user).await.unwrap();
+    assert_eq!(
+        store.get_serde::<User>("json_user").await.unwrap(),
+        Some(user.clone())
+    );
+    let raw = store.get::<Bytes>("json_user").await.unwrap().unwrap();
+    assert!(raw.starts_with(b"{\"name\":\"Violet\""));
+
+    let msgpack = store.with_codec(MessagePackCodec);
+    msgpack.set_serde("msgpack_user", &user).await.unwrap();
+    assert_eq!(
+        msgpack.get_serde::<User>("msgpack_user").await.unwrap(),
+        Some(user)
+    );
+    let msgpack_raw = store.get::<Bytes>("msgpack_user").await.unwrap().unwrap();
+    assert_ne!(raw, msgpack_raw);
+
+    // Reading with the wrong codec fails instead of returning garbage
+    assert!(store.get_serde::<User>("msgpack_user").await.is_err());
+    assert!(msgpack.get_serde::<User>("json_user").await.is_err());
+
+    assert_eq!(
+        msgpack.get_serde::<User>("missing_user").await.unwrap(),
+        None
     );
 }
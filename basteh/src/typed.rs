@@ -0,0 +1,66 @@
+use std::{convert::TryFrom, marker::PhantomData};
+
+use crate::dev::{OwnedValue, Value};
+use crate::error::{BastehError, Result};
+use crate::Basteh;
+
+/// A map-like view of a [`Basteh`](struct.Basteh.html) storing values of a single type `V`,
+/// returned by [`Basteh::typed`](struct.Basteh.html#method.typed).
+///
+/// It's only sugar over [`set`](struct.Basteh.html#method.set),
+/// [`get`](struct.Basteh.html#method.get) and [`remove`](struct.Basteh.html#method.remove), so
+/// reading a value of another type(ex. written by the `Basteh` itself) fails with
+/// [`BastehError::Conversion`](enum.BastehError.html#variant.Conversion).
+///
+/// ## Example
+/// ```rust
+/// # use basteh::{Basteh, BastehError};
+/// #
+/// # async fn index(store: Basteh) -> Result<i64, BastehError> {
+/// let scores = store.scope("scores").typed::<String, i64>();
+/// scores.insert("violet".to_owned(), 20).await?;
+/// let score = scores.get("violet".to_owned()).await?;
+/// #     Ok(score.unwrap_or_default())
+/// # }
+/// ```
+pub struct TypedStore<K, V> {
+    store: Basteh,
+    _types: PhantomData<fn(K) -> V>,
+}
+
+impl<K, V> Clone for TypedStore<K, V> {
+    fn clone(&self) -> Self {
+        Self::new(self.store.clone())
+    }
+}
+
+impl<K, V> TypedStore<K, V> {
+    pub(crate) fn new(store: Basteh) -> Self {
+        Self {
+            store,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<K, V> TypedStore<K, V>
+where
+    K: AsRef<[u8]>,
+    V: for<'a> Into<Value<'a>> + TryFrom<OwnedValue>,
+    <V as TryFrom<OwnedValue>>::Error: Into<BastehError>,
+{
+    /// Store the value, overwriting the previous one
+    pub async fn insert(&self, key: K, value: V) -> Result<()> {
+        self.store.set(key, value).await
+    }
+
+    /// Get the value of the key, fails if the stored value is not a `V`
+    pub async fn get(&self, key: K) -> Result<Option<V>> {
+        self.store.get(key).await
+    }
+
+    /// Remove the key, returning its value if it existed
+    pub async fn remove(&self, key: K) -> Result<Option<V>> {
+        self.store.remove(key).await
+    }
+}
@@ -16,6 +16,8 @@ pub enum ValueKind {
     String = 1,
     Bytes = 2,
     List = 3,
+    Map = 4,
+    BigNumber = 5,
 }
 
 impl ValueKind {
@@ -25,6 +27,8 @@ impl ValueKind {
             1 => Some(ValueKind::String),
             2 => Some(ValueKind::Bytes),
             3 => Some(ValueKind::List),
+            4 => Some(ValueKind::Map),
+            5 => Some(ValueKind::BigNumber),
             _ => None,
         }
     }
@@ -36,6 +40,11 @@ pub enum Value<'a> {
     String(Cow<'a, str>),
     Bytes(Bytes),
     List(Vec<Value<'a>>),
+    /// Fields and their values, see [`Basteh::hset`](crate::Basteh::hset)
+    Map(Vec<(Vec<u8>, Value<'a>)>),
+    /// Numbers which don't fit in an `i64`, see [`Basteh::incr_big`](crate::Basteh::incr_big).
+    /// Converting an `i128` makes a `Number` if it fits, so small numbers have a single form.
+    BigNumber(i128),
 }
 
 impl<'a> Value<'a> {
@@ -45,24 +54,47 @@ impl<'a> Value<'a> {
             Self::String(_) => ValueKind::String,
             Self::Bytes(_) => ValueKind::Bytes,
             Self::List(_) => ValueKind::List,
+            Self::Map(_) => ValueKind::Map,
+            Self::BigNumber(_) => ValueKind::BigNumber,
+        }
+    }
+
+    /// Size of the value's payload in bytes, numbers take 8 bytes and big numbers 16, lists are
+    /// the sum of their items and maps are the sum of their fields and values.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Number(_) => 8,
+            Self::BigNumber(_) => 16,
+            Self::String(s) => s.len(),
+            Self::Bytes(b) => b.len(),
+            Self::List(l) => l.iter().map(Value::size).sum(),
+            Self::Map(m) => m.iter().map(|(f, v)| f.len() + v.size()).sum(),
         }
     }
 
     pub fn to_owned(&self) -> OwnedValue {
         match &self {
             Value::Number(n) => OwnedValue::Number(*n),
+            Value::BigNumber(n) => OwnedValue::BigNumber(*n),
             Value::String(s) => OwnedValue::String(s.clone().into_owned()),
             Value::Bytes(b) => OwnedValue::Bytes(b.iter().collect()),
             Value::List(l) => OwnedValue::List(l.iter().map(|v| v.to_owned()).collect()),
+            Value::Map(m) => {
+                OwnedValue::Map(m.iter().map(|(f, v)| (f.clone(), v.to_owned())).collect())
+            }
         }
     }
 
     pub fn into_owned(self) -> OwnedValue {
         match self {
             Value::Number(n) => OwnedValue::Number(n),
+            Value::BigNumber(n) => OwnedValue::BigNumber(n),
             Value::String(s) => OwnedValue::String(s.into_owned()),
             Value::Bytes(b) => OwnedValue::Bytes(b.iter().collect()),
             Value::List(l) => OwnedValue::List(l.into_iter().map(|v| v.into_owned()).collect()),
+            Value::Map(m) => {
+                OwnedValue::Map(m.into_iter().map(|(f, v)| (f, v.into_owned())).collect())
+            }
         }
     }
 }
@@ -169,12 +201,29 @@ impl_from_number!(u32);
 impl_from_number!(i32);
 impl_from_number!(i64);
 
+impl<'a> From<i128> for Value<'a> {
+    fn from(value: i128) -> Self {
+        match i64::try_from(value) {
+            Ok(number) => Self::Number(number),
+            Err(_) => Self::BigNumber(value),
+        }
+    }
+}
+
+impl<'a, 'b> From<&'b i128> for Value<'a> {
+    fn from(value: &'b i128) -> Self {
+        <Self as From<i128>>::from(*value)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum OwnedValue {
     Number(i64),
     String(String),
     Bytes(BytesMut),
     List(Vec<OwnedValue>),
+    Map(Vec<(Vec<u8>, OwnedValue)>),
+    BigNumber(i128),
 }
 
 impl OwnedValue {
@@ -184,15 +233,31 @@ impl OwnedValue {
             Self::String(_) => ValueKind::String,
             Self::Bytes(_) => ValueKind::Bytes,
             Self::List(_) => ValueKind::List,
+            Self::Map(_) => ValueKind::Map,
+            Self::BigNumber(_) => ValueKind::BigNumber,
         }
     }
 
     pub fn as_value(&self) -> Value<'_> {
         match &self {
             OwnedValue::Number(n) => Value::Number(*n),
+            OwnedValue::BigNumber(n) => Value::BigNumber(*n),
             OwnedValue::String(s) => Value::String(Cow::Borrowed(&s)),
             OwnedValue::Bytes(b) => Value::Bytes(b.clone().freeze()),
             OwnedValue::List(l) => Value::List(l.into_iter().map(|v| v.as_value()).collect()),
+            OwnedValue::Map(m) => {
+                Value::Map(m.iter().map(|(f, v)| (f.clone(), v.as_value())).collect())
+            }
+        }
+    }
+}
+
+impl From<i128> for OwnedValue {
+    /// A `Number` if it fits in an `i64` and a `BigNumber` otherwise
+    fn from(value: i128) -> Self {
+        match i64::try_from(value) {
+            Ok(number) => Self::Number(number),
+            Err(_) => Self::BigNumber(value),
         }
     }
 }
@@ -204,8 +269,9 @@ impl<'a> TryFrom<OwnedValue> for String {
         match value {
             OwnedValue::String(val) => Ok(val),
             OwnedValue::Number(n) => Ok(n.to_string()),
+            OwnedValue::BigNumber(n) => Ok(n.to_string()),
             OwnedValue::Bytes(b) => Ok(String::from_utf8_lossy(&b).into_owned()),
-            OwnedValue::List(_) => Err(BastehError::TypeConversion),
+            OwnedValue::List(_) | OwnedValue::Map(_) => Err(BastehError::TypeConversion),
         }
     }
 }
@@ -244,6 +310,9 @@ macro_rules! impl_from_value_for_number {
                     OwnedValue::Number(val) => {
                         val.try_into().map_err(|_| BastehError::TypeConversion)
                     }
+                    OwnedValue::BigNumber(val) => {
+                        val.try_into().map_err(|_| BastehError::TypeConversion)
+                    }
                     _ => Err(BastehError::TypeConversion),
                 }
             }
@@ -259,3 +328,57 @@ impl_from_value_for_number!(u32);
 impl_from_value_for_number!(i32);
 impl_from_value_for_number!(i64);
 impl_from_value_for_number!(u64);
+impl_from_value_for_number!(i128);
+impl_from_value_for_number!(u128);
+
+/// Stored values rendered as JSON, bytes are encoded as standard base64 strings(with padding)
+/// since JSON can't carry them as they are, big numbers become strings for the same reason and
+/// maps become objects.
+///
+/// It fails with `TypeConversion` if a field of a map is not valid utf-8.
+///
+/// ## Example
+/// ```rust
+/// # use std::convert::TryFrom;
+/// use basteh::OwnedValue;
+///
+/// let value = OwnedValue::List(vec![
+///     OwnedValue::Number(10),
+///     OwnedValue::Bytes(b"bytes"[..].into()),
+/// ]);
+/// assert_eq!(
+///     serde_json::Value::try_from(value).unwrap(),
+///     serde_json::json!([10, "Ynl0ZXM="])
+/// );
+/// ```
+#[cfg(feature = "serde")]
+impl TryFrom<OwnedValue> for serde_json::Value {
+    type Error = BastehError;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        use base64::Engine;
+
+        Ok(match value {
+            OwnedValue::Number(n) => serde_json::Value::from(n),
+            OwnedValue::BigNumber(n) => serde_json::Value::String(n.to_string()),
+            OwnedValue::String(s) => serde_json::Value::String(s),
+            OwnedValue::Bytes(b) => {
+                serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(b))
+            }
+            OwnedValue::List(l) => serde_json::Value::Array(
+                l.into_iter()
+                    .map(serde_json::Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            OwnedValue::Map(m) => serde_json::Value::Object(
+                m.into_iter()
+                    .map(|(field, value)| {
+                        let field =
+                            String::from_utf8(field).map_err(|_| BastehError::TypeConversion)?;
+                        Ok((field, serde_json::Value::try_from(value)?))
+                    })
+                    .collect::<Result<_, BastehError>>()?,
+            ),
+        })
+    }
+}
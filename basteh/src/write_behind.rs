@@ -0,0 +1,640 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    dev::{
+        BloomParams, Capabilities, ListMutation, Mutation, OwnedValue, Provider, SnapshotEntry,
+        Value,
+    },
+    error::Result,
+};
+
+type PendingKey = (Arc<[u8]>, Vec<u8>);
+
+enum PendingOp {
+    Set(OwnedValue),
+    Remove,
+}
+
+struct Inner<P> {
+    provider: P,
+    pending: Mutex<HashMap<PendingKey, Arc<PendingOp>>>,
+    // Held while flushing, so the writes of a key reach the provider in the order they were made
+    flushing: tokio::sync::Mutex<()>,
+    max_pending: usize,
+}
+
+impl<P: Provider> Inner<P> {
+    async fn flush(&self) -> Result<()> {
+        let _flushing = self.flushing.lock().await;
+
+        // The operations stay buffered, and visible to the reads, until they're written
+        let ops = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, op)| (key.clone(), op.clone()))
+            .collect::<Vec<_>>();
+
+        for ((scope, key), op) in ops {
+            match &*op {
+                PendingOp::Set(value) => self.provider.set(&scope, &key, value.as_value()).await?,
+                PendingOp::Remove => {
+                    self.provider.remove(&scope, &key).await?;
+                }
+            }
+
+            // Unless they were overwritten in the meantime, the newer operation is left for the
+            // next flush
+            let mut pending = self.pending.lock().unwrap();
+            let key = (scope, key);
+            if matches!(pending.get(&key), Some(current) if Arc::ptr_eq(current, &op)) {
+                pending.remove(&key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A write-behind provider buffering `set` and `remove` operations in memory and flushing them
+/// to the inner provider in batches.
+///
+/// Buffered operations are flushed every `interval`, when the number of pending keys reaches
+/// `max_pending`, when [`flush`](WriteBehindProvider::flush) is called or when the provider is
+/// dropped. Reads(`get` and `contains_key`) see the buffered writes, every other operation flushes
+/// the buffer before reaching the inner provider.
+///
+/// Flushes run one at a time, and the operations being flushed stay in the buffer until the
+/// inner provider has them, so the reads don't miss them in between.
+///
+/// ## Data loss
+/// Writes return before they reach the inner provider, so anything written since the last
+/// successful flush(up to `interval` or `max_pending` keys) is lost if the process exits
+/// abruptly. Flush-on-drop is done on a spawned task, so it also needs the tokio runtime to
+/// keep running after the drop. Errors while flushing in the background are logged and the
+/// failed operations are retried on the next flush.
+///
+/// ## Example
+/// ```rust
+/// use std::time::Duration;
+/// use basteh::{dev::Provider, Basteh, WriteBehindProvider};
+///
+/// fn make_basteh(provider: impl Provider + 'static) -> Basteh {
+///     let provider = WriteBehindProvider::new(provider, Duration::from_secs(1), 1024);
+///     Basteh::build().provider(provider).finish()
+/// }
+/// ```
+pub struct WriteBehindProvider<P: Provider + 'static> {
+    inner: Arc<Inner<P>>,
+}
+
+impl<P: Provider + 'static> WriteBehindProvider<P> {
+    /// Create a new write-behind provider on top of `provider`, it should be called from within
+    /// a tokio runtime as it spawns the task flushing the buffer every `interval`.
+    pub fn new(provider: P, interval: Duration, max_pending: usize) -> Self {
+        let inner = Arc::new(Inner {
+            provider,
+            pending: Mutex::new(HashMap::new()),
+            flushing: tokio::sync::Mutex::new(()),
+            max_pending,
+        });
+
+        let weak = Arc::downgrade(&inner);
+        tokio::spawn(flush_periodically(weak, interval));
+
+        Self { inner }
+    }
+
+    /// Force all the buffered operations through to the inner provider.
+    pub async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+
+    async fn buffer(&self, scope: &[u8], key: &[u8], op: PendingOp) -> Result<()> {
+        let len = {
+            let mut pending = self.inner.pending.lock().unwrap();
+            pending.insert((scope.into(), key.to_vec()), Arc::new(op));
+            pending.len()
+        };
+
+        if len >= self.inner.max_pending {
+            self.flush().await
+        } else {
+            Ok(())
+        }
+    }
+
+    fn buffered<T>(&self, scope: &[u8], key: &[u8], f: impl FnOnce(&PendingOp) -> T) -> Option<T> {
+        // Avoid allocating for the lookup when there is nothing buffered
+        let pending = self.inner.pending.lock().unwrap();
+        if pending.is_empty() {
+            return None;
+        }
+        pending.get(&(scope.into(), key.to_vec())).map(|op| f(op))
+    }
+}
+
+impl<P: Provider + 'static> Drop for WriteBehindProvider<P> {
+    fn drop(&mut self) {
+        if self.inner.pending.lock().unwrap().is_empty() {
+            return;
+        }
+
+        let inner = self.inner.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(err) = inner.flush().await {
+                        log::error!("Failed to flush write-behind buffer on drop: {}", err);
+                    }
+                });
+            }
+            Err(_) => {
+                log::error!("Write-behind buffer dropped outside of a tokio runtime, buffered writes are lost");
+            }
+        }
+    }
+}
+
+async fn flush_periodically<P: Provider>(inner: Weak<Inner<P>>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    // The first tick completes immediately
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let inner = match inner.upgrade() {
+            Some(inner) => inner,
+            None => break,
+        };
+        if let Err(err) = inner.flush().await {
+            log::error!("Failed to flush write-behind buffer: {}", err);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> Provider for WriteBehindProvider<P>
+where
+    P: Provider + 'static,
+{
+    fn capabilities(&self) -> Capabilities {
+        self.inner.provider.capabilities()
+    }
+
+    async fn keys(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.flush().await?;
+        self.inner.provider.keys(scope).await
+    }
+
+    async fn keys_sorted(&self, scope: &[u8]) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.flush().await?;
+        self.inner.provider.keys_sorted(scope).await
+    }
+
+    async fn scopes(&self) -> Result<Box<dyn Iterator<Item = Vec<u8>>>> {
+        self.flush().await?;
+        self.inner.provider.scopes().await
+    }
+
+    async fn size_bytes(&self, scope: &[u8]) -> Result<u64> {
+        self.flush().await?;
+        self.inner.provider.size_bytes(scope).await
+    }
+
+    async fn drop_scope(&self, scope: &[u8]) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.drop_scope(scope).await
+    }
+
+    async fn set(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.buffer(scope, key, PendingOp::Set(value.into_owned()))
+            .await
+    }
+
+    async fn upsert(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<bool> {
+        self.flush().await?;
+        self.inner.provider.upsert(scope, key, value).await
+    }
+
+    async fn set_keepttl(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.set_keepttl(scope, key, value).await
+    }
+
+    async fn get(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let buffered = self.buffered(scope, key, |op| match op {
+            PendingOp::Set(value) => Some(value.clone()),
+            PendingOp::Remove => None,
+        });
+
+        match buffered {
+            Some(value) => Ok(value),
+            None => self.inner.provider.get(scope, key).await,
+        }
+    }
+
+    async fn get_range(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<OwnedValue>> {
+        self.flush().await?;
+        self.inner.provider.get_range(scope, key, start, end).await
+    }
+
+    async fn get_range_multiple(
+        &self,
+        scope: &[u8],
+        requests: &[(&[u8], i64, i64)],
+    ) -> Result<Vec<Vec<OwnedValue>>> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .get_range_multiple(scope, requests)
+            .await
+    }
+
+    async fn push(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.push(scope, key, value).await
+    }
+
+    async fn push_multiple(&self, scope: &[u8], key: &[u8], value: Vec<Value<'_>>) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.push_multiple(scope, key, value).await
+    }
+
+    async fn push_capped(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        max_len: u64,
+    ) -> Result<()> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .push_capped(scope, key, value, max_len)
+            .await
+    }
+
+    async fn push_returning_len(&self, scope: &[u8], key: &[u8], value: Value<'_>) -> Result<u64> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .push_returning_len(scope, key, value)
+            .await
+    }
+
+    async fn mutate_list(&self, scope: &[u8], key: &[u8], mutations: ListMutation) -> Result<u64> {
+        self.flush().await?;
+        self.inner.provider.mutate_list(scope, key, mutations).await
+    }
+
+    async fn pop(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        self.flush().await?;
+        self.inner.provider.pop(scope, key).await
+    }
+
+    async fn pop_blocking(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        timeout: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        self.flush().await?;
+        self.inner.provider.pop_blocking(scope, key, timeout).await
+    }
+
+    async fn pop_push(
+        &self,
+        src_scope: &[u8],
+        src_key: &[u8],
+        dst_scope: &[u8],
+        dst_key: &[u8],
+    ) -> Result<Option<OwnedValue>> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .pop_push(src_scope, src_key, dst_scope, dst_key)
+            .await
+    }
+
+    async fn set_raw(&self, scope: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.set_raw(scope, key, value).await
+    }
+
+    async fn get_raw(&self, scope: &[u8], key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.flush().await?;
+        self.inner.provider.get_raw(scope, key).await
+    }
+
+    async fn remove_if(&self, scope: &[u8], key: &[u8], expected: Value<'_>) -> Result<bool> {
+        self.flush().await?;
+        self.inner.provider.remove_if(scope, key, expected).await
+    }
+
+    async fn remove_matching(&self, scope: &[u8], pattern: &str) -> Result<u64> {
+        self.flush().await?;
+        self.inner.provider.remove_matching(scope, pattern).await
+    }
+
+    async fn incr_float(&self, scope: &[u8], key: &[u8], delta: f64) -> Result<f64> {
+        self.flush().await?;
+        self.inner.provider.incr_float(scope, key, delta).await
+    }
+
+    async fn incr_big(&self, scope: &[u8], key: &[u8], delta: i128) -> Result<i128> {
+        self.flush().await?;
+        self.inner.provider.incr_big(scope, key, delta).await
+    }
+
+    async fn set_if(&self, scope: &[u8], key: &[u8], value: i64, ord: Ordering) -> Result<bool> {
+        self.flush().await?;
+        self.inner.provider.set_if(scope, key, value, ord).await
+    }
+
+    async fn incr_with_ttl_floor(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        delta: i64,
+        ttl: Duration,
+    ) -> Result<i64> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .incr_with_ttl_floor(scope, key, delta, ttl)
+            .await
+    }
+
+    async fn list_drain(&self, scope: &[u8], key: &[u8]) -> Result<Vec<OwnedValue>> {
+        self.flush().await?;
+        self.inner.provider.list_drain(scope, key).await
+    }
+
+    async fn mutate(&self, scope: &[u8], key: &[u8], mutations: Mutation) -> Result<i64> {
+        self.flush().await?;
+        self.inner.provider.mutate(scope, key, mutations).await
+    }
+
+    async fn hset(&self, scope: &[u8], key: &[u8], field: &[u8], value: Value<'_>) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.hset(scope, key, field, value).await
+    }
+
+    async fn hincr(&self, scope: &[u8], key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        self.flush().await?;
+        self.inner.provider.hincr(scope, key, field, delta).await
+    }
+
+    async fn hget(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<Option<OwnedValue>> {
+        self.flush().await?;
+        self.inner.provider.hget(scope, key, field).await
+    }
+
+    async fn hdel(&self, scope: &[u8], key: &[u8], field: &[u8]) -> Result<bool> {
+        self.flush().await?;
+        self.inner.provider.hdel(scope, key, field).await
+    }
+
+    async fn hgetall(&self, scope: &[u8], key: &[u8]) -> Result<Vec<(Vec<u8>, OwnedValue)>> {
+        self.flush().await?;
+        self.inner.provider.hgetall(scope, key).await
+    }
+
+    async fn set_bit(&self, scope: &[u8], key: &[u8], offset: u64, value: bool) -> Result<bool> {
+        self.flush().await?;
+        self.inner.provider.set_bit(scope, key, offset, value).await
+    }
+
+    async fn get_bit(&self, scope: &[u8], key: &[u8], offset: u64) -> Result<bool> {
+        self.flush().await?;
+        self.inner.provider.get_bit(scope, key, offset).await
+    }
+
+    async fn bloom_add(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<()> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .bloom_add(scope, key, item, params)
+            .await
+    }
+
+    async fn bloom_maybe_contains(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        item: &[u8],
+        params: &BloomParams,
+    ) -> Result<bool> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .bloom_maybe_contains(scope, key, item, params)
+            .await
+    }
+
+    async fn mutate_returning(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        mutations: Mutation,
+    ) -> Result<(i64, i64)> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .mutate_returning(scope, key, mutations)
+            .await
+    }
+
+    async fn remove(&self, scope: &[u8], key: &[u8]) -> Result<Option<OwnedValue>> {
+        let value = self.get(scope, key).await?;
+        self.buffer(scope, key, PendingOp::Remove).await?;
+        Ok(value)
+    }
+
+    async fn delete(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let existed = self.contains_key(scope, key).await?;
+        self.buffer(scope, key, PendingOp::Remove).await?;
+        Ok(existed)
+    }
+
+    async fn contains_key(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let buffered = self.buffered(scope, key, |op| matches!(op, PendingOp::Set(_)));
+
+        match buffered {
+            Some(exists) => Ok(exists),
+            None => self.inner.provider.contains_key(scope, key).await,
+        }
+    }
+
+    async fn touch(&self, scope: &[u8], key: &[u8]) -> Result<bool> {
+        let buffered = self.buffered(scope, key, |op| matches!(op, PendingOp::Set(_)));
+
+        match buffered {
+            Some(exists) => Ok(exists),
+            None => self.inner.provider.touch(scope, key).await,
+        }
+    }
+
+    async fn exists_multiple(&self, scope: &[u8], keys: &[&[u8]]) -> Result<Vec<bool>> {
+        let mut res = keys
+            .iter()
+            .map(|key| self.buffered(scope, key, |op| matches!(op, PendingOp::Set(_))))
+            .collect::<Vec<_>>();
+
+        // Only ask the inner provider about the keys which are not buffered
+        let missing = keys
+            .iter()
+            .zip(res.iter())
+            .filter(|(_, exists)| exists.is_none())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let mut found = self
+                .inner
+                .provider
+                .exists_multiple(scope, &missing)
+                .await?
+                .into_iter();
+            for exists in res.iter_mut().filter(|exists| exists.is_none()) {
+                *exists = found.next();
+            }
+        }
+
+        Ok(res
+            .into_iter()
+            .map(|exists| exists.unwrap_or(false))
+            .collect())
+    }
+
+    async fn persist(&self, scope: &[u8], key: &[u8]) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.persist(scope, key).await
+    }
+
+    async fn expire(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.expire(scope, key, expire_in).await
+    }
+
+    async fn expire_multiple(&self, scope: &[u8], entries: &[(&[u8], Duration)]) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.expire_multiple(scope, entries).await
+    }
+
+    async fn expiry(&self, scope: &[u8], key: &[u8]) -> Result<Option<Duration>> {
+        self.flush().await?;
+        self.inner.provider.expiry(scope, key).await
+    }
+
+    async fn modified_at(&self, scope: &[u8], key: &[u8]) -> Result<Option<SystemTime>> {
+        self.flush().await?;
+        self.inner.provider.modified_at(scope, key).await
+    }
+
+    async fn extend(&self, scope: &[u8], key: &[u8], expire_in: Duration) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.extend(scope, key, expire_in).await
+    }
+
+    async fn set_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> Result<()> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .set_expiring(scope, key, value, expire_in)
+            .await
+    }
+
+    async fn swap_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .swap_expiring(scope, key, value, expire_in)
+            .await
+    }
+
+    async fn set_versioned(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        value: Value<'_>,
+        version: u64,
+    ) -> Result<bool> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .set_versioned(scope, key, value, version)
+            .await
+    }
+
+    async fn get_expiring(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+    ) -> Result<Option<(OwnedValue, Option<Duration>)>> {
+        self.flush().await?;
+        self.inner.provider.get_expiring(scope, key).await
+    }
+
+    async fn preload(&self, scope: &[u8]) -> Result<()> {
+        self.flush().await?;
+        self.inner.provider.preload(scope).await
+    }
+
+    async fn get_expiring_multiple(
+        &self,
+        scope: &[u8],
+        keys: &[&[u8]],
+    ) -> Result<Vec<Option<(OwnedValue, Option<Duration>)>>> {
+        self.flush().await?;
+        self.inner.provider.get_expiring_multiple(scope, keys).await
+    }
+
+    async fn snapshot(&self, scope: &[u8]) -> Result<Vec<SnapshotEntry>> {
+        self.flush().await?;
+        self.inner.provider.snapshot(scope).await
+    }
+
+    async fn get_and_extend(
+        &self,
+        scope: &[u8],
+        key: &[u8],
+        expire_in: Duration,
+    ) -> Result<Option<OwnedValue>> {
+        self.flush().await?;
+        self.inner
+            .provider
+            .get_and_extend(scope, key, expire_in)
+            .await
+    }
+}
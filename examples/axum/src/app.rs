@@ -0,0 +1,31 @@
+use axum::{extract::Path, routing::get, Router};
+use basteh::Basteh;
+
+/// Count the visits of each name, the store is extracted from the router's state
+async fn index(Path(name): Path<String>, basteh: Basteh) -> String {
+    let visits = basteh.mutate(&name, |m| m.incr(1)).await.unwrap();
+
+    format!("Hello {}, you've been here {} time(s)", name, visits)
+}
+
+/// Reset the counter of a name, returning its last value
+async fn reset(Path(name): Path<String>, basteh: Basteh) -> String {
+    let visits = basteh.remove::<i64>(&name).await.unwrap().unwrap_or(0);
+
+    format!("Goodbye {}, you were here {} time(s)", name, visits)
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let provider = basteh_memory::MemoryBackend::start_default();
+    let basteh = Basteh::build().provider(provider).finish();
+
+    // Basteh is cheap to clone, so it can be used as the state directly
+    let app = Router::new()
+        .route("/{name}", get(index))
+        .route("/{name}/reset", get(reset))
+        .with_state(basteh);
+
+    let listener = tokio::net::TcpListener::bind("localhost:5000").await?;
+    axum::serve(listener, app).await
+}